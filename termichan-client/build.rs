@@ -0,0 +1,12 @@
+// 与服务端共用同一份接口定义
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 默认使用 protoc-bin-vendored 自带的 protoc，构建时无需系统安装；设置了 `PROTOC` 时以其为准
+    if std::env::var_os("PROTOC").is_none() {
+        // SAFETY: 构建脚本是单线程的，此时没有其他线程读取环境变量
+        unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+    }
+    tonic_build::configure()
+        .build_server(false)
+        .compile(&["../termichan-server/proto/termichan.proto"], &["../termichan-server/proto"])?;
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use thiserror::Error;
+use tonic::transport::Channel;
+
+/// 由 `termichan-server/proto/termichan.proto` 生成的消息和客户端类型。
+pub mod proto {
+    tonic::include_proto!("termichan");
+}
+
+use proto::termichan_client::TermichanClient as GrpcClient;
+use proto::{ExplainRequest, ExplainResponse, GenerateRequest, HistoryEntry, HistoryRequest};
+
+/// `termichan server --grpc` 默认监听的地址。
+pub const DEFAULT_GRPC_ENDPOINT: &str = "http://127.0.0.1:50051";
+
+/// 客户端错误类型。
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Failed to connect to termichan server: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    /// `tonic::Status` 较大，装箱后 `Result<_, ClientError>` 不会因此变大。
+    #[error("termichan server returned an error: {0}")]
+    Status(Box<tonic::Status>),
+}
+
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Status(Box::new(status))
+    }
+}
+
+/// 供编辑器插件使用的 termichan gRPC 客户端。
+#[derive(Debug, Clone)]
+pub struct TermichanClient {
+    inner: GrpcClient<Channel>,
+}
+
+impl TermichanClient {
+    /// 连接到 `endpoint`，例如 `DEFAULT_GRPC_ENDPOINT`。
+    ///
+    /// # Errors
+    ///
+    /// 地址无效或无法建立连接时返回 `ClientError::Transport`。
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self, ClientError> {
+        let inner = GrpcClient::connect(endpoint.into()).await?;
+        Ok(Self { inner })
+    }
+
+    /// 根据自然语言查询生成命令，返回 LLM 输出的内容块。
+    ///
+    /// `pwd` 和 `shell` 描述调用方（通常是编辑器中的终端）的环境，
+    /// 为 `None` 时使用服务进程自身的环境。
+    pub async fn generate(
+        &mut self,
+        query: impl Into<String>,
+        pwd: Option<String>,
+        shell: Option<String>,
+    ) -> Result<BoxStream<'static, Result<String, ClientError>>, ClientError> {
+        let request = GenerateRequest {
+            query: query.into(),
+            pwd,
+            shell,
+        };
+        let stream = self.inner.generate_command(request).await?.into_inner();
+        Ok(stream
+            .map(|token| token.map(|token| token.text).map_err(ClientError::from))
+            .boxed())
+    }
+
+    /// 解释一条命令，同时返回其影响类别。
+    pub async fn explain(&mut self, command: impl Into<String>) -> Result<ExplainResponse, ClientError> {
+        let request = ExplainRequest {
+            command: command.into(),
+        };
+        Ok(self.inner.explain_command(request).await?.into_inner())
+    }
+
+    /// 最近的 `limit` 条历史记录，按时间顺序排列；`limit` 为 0 时返回全部。
+    pub async fn history(&mut self, limit: u32) -> Result<Vec<HistoryEntry>, ClientError> {
+        let response = self.inner.get_history(HistoryRequest { limit }).await?;
+        Ok(response.into_inner().entries)
+    }
+}
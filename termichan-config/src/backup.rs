@@ -0,0 +1,293 @@
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, CURRENT_SCHEMA_VERSION};
+use crate::error::ConfigError;
+
+/// 超过该天数的备份会在创建新备份时被清理。
+pub const BACKUP_RETENTION_DAYS: i64 = 30;
+
+/// 备份文件名中时间戳的格式，例如 `2024-05-01_13-45-10`。
+///
+/// 同一秒内的后续备份在时间戳后追加序号，例如 `2024-05-01_13-45-10_1`。
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+const BACKUP_PREFIX: &str = "config_";
+const BACKUP_SUFFIX: &str = ".toml.bak";
+
+/// 一个配置文件备份。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigBackup {
+    /// 文件名中的时间戳（同一秒内的后续备份带有序号），可传给 `Config::restore_backup`。
+    pub timestamp: String,
+    /// 备份文件路径。
+    pub path: PathBuf,
+    /// 解析后的备份时间（本地时间）。
+    pub created_at: NaiveDateTime,
+}
+
+/// 默认配置文件的路径，与 `load_or_create_config(None)` 加载的文件一致。
+pub fn config_file_path() -> Result<PathBuf, ConfigError> {
+    Ok(confy::get_configuration_file_path("termichan", None)?)
+}
+
+impl Config {
+    /// 默认备份目录：配置文件所在目录下的 `backups`。
+    pub fn default_backup_dir() -> Result<PathBuf, ConfigError> {
+        let path = config_file_path()?;
+        Ok(path.parent().unwrap_or(Path::new(".")).join("backups"))
+    }
+
+    /// 将当前配置文件复制到 `<backup_dir>/config_<YYYY-MM-DD_HH-MM-SS>.toml.bak`，返回备份路径。
+    ///
+    /// 同一秒内已有备份时，文件名中的时间戳后追加 `_1`、`_2` 等序号，不会覆盖已有备份。
+    /// 配置文件尚不存在时（例如仅从环境变量构建配置），备份 `self` 序列化后的内容。
+    /// 创建备份后会清理超过 `BACKUP_RETENTION_DAYS` 天的旧备份。
+    pub fn backup(&self, backup_dir: &Path) -> Result<PathBuf, ConfigError> {
+        self.backup_from(&config_file_path()?, backup_dir)
+    }
+
+    /// 将 `config_path` 处的配置文件备份到 `backup_dir`，见 `backup`。
+    fn backup_from(&self, config_path: &Path, backup_dir: &Path) -> Result<PathBuf, ConfigError> {
+        let content = match fs::read(config_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => toml::to_string_pretty(self)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                .into_bytes(),
+            Err(e) => return Err(e.into()),
+        };
+
+        fs::create_dir_all(backup_dir)?;
+        let timestamp = Local::now().format(TIMESTAMP_FORMAT).to_string();
+        let (mut file, backup_path) = create_backup_file(backup_dir, &timestamp)?;
+        file.write_all(&content)?;
+
+        prune_backups(backup_dir)?;
+        Ok(backup_path)
+    }
+
+    /// 将配置写入默认配置文件。
+    ///
+    /// 所有修改配置文件的操作都应通过此方法，以便在 `ConfigConfig::auto_backup` 开启时先创建备份。
+    /// 写入的 `schema_version` 为当前版本。
+    pub fn store(&self) -> Result<(), ConfigError> {
+        if self.config.auto_backup {
+            self.backup(&Self::default_backup_dir()?)?;
+        }
+        let mut config = self.clone();
+        config.schema_version = CURRENT_SCHEMA_VERSION.to_string();
+        Ok(confy::store("termichan", None, config)?)
+    }
+
+    /// 列出 `backup_dir` 中的所有备份，按时间从旧到新排序。目录不存在时返回空列表。
+    pub fn list_backups(backup_dir: &Path) -> Result<Vec<ConfigBackup>, ConfigError> {
+        let entries = match fs::read_dir(backup_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut backups = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let Some(timestamp) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(BACKUP_PREFIX))
+                .and_then(|name| name.strip_suffix(BACKUP_SUFFIX))
+            else {
+                continue;
+            };
+            // 忽略时间戳格式不符的文件，它们不是由 `backup` 创建的
+            if let Some((created_at, sequence)) = parse_timestamp(timestamp) {
+                let backup = ConfigBackup {
+                    timestamp: timestamp.to_string(),
+                    path,
+                    created_at,
+                };
+                backups.push((sequence, backup));
+            }
+        }
+        backups.sort_by_key(|(sequence, b)| (b.created_at, *sequence));
+        Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+    }
+
+    /// 用 `backup_dir` 中时间戳为 `timestamp` 的备份替换配置文件，返回配置文件路径。
+    ///
+    /// 先写入临时文件再重命名，保证替换是原子的。`ConfigConfig::auto_backup`
+    /// 开启时，替换前会先备份当前配置文件。
+    pub fn restore_backup(&self, backup_dir: &Path, timestamp: &str) -> Result<PathBuf, ConfigError> {
+        let config_path = config_file_path()?;
+        self.restore_backup_to(&config_path, backup_dir, timestamp)?;
+        Ok(config_path)
+    }
+
+    /// 用 `backup_dir` 中的备份替换 `config_path` 处的配置文件，见 `restore_backup`。
+    fn restore_backup_to(&self, config_path: &Path, backup_dir: &Path, timestamp: &str) -> Result<(), ConfigError> {
+        let backup = Self::list_backups(backup_dir)?
+            .into_iter()
+            .find(|b| b.timestamp == timestamp)
+            .ok_or_else(|| ConfigError::BackupNotFound {
+                timestamp: timestamp.to_string(),
+                dir: backup_dir.to_path_buf(),
+            })?;
+
+        if self.config.auto_backup {
+            self.backup_from(config_path, backup_dir)?;
+        }
+
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = config_path.with_extension("toml.tmp");
+        fs::copy(&backup.path, &tmp)?;
+        fs::rename(&tmp, config_path)?;
+        Ok(())
+    }
+}
+
+/// 在 `backup_dir` 中新建时间戳为 `timestamp` 的备份文件；该文件已存在时依次尝试追加序号 `_1`、`_2`……
+fn create_backup_file(backup_dir: &Path, timestamp: &str) -> io::Result<(File, PathBuf)> {
+    let mut sequence = 0u32;
+    loop {
+        let name = match sequence {
+            0 => format!("{BACKUP_PREFIX}{timestamp}{BACKUP_SUFFIX}"),
+            n => format!("{BACKUP_PREFIX}{timestamp}_{n}{BACKUP_SUFFIX}"),
+        };
+        let path = backup_dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => sequence += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 解析备份文件名中的时间戳，返回备份时间和同一秒内的序号（没有序号时为 0）。
+fn parse_timestamp(timestamp: &str) -> Option<(NaiveDateTime, u32)> {
+    if let Ok(created_at) = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT) {
+        return Some((created_at, 0));
+    }
+    let (stamp, sequence) = timestamp.rsplit_once('_')?;
+    if !sequence.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let created_at = NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()?;
+    Some((created_at, sequence.parse().ok()?))
+}
+
+/// 删除创建时间早于 `BACKUP_RETENTION_DAYS` 天前的备份。
+fn prune_backups(backup_dir: &Path) -> Result<(), ConfigError> {
+    let cutoff = Local::now().naive_local() - TimeDelta::days(BACKUP_RETENTION_DAYS);
+    for backup in Config::list_backups(backup_dir)? {
+        if backup.created_at < cutoff {
+            log::debug!("Removing expired config backup {}", backup.path.display());
+            fs::remove_file(&backup.path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("termichan-backup-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_backup(dir: &Path, timestamp: &str, content: &str) {
+        fs::write(dir.join(format!("{BACKUP_PREFIX}{timestamp}{BACKUP_SUFFIX}")), content).unwrap();
+    }
+
+    #[test]
+    fn list_backups_sorts_by_time_and_ignores_other_files() {
+        let dir = scratch_dir("list");
+        assert!(Config::list_backups(&dir.join("missing")).unwrap().is_empty());
+
+        write_backup(&dir, "2024-05-02_08-00-00", "");
+        write_backup(&dir, "2024-05-01_13-45-10_1", "");
+        write_backup(&dir, "2024-05-01_13-45-10", "");
+        write_backup(&dir, "2024-05-01_13-45-10_x", "");
+        write_backup(&dir, "yesterday", "");
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let timestamps: Vec<_> = Config::list_backups(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.timestamp)
+            .collect();
+        assert_eq!(
+            timestamps,
+            ["2024-05-01_13-45-10", "2024-05-01_13-45-10_1", "2024-05-02_08-00-00"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backups_in_the_same_second_get_a_sequence_number() {
+        let dir = scratch_dir("sequence");
+        let (_, first) = create_backup_file(&dir, "2024-05-01_13-45-10").unwrap();
+        let (_, second) = create_backup_file(&dir, "2024-05-01_13-45-10").unwrap();
+        assert_ne!(first, second);
+        assert!(second.ends_with("config_2024-05-01_13-45-10_1.toml.bak"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restore_keeps_the_chosen_backup_when_auto_backup_runs_in_the_same_second() {
+        let dir = scratch_dir("restore");
+        let config_path = dir.join("config.toml");
+        let backup_dir = dir.join("backups");
+        let config = Config::default();
+        assert!(config.config.auto_backup);
+
+        fs::write(&config_path, "old").unwrap();
+        config.backup_from(&config_path, &backup_dir).unwrap();
+        let chosen = Config::list_backups(&backup_dir).unwrap().remove(0);
+
+        fs::write(&config_path, "new").unwrap();
+        config
+            .restore_backup_to(&config_path, &backup_dir, &chosen.timestamp)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "old");
+
+        let contents: Vec<_> = Config::list_backups(&backup_dir)
+            .unwrap()
+            .iter()
+            .map(|b| fs::read_to_string(&b.path).unwrap())
+            .collect();
+        assert_eq!(contents, ["old", "new"]);
+
+        match config.restore_backup_to(&config_path, &backup_dir, "2000-01-01_00-00-00") {
+            Err(ConfigError::BackupNotFound { timestamp, .. }) => assert_eq!(timestamp, "2000-01-01_00-00-00"),
+            other => panic!("expected ConfigError::BackupNotFound, got {other:?}"),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_removes_only_expired_backups() {
+        let dir = scratch_dir("prune");
+        let recent = Local::now() - TimeDelta::days(BACKUP_RETENTION_DAYS - 1);
+        let recent = recent.format(TIMESTAMP_FORMAT).to_string();
+        write_backup(&dir, "2000-01-01_00-00-00", "");
+        write_backup(&dir, "2000-01-01_00-00-00_1", "");
+        write_backup(&dir, &recent, "");
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        prune_backups(&dir).unwrap();
+        let timestamps: Vec<_> = Config::list_backups(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.timestamp)
+            .collect();
+        assert_eq!(timestamps, [recent]);
+        assert!(dir.join("notes.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+
+use crate::config::Config;
+
+/// 所有配置项的说明，由 `#[termichan_doc]` 从文档注释生成。
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDocumentation {
+    /// 按结构体中声明的顺序排列的配置项。
+    pub fields: Vec<FieldDoc>,
+}
+
+/// 单个配置项的说明。
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldDoc {
+    /// 以 `.` 分隔的路径，与 TOML 中的位置对应，例如 `llm.temperature`。
+    pub path: String,
+    /// Rust 类型名，例如 `Option<u32>`。
+    pub type_name: String,
+    /// `Config::default()` 中的值，以 TOML 格式表示；`None` 显示为空字符串。
+    pub default_value: String,
+    /// 字段的文档注释。
+    pub description: String,
+    /// 示例值（如果提供）。
+    pub example: Option<String>,
+}
+
+/// 由 `#[termichan_doc]` 实现，收集结构体各字段的说明。
+pub(crate) trait Documented {
+    fn collect_docs(&self, prefix: &str, out: &mut Vec<FieldDoc>);
+}
+
+impl Config {
+    /// 所有配置项的说明，默认值取自 `Config::default()`。
+    pub fn documentation() -> ConfigDocumentation {
+        let mut fields = Vec::new();
+        Config::default().collect_docs("", &mut fields);
+        ConfigDocumentation { fields }
+    }
+}
+
+impl ConfigDocumentation {
+    /// 查找路径为 `path` 或以 `path.` 开头的配置项，例如 `llm` 返回所有 LLM 配置。
+    pub fn find(&self, path: &str) -> Vec<&FieldDoc> {
+        let prefix = format!("{path}.");
+        self.fields
+            .iter()
+            .filter(|f| f.path == path || f.path.starts_with(&prefix))
+            .collect()
+    }
+}
+
+pub(crate) fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+/// 以 TOML 格式表示字段值，无法表示的值（例如 `None`）返回空字符串。
+pub(crate) fn format_value<T: Serialize>(value: &T) -> String {
+    match toml::Value::try_from(value) {
+        // TOML 浮点数为 f64，`f32` 字段按 f32 显示，避免出现 0.699999988 之类的值
+        Ok(toml::Value::Float(f)) if f64::from(f as f32) == f => (f as f32).to_string(),
+        Ok(v) => v.to_string(),
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,242 @@
+use crate::config::Config;
+use crate::env::apply_overrides;
+use crate::error::ConfigError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 当前目录下自动读取的 dotenv 文件名。
+pub const DOTENV_FILE: &str = ".env";
+
+/// dotenv 文件不能设置的变量前缀。
+///
+/// dotenv 文件随项目目录分发，克隆的仓库中可能带有不可信的 `.env`。决定请求和密钥发往何处、
+/// 哪些命令无需确认、会执行什么外部程序或写入哪些文件的设置只能通过配置文件或环境变量修改。
+const DOTENV_BLOCKED_PREFIXES: &[&str] = &["TERMICHAN_SECURITY_", "TERMICHAN_NETWORK_"];
+/// dotenv 文件不能设置的变量，原因见 `DOTENV_BLOCKED_PREFIXES`。
+const DOTENV_BLOCKED: &[&str] = &[
+    "TERMICHAN_LLM_API_KEY",
+    "TERMICHAN_LLM_BASE_URL",
+    "TERMICHAN_LLM_PROVIDER_HEADERS",
+    "TERMICHAN_LLM_ENABLE_FUNCTION_CALLING",
+    "TERMICHAN_LLM_CACHE_FILE",
+    "TERMICHAN_HISTORY_FILE_PATH",
+    "TERMICHAN_HISTORY_EXPORT_PATH",
+    "TERMICHAN_PROMPT_LAST_ERROR_LOG",
+    "TERMICHAN_UI_PAGER_COMMAND",
+];
+
+/// dotenv 文件能否设置 `name`。
+fn allowed_in_dotenv(name: &str) -> bool {
+    !DOTENV_BLOCKED.contains(&name) && !DOTENV_BLOCKED_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+impl Config {
+    /// 读取 dotenv 文件中的 `KEY=VALUE`，像环境变量一样覆盖当前配置。
+    ///
+    /// 只有 `TERMICHAN_*` 变量会被使用，其余变量被忽略。`security.*`、`network.*`、API 密钥和地址等
+    /// 敏感设置不能通过 dotenv 文件修改，出现时记录警告并忽略。支持 `#` 注释、`export` 前缀、
+    /// 单引号和双引号包围的值，引号内的值可以跨越多行；双引号内支持 `\n`、`\t` 等转义。
+    ///
+    /// # Errors
+    ///
+    /// 无法读取文件时返回 `ConfigError::Io`，文件格式错误时返回 `ConfigError::InvalidDotenv`，
+    /// 变量值无法解析时返回 `ConfigError::InvalidEnvVar`。
+    pub fn merge_from_dotenv(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let content = fs::read_to_string(path)?;
+        let vars: HashMap<String, String> = parse(&content)
+            .map_err(|(line, reason)| ConfigError::InvalidDotenv {
+                path: path.to_path_buf(),
+                line,
+                reason,
+            })?
+            .into_iter()
+            .filter(|(name, _)| {
+                let allowed = allowed_in_dotenv(name);
+                if !allowed {
+                    log::warn!(
+                        "Ignoring {name} in {}: it can only be set in the config file or environment",
+                        path.display()
+                    );
+                }
+                allowed
+            })
+            .collect();
+        apply_overrides(self, |name| vars.get(name).cloned())
+    }
+}
+
+/// 解析 dotenv 文件内容，按出现顺序返回变量；同名变量以最后一次为准由调用方处理。
+///
+/// 出错时返回 `(行号, 原因)`，行号从 1 开始。
+fn parse(content: &str) -> Result<Vec<(String, String)>, (usize, String)> {
+    let mut pairs = Vec::new();
+    let mut lines = content.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_no = index + 1;
+        let line = line.trim_start();
+        if line.trim_end().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map_or(line, str::trim_start);
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (line_no, "expected KEY=VALUE".to_string()))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            return Err((line_no, format!("invalid variable name `{key}`")));
+        }
+
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let mut raw = value[1..].to_string();
+                let closing = loop {
+                    if let Some(end) = find_closing_quote(&raw, quote) {
+                        break end;
+                    }
+                    let (_, next) = lines
+                        .next()
+                        .ok_or_else(|| (line_no, format!("unterminated {quote}-quoted value")))?;
+                    raw.push('\n');
+                    raw.push_str(next);
+                };
+                let rest = raw[closing + 1..].trim();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err((line_no, format!("unexpected `{rest}` after quoted value")));
+                }
+                match quote {
+                    '"' => unescape(&raw[..closing]),
+                    _ => raw[..closing].to_string(),
+                }
+            }
+            _ => strip_inline_comment(value).trim_end().to_string(),
+        };
+        pairs.push((key.to_string(), value));
+    }
+    Ok(pairs)
+}
+
+/// 结束引号的位置；双引号内被 `\` 转义的引号不算。
+fn find_closing_quote(raw: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in raw.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 处理双引号值中的转义序列，无法识别的转义保持原样。
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(c @ ('"' | '\\' | '$')) => out.push(c),
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// 去掉未加引号的值中以空白开头的 `#` 注释。
+fn strip_inline_comment(value: &str) -> &str {
+    let mut prev_is_space = true;
+    for (i, c) in value.char_indices() {
+        if c == '#' && prev_is_space {
+            return &value[..i];
+        }
+        prev_is_space = c.is_whitespace();
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(content: &str) -> Vec<(String, String)> {
+        parse(content).expect("valid dotenv content")
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn parses_comments_export_and_inline_comments() {
+        let content = "# comment\n\nexport TERMICHAN_LLM_MODEL=gpt-4o-mini # cheaper\nTERMICHAN_LLM_PROVIDER = openai\nURL=http://host/#anchor\n";
+        assert_eq!(
+            parsed(content),
+            vec![
+                pair("TERMICHAN_LLM_MODEL", "gpt-4o-mini"),
+                pair("TERMICHAN_LLM_PROVIDER", "openai"),
+                pair("URL", "http://host/#anchor"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_quoted_and_multiline_values() {
+        let content = "A=\"line one\\nline \\\"two\\\"\" # comment\nB='literal \\n # not a comment'\nC=\"first\nsecond\"\n";
+        assert_eq!(
+            parsed(content),
+            vec![
+                pair("A", "line one\nline \"two\""),
+                pair("B", "literal \\n # not a comment"),
+                pair("C", "first\nsecond"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sensitive_settings_are_ignored() {
+        let dir = std::env::temp_dir().join(format!("termichan-dotenv-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DOTENV_FILE);
+        let content = "TERMICHAN_LLM_MODEL=gpt-4o-mini\nTERMICHAN_LLM_BASE_URL=http://evil.example\n\
+                       TERMICHAN_LLM_API_KEY=sk-evil\nTERMICHAN_SECURITY_CONFIRMATION_MODE=never\n\
+                       TERMICHAN_NETWORK_TRUST_INVALID_CERTS=true\nTERMICHAN_UI_PAGER_COMMAND=sh\n";
+        fs::write(&path, content).unwrap();
+
+        let mut config = Config::default();
+        config.merge_from_dotenv(&path).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        let defaults = Config::default();
+        assert_eq!(config.llm.model, "gpt-4o-mini");
+        assert_eq!(config.llm.base_url, defaults.llm.base_url);
+        assert_eq!(config.llm.api_key, defaults.llm.api_key);
+        assert_eq!(config.security.confirmation_mode, defaults.security.confirmation_mode);
+        assert_eq!(config.network.trust_invalid_certs, defaults.network.trust_invalid_certs);
+        assert_eq!(config.ui.pager_command, defaults.ui.pager_command);
+    }
+
+    #[test]
+    fn reports_line_of_malformed_entries() {
+        assert_eq!(parse("A=1\nnot a pair\n").unwrap_err().0, 2);
+        assert_eq!(parse("A=\"unterminated\nB=2\n").unwrap_err().0, 1);
+        assert_eq!(parse("A='x' trailing\n").unwrap_err().0, 1);
+    }
+}
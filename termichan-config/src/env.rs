@@ -0,0 +1,959 @@
+use crate::config::{Config, ConfirmationMode, ImpactClass, OutputFormat, SpinnerStyle, TieredAction};
+use crate::error::ConfigError;
+use crate::with_api_key_fallback;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// 所有 `termichan` 环境变量共用的前缀。
+pub const ENV_PREFIX: &str = "TERMICHAN_";
+
+/// 设置为真值 (`1`、`true`、`yes`) 时，跳过配置文件，仅从环境变量构建配置。
+pub const NO_CONFIG_FILE_ENV: &str = "TERMICHAN_NO_CONFIG_FILE";
+
+/// 一个可通过环境变量覆盖的配置项。
+pub struct EnvVarSpec {
+    /// 环境变量名称，例如 `TERMICHAN_LLM_MODEL`。
+    pub name: &'static str,
+    /// 配置项的简要说明。
+    pub description: &'static str,
+    get: fn(&Config) -> String,
+    set: fn(&mut Config, &str) -> Result<(), String>,
+}
+
+impl EnvVarSpec {
+    /// 以环境变量值的形式返回该配置项在 `config` 中的当前值。
+    ///
+    /// `None` 的可选项返回空字符串，列表项以逗号连接。
+    pub fn value(&self, config: &Config) -> String {
+        (self.get)(config)
+    }
+}
+
+/// 所有受支持的 `TERMICHAN_*` 环境变量。
+///
+/// 可选项设置为空字符串时表示 `None`；列表项使用逗号分隔。
+pub const ENV_VARS: &[EnvVarSpec] = &[
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_PROVIDER",
+        description: "LLM service provider, e.g. openai, ollama",
+        get: |c| c.llm.provider.clone(),
+        set: |c, v| {
+            c.llm.provider = v.to_string();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_API_KEY",
+        description: "LLM API key (OPENAI_API_KEY is used as a fallback)",
+        get: |c| c.llm.api_key.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.api_key = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_BASE_URL",
+        description: "Base URL of an OpenAI compatible API",
+        get: |c| c.llm.base_url.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.base_url = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_ORGANIZATION_ID",
+        description: "OpenAI organization to bill requests to",
+        get: |c| c.llm.organization_id.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.organization_id = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_PROJECT_ID",
+        description: "OpenAI project to bill requests to",
+        get: |c| c.llm.project_id.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.project_id = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_MODEL",
+        description: "Model name used for command generation",
+        get: |c| c.llm.model.clone(),
+        set: |c, v| {
+            c.llm.model = v.to_string();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_TEMPERATURE",
+        description: "Sampling temperature, 0.0 to 2.0",
+        get: |c| c.llm.temperature.to_string(),
+        set: |c, v| {
+            c.llm.temperature = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_TOP_P",
+        description: "Nucleus sampling probability mass, 0.0 to 1.0",
+        get: |c| format_optional(c.llm.top_p),
+        set: |c, v| {
+            c.llm.top_p = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_LOGIT_BIAS",
+        description: "Token biases for OpenAI-compatible APIs, e.g. `=-100,**=-50",
+        get: |c| {
+            let mut pairs: Vec<_> = c.llm.logit_bias.iter().flatten().collect();
+            pairs.sort_by(|a, b| a.0.cmp(b.0));
+            pairs
+                .into_iter()
+                .map(|(token, bias)| format!("{token}={bias}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        },
+        set: |c, v| {
+            let pairs = parse_pairs(v)?;
+            c.llm.logit_bias = if pairs.is_empty() {
+                None
+            } else {
+                Some(
+                    pairs
+                        .into_iter()
+                        .map(|(token, bias)| Ok((token, parse(&bias)?)))
+                        .collect::<Result<_, String>>()?,
+                )
+            };
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_MAX_TOKENS",
+        description: "Maximum number of tokens in a response",
+        get: |c| format_optional(c.llm.max_tokens),
+        set: |c, v| {
+            c.llm.max_tokens = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_CONTEXT_WINDOW",
+        description: "Context window size of the model, overrides the built-in model table",
+        get: |c| format_optional(c.llm.context_window),
+        set: |c, v| {
+            c.llm.context_window = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_N_COMPLETIONS",
+        description: "Number of candidate responses to sample per request",
+        get: |c| format_optional(c.llm.n_completions),
+        set: |c, v| {
+            c.llm.n_completions = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_TIMEOUT_SECS",
+        description: "API request timeout in seconds",
+        get: |c| c.llm.timeout_secs.to_string(),
+        set: |c, v| {
+            c.llm.timeout_secs = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_MAX_RETRIES",
+        description: "Maximum retries after a rate limit response, 0 disables retrying",
+        get: |c| c.llm.max_retries.to_string(),
+        set: |c, v| {
+            c.llm.max_retries = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_PREWARM_ON_STARTUP",
+        description: "Open a connection to the LLM service in the background at startup",
+        get: |c| c.llm.prewarm_on_startup.to_string(),
+        set: |c, v| {
+            c.llm.prewarm_on_startup = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_VALIDATE_KEY_ON_STARTUP",
+        description: "Check that the API key is valid before sending the first request",
+        get: |c| c.llm.validate_key_on_startup.to_string(),
+        set: |c, v| {
+            c.llm.validate_key_on_startup = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_ENABLE_PROMPT_CACHE",
+        description: "Mark the system prompt as cacheable (Anthropic only)",
+        get: |c| c.llm.enable_prompt_cache.to_string(),
+        set: |c, v| {
+            c.llm.enable_prompt_cache = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_POOL_SIZE",
+        description: "Maximum idle HTTP connections kept per host",
+        get: |c| c.llm.pool_size.to_string(),
+        set: |c, v| {
+            c.llm.pool_size = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_SLOW_QUERY_WARN_MS",
+        description: "Warn when a request takes longer than this many milliseconds",
+        get: |c| format_optional(c.llm.slow_query_warn_ms),
+        set: |c, v| {
+            c.llm.slow_query_warn_ms = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_REQUEST_ID_HEADER",
+        description: "HTTP header carrying a generated request ID, e.g. X-Request-Id",
+        get: |c| c.llm.request_id_header.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.request_id_header = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_REQUEST_ID_PREFIX",
+        description: "Prefix prepended to generated request IDs",
+        get: |c| c.llm.request_id_prefix.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.request_id_prefix = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_ENABLE_FUNCTION_CALLING",
+        description: "Let the model call read-only tools such as git_status before answering",
+        get: |c| c.llm.enable_function_calling.to_string(),
+        set: |c, v| {
+            c.llm.enable_function_calling = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_SYSTEM_PROMPT_INJECTION_GUARD",
+        description: "Mark user content in the prompt as data so it cannot override the system prompt",
+        get: |c| c.llm.system_prompt_injection_guard.to_string(),
+        set: |c, v| {
+            c.llm.system_prompt_injection_guard = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_ACTIVE_PERSONA",
+        description: "Assistant persona to use, e.g. expert, teacher or safety-first",
+        get: |c| c.llm.active_persona.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.llm.active_persona = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_AUTO_INJECT_SESSION_CONTEXT",
+        description: "Send recent queries and commands from this terminal session as conversation context",
+        get: |c| c.llm.auto_inject_session_context.to_string(),
+        set: |c, v| {
+            c.llm.auto_inject_session_context = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_MAX_CONTEXT_MESSAGES",
+        description: "Number of recent query and command pairs sent as session context",
+        get: |c| c.llm.max_context_messages.to_string(),
+        set: |c, v| {
+            c.llm.max_context_messages = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_ENABLE_EMBEDDINGS",
+        description: "Store query embeddings in the history for semantic search",
+        get: |c| c.llm.enable_embeddings.to_string(),
+        set: |c, v| {
+            c.llm.enable_embeddings = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_EMBEDDING_MODEL",
+        description: "Model used to embed history queries",
+        get: |c| c.llm.embedding_model.clone(),
+        set: |c, v| {
+            c.llm.embedding_model = v.to_string();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_PROVIDER_HEADERS",
+        description: "Extra HTTP headers, e.g. X-Tenant-Id=acme,X-Gateway-Token=env:GATEWAY_TOKEN",
+        get: |c| {
+            let mut pairs: Vec<_> = c.llm.provider_headers.iter().collect();
+            pairs.sort();
+            pairs
+                .into_iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        },
+        set: |c, v| {
+            c.llm.provider_headers = parse_pairs(v)?.into_iter().collect();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_CACHE_FILE",
+        description: "File the response cache is stored in, empty disables caching",
+        get: |c| format_optional(c.llm.cache_file.as_ref().map(|p| p.display())),
+        set: |c, v| {
+            c.llm.cache_file = parse_optional(v).map(PathBuf::from);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_LLM_CACHE_TTL_SECS",
+        description: "Seconds a cached response stays valid",
+        get: |c| c.llm.cache_ttl_secs.to_string(),
+        set: |c, v| {
+            c.llm.cache_ttl_secs = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_CONFIRMATION_MODE",
+        description: "Confirmation before execution: always, never, dangerous, tiered",
+        get: |c| format!("{:?}", c.security.confirmation_mode).to_lowercase(),
+        set: |c, v| {
+            c.security.confirmation_mode = v.parse()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_DANGEROUS_COMMANDS",
+        description: "Comma separated command prefixes that require confirmation",
+        get: |c| c.security.dangerous_commands.join(","),
+        set: |c, v| {
+            c.security.dangerous_commands = parse_list(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_TIERED_THRESHOLDS",
+        description: "Tiered mode actions, e.g. read_only=auto_execute,destructive=reject",
+        get: |c| {
+            let mut pairs: Vec<_> = c.security.tiered_thresholds.iter().collect();
+            pairs.sort_by_key(|(class, _)| **class);
+            pairs
+                .into_iter()
+                .map(|(class, action)| format!("{}={}", class.as_str(), action.as_str()))
+                .collect::<Vec<_>>()
+                .join(",")
+        },
+        set: |c, v| {
+            c.security.tiered_thresholds = parse_pairs(v)?
+                .into_iter()
+                .map(|(class, action)| Ok((class.parse()?, action.parse()?)))
+                .collect::<Result<_, String>>()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_MAX_COMMAND_LENGTH",
+        description: "Longest generated command (in characters) that may be executed",
+        get: |c| c.security.max_command_length.to_string(),
+        set: |c, v| {
+            c.security.max_command_length = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_MAX_COMMAND_LINES",
+        description: "Most lines a generated command may span to be executed",
+        get: |c| c.security.max_command_lines.to_string(),
+        set: |c, v| {
+            c.security.max_command_lines = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_MAX_COMMAND_OUTPUT_BYTES",
+        description: "Kill executed commands after this many bytes of output, empty for no limit",
+        get: |c| format_optional(c.security.max_command_output_bytes),
+        set: |c, v| {
+            c.security.max_command_output_bytes = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_MAX_EXECUTION_WALL_SECONDS",
+        description: "Kill executed commands after this many seconds, 0 for no limit",
+        get: |c| c.security.max_execution_wall_seconds.to_string(),
+        set: |c, v| {
+            c.security.max_execution_wall_seconds = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_REQUIRE_EXPLANATION_FOR_DANGEROUS",
+        description: "Require an explanation before executing dangerous commands",
+        get: |c| c.security.require_explanation_for_dangerous.to_string(),
+        set: |c, v| {
+            c.security.require_explanation_for_dangerous = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_ENABLE_AUDIT",
+        description: "Record security events in the audit log",
+        get: |c| c.security.enable_audit.to_string(),
+        set: |c, v| {
+            c.security.enable_audit = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_SECURITY_AUDIT_LOG",
+        description: "File security events are appended to, enables auditing when set",
+        get: |c| format_optional(c.security.audit_log.as_ref().map(|p| p.display())),
+        set: |c, v| {
+            c.security.audit_log = parse_optional(v).map(PathBuf::from);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_ENABLED",
+        description: "Whether to record command history",
+        get: |c| c.history.enabled.to_string(),
+        set: |c, v| {
+            c.history.enabled = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_FILE_PATH",
+        description: "Path of the history file",
+        get: |c| c.history.file_path.display().to_string(),
+        set: |c, v| {
+            c.history.file_path = PathBuf::from(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_MAX_ENTRIES",
+        description: "Maximum number of history entries to keep",
+        get: |c| c.history.max_entries.to_string(),
+        set: |c, v| {
+            c.history.max_entries = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_EXPORT_ON_EXIT",
+        description: "Whether to export the history to JSON on exit",
+        get: |c| c.history.export_on_exit.to_string(),
+        set: |c, v| {
+            c.history.export_on_exit = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_EXPORT_PATH",
+        description: "JSON file the history is exported to on exit",
+        get: |c| format_optional(c.history.export_path.as_ref().map(|p| p.display())),
+        set: |c, v| {
+            c.history.export_path = parse_optional(v).map(PathBuf::from);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_SESSION_RETENTION_DAYS",
+        description: "Days `termichan gc` keeps history sessions, 0 keeps them forever",
+        get: |c| c.history.session_retention_days.to_string(),
+        set: |c, v| {
+            c.history.session_retention_days = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_MAX_BACKUP_COUNT",
+        description: "Number of config backups `termichan gc` keeps",
+        get: |c| c.history.max_backup_count.to_string(),
+        set: |c, v| {
+            c.history.max_backup_count = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_SUGGESTION_THRESHOLD",
+        description: "Similarity (0 to 1) above which a past successful command is offered for reuse",
+        get: |c| c.history.suggestion_threshold.to_string(),
+        set: |c, v| {
+            c.history.suggestion_threshold = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_RETENTION_MAX_AGE_DAYS",
+        description: "Delete history entries older than this many days",
+        get: |c| format_optional(c.history.retention_policy.max_age_days),
+        set: |c, v| {
+            c.history.retention_policy.max_age_days = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_RETENTION_MAX_ENTRIES",
+        description: "Keep at most this many history entries under the retention policy",
+        get: |c| format_optional(c.history.retention_policy.max_entries),
+        set: |c, v| {
+            c.history.retention_policy.max_entries = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_RETENTION_PURGE_ON_SUCCESS",
+        description: "Delete history entries of commands that exited with 0",
+        get: |c| c.history.retention_policy.purge_on_success.to_string(),
+        set: |c, v| {
+            c.history.retention_policy.purge_on_success = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_HISTORY_RETENTION_PURGE_ON_FAILURE",
+        description: "Delete history entries of commands that exited with a non-zero code",
+        get: |c| c.history.retention_policy.purge_on_failure.to_string(),
+        set: |c, v| {
+            c.history.retention_policy.purge_on_failure = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_PROMPT_SYSTEM_PROMPT",
+        description: "System prompt, supports {os}, {shell} and {pwd} placeholders",
+        get: |c| c.prompt.system_prompt.clone(),
+        set: |c, v| {
+            c.prompt.system_prompt = v.to_string();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_PROMPT_USER_PROMPT_TEMPLATE",
+        description: "User prompt template, supports the {user_input} placeholder",
+        get: |c| c.prompt.user_prompt_template.clone(),
+        set: |c, v| {
+            c.prompt.user_prompt_template = v.to_string();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_PROMPT_INJECT_RECENT_ERRORS",
+        description: "Provide {last_exit_code} and {last_error} from shell integration",
+        get: |c| c.prompt.inject_recent_errors.to_string(),
+        set: |c, v| {
+            c.prompt.inject_recent_errors = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_PROMPT_LAST_ERROR_LOG",
+        description: "File the shell integration writes the last command's stderr to",
+        get: |c| c.prompt.last_error_log.display().to_string(),
+        set: |c, v| {
+            c.prompt.last_error_log = PathBuf::from(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_PROMPT_MAX_CONTEXT_CHARS",
+        description: "Maximum characters of environment context injected into the prompt",
+        get: |c| c.prompt.max_context_chars.to_string(),
+        set: |c, v| {
+            c.prompt.max_context_chars = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_OUTPUT_FORMAT",
+        description: "Output format: plain, markdown, rich, json, csv",
+        get: |c| format!("{:?}", c.ui.output_format).to_lowercase(),
+        set: |c, v| {
+            c.ui.output_format = v.parse()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_SHOW_EXPLANATION",
+        description: "Whether to show command explanations",
+        get: |c| c.ui.show_explanation.to_string(),
+        set: |c, v| {
+            c.ui.show_explanation = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_COMPACT_MODE",
+        description: "Whether to reduce vertical spacing in output",
+        get: |c| c.ui.compact_mode.to_string(),
+        set: |c, v| {
+            c.ui.compact_mode = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_SYNTAX_HIGHLIGHTING",
+        description: "Whether to highlight generated commands",
+        get: |c| c.ui.syntax_highlighting.to_string(),
+        set: |c, v| {
+            c.ui.syntax_highlighting = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_PAGER_COMMAND",
+        description: "Pager for long output, e.g. \"less -R\"",
+        get: |c| c.ui.pager_command.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.ui.pager_command = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_KEYBINDINGS",
+        description: "Confirmation prompt keys, e.g. confirm=y,reject=n,edit=e,dry_run=d,copy=c",
+        get: |c| {
+            let mut pairs: Vec<_> = c.ui.keybindings.iter().collect();
+            pairs.sort();
+            pairs
+                .into_iter()
+                .map(|(action, key)| format!("{action}={key}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        },
+        set: |c, v| {
+            c.ui.keybindings = parse_pairs(v)?.into_iter().collect();
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_SPINNER_STYLE",
+        description: "Spinner animation: braille, dots, bar, arc, clock, pipe, none",
+        get: |c| format!("{:?}", c.ui.spinner_style).to_lowercase(),
+        set: |c, v| {
+            c.ui.spinner_style = v.parse()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_STREAM_BUFFER_MS",
+        description: "Flush interval in milliseconds for streamed output, 0 writes every chunk immediately",
+        get: |c| c.ui.stream_buffer_ms.to_string(),
+        set: |c, v| {
+            c.ui.stream_buffer_ms = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_UI_TRUNCATE_COMMAND_AT",
+        description: "Maximum characters per displayed command line, 0 disables truncation",
+        get: |c| c.ui.truncate_command_at.to_string(),
+        set: |c, v| {
+            c.ui.truncate_command_at = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_PROXY",
+        description: "Proxy URL, e.g. socks5://localhost:1080",
+        get: |c| c.network.proxy.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.network.proxy = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_TRUST_INVALID_CERTS",
+        description: "Accept invalid TLS certificates (insecure)",
+        get: |c| c.network.trust_invalid_certs.to_string(),
+        set: |c, v| {
+            c.network.trust_invalid_certs = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_DNS_OVERRIDE",
+        description: "Custom DNS server address, e.g. 8.8.8.8:53",
+        get: |c| format_optional(c.network.dns_override),
+        set: |c, v| {
+            c.network.dns_override = parse_optional(v).map(|v| parse(&v)).transpose()?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_DNS_OVER_HTTPS_URL",
+        description: "DNS over HTTPS resolver URL, e.g. https://1.1.1.1/dns-query",
+        get: |c| c.network.dns_over_https_url.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.network.dns_over_https_url = parse_optional(v);
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_RETRY_ON_NETWORK_ERROR",
+        description: "Retry requests whose connection could not be established",
+        get: |c| c.network.retry_on_network_error.to_string(),
+        set: |c, v| {
+            c.network.retry_on_network_error = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_RETRY_COUNT",
+        description: "Maximum retries after a connection failure",
+        get: |c| c.network.network_retry_count.to_string(),
+        set: |c, v| {
+            c.network.network_retry_count = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_RETRY_ON_DNS_FAILURE",
+        description: "Retry requests whose host name could not be resolved",
+        get: |c| c.network.retry_on_dns_failure.to_string(),
+        set: |c, v| {
+            c.network.retry_on_dns_failure = parse_bool(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_NETWORK_DNS_RETRY_COUNT",
+        description: "Maximum retries after a DNS resolution failure",
+        get: |c| c.network.dns_retry_count.to_string(),
+        set: |c, v| {
+            c.network.dns_retry_count = parse(v)?;
+            Ok(())
+        },
+    },
+    EnvVarSpec {
+        name: "TERMICHAN_CONFIG_AUTO_BACKUP",
+        description: "Back up the config file before modifying it",
+        get: |c| c.config.auto_backup.to_string(),
+        set: |c, v| {
+            c.config.auto_backup = parse_bool(v)?;
+            Ok(())
+        },
+    },
+];
+
+impl Config {
+    /// 仅从 `TERMICHAN_*` 环境变量构建配置，不读取任何配置文件。
+    ///
+    /// 未设置的变量使用 `Config::default()` 中的默认值。没有设置 `TERMICHAN_LLM_API_KEY` 时
+    /// 与加载配置文件时一样从 `OPENAI_API_KEY` 读取 API 密钥。
+    /// 适用于 Docker、Kubernetes 等无法提供配置文件的容器化部署。
+    ///
+    /// # Errors
+    ///
+    /// 如果某个环境变量的值无法解析，返回 `ConfigError::InvalidEnvVar`。
+    pub fn from_env_only() -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.apply_env_overrides()?;
+        with_api_key_fallback(config)
+    }
+
+    /// 使用已设置的 `TERMICHAN_*` 环境变量覆盖当前配置。
+    ///
+    /// # Errors
+    ///
+    /// 如果某个环境变量的值无法解析，返回 `ConfigError::InvalidEnvVar`。
+    pub fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        apply_overrides(self, |name| std::env::var(name).ok())
+    }
+}
+
+/// 使用 `lookup` 查找 `TERMICHAN_*` 变量并覆盖 `config` 中的对应项。
+///
+/// `lookup` 返回 `None` 的变量保持原值不变。
+pub(crate) fn apply_overrides(
+    config: &mut Config,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    for spec in ENV_VARS {
+        if let Some(value) = lookup(spec.name) {
+            (spec.set)(config, &value).map_err(|reason| ConfigError::InvalidEnvVar {
+                name: spec.name.to_string(),
+                value,
+                reason,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// 判断环境变量值是否表示“真”。
+pub(crate) fn is_truthy(value: &str) -> bool {
+    parse_bool(value).unwrap_or(false)
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value.trim().parse().map_err(|e: T::Err| e.to_string())
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err("expected a boolean (true/false, 1/0, yes/no)".to_string()),
+    }
+}
+
+fn parse_optional(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|v| !v.trim().is_empty())
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 解析逗号分隔的 `key=value` 列表。
+fn parse_pairs(value: &str) -> Result<Vec<(String, String)>, String> {
+    parse_list(value)
+        .iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .ok_or_else(|| format!("expected key=value, got `{pair}`"))
+        })
+        .collect()
+}
+
+fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+impl FromStr for ConfirmationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            "dangerous" => Ok(Self::Dangerous),
+            "tiered" => Ok(Self::Tiered),
+            _ => Err("expected one of: always, never, dangerous, tiered".to_string()),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "plain" => Ok(Self::Plain),
+            "markdown" => Ok(Self::Markdown),
+            "rich" => Ok(Self::Rich),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err("expected one of: plain, markdown, rich, json, csv".to_string()),
+        }
+    }
+}
+
+impl FromStr for SpinnerStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "braille" => Ok(Self::Braille),
+            "dots" => Ok(Self::Dots),
+            "bar" => Ok(Self::Bar),
+            "arc" => Ok(Self::Arc),
+            "clock" => Ok(Self::Clock),
+            "pipe" => Ok(Self::Pipe),
+            "none" => Ok(Self::None),
+            _ => Err("expected one of: braille, dots, bar, arc, clock, pipe, none".to_string()),
+        }
+    }
+}
+
+impl ImpactClass {
+    /// 与 TOML 中相同的蛇形命名。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read_only",
+            Self::Network => "network",
+            Self::FileModification => "file_modification",
+            Self::Privileged => "privileged",
+            Self::Destructive => "destructive",
+        }
+    }
+}
+
+impl FromStr for ImpactClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "read_only" => Ok(Self::ReadOnly),
+            "network" => Ok(Self::Network),
+            "file_modification" => Ok(Self::FileModification),
+            "privileged" => Ok(Self::Privileged),
+            "destructive" => Ok(Self::Destructive),
+            _ => Err(
+                "expected one of: read_only, network, file_modification, privileged, destructive"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+impl TieredAction {
+    /// 与 TOML 中相同的蛇形命名。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AutoExecute => "auto_execute",
+            Self::Confirm => "confirm",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+impl FromStr for TieredAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto_execute" => Ok(Self::AutoExecute),
+            "confirm" => Ok(Self::Confirm),
+            "reject" => Ok(Self::Reject),
+            _ => Err("expected one of: auto_execute, confirm, reject".to_string()),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// `Config::validate` 发现的一项错误，即对应检查返回的 `ConfigError`。
+pub type ConfigValidationError = ConfigError;
+
+/// 加载或构建配置时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// 环境变量的值无法解析为对应配置项的类型。
+    #[error("Invalid value `{value}` for environment variable {name}: {reason}")]
+    InvalidEnvVar {
+        name: String,
+        value: String,
+        reason: String,
+    },
+
+    /// 读写配置文件或备份文件失败，或无法创建配置目录。
+    #[error("Config file I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// 指定时间戳的备份不存在。
+    #[error("No config backup with timestamp {timestamp} in {}", dir.display())]
+    BackupNotFound { timestamp: String, dir: PathBuf },
+
+    /// dotenv 文件中有无法解析的行。
+    #[error("Malformed dotenv file {}:{line}: {reason}", path.display())]
+    InvalidDotenv {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+
+    /// `UiConfig::keybindings` 无效。
+    #[error("Invalid keybindings: {0}")]
+    InvalidKeybindings(String),
+
+    /// `UiConfig::date_format` 不是有效的 strftime 格式。
+    #[error("Invalid ui.date_format `{0}`: expected a strftime format such as `%Y-%m-%d %H:%M` or `relative`")]
+    InvalidDateFormat(String),
+
+    /// `LlmConfig::max_tokens` 超过了所选模型的单次输出上限。
+    #[error("max_tokens {requested} exceeds the model's output limit of {model_limit} tokens")]
+    MaxTokensExceedsModelLimit { requested: u32, model_limit: u32 },
+
+    /// `LlmConfig::max_tokens` 超过了模型的上下文窗口。
+    #[error(
+        "llm.max_tokens {requested} exceeds the {context_window}-token context window assumed for model `{model}`; \
+         set llm.context_window if the model supports more"
+    )]
+    MaxTokensExceedsContextWindow {
+        requested: u32,
+        context_window: usize,
+        model: String,
+    },
+
+    /// `LlmConfig::logit_bias` 中的 token 为空，或偏置值不在 -100.0 到 100.0 之间。
+    #[error("Invalid llm.logit_bias entry `{token}` = {bias}: tokens must be non-empty and biases within [-100, 100]")]
+    InvalidLogitBias { token: String, bias: f32 },
+
+    /// `LlmConfig::base_url` 不是有效的 `http` 或 `https` URL。
+    #[error("Invalid llm.base_url {0}")]
+    InvalidBaseUrl(String),
+
+    /// `LlmConfig::provider_headers` 中的请求头引用了未设置的环境变量。
+    #[error("llm.provider_headers `{header}` refers to unset environment variable {var}")]
+    MissingHeaderEnvVar { header: String, var: String },
+
+    /// 配置文件的内容不是有效的 TOML 或不符合配置结构。
+    #[error("Invalid config file: {0}")]
+    TomlParse(#[from] toml::de::Error),
+
+    /// 配置无法序列化为 TOML，例如写入配置文件时。
+    #[error("Failed to serialize config: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+
+    /// `PromptConfig::context_providers` 中没有设置 `command` 的提供者不是内置提供者。
+    #[error("Unknown context provider `{0}`: set `command` for a custom provider or use one of git, docker, k8s, venv, project, env_vars, directory")]
+    UnknownContextProvider(String),
+
+    /// `SecurityConfig::dangerous_patterns` 中的正则表达式无效。
+    #[error("Invalid security.dangerous_patterns entry `{pattern}`: {reason}")]
+    InvalidDangerousPattern { pattern: String, reason: String },
+
+    /// `Config::merge_from` 中指定的配置段不存在。
+    #[error("Unknown config section `{0}`")]
+    UnknownSection(String),
+
+    /// `LlmConfig::active_persona` 既不是内置风格，也不在 `PromptConfig::role_play_personas` 中。
+    #[error("Unknown persona `{0}`: run `termichan persona list` to see the available personas")]
+    UnknownPersona(String),
+
+    /// `Config::validate` 发现的全部错误，按检查的顺序排列，至少有一项。
+    #[error("{}", describe_validation_errors(.0))]
+    ValidationFailed(Vec<ConfigValidationError>),
+
+    /// 请求的配置 profile 不存在。
+    #[error("Config profile `{0}` not found")]
+    ProfileNotFound(String),
+
+    /// 无法把配置从旧版本的格式迁移到当前格式。
+    #[error("Failed to migrate config from format version {from_version}: {reason}")]
+    MigrationFailed { from_version: u32, reason: String },
+
+    /// 无法监视配置文件的变化。
+    #[error("Failed to watch config file: {0}")]
+    Watch(#[from] notify::Error),
+
+    /// 配置格式的版本低于 `Config::assert_minimum_version` 要求的版本。
+    #[error("Config schema version {found} is older than the required {required}; save the config with a newer termichan to upgrade it")]
+    IncompatibleVersion { required: String, found: String },
+
+    /// 传给 `Config::assert_minimum_version` 的版本不是有效的 semver 版本。
+    #[error("Invalid version {0}")]
+    InvalidVersion(String),
+
+    /// 另一个 `termichan` 进程正持有配置文件的锁。
+    #[error("Config file is locked by another termichan process (pid {locked_by_pid})")]
+    Locked { locked_by_pid: u32 },
+}
+
+/// 只有一项错误时直接显示，否则逐行列出。
+fn describe_validation_errors(errors: &[ConfigValidationError]) -> String {
+    match errors {
+        [error] => error.to_string(),
+        errors => {
+            let lines: Vec<String> = errors.iter().map(|e| format!("\n  - {e}")).collect();
+            format!("{} problems in config:{}", errors.len(), lines.concat())
+        }
+    }
+}
+
+/// `confy` 只是加载和保存配置的实现细节，它的错误转换为对应的 `ConfigError`，
+/// I/O 错误附带失败的操作，方便用户判断原因。
+impl From<confy::ConfyError> for ConfigError {
+    fn from(error: confy::ConfyError) -> Self {
+        use confy::ConfyError;
+
+        let io_error = |action: &str, e: io::Error| io::Error::new(e.kind(), format!("failed to {action}: {e}"));
+        match error {
+            ConfyError::BadTomlData(e) => Self::TomlParse(e),
+            ConfyError::SerializeTomlError(e) => Self::TomlSerialize(e),
+            ConfyError::DirectoryCreationFailed(e) => Self::Io(io_error("create config directory", e)),
+            ConfyError::GeneralLoadError(e) | ConfyError::ReadConfigurationFileError(e) => {
+                Self::Io(io_error("read config file", e))
+            }
+            ConfyError::OpenConfigurationFileError(e) => Self::Io(io_error("open config file", e)),
+            ConfyError::WriteConfigurationFileError(e) => Self::Io(io_error("write config file", e)),
+            ConfyError::SetPermissionsFileError(e) => Self::Io(io_error("set config file permissions", e)),
+            ConfyError::BadConfigDirectory(reason) => {
+                Self::Io(io::Error::other(format!("cannot determine config directory: {reason}")))
+            }
+        }
+    }
+}
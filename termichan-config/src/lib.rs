@@ -1,12 +1,36 @@
+mod backup;
 mod config;
+mod diff;
+mod docs;
+mod dotenv;
+mod env;
+mod error;
+mod lock;
+mod mask;
+mod merge;
+mod model_limits;
+mod persona;
+mod watch;
 
 // 公开导出配置相关的结构体和枚举，方便其他 crate 使用。
 pub use config::{
-    Config, ConfirmationMode, HistoryConfig, LlmConfig, NetworkConfig, OutputFormat, PromptConfig,
-    SecurityConfig, UiConfig,
+    format_relative, AbTestConfig, Config, ConfigConfig, ConfirmationMode, ContextProviderConfig, HistoryConfig,
+    ImpactClass, LlmConfig, NetworkConfig, OutputFormat, PreProcessorConfig, PromptConfig, RetentionPolicy,
+    SecurityConfig, SpinnerStyle, TieredAction, UiConfig, BUILTIN_CONTEXT_PROVIDERS, KEYBINDING_ACTIONS,
+    RELATIVE_DATE_FORMAT,
 };
+pub use backup::{config_file_path, ConfigBackup, BACKUP_RETENTION_DAYS};
+pub use diff::ConfigDiff;
+pub use docs::{ConfigDocumentation, FieldDoc};
+pub use dotenv::DOTENV_FILE;
+pub use env::{EnvVarSpec, ENV_PREFIX, ENV_VARS, NO_CONFIG_FILE_ENV};
+pub use error::{ConfigError, ConfigValidationError};
+pub use lock::LockedConfig;
+pub use merge::CONFIG_SECTIONS;
+pub use model_limits::{ModelLimits, DEFAULT_CONTEXT_WINDOW};
+pub use persona::{builtin_personas, Persona};
+pub use watch::WatchHandle;
 
-use confy;
 use std::path::PathBuf;
 
 /// 加载 `termichan` 配置，如果不存在则创建默认配置。
@@ -18,19 +42,29 @@ use std::path::PathBuf;
 /// `confy` 会在文件不存在时自动尝试创建它，使用 `Config::default()` 并将其序列化为 TOML。
 /// 它还会处理父目录的创建。
 ///
+/// 加载文件后，当前目录下 `.env` 文件中的 `TERMICHAN_*` 变量（见 `Config::merge_from_dotenv`）
+/// 和已设置的 `TERMICHAN_*` 环境变量依次覆盖文件中的对应项。
+/// 如果设置了 `TERMICHAN_NO_CONFIG_FILE=1`，则完全跳过配置文件，等同于 `Config::from_env_only()`。
+///
 /// # Arguments
 ///
 /// * `config_path_override` - 可选的配置文件路径，用于覆盖默认加载行为。
 ///
 /// # Errors
 ///
-/// 如果发生无法恢复的错误（例如，无法读取/写入文件权限问题，TOML 格式错误，无法创建目录等），
-/// 则返回 `confy::ConfyError`。
+/// 无法读取/写入文件（例如权限问题）或无法创建目录时返回 `ConfigError::Io`；
+/// 文件不是有效的 TOML 或不符合配置结构时返回 `ConfigError::TomlParse`；
+/// 无法序列化默认配置时返回 `ConfigError::TomlSerialize`；`.env` 文件格式错误时返回 `ConfigError::InvalidDotenv`；
+/// 环境变量的值无效时返回 `ConfigError::InvalidEnvVar`。
 ///
 /// # Returns
 ///
 /// 成功时返回加载的 `Config` 实例。
-pub fn load_or_create_config(config_path_override: Option<PathBuf>) -> Result<Config, confy::ConfyError> {
+pub fn load_or_create_config(config_path_override: Option<PathBuf>) -> Result<Config, ConfigError> {
+    if config_file_disabled() {
+        return Config::from_env_only();
+    }
+
     let mut config: Config = match config_path_override {
         // 如果提供了覆盖路径，使用 confy::load_path。
         // confy::load_path 也会在文件不存在时尝试创建默认文件。
@@ -38,11 +72,28 @@ pub fn load_or_create_config(config_path_override: Option<PathBuf>) -> Result<Co
         // 如果没有提供覆盖路径，使用 confy::load 让它处理标准路径和文件名。
         None => confy::load("termichan", None), // "termichan" 是应用名称，None 使用默认文件名 "config.toml"
     }?;
+    let dotenv = std::path::Path::new(DOTENV_FILE);
+    if dotenv.is_file() {
+        config.merge_from_dotenv(dotenv)?;
+    }
+    config.apply_env_overrides()?;
+
+    with_api_key_fallback(config)
+}
+
+/// 是否通过 `TERMICHAN_NO_CONFIG_FILE` 禁用了配置文件。
+pub fn config_file_disabled() -> bool {
+    std::env::var(NO_CONFIG_FILE_ENV).is_ok_and(|v| env::is_truthy(&v))
+}
 
+/// 如果配置中没有 API 密钥，尝试从 `OPENAI_API_KEY` 环境变量读取。
+pub(crate) fn with_api_key_fallback(mut config: Config) -> Result<Config, ConfigError> {
     // If api_key not exists, try load from env var
-    if let None = config.llm.api_key {
+    if config.llm.api_key.is_none() {
         config.llm.api_key = std::env::var("OPENAI_API_KEY").ok();
-        log::warn!("OPENAI_API_KEY isn't set in environment variable and config file.")
+        if config.llm.api_key.is_none() {
+            log::warn!("OPENAI_API_KEY isn't set in environment variable and config file.")
+        }
     }
 
     Ok(config)
@@ -0,0 +1,194 @@
+use fd_lock::RwLock;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::{mem, thread};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::ConfigError;
+
+/// 等待其他进程释放配置锁的最长时间。
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// 等待配置锁时重试的间隔。
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 持有配置文件锁期间可读写的配置。
+///
+/// 通过 `Config::lock` 获得；离开作用域时释放锁。锁文件本身保留在磁盘上，
+/// 其中记录最近一次持有锁的进程 ID。
+#[derive(Debug)]
+pub struct LockedConfig {
+    config: Config,
+    path: PathBuf,
+    _lock: RwLock<File>,
+}
+
+impl Config {
+    /// 锁定 `path` 处的配置文件并加载其中的配置，防止多个 `termichan` 进程同时写入。
+    ///
+    /// 锁保存在 `<path>.lock` 中。其他进程持有锁时最多等待 5 秒。
+    ///
+    /// # Errors
+    ///
+    /// 等待超时时返回 `ConfigError::Locked`；无法创建锁文件或读取配置文件时返回 `ConfigError::Io`；
+    /// 配置文件内容无效时返回 `ConfigError::TomlParse`。
+    pub fn lock(path: &Path) -> Result<LockedConfig, ConfigError> {
+        let lock = acquire(path)?;
+        Ok(LockedConfig {
+            config: confy::load_path(path)?,
+            path: path.to_path_buf(),
+            _lock: lock,
+        })
+    }
+
+    /// 与 `Config::lock` 一样锁定 `path` 处的配置文件，但不读取现有内容，`store` 时以 `self` 覆盖。
+    ///
+    /// 用于 `termichan init` 等替换整个配置文件的场景，现有文件无效时也能覆盖。
+    ///
+    /// # Errors
+    ///
+    /// 等待超时时返回 `ConfigError::Locked`；无法创建锁文件时返回 `ConfigError::Io`。
+    pub fn lock_replacing(self, path: &Path) -> Result<LockedConfig, ConfigError> {
+        Ok(LockedConfig {
+            _lock: acquire(path)?,
+            config: self,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl LockedConfig {
+    /// 被锁定的配置文件路径。
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 将修改后的配置写回文件；`ConfigConfig::auto_backup` 开启时先创建备份。
+    ///
+    /// 先写入临时文件再重命名，写入中断时不会留下不完整的配置文件。
+    pub fn store(&self) -> Result<(), ConfigError> {
+        if self.config.config.auto_backup {
+            self.config.backup(&Config::default_backup_dir()?)?;
+        }
+        let content =
+            toml::to_string_pretty(&self.config).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.path.with_extension("toml.tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl Deref for LockedConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl DerefMut for LockedConfig {
+    fn deref_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// 获取 `path` 的配置锁，并在锁文件中记录当前进程 ID。
+fn acquire(path: &Path) -> Result<RwLock<File>, ConfigError> {
+    let lock_path = lock_path(path);
+    if let Some(parent) = lock_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)?;
+    let mut lock = RwLock::new(file);
+
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    loop {
+        let acquired = match lock.try_write() {
+            Ok(mut guard) => {
+                guard.set_len(0)?;
+                guard.rewind()?;
+                write!(guard, "{}", std::process::id())?;
+                guard.flush()?;
+                // 守卫借用了 `lock`，无法与其一起保存；锁在文件关闭（`LockedConfig` 离开作用域）时释放
+                mem::forget(guard);
+                true
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => false,
+            Err(e) => return Err(e.into()),
+        };
+        if acquired {
+            break;
+        }
+        if Instant::now() >= deadline {
+            return Err(ConfigError::Locked {
+                locked_by_pid: read_pid(&mut lock.into_inner()),
+            });
+        }
+        thread::sleep(LOCK_RETRY_INTERVAL);
+    }
+
+    Ok(lock)
+}
+
+/// 锁文件中记录的进程 ID，无法读取时为 0。
+fn read_pid(file: &mut File) -> u32 {
+    let mut content = String::new();
+    if file.rewind().is_err() || file.read_to_string(&mut content).is_err() {
+        return 0;
+    }
+    content.trim().parse().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_config(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("termichan-lock-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("config.toml")
+    }
+
+    #[test]
+    fn a_held_lock_blocks_other_writers() {
+        let path = scratch_config("held");
+        let held = Config::lock(&path).unwrap();
+        match Config::lock(&path) {
+            Err(ConfigError::Locked { locked_by_pid }) => assert_eq!(locked_by_pid, std::process::id()),
+            other => panic!("expected ConfigError::Locked, got {other:?}"),
+        }
+        drop(held);
+        assert!(Config::lock(&path).is_ok());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn lock_replacing_overwrites_an_invalid_file() {
+        let path = scratch_config("replace");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not = [valid").unwrap();
+        assert!(Config::lock(&path).is_err());
+
+        let mut config = Config::default();
+        config.config.auto_backup = false;
+        config.llm.model = "replaced".to_string();
+        config.lock_replacing(&path).unwrap().store().unwrap();
+        assert_eq!(Config::lock(&path).unwrap().llm.model, "replaced");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::error::ConfigError;
+
+/// `Config` 中可以单独导入的顶层配置段。
+pub const CONFIG_SECTIONS: &[&str] = &["llm", "security", "history", "prompt", "ui", "network", "config"];
+
+impl Config {
+    /// 从 `path` 处的 TOML 文件加载配置，文件中未设置的配置项取默认值。
+    ///
+    /// 与 `confy::load_path` 不同，文件不存在时返回错误而不是创建默认配置文件。
+    ///
+    /// # Errors
+    ///
+    /// 无法读取文件时返回 `ConfigError::Io`；内容不是有效的配置时返回 `ConfigError::TomlParse`。
+    pub fn load_file(path: &Path) -> Result<Config, ConfigError> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// 将 `other` 中与默认值不同的配置项合并到 `self`，其余配置项保持不变。
+    ///
+    /// `section` 为 `Some` 时只合并该顶层配置段，例如 `llm`。由于只比较与默认值的差异，
+    /// `other` 无法把某项改回默认值，也无法清除 `self` 中已设置的可选项。
+    ///
+    /// # Errors
+    ///
+    /// `section` 不是 `CONFIG_SECTIONS` 之一时返回 `ConfigError::UnknownSection`。
+    pub fn merge_from(&mut self, other: &Config, section: Option<&str>) -> Result<(), ConfigError> {
+        if let Some(section) = section.filter(|s| !CONFIG_SECTIONS.contains(s)) {
+            return Err(ConfigError::UnknownSection(section.to_string()));
+        }
+        let mut target = to_table(self);
+        let mut overrides = to_table(other);
+        let defaults = to_table(&Config::default());
+        if let Some(section) = section {
+            overrides.retain(|key, _| key == section);
+        }
+        merge_tables(&mut target, &overrides, Some(&defaults));
+        *self = toml::Value::Table(target).try_into()?;
+        Ok(())
+    }
+}
+
+fn to_table(config: &Config) -> toml::Table {
+    match toml::Value::try_from(config) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::Table::new(),
+    }
+}
+
+/// 将 `overrides` 中与 `defaults` 不同的值写入 `target`，嵌套的表逐项合并，数组整体替换。
+fn merge_tables(target: &mut toml::Table, overrides: &toml::Table, defaults: Option<&toml::Table>) {
+    for (key, value) in overrides {
+        let default = defaults.and_then(|d| d.get(key));
+        if default == Some(value) {
+            continue;
+        }
+        let toml::Value::Table(nested) = value else {
+            target.insert(key.clone(), value.clone());
+            continue;
+        };
+        let nested_defaults = default.and_then(toml::Value::as_table);
+        match target.get_mut(key) {
+            Some(toml::Value::Table(existing)) => merge_tables(existing, nested, nested_defaults),
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_values_the_import_leaves_at_default() {
+        let mut current = Config::default();
+        current.llm.model = "gpt-4o".to_string();
+        current.ui.compact_mode = !current.ui.compact_mode;
+
+        let mut imported = Config::default();
+        imported.llm.api_key = Some("sk-imported".to_string());
+        imported.network.proxy = Some("http://proxy:8080".to_string());
+
+        let mut merged = current.clone();
+        merged.merge_from(&imported, None).unwrap();
+        assert_eq!(merged.llm.model, "gpt-4o");
+        assert_eq!(merged.ui.compact_mode, current.ui.compact_mode);
+        assert_eq!(merged.llm.api_key.as_deref(), Some("sk-imported"));
+        assert_eq!(merged.network.proxy.as_deref(), Some("http://proxy:8080"));
+
+        let mut llm_only = current.clone();
+        llm_only.merge_from(&imported, Some("llm")).unwrap();
+        assert_eq!(llm_only.llm.api_key.as_deref(), Some("sk-imported"));
+        assert_eq!(llm_only.network.proxy, None);
+
+        assert!(matches!(
+            current.merge_from(&imported, Some("bogus")),
+            Err(ConfigError::UnknownSection(_))
+        ));
+    }
+}
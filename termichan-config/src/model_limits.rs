@@ -0,0 +1,50 @@
+/// 模型的上下文窗口和单次输出的 token 上限。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelLimits {
+    /// 上下文窗口大小（输入与输出的 token 总数）。
+    pub context_tokens: u32,
+    /// 单次回复最多生成的 token 数。
+    pub max_output_tokens: u32,
+}
+
+/// 未设置 `llm.context_window` 且模型不在上限表中时使用的上下文窗口大小。
+pub const DEFAULT_CONTEXT_WINDOW: usize = 4096;
+
+/// 已知模型的上限，按模型名称前缀匹配。
+///
+/// 更具体的前缀必须排在前面，例如 `gpt-4o-mini` 在 `gpt-4o` 之前，`gpt-4` 放在最后。
+const MODEL_LIMITS: &[(&str, ModelLimits)] = &[
+    ("gpt-4o-mini", ModelLimits::new(128_000, 16_384)),
+    ("gpt-4o", ModelLimits::new(128_000, 16_384)),
+    ("gpt-4.1", ModelLimits::new(1_047_576, 32_768)),
+    ("gpt-4-turbo", ModelLimits::new(128_000, 4_096)),
+    ("gpt-4-32k", ModelLimits::new(32_768, 32_768)),
+    ("gpt-4", ModelLimits::new(8_192, 8_192)),
+    ("gpt-3.5-turbo", ModelLimits::new(16_385, 4_096)),
+    ("o1-mini", ModelLimits::new(128_000, 65_536)),
+    ("o1", ModelLimits::new(200_000, 100_000)),
+    ("o3-mini", ModelLimits::new(200_000, 100_000)),
+    ("claude-3-5-sonnet", ModelLimits::new(200_000, 8_192)),
+    ("claude-3-5-haiku", ModelLimits::new(200_000, 8_192)),
+    ("claude-3-opus", ModelLimits::new(200_000, 4_096)),
+    ("claude-3-sonnet", ModelLimits::new(200_000, 4_096)),
+    ("claude-3-haiku", ModelLimits::new(200_000, 4_096)),
+];
+
+impl ModelLimits {
+    const fn new(context_tokens: u32, max_output_tokens: u32) -> Self {
+        Self {
+            context_tokens,
+            max_output_tokens,
+        }
+    }
+
+    /// 查找 `model` 的上限；未知模型（例如本地模型或自定义部署）返回 `None`。
+    pub fn for_model(model: &str) -> Option<Self> {
+        let model = model.trim().to_ascii_lowercase();
+        MODEL_LIMITS
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix))
+            .map(|(_, limits)| *limits)
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Config, ConfigError, PromptConfig};
+
+/// 助手的一种风格，见 `PromptConfig::role_play_personas` 和 `LlmConfig::active_persona`。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Persona {
+    /// 名称，`termichan persona use <name>` 和 `llm.active_persona` 使用。
+    pub name: String,
+    /// 加在 `system_prompt` 之前的文本，与其之间空一行。
+    pub system_prompt_prefix: String,
+    /// 使用这个风格时代替 `llm.temperature` 的值 (可选)。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_override: Option<f32>,
+}
+
+impl Persona {
+    fn builtin(name: &str, system_prompt_prefix: &str, temperature_override: Option<f32>) -> Self {
+        Self {
+            name: name.to_string(),
+            system_prompt_prefix: system_prompt_prefix.to_string(),
+            temperature_override,
+        }
+    }
+}
+
+/// 内置的风格：`expert`（简洁）、`teacher`（详细解释）和 `safety-first`（总是附带警告）。
+pub fn builtin_personas() -> Vec<Persona> {
+    vec![
+        Persona::builtin(
+            "expert",
+            "You are talking to an experienced engineer. Reply with the command only: no explanation, \
+no comments and no alternatives unless the request is ambiguous.",
+            Some(0.2),
+        ),
+        Persona::builtin(
+            "teacher",
+            "You are talking to someone learning the command line. After the command, always add an \
+`# Explanation:` section that walks through each part of the command and every flag it uses, \
+and mention related commands worth learning.",
+            None,
+        ),
+        Persona::builtin(
+            "safety-first",
+            "Treat every command as if it will run on a production machine. Always add a `# Be careful:` \
+comment describing what the command changes and how to undo it, prefer dry-run or interactive flags \
+(such as `-i`, `--dry-run` or `-n`) where they exist, and never suggest `sudo` unless it is required.",
+            Some(0.3),
+        ),
+    ]
+}
+
+impl PromptConfig {
+    /// 可用的风格：内置风格和 `role_play_personas`，后者中同名的风格替换内置风格。
+    pub fn personas(&self) -> Vec<Persona> {
+        let mut personas: Vec<Persona> = builtin_personas()
+            .into_iter()
+            .filter(|builtin| !self.role_play_personas.iter().any(|p| p.name == builtin.name))
+            .collect();
+        personas.extend(self.role_play_personas.iter().cloned());
+        personas
+    }
+
+    /// 按名称查找风格，见 `personas`。
+    pub fn persona(&self, name: &str) -> Option<Persona> {
+        self.personas().into_iter().find(|p| p.name == name)
+    }
+}
+
+impl Config {
+    /// 检查 `llm.active_persona` 是已知的风格。
+    pub fn validate_active_persona(&self) -> Result<(), ConfigError> {
+        match &self.llm.active_persona {
+            Some(name) if self.prompt.persona(name).is_none() => Err(ConfigError::UnknownPersona(name.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    /// 应用 `llm.active_persona`：把风格的前缀加在系统提示词（包括 A/B 测试的变体 B）之前，
+    /// 并按 `temperature_override` 修改 `llm.temperature`。在 `validate` 之后调用，每份配置只应调用一次。
+    ///
+    /// # Errors
+    ///
+    /// - `ConfigError::UnknownPersona`: `llm.active_persona` 不是已知的风格。
+    pub fn apply_active_persona(&mut self) -> Result<(), ConfigError> {
+        let Some(name) = &self.llm.active_persona else {
+            return Ok(());
+        };
+        let persona = self
+            .prompt
+            .persona(name)
+            .ok_or_else(|| ConfigError::UnknownPersona(name.clone()))?;
+        let prefix = persona.system_prompt_prefix.trim_end();
+        if !prefix.is_empty() {
+            self.prompt.system_prompt = format!("{prefix}\n\n{}", self.prompt.system_prompt);
+            if let Some(ab_test) = &mut self.prompt.ab_test {
+                ab_test.variant_b_prompt = format!("{prefix}\n\n{}", ab_test.variant_b_prompt);
+            }
+        }
+        if let Some(temperature) = persona.temperature_override {
+            self.llm.temperature = temperature;
+        }
+        Ok(())
+    }
+}
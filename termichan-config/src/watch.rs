@@ -0,0 +1,92 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::error::ConfigError;
+use crate::{with_api_key_fallback, DOTENV_FILE};
+
+/// `Config::watch` 返回的句柄，被丢弃时停止监视配置文件。
+pub struct WatchHandle {
+    _watcher: RecommendedWatcher,
+}
+
+impl Config {
+    /// 监视 `path` 处的配置文件，文件内容变化时以重新加载的配置调用 `callback`。
+    ///
+    /// 重新加载与启动时相同：读取文件后应用 dotenv 文件和 `TERMICHAN_*` 环境变量，再调用 `validate`。
+    /// 监视的是文件所在的目录，因此编辑器或 `LockedConfig::store` 以重命名方式替换文件时同样生效。
+    /// 一次保存通常触发多个事件，与上次加载的配置相比没有变化时不调用 `callback`；
+    /// 无法解析或校验失败的配置只记录警告，继续使用之前的配置。
+    ///
+    /// # Errors
+    ///
+    /// 无法创建文件监视器或监视所在目录时返回 `ConfigError::Watch`。
+    pub fn watch<F>(path: &Path, callback: F) -> Result<WatchHandle, ConfigError>
+    where
+        F: Fn(&Config) + Send + 'static,
+    {
+        let path = path.to_path_buf();
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let current = Mutex::new(reload(&path).ok());
+
+        let watched = path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("Config watcher error: {e}");
+                    return;
+                }
+            };
+            let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.iter().any(|p| p.file_name() == watched.file_name());
+            if !relevant {
+                return;
+            }
+            let config = match reload(&watched) {
+                Ok(config) => config,
+                Err(e) => {
+                    log::warn!("Ignoring invalid config change in {}: {e}", watched.display());
+                    return;
+                }
+            };
+
+            let mut current = current.lock().unwrap_or_else(|e| e.into_inner());
+            let changed: Vec<String> = match current.as_ref() {
+                Some(previous) => previous.diff(&config).into_iter().map(|d| d.path).collect(),
+                None => vec!["(all)".to_string()],
+            };
+            if changed.is_empty() {
+                return;
+            }
+            log::info!(
+                "Reloaded config from {}, changed: {}",
+                watched.display(),
+                changed.join(", ")
+            );
+            callback(&config);
+            *current = Some(config);
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(WatchHandle { _watcher: watcher })
+    }
+}
+
+/// 按启动时的方式重新加载 `path` 处的配置文件。
+fn reload(path: &Path) -> Result<Config, ConfigError> {
+    let mut config = Config::load_file(path)?;
+    let dotenv = Path::new(DOTENV_FILE);
+    if dotenv.is_file() {
+        config.merge_from_dotenv(dotenv)?;
+    }
+    config.apply_env_overrides()?;
+    let mut config = with_api_key_fallback(config)?;
+    config.validate()?;
+    config.apply_active_persona()?;
+    Ok(config)
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use termichan_config::{ConfirmationMode, ImpactClass, SecurityConfig};
+
+use crate::safety::CommandClassifier;
+
+/// 安全审计事件的类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AuditEventType {
+    /// LLM 生成了一条命令。
+    CommandGenerated,
+    /// 命令被确认执行（包括按确认策略无需确认直接执行）。
+    CommandConfirmed,
+    /// 用户在确认提示中拒绝了命令。
+    CommandRejected,
+    /// 命令被识别为危险命令，见 `CommandClassifier::is_dangerous`。
+    DangerousDetected,
+    /// 命令的影响类别不在分级确认策略允许执行的范围内，被拒绝执行。
+    WhitelistViolation,
+    /// 命令超过了长度或行数限制，通常意味着提示词注入，见 `CommandClassifier::check_limits`。
+    PromptInjectionDetected,
+}
+
+/// 审计日志中的一行。
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    /// 事件发生的时间。
+    pub timestamp: DateTime<Utc>,
+    /// 事件类型。
+    pub event_type: AuditEventType,
+    /// 命令的 SHA-256 摘要（十六进制），不记录命令原文。
+    pub command_hash: String,
+    /// 用户查询的 SHA-256 摘要（十六进制），不记录查询原文。
+    pub query_hash: String,
+    /// 事件发生时的确认策略。
+    pub confirmation_mode: ConfirmationMode,
+    /// 命令的影响类别。
+    pub impact_class: ImpactClass,
+    /// 运行 `termichan` 的用户名。
+    pub user: String,
+    /// 请求计费的 OpenAI 组织，见 `LlmConfig::organization_id`。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<String>,
+    /// 请求计费的 OpenAI 项目，见 `LlmConfig::project_id`。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+}
+
+impl AuditEvent {
+    /// 为 `query` 生成的 `command` 创建事件，时间为当前时间。
+    pub fn new(event_type: AuditEventType, query: &str, command: &str, security: &SecurityConfig) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            event_type,
+            command_hash: sha256_hex(command),
+            query_hash: sha256_hex(query),
+            confirmation_mode: security.confirmation_mode.clone(),
+            impact_class: CommandClassifier::impact(command),
+            user: whoami::username(),
+            organization_id: None,
+            project_id: None,
+        }
+    }
+}
+
+/// 以 JSON Lines 格式追加安全审计事件的日志文件。
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// 按 `SecurityConfig::audit_log_path` 打开审计日志，未启用审计时返回 `None`。
+    pub fn from_config(security: &SecurityConfig) -> Option<Self> {
+        security.audit_log_path().map(|path| Self { path })
+    }
+
+    /// 审计日志路径。
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 将事件追加到日志末尾，文件或目录不存在时创建。
+    ///
+    /// # Errors
+    ///
+    /// 无法创建目录、打开或写入文件时返回 `io::Error`。
+    pub fn record(&self, event: &AuditEvent) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(event).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        line.push('\n');
+        // 整行一次写入，多个进程同时追加时各行不会交错
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+}
+
+pub(crate) fn sha256_hex(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
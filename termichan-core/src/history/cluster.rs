@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use super::{HistoryEntry, HistoryManager};
+
+/// k-means 的最大迭代次数。
+const MAX_ITERATIONS: usize = 50;
+/// TF-IDF 向量最多使用的词数，按出现的记录数从多到少选取。
+const MAX_VOCABULARY: usize = 1000;
+/// 描述一个聚类时列出的词数。
+const DESCRIPTION_TERMS: usize = 3;
+
+/// `HistoryManager::cluster_commands` 得到的一组相关命令。
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandCluster<'a> {
+    /// 最能代表这组命令的几个词，以 `, ` 分隔，例如 `docker, compose, logs`。
+    pub centroid_description: String,
+    /// 属于这组的记录，离聚类中心最近（最有代表性）的在前。
+    pub entries: Vec<&'a HistoryEntry>,
+}
+
+impl HistoryManager {
+    /// 用 k-means 把记录分为最多 `n_clusters` 组，按记录数从多到少排列。
+    ///
+    /// 所有记录都有维度相同的 `query_embedding` 时按嵌入向量聚类，否则按命令中词的 TF-IDF 聚类。
+    /// 初始中心按最远点选取，同样的记录总是得到同样的结果。记录数少于 `n_clusters` 时每条记录一组。
+    pub fn cluster_commands(&self, n_clusters: usize) -> Vec<CommandCluster<'_>> {
+        if self.entries.is_empty() || n_clusters == 0 {
+            return Vec::new();
+        }
+        let tokens: Vec<Vec<String>> = self.entries.iter().map(|e| command_tokens(&e.generated_command)).collect();
+        let idf = inverse_document_frequency(&tokens);
+        let vectors = embeddings(&self.entries).unwrap_or_else(|| tf_idf_vectors(&tokens, &idf));
+
+        let (assignments, centroids) = k_means(&vectors, n_clusters.min(vectors.len()));
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); centroids.len()];
+        for (index, &cluster) in assignments.iter().enumerate() {
+            members[cluster].push(index);
+        }
+        let mut clusters: Vec<CommandCluster<'_>> = members
+            .into_iter()
+            .zip(&centroids)
+            .filter(|(indices, _)| !indices.is_empty())
+            .map(|(mut indices, centroid)| {
+                indices.sort_by(|&a, &b| distance(&vectors[a], centroid).total_cmp(&distance(&vectors[b], centroid)));
+                CommandCluster {
+                    centroid_description: describe(indices.iter().map(|&i| &tokens[i]), &idf),
+                    entries: indices.into_iter().map(|i| &self.entries[i]).collect(),
+                }
+            })
+            .collect();
+        clusters.sort_by_key(|c| Reverse(c.entries.len()));
+        clusters
+    }
+}
+
+/// 所有记录的嵌入向量；有记录没有向量或维度不同时为 `None`。
+fn embeddings(entries: &[HistoryEntry]) -> Option<Vec<Vec<f32>>> {
+    let vectors: Vec<Vec<f32>> = entries
+        .iter()
+        .map(|e| e.query_embedding.clone())
+        .collect::<Option<_>>()?;
+    let dimension = vectors.first()?.len();
+    (dimension > 0 && vectors.iter().all(|v| v.len() == dimension)).then_some(vectors)
+}
+
+/// 把命令按空白和 shell 运算符拆分为小写的词，去掉不含字母的部分（例如数字和 `-`）。
+fn command_tokens(command: &str) -> Vec<String> {
+    command
+        .split(|c: char| c.is_whitespace() || matches!(c, '|' | '&' | ';' | '(' | ')' | '<' | '>' | '"' | '\''))
+        .map(|token| token.trim_start_matches('-').to_lowercase())
+        .filter(|token| token.chars().any(char::is_alphabetic))
+        .collect()
+}
+
+/// 每个词的逆文档频率 `ln(记录数 / 含有该词的记录数) + 1`。
+fn inverse_document_frequency(tokens: &[Vec<String>]) -> HashMap<String, f32> {
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for words in tokens {
+        for word in words.iter().map(String::as_str).collect::<HashSet<_>>() {
+            *document_frequency.entry(word).or_default() += 1;
+        }
+    }
+    let documents = tokens.len() as f32;
+    document_frequency
+        .into_iter()
+        .map(|(word, count)| (word.to_string(), (documents / count as f32).ln() + 1.0))
+        .collect()
+}
+
+/// 每条命令归一化后的 TF-IDF 向量，只使用出现在最多记录中的 `MAX_VOCABULARY` 个词。
+fn tf_idf_vectors(tokens: &[Vec<String>], idf: &HashMap<String, f32>) -> Vec<Vec<f32>> {
+    let mut vocabulary: Vec<(&str, f32)> = idf.iter().map(|(word, &weight)| (word.as_str(), weight)).collect();
+    // IDF 越小出现的记录越多；相同时按词排序，保证结果稳定
+    vocabulary.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+    vocabulary.truncate(MAX_VOCABULARY);
+    let index: HashMap<&str, usize> = vocabulary.iter().enumerate().map(|(i, (word, _))| (*word, i)).collect();
+
+    tokens
+        .iter()
+        .map(|words| {
+            let mut vector = vec![0.0; vocabulary.len()];
+            for word in words {
+                if let Some(&i) = index.get(word.as_str()) {
+                    vector[i] += vocabulary[i].1;
+                }
+            }
+            let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                vector.iter_mut().for_each(|x| *x /= norm);
+            }
+            vector
+        })
+        .collect()
+}
+
+/// 把 `vectors` 分为 `k` 组，返回每个向量所属的组和各组的中心。
+fn k_means(vectors: &[Vec<f32>], k: usize) -> (Vec<usize>, Vec<Vec<f32>>) {
+    let mut centroids = farthest_point_centroids(vectors, k);
+    let mut assignments = vec![usize::MAX; vectors.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (vector, assignment) in vectors.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest_centroid(vector, &centroids);
+            if *assignment != nearest {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        let dimension = centroids[0].len();
+        let mut sums = vec![vec![0.0; dimension]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for (vector, &cluster) in vectors.iter().zip(&assignments) {
+            sums[cluster].iter_mut().zip(vector).for_each(|(sum, x)| *sum += x);
+            counts[cluster] += 1;
+        }
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // 没有成员的组保留原来的中心
+            if count > 0 {
+                *centroid = sum.into_iter().map(|x| x / count as f32).collect();
+            }
+        }
+    }
+    (assignments, centroids)
+}
+
+/// 以第一个向量为起点，依次选取离已选中心最远的向量作为初始中心。
+fn farthest_point_centroids(vectors: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let mut centroids = vec![vectors[0].clone()];
+    let mut nearest: Vec<f32> = vectors.iter().map(|v| distance(v, &vectors[0])).collect();
+    while centroids.len() < k {
+        let Some((farthest, _)) = nearest
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .filter(|(_, &d)| d > 0.0)
+        else {
+            // 剩下的向量都与已选中心重合
+            break;
+        };
+        centroids.push(vectors[farthest].clone());
+        for (d, vector) in nearest.iter_mut().zip(vectors) {
+            *d = d.min(distance(vector, &vectors[farthest]));
+        }
+    }
+    centroids
+}
+
+fn nearest_centroid(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(vector, a.1).total_cmp(&distance(vector, b.1)))
+        .map_or(0, |(i, _)| i)
+}
+
+/// 欧几里得距离的平方。
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// 组内 TF-IDF 权重之和最大的几个词。
+fn describe<'a>(members: impl Iterator<Item = &'a Vec<String>>, idf: &HashMap<String, f32>) -> String {
+    let mut weights: HashMap<&str, f32> = HashMap::new();
+    for words in members {
+        for word in words {
+            *weights.entry(word.as_str()).or_default() += idf.get(word).copied().unwrap_or_default();
+        }
+    }
+    let mut terms: Vec<(&str, f32)> = weights.into_iter().collect();
+    terms.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    terms
+        .into_iter()
+        .take(DESCRIPTION_TERMS)
+        .map(|(word, _)| word)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use termichan_config::LlmConfig;
+use uuid::Uuid;
+
+use super::current_session_id;
+use crate::audit::sha256_hex;
+
+/// 一条命令历史记录。
+///
+/// 历史文件中每行保存一条记录的 JSON。新增字段需要提供 `#[serde(default)]`，
+/// 以便读取旧版本写入的历史文件。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// 记录编号，在同一历史文件中唯一且递增。
+    pub id: u64,
+    /// 生成命令的时间。
+    pub timestamp: DateTime<Utc>,
+    /// 用户的原始查询。
+    pub query: String,
+    /// LLM 生成的命令。
+    pub generated_command: String,
+    /// 命令是否已被执行。
+    #[serde(default)]
+    pub executed: bool,
+    /// 命令执行后的退出码；未执行或无法获取时为 `None`。
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// 生成命令的 LLM 服务提供商，取自生成时的 `LlmConfig::provider`。
+    #[serde(default)]
+    pub provider: String,
+    /// 生成命令的模型，取自生成时的 `LlmConfig::model`。
+    #[serde(default)]
+    pub model: String,
+    /// LLM 请求的耗时（毫秒）。
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// 请求的追踪 ID，仅在配置了 `LlmConfig::request_id_header` 时记录。
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// 通过 `HistoryManager::replay` 重新执行时，原记录的编号。
+    #[serde(default)]
+    pub replayed_from: Option<u64>,
+    /// 启用 `PromptConfig::ab_test` 时，生成命令所用的提示词变体（`"A"` 或 `"B"`）。
+    #[serde(default)]
+    pub prompt_variant: Option<String>,
+    /// 生成命令时所在的终端会话，见 `current_session_id`；旧记录为全 0 的 UUID。
+    #[serde(default)]
+    pub session_id: Uuid,
+    /// 启用 `LlmConfig::enable_embeddings` 时查询的嵌入向量，用于 `HistoryManager::semantic_search`。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_embedding: Option<Vec<f32>>,
+    /// 命令在确认提示中被编辑或由 LLM 改写时，修改前生成的命令；`generated_command` 为修改后的命令。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_command: Option<String>,
+    /// 附加说明，例如 `HistoryManager::merge` 合并时与已有记录的时间相同但命令不同。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<String>,
+}
+
+impl HistoryEntry {
+    /// 为一次新生成的命令创建记录，`id` 由 `HistoryManager::add` 分配。
+    pub fn new(query: impl Into<String>, generated_command: impl Into<String>, llm: &LlmConfig) -> Self {
+        Self {
+            id: 0,
+            timestamp: Utc::now(),
+            query: query.into(),
+            generated_command: generated_command.into(),
+            executed: false,
+            exit_code: None,
+            provider: llm.provider.clone(),
+            model: llm.model.clone(),
+            latency_ms: None,
+            request_id: None,
+            replayed_from: None,
+            prompt_variant: None,
+            session_id: current_session_id(),
+            query_embedding: None,
+            original_command: None,
+            annotation: None,
+        }
+    }
+
+    /// 命令的 SHA-256 摘要（十六进制），与审计日志中的 `command_hash` 相同，可在不泄露命令的情况下比对。
+    pub fn command_hash(&self) -> String {
+        sha256_hex(&self.generated_command)
+    }
+
+    /// 命令是否已执行且退出码为 0。
+    pub fn succeeded(&self) -> bool {
+        self.executed && self.exit_code == Some(0)
+    }
+}
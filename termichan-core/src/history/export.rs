@@ -0,0 +1,33 @@
+use termichan_config::HistoryConfig;
+
+use super::HistoryManager;
+
+/// 在被丢弃时将历史记录导出为 JSON 的守卫。
+///
+/// 由 `main` 持有，程序正常退出时触发导出。只有 `HistoryConfig::export_on_exit`
+/// 为 `true` 且设置了 `export_path` 时才会导出，格式与 `HistoryManager::export_json` 相同。
+/// 导出失败不影响退出，只记录警告。
+pub struct ExportOnExit {
+    config: HistoryConfig,
+}
+
+impl ExportOnExit {
+    /// 创建守卫，导出时按 `config` 重新加载历史文件，以包含本次运行新增的记录。
+    pub fn new(config: &HistoryConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+impl Drop for ExportOnExit {
+    fn drop(&mut self) {
+        let Some(path) = self.config.export_path.as_deref().filter(|_| self.config.export_on_exit) else {
+            return;
+        };
+        let result = HistoryManager::load(&self.config).and_then(|manager| manager.export_json(path));
+        if let Err(e) = result {
+            log::warn!("Failed to export history to {}: {e}", path.display());
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use termichan_config::Config;
+use uuid::Uuid;
+
+use super::{serialize_entries, write_atomic, HistoryEntry, HistoryError, HistoryManager};
+
+/// `HistoryManager::garbage_collect` 的清理结果。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GcReport {
+    /// 删除的文件数（配置备份和过期的响应缓存）。
+    pub files_deleted: usize,
+    /// 从历史文件中删除的记录数。
+    pub entries_pruned: usize,
+    /// 释放的磁盘空间（字节）。
+    pub bytes_freed: u64,
+}
+
+impl HistoryManager {
+    /// 清理历史记录和其他随使用增长的文件。
+    ///
+    /// - 删除最后一条记录早于 `HistoryConfig::session_retention_days` 天的会话；
+    /// - 将历史文件裁剪到 `HistoryConfig::max_entries` 条；
+    /// - 只保留最近的 `HistoryConfig::max_backup_count` 个配置备份；
+    /// - 删除超过 `LlmConfig::cache_ttl_secs` 秒未更新的响应缓存文件。
+    ///
+    /// `dry_run` 为 `true` 时只统计将要清理的内容，不修改任何文件，也不修改内存中的记录。
+    ///
+    /// # Errors
+    ///
+    /// 无法读写或删除文件、无法确定备份目录时返回 `HistoryError`。
+    pub fn garbage_collect(&mut self, config: &Config, dry_run: bool) -> Result<GcReport, HistoryError> {
+        let mut report = GcReport::default();
+
+        let mut kept = self.entries.clone();
+        if config.history.session_retention_days > 0 {
+            let cutoff = Utc::now() - TimeDelta::days(config.history.session_retention_days.into());
+            let expired = expired_sessions(&kept, cutoff);
+            kept.retain(|e| !is_expired(e, &expired, cutoff));
+        }
+        if kept.len() > self.max_entries {
+            let excess = kept.len() - self.max_entries;
+            kept.drain(..excess);
+        }
+        report.entries_pruned = self.entries.len() - kept.len();
+        if report.entries_pruned > 0 {
+            let content = serialize_entries(&kept)?;
+            report.bytes_freed += file_size(&self.path)?.saturating_sub(content.len() as u64);
+            if !dry_run {
+                write_atomic(&self.path, &content)?;
+                self.entries = kept;
+            }
+        }
+
+        let backups = Config::list_backups(&Config::default_backup_dir()?)?;
+        let excess = backups.len().saturating_sub(config.history.max_backup_count);
+        // `list_backups` 按时间从旧到新排序
+        for backup in &backups[..excess] {
+            report.bytes_freed += remove_file(&backup.path, dry_run)?;
+            report.files_deleted += 1;
+        }
+
+        if let Some(cache_file) = &config.llm.cache_file {
+            let ttl = Duration::from_secs(config.llm.cache_ttl_secs);
+            if is_stale(cache_file, ttl)? {
+                report.bytes_freed += remove_file(cache_file, dry_run)?;
+                report.files_deleted += 1;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// 最后一条记录早于 `cutoff` 的会话。
+fn expired_sessions(entries: &[HistoryEntry], cutoff: DateTime<Utc>) -> Vec<Uuid> {
+    let mut last_seen: HashMap<Uuid, DateTime<Utc>> = HashMap::new();
+    for entry in entries.iter().filter(|e| !e.session_id.is_nil()) {
+        let last = last_seen.entry(entry.session_id).or_insert(entry.timestamp);
+        *last = (*last).max(entry.timestamp);
+    }
+    last_seen
+        .into_iter()
+        .filter(|&(_, last)| last < cutoff)
+        .map(|(session_id, _)| session_id)
+        .collect()
+}
+
+fn is_expired(entry: &HistoryEntry, expired_sessions: &[Uuid], cutoff: DateTime<Utc>) -> bool {
+    if entry.session_id.is_nil() {
+        // 没有会话 ID 的旧记录按各自的时间判断
+        entry.timestamp < cutoff
+    } else {
+        expired_sessions.contains(&entry.session_id)
+    }
+}
+
+/// 文件大小，文件不存在时为 0。
+fn file_size(path: &Path) -> io::Result<u64> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// 文件存在且超过 `ttl` 未修改。
+fn is_stale(path: &Path, ttl: Duration) -> io::Result<bool> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.modified()?.elapsed().is_ok_and(|age| age > ttl)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// 删除文件并返回其大小；`dry_run` 时只返回大小。
+fn remove_file(path: &Path, dry_run: bool) -> io::Result<u64> {
+    let size = file_size(path)?;
+    if !dry_run {
+        fs::remove_file(path)?;
+    }
+    Ok(size)
+}
@@ -0,0 +1,261 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{HistoryEntry, HistoryError};
+
+/// 导入记录的 `provider` 字段，便于在统计中与 LLM 生成的记录区分。
+pub(crate) const ASCIINEMA_PROVIDER: &str = "asciinema";
+
+/// 常见提示符的结尾，以及结尾前是否必须有类似提示符的前缀。
+///
+/// `#` 和 `>` 也常出现在普通输出中（注释、引用），只有前面带有主机名或路径时才视为提示符。
+const PROMPT_MARKERS: &[(&str, bool)] = &[("$ ", false), ("% ", false), ("❯ ", false), ("# ", true), ("> ", true)];
+
+/// 提示符前缀的最大字符数，超过时视为普通输出。
+const MAX_PROMPT_PREFIX_CHARS: usize = 80;
+
+/// asciinema v2 录制文件的首行。
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+    /// 录制开始的 Unix 时间戳（秒）。
+    timestamp: Option<i64>,
+}
+
+/// 从 asciinema v2 录制文件中提取在提示符后输入的命令。
+///
+/// 只使用输出事件 (`"o"`)：去掉终端控制序列后逐行查找常见的提示符结尾，
+/// 例如 `user@host:~$ `、`% `、`❯ `，提示符之后的内容即为命令。
+pub(crate) fn read_asciinema(path: &Path) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let content = fs::read_to_string(path)?;
+    let parse_error = |line: usize, source| HistoryError::Parse {
+        path: path.to_path_buf(),
+        line,
+        source,
+    };
+
+    let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let Some((index, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Header = serde_json::from_str(header).map_err(|e| parse_error(index + 1, e))?;
+    if header.version != 2 {
+        return Err(HistoryError::UnsupportedFormat(format!(
+            "asciinema recording version {} (only version 2 is supported)",
+            header.version
+        )));
+    }
+    let started = header
+        .timestamp
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .unwrap_or_else(Utc::now);
+
+    // 一个录制文件视为一个会话
+    let session_id = Uuid::new_v4();
+    let mut screen = ScreenLines::default();
+    let mut entries = Vec::new();
+    for (index, line) in lines {
+        let (time, kind, data): (f64, String, String) =
+            serde_json::from_str(line).map_err(|e| parse_error(index + 1, e))?;
+        if kind != "o" {
+            continue;
+        }
+        // 事件时间是相对录制开始的秒数，超出范围的值来自损坏或伪造的文件
+        let timestamp = TimeDelta::try_milliseconds((time * 1000.0) as i64)
+            .filter(|_| time >= 0.0)
+            .and_then(|offset| started.checked_add_signed(offset))
+            .ok_or_else(|| HistoryError::InvalidEventTime {
+                path: path.to_path_buf(),
+                line: index + 1,
+                time,
+            })?;
+        for line in screen.feed(&data) {
+            if let Some(command) = command_after_prompt(&line) {
+                entries.push(imported_entry(command, timestamp, session_id));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn imported_entry(command: &str, timestamp: DateTime<Utc>, session_id: Uuid) -> HistoryEntry {
+    HistoryEntry {
+        id: 0,
+        timestamp,
+        query: String::new(),
+        generated_command: command.to_string(),
+        executed: true,
+        // 录制中没有退出码
+        exit_code: None,
+        provider: ASCIINEMA_PROVIDER.to_string(),
+        model: String::new(),
+        latency_ms: None,
+        request_id: None,
+        replayed_from: None,
+        prompt_variant: None,
+        session_id,
+        query_embedding: None,
+        original_command: None,
+        annotation: None,
+    }
+}
+
+/// 一行输出中提示符之后的命令；不是提示符行或命令为空时返回 `None`。
+fn command_after_prompt(line: &str) -> Option<&str> {
+    let (index, marker, needs_prefix) = PROMPT_MARKERS
+        .iter()
+        .filter_map(|&(marker, needs_prefix)| line.find(marker).map(|i| (i, marker, needs_prefix)))
+        .min_by_key(|&(i, _, _)| i)?;
+    let prefix = line[..index].trim();
+    let prompt_like = if prefix.is_empty() {
+        !needs_prefix
+    } else {
+        prefix.chars().count() <= MAX_PROMPT_PREFIX_CHARS
+            && prefix.split_whitespace().next_back().is_some_and(prompt_like_word)
+    };
+    if !prompt_like {
+        return None;
+    }
+    Some(line[index + marker.len()..].trim()).filter(|command| !command.is_empty())
+}
+
+/// 提示符结尾前的最后一个词是否像主机名或路径，例如 `user@host:~`、`dir]`、`C:\`。
+///
+/// `Total: 5% done` 中的 `5` 之类的数字不算，冒号只有和字母一起出现（`host:dir`）时才算。
+fn prompt_like_word(word: &str) -> bool {
+    word.contains(['@', '~', '/', '\\', ']', ')']) || (word.contains(':') && word.contains(char::is_alphabetic))
+}
+
+/// 将终端输出还原为文本行：去掉控制序列，处理退格和回车。
+#[derive(Default)]
+struct ScreenLines {
+    current: String,
+    escape: Escape,
+}
+
+/// 控制序列的解析状态，序列可能跨越多个输出事件。
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    #[default]
+    None,
+    /// 读到 `ESC`。
+    Start,
+    /// `ESC [` 开始的 CSI 序列，以 `@` 到 `~` 之间的字符结束。
+    Csi,
+    /// `ESC ]` 开始的 OSC 序列（例如设置窗口标题），以 `BEL` 或 `ESC \` 结束。
+    Osc,
+    /// OSC 序列中读到 `ESC`。
+    OscEsc,
+}
+
+impl ScreenLines {
+    /// 处理一段输出，返回其中已结束的行。
+    fn feed(&mut self, data: &str) -> Vec<String> {
+        let mut finished = Vec::new();
+        let mut chars = data.chars().peekable();
+        while let Some(c) = chars.next() {
+            self.escape = match (self.escape, c) {
+                (Escape::None, '\x1b') => Escape::Start,
+                (Escape::None, '\n') => {
+                    finished.push(std::mem::take(&mut self.current));
+                    Escape::None
+                }
+                (Escape::None, '\r') => {
+                    // 单独的回车会让光标回到行首，之后的输出覆盖本行
+                    if chars.peek() != Some(&'\n') {
+                        self.current.clear();
+                    }
+                    Escape::None
+                }
+                (Escape::None, '\x08' | '\x7f') => {
+                    self.current.pop();
+                    Escape::None
+                }
+                (Escape::None, c) if c.is_control() => Escape::None,
+                (Escape::None, c) => {
+                    self.current.push(c);
+                    Escape::None
+                }
+                (Escape::Start, '[') => Escape::Csi,
+                (Escape::Start, ']') => Escape::Osc,
+                (Escape::Start, _) => Escape::None,
+                (Escape::Csi, '@'..='~') => Escape::None,
+                (Escape::Csi, _) => Escape::Csi,
+                (Escape::Osc, '\x07') => Escape::None,
+                (Escape::Osc, '\x1b') => Escape::OscEsc,
+                (Escape::Osc, _) => Escape::Osc,
+                (Escape::OscEsc, _) => Escape::None,
+            };
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(name: &str, events: &[&str]) -> Vec<HistoryEntry> {
+        let path = std::env::temp_dir().join(format!("termichan-{name}-{}.cast", std::process::id()));
+        let mut content = String::from("{\"version\": 2, \"width\": 80, \"height\": 24, \"timestamp\": 1700000000}\n");
+        for event in events {
+            content.push_str(event);
+            content.push('\n');
+        }
+        fs::write(&path, content).unwrap();
+        let result = read_asciinema(&path);
+        fs::remove_file(&path).unwrap();
+        result.unwrap()
+    }
+
+    #[test]
+    fn recognizes_prompts() {
+        assert_eq!(command_after_prompt("user@host:~/src$ cargo build"), Some("cargo build"));
+        assert_eq!(command_after_prompt("[user@host src]$ ls -la"), Some("ls -la"));
+        assert_eq!(command_after_prompt("(venv) user@host:~$ pip list"), Some("pip list"));
+        assert_eq!(command_after_prompt("$ make"), Some("make"));
+        assert_eq!(command_after_prompt("~/src % git status"), Some("git status"));
+        assert_eq!(command_after_prompt("root@box:/# apt update"), Some("apt update"));
+        assert_eq!(command_after_prompt("user@host:~$ "), None);
+    }
+
+    #[test]
+    fn ignores_output_that_looks_like_a_prompt() {
+        assert_eq!(command_after_prompt("Total: 5% done"), None);
+        assert_eq!(command_after_prompt("Downloading: 42% complete"), None);
+        assert_eq!(command_after_prompt("Cost: 3 $ per month"), None);
+        assert_eq!(command_after_prompt("# a shell comment"), None);
+        assert_eq!(command_after_prompt("> quoted text"), None);
+    }
+
+    #[test]
+    fn imports_commands_with_event_times() {
+        let entries = recording(
+            "import",
+            &[
+                r#"[0.5, "o", "user@host:~$ ls\r\n"]"#,
+                r#"[0.6, "o", "Total: 5% done\r\n"]"#,
+                r#"[1.0, "i", "pwd\r"]"#,
+                r#"[2.25, "o", "user@host:~$ pwd\r\n/home/user\r\n"]"#,
+            ],
+        );
+        let commands: Vec<&str> = entries.iter().map(|e| e.generated_command.as_str()).collect();
+        assert_eq!(commands, ["ls", "pwd"]);
+        assert_eq!(entries[1].timestamp.timestamp_millis(), 1_700_000_002_250);
+        assert_eq!(entries[0].session_id, entries[1].session_id);
+    }
+
+    #[test]
+    fn rejects_out_of_range_event_times() {
+        let path = std::env::temp_dir().join(format!("termichan-bad-time-{}.cast", std::process::id()));
+        for time in ["1e300", "-5"] {
+            fs::write(&path, format!("{{\"version\": 2}}\n[{time}, \"o\", \"$ ls\\n\"]\n")).unwrap();
+            let err = read_asciinema(&path).unwrap_err();
+            assert!(matches!(err, HistoryError::InvalidEventTime { line: 2, .. }), "{time}: {err}");
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::{read_entries, HistoryError, HistoryManager};
+
+/// `HistoryManager::merge` 的合并结果。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MergeReport {
+    /// 添加的记录数，包括 `conflicts`。
+    pub added: usize,
+    /// 时间和命令都与已有记录相同而跳过的记录数。
+    pub duplicates_skipped: usize,
+    /// 与已有记录时间相同但命令不同的记录数，这些记录仍会添加并带有 `annotation`。
+    pub conflicts: usize,
+}
+
+impl HistoryManager {
+    /// 把另一个历史文件（例如在其他机器上生成的）中的记录合并进来，按时间排序后保存。
+    ///
+    /// 时间和命令都相同的记录视为重复，不再添加。时间相同但命令不同的记录视为冲突，
+    /// 两条都保留，新添加的记录在 `annotation` 中注明。添加的记录分配新的编号，
+    /// `replayed_from` 指向同一文件中一并添加的记录时随之更新，否则清空。
+    ///
+    /// # Errors
+    ///
+    /// `other_path` 不存在、无法读取或某行不是有效的记录，以及无法保存时返回 `HistoryError`。
+    pub fn merge(&mut self, other_path: &Path) -> Result<MergeReport, HistoryError> {
+        // 文件不存在时 `read_entries` 返回空记录，这里需要报告错误
+        fs::metadata(other_path)?;
+        let other = read_entries(other_path)?;
+
+        let mut seen: HashSet<(DateTime<Utc>, String)> = self
+            .entries
+            .iter()
+            .map(|e| (e.timestamp, e.generated_command.clone()))
+            .collect();
+        let mut timestamps: HashSet<DateTime<Utc>> = self.entries.iter().map(|e| e.timestamp).collect();
+        let mut report = MergeReport::default();
+        let mut new_ids = HashMap::new();
+        let mut added = HashSet::new();
+        for mut entry in other {
+            if !seen.insert((entry.timestamp, entry.generated_command.clone())) {
+                report.duplicates_skipped += 1;
+                continue;
+            }
+            if !timestamps.insert(entry.timestamp) {
+                report.conflicts += 1;
+                entry.annotation = Some(format!(
+                    "Merged from {}: another entry has the same timestamp",
+                    other_path.display()
+                ));
+            }
+            let old_id = entry.id;
+            let new_id = self.add(entry);
+            new_ids.insert(old_id, new_id);
+            added.insert(new_id);
+            report.added += 1;
+        }
+
+        for entry in self.entries.iter_mut().filter(|e| added.contains(&e.id)) {
+            entry.replayed_from = entry.replayed_from.and_then(|id| new_ids.get(&id).copied());
+        }
+        // 稳定排序，时间相同的记录保持原有记录在前
+        self.entries.sort_by_key(|e| e.timestamp);
+        self.save()?;
+        Ok(report)
+    }
+}
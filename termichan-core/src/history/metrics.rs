@@ -0,0 +1,87 @@
+use std::io::Write;
+use termichan_config::ImpactClass;
+
+use super::{HistoryError, HistoryManager};
+use crate::safety::CommandClassifier;
+
+impl HistoryManager {
+    /// 以 Prometheus 文本格式写出历史记录的统计指标，供监控系统抓取。
+    ///
+    /// 包括记录总数、成功率、平均耗时、各提供商生成的命令数，以及被 `CommandClassifier::impact`
+    /// 归为 `Destructive` 的命令数。历史记录中不保存确认时的安全配置，因此这里不考虑
+    /// `SecurityConfig::dangerous_commands`。没有数据的指标写为 `NaN`。
+    ///
+    /// # Errors
+    ///
+    /// 写入 `writer` 失败时返回 `HistoryError::Io`。
+    pub fn export_prometheus_metrics(&self, writer: &mut dyn Write) -> Result<(), HistoryError> {
+        let stats = self.statistics();
+        let gauge = |value: Option<f64>| value.map_or_else(|| "NaN".to_string(), |v| v.to_string());
+
+        metric_header(
+            writer,
+            "termichan_history_total_entries",
+            "gauge",
+            "Number of entries in the history file.",
+        )?;
+        writeln!(writer, "termichan_history_total_entries {}", stats.total_entries)?;
+
+        metric_header(
+            writer,
+            "termichan_history_success_rate",
+            "gauge",
+            "Share of executed commands that exited with status 0.",
+        )?;
+        writeln!(writer, "termichan_history_success_rate {}", gauge(stats.success_rate))?;
+
+        metric_header(
+            writer,
+            "termichan_history_avg_latency_ms",
+            "gauge",
+            "Average LLM request latency in milliseconds.",
+        )?;
+        writeln!(writer, "termichan_history_avg_latency_ms {}", gauge(stats.avg_latency_ms))?;
+
+        metric_header(
+            writer,
+            "termichan_history_commands_by_provider",
+            "counter",
+            "Number of commands generated by each LLM provider.",
+        )?;
+        let mut providers: Vec<_> = stats.provider_breakdown.iter().collect();
+        providers.sort_by(|a, b| a.0.cmp(b.0));
+        for (provider, provider_stats) in providers {
+            writeln!(
+                writer,
+                "termichan_history_commands_by_provider{{provider=\"{}\"}} {}",
+                escape_label(provider),
+                provider_stats.request_count
+            )?;
+        }
+
+        let dangerous = self
+            .entries
+            .iter()
+            .filter(|e| CommandClassifier::impact(&e.generated_command) == ImpactClass::Destructive)
+            .count();
+        metric_header(
+            writer,
+            "termichan_history_dangerous_commands_total",
+            "counter",
+            "Number of generated commands classified as destructive.",
+        )?;
+        writeln!(writer, "termichan_history_dangerous_commands_total {dangerous}")?;
+        Ok(())
+    }
+}
+
+fn metric_header(writer: &mut dyn Write, name: &str, kind: &str, help: &str) -> Result<(), HistoryError> {
+    writeln!(writer, "# HELP {name} {help}")?;
+    writeln!(writer, "# TYPE {name} {kind}")?;
+    Ok(())
+}
+
+/// 按 Prometheus 文本格式转义标签值中的反斜杠、双引号和换行。
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,318 @@
+mod anonymize;
+mod cluster;
+mod entry;
+mod export;
+mod gc;
+mod import;
+mod merge;
+mod metrics;
+mod patterns;
+mod retention;
+mod semantic;
+mod session;
+mod stats;
+mod watch;
+
+pub use cluster::CommandCluster;
+pub use entry::HistoryEntry;
+pub use export::ExportOnExit;
+pub use gc::GcReport;
+pub use merge::MergeReport;
+pub use patterns::CommandPattern;
+pub use semantic::ScoredEntry;
+pub use session::{current_session_id, SessionSummary, SESSION_ID_ENV};
+pub use stats::{CrossSessionStats, HistoryStats, ProviderStats};
+pub use watch::WatchHandle;
+
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use termichan_config::{ConfigError, HistoryConfig, RetentionPolicy};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// 历史记录相关的错误类型。
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("History I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed history entry at {path}:{line}: {source}")]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Failed to serialize history entry: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Unsupported import format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Invalid event time {time} at {path}:{line}")]
+    InvalidEventTime { path: PathBuf, line: usize, time: f64 },
+    #[error("Failed to locate config backups: {0}")]
+    Config(#[from] ConfigError),
+    #[error("Failed to watch history file: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+/// 管理命令历史记录的加载、追加和保存。
+///
+/// 历史文件为 JSON Lines 格式，每行一条 `HistoryEntry`。
+pub struct HistoryManager {
+    path: PathBuf,
+    max_entries: usize,
+    suggestion_threshold: f64,
+    retention_policy: RetentionPolicy,
+    entries: Vec<HistoryEntry>,
+    /// `watch` 的读取位置，没有在监视时为 `None`。
+    tail: Arc<Mutex<Option<watch::Tail>>>,
+}
+
+impl HistoryManager {
+    /// 从 `HistoryConfig::file_path` 加载历史记录，文件不存在时从空记录开始。
+    ///
+    /// # Errors
+    ///
+    /// 无法读取文件或某行不是有效的记录时返回 `HistoryError`。
+    pub fn load(config: &HistoryConfig) -> Result<Self, HistoryError> {
+        let entries = read_entries(&config.file_path)?;
+        Ok(Self {
+            path: config.file_path.clone(),
+            max_entries: config.max_entries,
+            suggestion_threshold: config.suggestion_threshold,
+            retention_policy: config.retention_policy.clone(),
+            entries,
+            tail: Arc::default(),
+        })
+    }
+
+    /// 历史文件路径。
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 所有记录，按添加顺序排列。
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// 添加一条记录并为其分配新的编号，返回该编号。
+    ///
+    /// 记录只保存在内存中，需要调用 `save` 写入文件。
+    pub fn add(&mut self, mut entry: HistoryEntry) -> u64 {
+        entry.id = self.entries.iter().map(|e| e.id).max().map_or(1, |id| id + 1);
+        let id = entry.id;
+        self.entries.push(entry);
+        id
+    }
+
+    /// 按编号查找记录。
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// 基于编号为 `entry_id` 的记录创建一条新的重放记录，不存在时返回 `None`。
+    ///
+    /// 新记录沿用原记录的查询、命令和服务信息，`replayed_from` 指向原记录，
+    /// 执行状态被重置。调用方可以在确认前修改 `generated_command`，再通过 `add` 保存。
+    pub fn replay(&self, entry_id: u64) -> Result<Option<HistoryEntry>, HistoryError> {
+        Ok(self.get(entry_id).map(|original| HistoryEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            executed: false,
+            exit_code: None,
+            latency_ms: None,
+            request_id: None,
+            replayed_from: Some(original.id),
+            session_id: current_session_id(),
+            original_command: None,
+            annotation: None,
+            ..original.clone()
+        }))
+    }
+
+    /// 与 `query` 最相似且执行成功（退出码为 0）的记录，用于在请求 LLM 之前复用历史命令。
+    ///
+    /// 比较忽略大小写和首尾空白的 Jaro-Winkler 相似度，低于 `HistoryConfig::suggestion_threshold`
+    /// 时返回 `None`；相似度相同时取最近的记录。
+    pub fn suggest_from_history(&self, query: &str) -> Option<HistoryEntry> {
+        let query = query.trim().to_lowercase();
+        self.entries
+            .iter()
+            .filter(|e| e.exit_code == Some(0) && !e.query.trim().is_empty())
+            .map(|e| (strsim::jaro_winkler(&query, &e.query.trim().to_lowercase()), e))
+            .filter(|&(similarity, _)| similarity >= self.suggestion_threshold)
+            // `max_by` 在相等时返回最后一个，即最近的记录
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, e)| e.clone())
+    }
+
+    /// 从 asciinema v2 录制文件中导入在提示符后输入的命令，返回导入的记录数。
+    ///
+    /// 导入的记录标记为已执行，退出码未知，`provider` 为 `"asciinema"`。
+    /// 与 `add` 一样只保存在内存中，需要调用 `save` 写入文件。
+    ///
+    /// # Errors
+    ///
+    /// 无法读取文件、某行不是有效的 JSON 或录制文件不是 v2 格式时返回 `HistoryError`。
+    pub fn import_from_asciinema(&mut self, path: &Path) -> Result<usize, HistoryError> {
+        let imported = import::read_asciinema(path)?;
+        let count = imported.len();
+        for entry in imported {
+            self.add(entry);
+        }
+        Ok(count)
+    }
+
+    /// 属于会话 `session_id` 的记录，按添加顺序排列。
+    pub fn session_entries(&self, session_id: Uuid) -> Vec<&HistoryEntry> {
+        self.entries.iter().filter(|e| e.session_id == session_id).collect()
+    }
+
+    /// 所有会话的记录数和时间范围，按开始时间排列。
+    pub fn sessions(&self) -> Vec<SessionSummary> {
+        SessionSummary::from_entries(&self.entries)
+    }
+
+    /// 将记录写回历史文件：先应用 `HistoryConfig::retention_policy`，再只保留最近的 `max_entries` 条。
+    ///
+    /// 先写入临时文件再重命名，避免写入中断时损坏历史文件。
+    pub fn save(&mut self) -> Result<(), HistoryError> {
+        let policy = self.retention_policy.clone();
+        let purged = self.apply_retention_policy(&policy);
+        if purged > 0 {
+            log::debug!("Retention policy removed {purged} history entries");
+        }
+        if self.entries.len() > self.max_entries {
+            let excess = self.entries.len() - self.max_entries;
+            self.entries.drain(..excess);
+        }
+        write_entries(&self.path, &self.entries)?;
+        // 本进程写入的记录不需要通知 `watch` 的回调
+        if let Some(tail) = self.tail.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            tail.skip_to_end(&self.path);
+        }
+        Ok(())
+    }
+
+    /// 将所有记录以 JSON 数组的形式导出到 `path`。
+    ///
+    /// 与 `save` 一样先写入临时文件再重命名，导出中断时不会留下不完整的文件。
+    pub fn export_json(&self, path: &Path) -> Result<(), HistoryError> {
+        let mut content = serde_json::to_string_pretty(&self.entries)?;
+        content.push('\n');
+        write_atomic(path, &content)
+    }
+
+    /// 生成时间在 `from` 和 `to` 之间（包括两端）的记录，按添加顺序排列。
+    pub fn search_by_date_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<&HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|e| (from..=to).contains(&e.timestamp))
+            .collect()
+    }
+
+    /// 请求耗时不低于 `threshold_ms` 毫秒的记录，按耗时从长到短排列。
+    pub fn slow_queries(&self, threshold_ms: u64) -> Vec<&HistoryEntry> {
+        let mut slow: Vec<&HistoryEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.latency_ms.is_some_and(|ms| ms >= threshold_ms))
+            .collect();
+        slow.sort_by_key(|e| Reverse(e.latency_ms));
+        slow
+    }
+
+    /// 所有记录的统计信息。
+    pub fn statistics(&self) -> HistoryStats {
+        HistoryStats::from_entries(&self.entries)
+    }
+
+    /// 历史文件中所有记录的长期使用统计，例如最长连续使用天数和最近 30 天的日均命令数。
+    pub fn cross_session_statistics(&self) -> CrossSessionStats {
+        CrossSessionStats::from_entries(&self.entries, Utc::now())
+    }
+
+    /// 同一会话（相邻记录间隔不超过 30 分钟）中反复出现（超过 3 次）的命令序列，按出现次数排列。
+    pub fn analyze_patterns(&self) -> Vec<CommandPattern> {
+        CommandPattern::from_entries(&self.entries)
+    }
+
+    /// 仅统计由 `provider` 生成的记录（不区分大小写）。
+    pub fn provider_statistics(&self, provider: &str) -> HistoryStats {
+        HistoryStats::from_entries(
+            self.entries
+                .iter()
+                .filter(|e| e.provider.eq_ignore_ascii_case(provider)),
+        )
+    }
+
+    /// 按提示词变体分组的统计，只包含启用 `PromptConfig::ab_test` 时生成的记录，按变体名排序。
+    pub fn variant_statistics(&self) -> BTreeMap<String, HistoryStats> {
+        let mut by_variant: BTreeMap<&str, Vec<&HistoryEntry>> = BTreeMap::new();
+        for entry in &self.entries {
+            if let Some(variant) = &entry.prompt_variant {
+                by_variant.entry(variant.as_str()).or_default().push(entry);
+            }
+        }
+        by_variant
+            .into_iter()
+            .map(|(variant, entries)| (variant.to_string(), HistoryStats::from_entries(entries)))
+            .collect()
+    }
+}
+
+/// 读取 JSON Lines 格式的历史文件，忽略空行。
+pub(crate) fn read_entries(path: &Path) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|source| HistoryError::Parse {
+            path: path.to_path_buf(),
+            line: index + 1,
+            source,
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// 原子地将记录写入 JSON Lines 文件：先写临时文件，再重命名。
+pub(crate) fn write_entries(path: &Path, entries: &[HistoryEntry]) -> Result<(), HistoryError> {
+    write_atomic(path, &serialize_entries(entries)?)
+}
+
+/// 将记录序列化为 JSON Lines，每行一条。
+fn serialize_entries(entries: &[HistoryEntry]) -> Result<String, HistoryError> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry)?);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// 先写入同目录下的临时文件再重命名，保证 `path` 要么是旧内容要么是完整的新内容。
+fn write_atomic(path: &Path, content: &str) -> Result<(), HistoryError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
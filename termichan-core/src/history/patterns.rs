@@ -0,0 +1,159 @@
+use chrono::Duration;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::HistoryEntry;
+
+/// 相邻两条记录间隔不超过此时长时属于同一会话。
+const SESSION_GAP_MINUTES: i64 = 30;
+/// 分析的命令序列长度。
+const NGRAM_LENGTHS: [usize; 2] = [2, 3];
+/// 序列出现超过此次数才视为规律。
+const MIN_FREQUENCY: usize = 3;
+
+/// 历史记录中反复出现的命令序列。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandPattern {
+    /// 以 ` && ` 连接的命令模板，参数替换为 `<dir>`、`<arg>`，例如 `cd <dir> && git status`。
+    pub template: String,
+    /// 序列出现的次数。
+    pub frequency: usize,
+    /// 封装该序列的 shell 别名或函数定义。
+    pub suggested_alias: String,
+}
+
+impl CommandPattern {
+    pub(crate) fn from_entries(entries: &[HistoryEntry]) -> Vec<Self> {
+        let mut counts: HashMap<Vec<String>, usize> = HashMap::new();
+        for session in sessions(entries) {
+            let templates: Vec<String> = session
+                .iter()
+                .map(|e| template(&e.generated_command))
+                .collect();
+            for n in NGRAM_LENGTHS {
+                for window in templates.windows(n) {
+                    // 同一命令的重复不是有意义的序列
+                    if window.windows(2).all(|pair| pair[0] == pair[1]) {
+                        continue;
+                    }
+                    *counts.entry(window.to_vec()).or_default() += 1;
+                }
+            }
+        }
+
+        let frequent: Vec<(Vec<String>, usize)> = counts
+            .into_iter()
+            .filter(|(_, count)| *count > MIN_FREQUENCY)
+            .collect();
+        // 被同样频繁的更长序列包含的序列是多余的
+        let mut patterns: Vec<Self> = frequent
+            .iter()
+            .filter(|(sequence, count)| {
+                !frequent.iter().any(|(longer, longer_count)| {
+                    longer.len() > sequence.len()
+                        && longer_count >= count
+                        && longer
+                            .windows(sequence.len())
+                            .any(|w| w == sequence.as_slice())
+                })
+            })
+            .map(|(sequence, count)| Self {
+                template: sequence.join(" && "),
+                frequency: *count,
+                suggested_alias: suggest_alias(sequence),
+            })
+            .collect();
+        patterns.sort_by(|a, b| {
+            b.frequency
+                .cmp(&a.frequency)
+                .then(a.template.cmp(&b.template))
+        });
+        patterns
+    }
+}
+
+/// 按时间间隔将记录分成会话。
+fn sessions(entries: &[HistoryEntry]) -> Vec<&[HistoryEntry]> {
+    let gap = Duration::minutes(SESSION_GAP_MINUTES);
+    let mut sessions = Vec::new();
+    let mut start = 0;
+    for i in 1..=entries.len() {
+        let split = i == entries.len() || entries[i].timestamp - entries[i - 1].timestamp > gap;
+        if split {
+            sessions.push(&entries[start..i]);
+            start = i;
+        }
+    }
+    sessions
+}
+
+/// 命令的模板：保留程序名、子命令和选项，其余参数替换为占位符。
+///
+/// 例如 `git commit -m "fix"` 变为 `git commit -m <arg>`，`cd src/app` 变为 `cd <dir>`。
+fn template(command: &str) -> String {
+    let mut tokens = command.split_whitespace();
+    let Some(program) = tokens.next() else {
+        return String::new();
+    };
+    let placeholder = if program == "cd" { "<dir>" } else { "<arg>" };
+
+    let mut parts = vec![program.to_string()];
+    for (position, token) in tokens.enumerate() {
+        let is_subcommand = position == 0
+            && token.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+            && program != "cd";
+        let part = if token.starts_with('-') || is_subcommand {
+            token
+        } else {
+            placeholder
+        };
+        // 连续的参数合并为一个占位符
+        if part != placeholder || parts.last().is_none_or(|last| last != placeholder) {
+            parts.push(part.to_string());
+        }
+    }
+    parts.join(" ")
+}
+
+/// 为命令序列生成别名；含有参数时生成按顺序接收参数的函数。
+///
+/// 名称由每条命令的程序名和子命令的首字母组成，例如 `cd <dir> && git status` 得到 `cgs`。
+fn suggest_alias(sequence: &[String]) -> String {
+    let name: String = sequence
+        .iter()
+        .flat_map(|command| {
+            command
+                .split_whitespace()
+                .take(2)
+                .filter(|word| !word.starts_with(['-', '<']))
+                .filter_map(|word| word.chars().find(char::is_ascii_alphabetic))
+                .collect::<Vec<_>>()
+        })
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    let mut argument = 0;
+    let body = sequence
+        .iter()
+        .map(|command| {
+            command
+                .split_whitespace()
+                .map(|word| match word {
+                    "<dir>" | "<arg>" => {
+                        argument += 1;
+                        format!("\"${argument}\"")
+                    }
+                    word => word.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(" && ");
+
+    if argument == 0 {
+        format!("alias {name}='{body}'")
+    } else {
+        format!("{name}() {{ {body}; }}")
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{TimeDelta, Utc};
+use termichan_config::RetentionPolicy;
+
+use super::{HistoryEntry, HistoryManager};
+
+impl HistoryManager {
+    /// 删除违反保留策略的记录，返回删除的记录数。
+    ///
+    /// 依次删除早于 `max_age_days` 天的记录、按退出码删除 `purge_on_success` 或
+    /// `purge_on_failure` 指定的记录，最后只保留最近的 `max_entries` 条。
+    /// 未执行或退出码未知的记录不受 `purge_on_*` 影响。
+    ///
+    /// 只修改内存中的记录，需要调用 `save` 写入文件。
+    pub fn apply_retention_policy(&mut self, policy: &RetentionPolicy) -> usize {
+        let before = self.entries.len();
+        let cutoff = policy
+            .max_age_days
+            .map(|days| Utc::now() - TimeDelta::days(days.into()));
+        self.entries
+            .retain(|e| cutoff.is_none_or(|cutoff| e.timestamp >= cutoff) && !purged_by_exit_code(e, policy));
+        if let Some(max_entries) = policy.max_entries {
+            let excess = self.entries.len().saturating_sub(max_entries);
+            self.entries.drain(..excess);
+        }
+        before - self.entries.len()
+    }
+}
+
+fn purged_by_exit_code(entry: &HistoryEntry, policy: &RetentionPolicy) -> bool {
+    match entry.exit_code {
+        Some(0) => policy.purge_on_success,
+        Some(_) => policy.purge_on_failure,
+        None => false,
+    }
+}
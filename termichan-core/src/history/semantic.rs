@@ -0,0 +1,52 @@
+use super::{HistoryEntry, HistoryManager};
+
+/// 语义搜索的一条结果。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredEntry {
+    /// 匹配的记录。
+    pub entry: HistoryEntry,
+    /// 与查询的余弦相似度，范围为 -1 到 1，越大越相似。
+    pub score: f32,
+}
+
+impl HistoryManager {
+    /// 按查询嵌入向量的余弦相似度返回最相似的 `k` 条记录，从高到低排列。
+    ///
+    /// `query_embedding` 由调用方通过 `LlmService::embed` 生成，需要与记录使用同一嵌入模型。
+    /// 只比较带有 `query_embedding` 且维度相同的记录；相似度相同时较新的记录在前。
+    pub fn semantic_search(&self, query_embedding: &[f32], k: usize) -> Vec<ScoredEntry> {
+        let mut scored: Vec<(f32, &HistoryEntry)> = self
+            .entries
+            .iter()
+            .rev()
+            .filter_map(|entry| {
+                let embedding = entry.query_embedding.as_deref()?;
+                Some((cosine_similarity(query_embedding, embedding)?, entry))
+            })
+            .collect();
+        // 稳定排序，相同分数保持从新到旧的顺序
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(score, entry)| ScoredEntry {
+                entry: entry.clone(),
+                score,
+            })
+            .collect()
+    }
+}
+
+/// 两个向量的余弦相似度；维度不同或任一向量为零向量时返回 `None`。
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a * norm_b))
+}
@@ -0,0 +1,118 @@
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage, ChatCompletionRequestUserMessageArgs,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use super::{HistoryEntry, HistoryManager};
+
+/// shell 集成导出终端会话 ID 的环境变量。
+pub const SESSION_ID_ENV: &str = "TERMICHAN_SESSION_ID";
+
+/// 当前进程所属的会话 ID。
+///
+/// 取自 `TERMICHAN_SESSION_ID`，未设置或不是有效的 UUID 时为本进程随机生成一个，
+/// 同一进程内多次调用返回相同的值。
+pub fn current_session_id() -> Uuid {
+    static SESSION_ID: OnceLock<Uuid> = OnceLock::new();
+    *SESSION_ID.get_or_init(|| {
+        env::var(SESSION_ID_ENV)
+            .ok()
+            .and_then(|id| Uuid::parse_str(id.trim()).ok())
+            .unwrap_or_else(Uuid::new_v4)
+    })
+}
+
+/// 一个会话的概况。
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    /// 会话 ID。
+    pub session_id: Uuid,
+    /// 会话中的记录数。
+    pub entry_count: usize,
+    /// 会话中最早一条记录的时间。
+    pub first: DateTime<Utc>,
+    /// 会话中最近一条记录的时间。
+    pub last: DateTime<Utc>,
+}
+
+impl SessionSummary {
+    /// 按会话分组，按开始时间排列；不包括没有会话 ID 的旧记录。
+    pub(crate) fn from_entries(entries: &[HistoryEntry]) -> Vec<Self> {
+        let mut sessions: HashMap<Uuid, SessionSummary> = HashMap::new();
+        for entry in entries.iter().filter(|e| !e.session_id.is_nil()) {
+            sessions
+                .entry(entry.session_id)
+                .and_modify(|s| {
+                    s.entry_count += 1;
+                    s.first = s.first.min(entry.timestamp);
+                    s.last = s.last.max(entry.timestamp);
+                })
+                .or_insert(SessionSummary {
+                    session_id: entry.session_id,
+                    entry_count: 1,
+                    first: entry.timestamp,
+                    last: entry.timestamp,
+                });
+        }
+        let mut sessions: Vec<SessionSummary> = sessions.into_values().collect();
+        sessions.sort_by_key(|s| s.first);
+        sessions
+    }
+}
+
+impl HistoryManager {
+    /// 会话 `session_id` 最近 `n` 条记录组成的多轮对话上下文，按时间顺序排列。
+    ///
+    /// 每条记录转换为一对消息：`user` 为查询，`assistant` 为生成的命令，
+    /// 放在系统提示词和本次查询之间，让模型延续同一会话中的前几轮问答。
+    /// 没有会话 ID 的旧记录和没有生成命令的记录不计入。
+    pub fn build_context_from_history(&self, session_id: Uuid, n: usize) -> Vec<ChatCompletionRequestMessage> {
+        if session_id.is_nil() {
+            return Vec::new();
+        }
+        let entries: Vec<&HistoryEntry> = self
+            .session_entries(session_id)
+            .into_iter()
+            .filter(|e| !e.generated_command.trim().is_empty())
+            .collect();
+        context_messages(&entries, n)
+    }
+
+    /// 不分会话，最近 `n` 条执行成功（见 `HistoryEntry::succeeded`）的记录组成的多轮对话上下文，
+    /// 按时间顺序排列，消息格式与 `build_context_from_history` 相同。
+    pub fn build_context_from_recent_successes(&self, n: usize) -> Vec<ChatCompletionRequestMessage> {
+        let entries: Vec<&HistoryEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.succeeded() && !e.generated_command.trim().is_empty())
+            .collect();
+        context_messages(&entries, n)
+    }
+}
+
+/// 把 `entries` 中最后 `n` 条记录各转换为一对 `user`（查询）和 `assistant`（生成的命令）消息。
+fn context_messages(entries: &[&HistoryEntry], n: usize) -> Vec<ChatCompletionRequestMessage> {
+    let start = entries.len().saturating_sub(n);
+    entries[start..]
+        .iter()
+        .flat_map(|entry| {
+            [
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(entry.query.clone())
+                    .build()
+                    .expect("user message has all required fields")
+                    .into(),
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .content(entry.generated_command.clone())
+                    .build()
+                    .expect("assistant message has all required fields")
+                    .into(),
+            ]
+        })
+        .collect()
+}
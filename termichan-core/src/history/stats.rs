@@ -0,0 +1,162 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, Utc, Weekday};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::HistoryEntry;
+
+/// 历史记录的统计信息。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistoryStats {
+    /// 参与统计的记录数。
+    pub total_entries: usize,
+    /// 已执行的命令数。
+    pub executed_count: usize,
+    /// 已执行命令中退出码为 0 的比例；没有已执行命令时为 `None`。
+    pub success_rate: Option<f64>,
+    /// 有耗时记录的请求的平均耗时（毫秒）。
+    pub avg_latency_ms: Option<f64>,
+    /// 请求耗时的第 95 百分位数（毫秒）。
+    pub p95_latency_ms: Option<u64>,
+    /// 请求耗时的第 99 百分位数（毫秒）。
+    pub p99_latency_ms: Option<u64>,
+    /// 按 LLM 服务提供商分组的统计。
+    pub provider_breakdown: HashMap<String, ProviderStats>,
+}
+
+/// 单个 LLM 服务提供商的统计信息。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderStats {
+    /// 该提供商生成的命令数。
+    pub request_count: usize,
+    /// 有耗时记录的请求的平均耗时（毫秒）。
+    pub avg_latency_ms: Option<f64>,
+    /// 已执行命令中退出码为 0 的比例；没有已执行命令时为 `None`。
+    pub success_rate: Option<f64>,
+}
+
+/// 跨会话的长期使用统计，基于历史文件中的所有记录。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CrossSessionStats {
+    /// 生成过的命令总数。
+    pub total_commands: usize,
+    /// 已执行命令中退出码为 0 的比例；没有已执行命令时为 `None`。
+    pub success_rate: Option<f64>,
+    /// 生成命令最多的星期几（按本地时间）；没有记录时为 `None`。
+    pub most_productive_weekday: Option<Weekday>,
+    /// 连续每天都有记录的最长天数。
+    pub longest_daily_streak: u32,
+    /// 最近 30 天内平均每天生成的命令数。
+    pub avg_commands_per_day_30d: f64,
+    /// 不同命令数与命令总数之比，越接近 1 表示用法越多样；没有记录时为 `None`。
+    pub vocabulary_diversity: Option<f64>,
+}
+
+/// 计算 `avg_commands_per_day_30d` 的天数。
+const RECENT_DAYS: i64 = 30;
+
+impl CrossSessionStats {
+    pub(crate) fn from_entries(entries: &[HistoryEntry], now: DateTime<Utc>) -> Self {
+        let refs: Vec<&HistoryEntry> = entries.iter().collect();
+        let local_date = |entry: &HistoryEntry| entry.timestamp.with_timezone(&Local).date_naive();
+
+        let mut by_weekday: HashMap<Weekday, usize> = HashMap::new();
+        for entry in entries {
+            *by_weekday.entry(local_date(entry).weekday()).or_default() += 1;
+        }
+        // 次数相同时取一周中靠前的一天，保证结果稳定
+        let most_productive_weekday = by_weekday
+            .into_iter()
+            .max_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then(b.0.num_days_from_monday().cmp(&a.0.num_days_from_monday()))
+            })
+            .map(|(weekday, _)| weekday);
+
+        let days: BTreeSet<NaiveDate> = entries.iter().map(local_date).collect();
+        let recent_since = now - chrono::Duration::days(RECENT_DAYS);
+        let recent = entries.iter().filter(|e| e.timestamp > recent_since).count();
+        let unique: HashSet<&str> = entries.iter().map(|e| e.generated_command.trim()).collect();
+
+        Self {
+            total_commands: entries.len(),
+            success_rate: success_rate(&refs),
+            most_productive_weekday,
+            longest_daily_streak: longest_streak(&days),
+            avg_commands_per_day_30d: recent as f64 / RECENT_DAYS as f64,
+            vocabulary_diversity: (!entries.is_empty()).then(|| unique.len() as f64 / entries.len() as f64),
+        }
+    }
+}
+
+/// 已排序日期中最长的连续天数。
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> u32 {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        current = match previous {
+            Some(prev) if prev.succ_opt() == Some(day) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(day);
+    }
+    longest
+}
+
+impl HistoryStats {
+    pub(crate) fn from_entries<'a>(entries: impl IntoIterator<Item = &'a HistoryEntry>) -> Self {
+        let entries: Vec<&HistoryEntry> = entries.into_iter().collect();
+
+        let mut by_provider: HashMap<&str, Vec<&HistoryEntry>> = HashMap::new();
+        for entry in &entries {
+            by_provider.entry(&entry.provider).or_default().push(entry);
+        }
+
+        let provider_breakdown = by_provider
+            .into_iter()
+            .map(|(provider, entries)| {
+                let stats = ProviderStats {
+                    request_count: entries.len(),
+                    avg_latency_ms: average(entries.iter().filter_map(|e| e.latency_ms)),
+                    success_rate: success_rate(&entries),
+                };
+                (provider.to_string(), stats)
+            })
+            .collect();
+
+        let mut latencies: Vec<u64> = entries.iter().filter_map(|e| e.latency_ms).collect();
+        latencies.sort_unstable();
+
+        Self {
+            total_entries: entries.len(),
+            executed_count: entries.iter().filter(|e| e.executed).count(),
+            success_rate: success_rate(&entries),
+            avg_latency_ms: average(latencies.iter().copied()),
+            p95_latency_ms: percentile(&latencies, 95.0),
+            p99_latency_ms: percentile(&latencies, 99.0),
+            provider_breakdown,
+        }
+    }
+}
+
+/// 已执行记录中成功的比例。
+fn success_rate(entries: &[&HistoryEntry]) -> Option<f64> {
+    let executed = entries.iter().filter(|e| e.executed).count();
+    let succeeded = entries.iter().filter(|e| e.succeeded()).count();
+    (executed > 0).then(|| succeeded as f64 / executed as f64)
+}
+
+fn average(values: impl Iterator<Item = u64>) -> Option<f64> {
+    let (sum, count) = values.fold((0u64, 0usize), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum as f64 / count as f64)
+}
+
+/// 最近秩法计算已排序样本的百分位数。
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
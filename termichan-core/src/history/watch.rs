@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::{HistoryEntry, HistoryError, HistoryManager};
+
+/// `HistoryManager::watch` 返回的句柄，被丢弃时停止监视历史文件。
+pub struct WatchHandle {
+    _watcher: RecommendedWatcher,
+    tail: Arc<Mutex<Option<Tail>>>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        // 不再监视后，本进程的 `save` 也不必再更新读取位置
+        *self.tail.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+impl HistoryManager {
+    /// 监视历史文件，其他进程每写入一条新记录就以该记录调用一次 `callback`。
+    ///
+    /// 使用 `notify` 的默认监视器（Linux 上为 inotify，macOS 上为 FSEvents）。
+    /// 已读取到的位置保存在历史文件旁的 `.offset` 文件中，重新开始监视时不会重复报告之前处理过的记录；
+    /// 第一次监视时从文件末尾开始。文件被整体改写（例如超出 `max_entries` 后删除了最旧的记录）时，
+    /// 按上次读到的最后一条记录重新定位。本进程通过 `save` 写入的记录不会报告。
+    ///
+    /// # Errors
+    ///
+    /// 无法创建文件监视器或监视所在目录时返回 `HistoryError::Watch`。
+    pub fn watch<F>(&self, callback: F) -> Result<WatchHandle, HistoryError>
+    where
+        F: Fn(&HistoryEntry) + Send + 'static,
+    {
+        let dir = self
+            .path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        *self.tail.lock().unwrap_or_else(|e| e.into_inner()) = Some(Tail::open(&self.path));
+
+        let tail = Arc::clone(&self.tail);
+        let watched = self.path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("History watcher error: {e}");
+                    return;
+                }
+            };
+            let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.iter().any(|p| p.file_name() == watched.file_name());
+            if !relevant {
+                return;
+            }
+            // 先释放锁再调用回调，回调中可以再次读写历史记录
+            let entries = match tail.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                Some(tail) => tail.read_new(&watched),
+                None => return,
+            };
+            for entry in &entries {
+                callback(entry);
+            }
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(WatchHandle {
+            _watcher: watcher,
+            tail: Arc::clone(&self.tail),
+        })
+    }
+}
+
+/// 历史文件的读取位置。
+#[derive(Debug)]
+pub(crate) struct Tail {
+    /// 已处理内容的字节数，总是位于行首。
+    offset: usize,
+    /// `offset` 之前最后一条记录的编号和时间，用于发现文件被整体改写。
+    last: Option<(u64, DateTime<Utc>)>,
+    offset_path: PathBuf,
+}
+
+impl Tail {
+    /// 从 `.offset` 文件恢复读取位置，没有保存过或位置已失效时从文件末尾开始。
+    fn open(path: &Path) -> Self {
+        let content = read_content(path);
+        let offset_path = offset_path(path);
+        let saved = fs::read_to_string(&offset_path)
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .filter(|&offset| at_line_start(&content, offset));
+        let offset = saved.unwrap_or(content.len());
+        Self {
+            offset,
+            last: last_entry(&content[..offset]),
+            offset_path,
+        }
+    }
+
+    /// 读取 `offset` 之后完整的新记录，并保存新的读取位置。
+    fn read_new(&mut self, path: &Path) -> Vec<HistoryEntry> {
+        let content = read_content(path);
+        let start = self.resume_position(&content);
+        // 最后一行可能还没写完，留到下次读取
+        let end = content[start..].rfind('\n').map_or(start, |i| start + i + 1);
+
+        let mut entries = Vec::new();
+        for line in content[start..end].lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<HistoryEntry>(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!("Skipping malformed history entry in {}: {e}", path.display()),
+            }
+        }
+        self.offset = end;
+        self.last = entries.last().map(key).or_else(|| last_entry(&content[..end]));
+        self.persist();
+        entries
+    }
+
+    /// 本进程写入历史文件后，跳过文件中已有的全部内容。
+    pub(crate) fn skip_to_end(&mut self, path: &Path) {
+        let content = read_content(path);
+        self.offset = content.len();
+        self.last = last_entry(&content);
+        self.persist();
+    }
+
+    /// 继续读取的位置：`offset` 之前的内容没有变化时就是 `offset`，否则是上次最后一条记录之后。
+    fn resume_position(&self, content: &str) -> usize {
+        if at_line_start(content, self.offset) && last_entry(&content[..self.offset]) == self.last {
+            return self.offset;
+        }
+        let Some(last) = self.last else {
+            return 0;
+        };
+        let mut position = 0;
+        for line in content.split_inclusive('\n') {
+            position += line.len();
+            if serde_json::from_str::<HistoryEntry>(line).is_ok_and(|e| key(&e) == last) {
+                return position;
+            }
+        }
+        // 上次的最后一条记录已被删除，无法判断哪些是新记录，不报告现有内容
+        log::debug!("History file was rewritten, skipping to its end");
+        content.len()
+    }
+
+    fn persist(&self) {
+        if let Err(e) = fs::write(&self.offset_path, self.offset.to_string()) {
+            log::warn!("Failed to save history offset {}: {e}", self.offset_path.display());
+        }
+    }
+}
+
+/// 历史文件旁保存读取位置的文件，例如 `history.log.offset`。
+fn offset_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".offset");
+    path.with_file_name(name)
+}
+
+/// 文件不存在或无法读取时视为空文件。
+fn read_content(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        if e.kind() != io::ErrorKind::NotFound {
+            log::warn!("Failed to read history file {}: {e}", path.display());
+        }
+        String::new()
+    })
+}
+
+fn at_line_start(content: &str, offset: usize) -> bool {
+    offset == 0 || (offset <= content.len() && content.as_bytes()[offset - 1] == b'\n')
+}
+
+fn key(entry: &HistoryEntry) -> (u64, DateTime<Utc>) {
+    (entry.id, entry.timestamp)
+}
+
+/// `content` 中最后一条可以解析的记录。
+fn last_entry(content: &str) -> Option<(u64, DateTime<Utc>)> {
+    content
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .and_then(|line| serde_json::from_str::<HistoryEntry>(line).ok())
+        .map(|entry| key(&entry))
+}
@@ -0,0 +1,14 @@
+mod audit;
+mod history;
+mod response;
+mod safety;
+
+// 公开导出核心逻辑相关的类型，方便其他 crate 使用。
+pub use audit::{AuditEvent, AuditEventType, AuditLog};
+pub use history::{
+    current_session_id, CommandCluster, CommandPattern, CrossSessionStats, ExportOnExit, GcReport, HistoryEntry,
+    HistoryError, HistoryManager, HistoryStats, MergeReport, ProviderStats, ScoredEntry, SessionSummary, WatchHandle,
+    SESSION_ID_ENV,
+};
+pub use response::{CommandResponse, ParsedResponse, ResponseParser};
+pub use safety::{CommandClassifier, SecurityChecker, SecurityError};
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+/// 标记危险命令说明的注释前缀。
+const SAFETY_MARKER: &str = "# Be careful:";
+/// 标记命令解释的注释前缀。
+const EXPLANATION_MARKER: &str = "# Explanation:";
+
+/// 从 LLM 原始输出中解析出的结构化响应。
+///
+/// 对应 `PromptConfig` 默认系统提示词约定的输出格式：
+/// 命令本身、可选的 `# Be careful:` 安全提示和可选的 `# Explanation:` 解释。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParsedResponse {
+    /// 生成的命令，不包含安全提示注释。多行命令以换行分隔。
+    pub command: String,
+    /// `# Explanation:` 之后的解释文本。
+    pub explanation: Option<String>,
+    /// `# Be careful:` 之后的安全提示。
+    pub safety_note: Option<String>,
+    /// 命令中需要用户替换的占位符，例如 `<filename>`，按出现顺序去重。
+    pub placeholders: Vec<String>,
+    /// 从提示词缓存读取的输入 token 数，仅在 Anthropic 返回了用量时设置。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_tokens: Option<u64>,
+    /// 写入提示词缓存的输入 token 数，仅在 Anthropic 返回了用量时设置。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_tokens: Option<u64>,
+}
+
+/// 展示给用户的完整命令响应：解析结果以及生成它的服务信息。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandResponse {
+    #[serde(flatten)]
+    pub parsed: ParsedResponse,
+    /// 生成该命令的 LLM 服务提供商。
+    pub provider: String,
+    /// 生成该命令的模型。
+    pub model: String,
+    /// 从发送请求到收到完整响应所用的时间（毫秒）。
+    pub latency_ms: u64,
+}
+
+/// 将 LLM 的原始输出解析为 `ParsedResponse`。
+pub struct ResponseParser;
+
+impl ResponseParser {
+    /// 解析 LLM 的原始输出。
+    ///
+    /// 模型偶尔会违反提示词要求，用 Markdown 代码块包裹命令，解析时会去掉代码块标记。
+    pub fn parse(raw: &str) -> ParsedResponse {
+        let mut command_lines = Vec::new();
+        let mut explanation_lines: Vec<&str> = Vec::new();
+        let mut safety_note = None;
+        let mut in_explanation = false;
+
+        for line in raw.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") {
+                continue;
+            }
+
+            if let Some(rest) = trimmed.strip_prefix(EXPLANATION_MARKER) {
+                in_explanation = true;
+                explanation_lines.push(rest.trim());
+                continue;
+            }
+            if in_explanation {
+                explanation_lines.push(trimmed);
+                continue;
+            }
+
+            match line.find(SAFETY_MARKER) {
+                Some(index) => {
+                    safety_note = Some(line[index + SAFETY_MARKER.len()..].trim().to_string());
+                    let before = line[..index].trim_end();
+                    if !before.is_empty() {
+                        command_lines.push(before);
+                    }
+                }
+                None if !trimmed.is_empty() => command_lines.push(line.trim_end()),
+                None => {}
+            }
+        }
+
+        let command = command_lines.join("\n").trim().to_string();
+        let explanation = Some(explanation_lines.join("\n").trim().to_string()).filter(|e| !e.is_empty());
+        let placeholders = find_placeholders(&command);
+
+        ParsedResponse {
+            command,
+            explanation,
+            safety_note: safety_note.filter(|n| !n.is_empty()),
+            placeholders,
+            // 缓存用量不在响应文本中，由调用方按 API 返回的用量填写
+            cache_read_tokens: None,
+            cache_creation_tokens: None,
+        }
+    }
+
+    /// `command` 中需要用户替换的占位符，例如 `<filename>`，按出现顺序去重。
+    ///
+    /// 与 `ParsedResponse::placeholders` 相同，用于检查用户编辑后的命令。
+    pub fn placeholders(command: &str) -> Vec<String> {
+        find_placeholders(command)
+    }
+}
+
+/// 查找形如 `<filename>`、`<host-name>` 的占位符。
+///
+/// 尖括号内只允许字母、数字、`_` 和 `-`，以免把 `sort < in > out` 之类的重定向误认为占位符。
+fn find_placeholders(command: &str) -> Vec<String> {
+    let mut placeholders: Vec<String> = Vec::new();
+    let mut rest = command;
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(after.len());
+        if len > 0 && after[len..].starts_with('>') {
+            let placeholder = format!("<{}>", &after[..len]);
+            if !placeholders.contains(&placeholder) {
+                placeholders.push(placeholder);
+            }
+        }
+        rest = after;
+    }
+    placeholders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_found_in_order_without_duplicates() {
+        assert_eq!(
+            ResponseParser::placeholders("scp <filename> <user>@<host-name>:~ && rm <filename>"),
+            ["<filename>", "<user>", "<host-name>"]
+        );
+        assert_eq!(ResponseParser::placeholders("tar czf <archive_1>.tgz ."), ["<archive_1>"]);
+    }
+
+    #[test]
+    fn redirections_are_not_placeholders() {
+        for command in [
+            "sort < input.txt > output.txt",
+            "make 2>&1 | tee build.log",
+            "diff <(ls a) <(ls b)",
+            "cat <<EOF > notes.txt",
+            "echo '<>' | grep -c '<'",
+        ] {
+            assert!(ResponseParser::placeholders(command).is_empty(), "{command}");
+        }
+    }
+
+    #[test]
+    fn parsed_responses_list_the_command_placeholders() {
+        let parsed = ResponseParser::parse(
+            "```\nssh <user>@<host> # Be careful: replace <user> first\n```\n# Explanation: connects to <host>",
+        );
+        assert_eq!(parsed.command, "ssh <user>@<host>");
+        assert_eq!(parsed.placeholders, ["<user>", "<host>"]);
+        assert_eq!(ResponseParser::parse("ls -la").placeholders, Vec::<String>::new());
+    }
+}
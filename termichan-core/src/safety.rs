@@ -0,0 +1,614 @@
+use regex::Regex;
+use std::sync::{Arc, RwLock};
+use termichan_config::{ConfirmationMode, ImpactClass, SecurityConfig, TieredAction};
+use thiserror::Error;
+
+/// 生成的命令未通过安全检查。
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SecurityError {
+    #[error(
+        "The generated command is unusually long ({length} characters, limit {limit}). \
+         Please reformulate your query more specifically."
+    )]
+    CommandTooLong { length: usize, limit: usize },
+    #[error(
+        "The generated command spans {lines} lines (limit {limit}). \
+         Multi-line scripts are not supported in single-command mode; \
+         please ask for a single command."
+    )]
+    TooManyLines { lines: usize, limit: usize },
+}
+
+/// 可能造成不可恢复数据丢失的命令。
+const DESTRUCTIVE: &[&str] = &[
+    "rm", "rmdir", "dd", "shred", "wipefs", "fdisk", "sfdisk", "parted", "truncate", "shutdown",
+    "reboot", "halt", "poweroff", "kill", "killall", "pkill",
+];
+/// 需要提升权限或修改系统状态的命令。
+const PRIVILEGED: &[&str] = &[
+    "sudo", "su", "doas", "systemctl", "service", "mount", "umount", "chown", "chgrp", "useradd",
+    "userdel", "usermod", "passwd", "apt", "apt-get", "dnf", "yum", "pacman", "zypper", "brew",
+    "snap", "modprobe", "sysctl", "iptables", "crontab",
+];
+/// 访问网络的命令。
+const NETWORK: &[&str] = &[
+    "curl", "wget", "ssh", "scp", "sftp", "rsync", "nc", "ncat", "telnet", "ftp", "ping", "dig",
+    "nslookup", "host", "traceroute", "http", "https",
+];
+/// 只读取信息的命令，`sort -o` 等写入文件或修改系统状态的用法见 `read_only_writes`。
+const READ_ONLY: &[&str] = &[
+    "ls", "cat", "head", "tail", "less", "more", "grep", "egrep", "fgrep", "rg", "wc", "du", "df",
+    "ps", "top", "htop", "echo", "printf", "pwd", "which", "whereis", "type", "whoami", "id",
+    "date", "cal", "stat", "file", "sort", "uniq", "cut", "tr", "tree", "printenv",
+    "uname", "hostname", "uptime", "free", "lsblk", "lscpu", "history", "diff", "cmp", "basename",
+    "dirname", "realpath", "readlink", "man", "jq", "column", "nl", "true", "false", "test",
+];
+/// 只读取信息的 `git` 子命令。
+const GIT_READ_ONLY: &[&str] = &[
+    "status", "log", "diff", "show", "blame", "describe", "rev-parse", "ls-files", "shortlog",
+];
+/// 只列出分支、标签或远程仓库时才是只读的 `git` 子命令，以及列出时可用的选项。
+const GIT_LIST: &[(&str, &[&str])] = &[
+    ("branch", &["-l", "--list", "-v", "-vv", "--verbose", "-a", "--all", "-r", "--remotes", "--show-current"]),
+    ("tag", &["-l", "--list"]),
+    ("remote", &["-v", "--verbose"]),
+];
+/// 访问网络的 `git` 子命令。
+const GIT_NETWORK: &[&str] = &["clone", "fetch", "pull", "push", "ls-remote"];
+/// 执行其他命令的包装命令，以及各自需要参数的选项。
+const WRAPPERS: &[(&str, &[&str])] = &[
+    ("env", &["-u", "--unset", "-C", "--chdir"]),
+    ("sudo", &["-u", "--user", "-g", "--group", "-C", "-D", "-h", "--host", "-p", "--prompt", "-r", "-t", "-U"]),
+    ("doas", &["-u", "-C"]),
+    ("nice", &["-n", "--adjustment"]),
+    ("nohup", &[]),
+    ("time", &["-f", "--format", "-o", "--output"]),
+    ("timeout", &["-s", "--signal", "-k", "--kill-after"]),
+    ("command", &[]),
+    ("exec", &["-a"]),
+    ("stdbuf", &["-i", "-o", "-e"]),
+    ("xargs", &["-a", "-d", "-E", "-I", "-L", "-n", "-P", "-s", "--arg-file", "--delimiter", "--max-args", "--max-procs"]),
+];
+/// `find` 中执行命令的动作。
+const FIND_EXEC: &[&str] = &["-exec", "-execdir", "-ok", "-okdir"];
+/// `find` 中写入文件的动作。
+const FIND_WRITE: &[&str] = &["-fls", "-fprint", "-fprint0", "-fprintf"];
+
+/// 对生成的命令进行安全分类，并根据 `SecurityConfig` 决定执行前的处理方式。
+pub struct CommandClassifier;
+
+impl CommandClassifier {
+    /// 判断命令的影响类别。
+    ///
+    /// 基于命令名的启发式判断：命令按管道、`;`、`&&`、`||`、`&` 拆分后分别分类，取最严重的类别。
+    /// `$(...)`、反引号和 `<(...)` 中的命令同样会执行，递归分类。
+    /// `env`、`sudo`、`xargs` 等包装命令和 `find -exec` 按被执行的命令分类。
+    /// 设置 `LANG`、`LC_*`、`TZ` 以外环境变量的命令至少是 `FileModification`。
+    /// 无法识别的命令归入 `FileModification`，宁可多确认一次。
+    pub fn impact(command: &str) -> ImpactClass {
+        let substituted = substitutions(command).into_iter().map(Self::impact);
+        split_segments(command)
+            .into_iter()
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .map(classify_segment)
+            .chain(substituted)
+            .max()
+            .unwrap_or(ImpactClass::ReadOnly)
+    }
+
+    /// 检查命令是否超过 `max_command_length` 或 `max_command_lines`。
+    ///
+    /// 应在显示确认提示之前调用，未通过检查的命令不应执行。
+    pub fn check_limits(command: &str, security: &SecurityConfig) -> Result<(), SecurityError> {
+        let length = command.chars().count();
+        if length > security.max_command_length {
+            return Err(SecurityError::CommandTooLong {
+                length,
+                limit: security.max_command_length,
+            });
+        }
+        let lines = command.lines().count();
+        if lines > security.max_command_lines {
+            return Err(SecurityError::TooManyLines {
+                lines,
+                limit: security.max_command_lines,
+            });
+        }
+        Ok(())
+    }
+
+    /// 命令是否危险：以 `dangerous_commands` 中任一项开头、与 `checker` 中任一表达式匹配，
+    /// 或影响类别为 `Privileged` 及以上。
+    ///
+    /// 与确认模式无关，用于 `require_explanation_for_dangerous` 等额外保护。
+    pub fn is_dangerous(command: &str, security: &SecurityConfig, checker: &SecurityChecker) -> bool {
+        matches_dangerous(command, security, checker) || Self::impact(command) >= ImpactClass::Privileged
+    }
+
+    /// 根据确认模式决定如何处理 `command`。
+    ///
+    /// - `Always`: 总是确认。
+    /// - `Never`: 直接执行。
+    /// - `Dangerous`: 命令以 `dangerous_commands` 中任一项开头或与 `checker` 中任一表达式匹配时确认，
+    ///   否则直接执行。
+    /// - `Tiered`: 按 `impact` 的结果在 `tiered_thresholds` 中查找，未配置的类别需要确认。
+    pub fn action(command: &str, security: &SecurityConfig, checker: &SecurityChecker) -> TieredAction {
+        match security.confirmation_mode {
+            ConfirmationMode::Always => TieredAction::Confirm,
+            ConfirmationMode::Never => TieredAction::AutoExecute,
+            ConfirmationMode::Dangerous if matches_dangerous(command, security, checker) => TieredAction::Confirm,
+            ConfirmationMode::Dangerous => TieredAction::AutoExecute,
+            ConfirmationMode::Tiered => security
+                .tiered_thresholds
+                .get(&Self::impact(command))
+                .copied()
+                .unwrap_or(TieredAction::Confirm),
+        }
+    }
+}
+
+/// 运行期间可以增删的危险命令正则表达式，初始内容取自 `SecurityConfig::dangerous_patterns`。
+///
+/// 表达式只在创建和添加时编译一次。克隆的实例共享同一组表达式，一处的修改对所有持有者立即可见，
+/// 供守护进程等长时间运行的场景在不重启的情况下调整规则。
+#[derive(Debug, Clone, Default)]
+pub struct SecurityChecker {
+    patterns: Arc<RwLock<Vec<Regex>>>,
+}
+
+impl SecurityChecker {
+    /// 使用 `security.dangerous_patterns` 创建，有无效的表达式时返回第一个错误。
+    pub fn new(security: &SecurityConfig) -> Result<Self, regex::Error> {
+        let patterns = security
+            .dangerous_patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            patterns: Arc::new(RwLock::new(patterns)),
+        })
+    }
+
+    /// 添加一个表达式，已存在相同的表达式时不重复添加。
+    pub fn add_pattern(&self, pattern: &str) -> Result<(), regex::Error> {
+        let regex = Regex::new(pattern)?;
+        let mut patterns = self.patterns.write().unwrap_or_else(|e| e.into_inner());
+        if !patterns.iter().any(|p| p.as_str() == pattern) {
+            patterns.push(regex);
+        }
+        Ok(())
+    }
+
+    /// 删除与 `pattern` 完全相同的表达式，返回是否删除了表达式。
+    pub fn remove_pattern(&self, pattern: &str) -> bool {
+        let mut patterns = self.patterns.write().unwrap_or_else(|e| e.into_inner());
+        let before = patterns.len();
+        patterns.retain(|p| p.as_str() != pattern);
+        patterns.len() != before
+    }
+
+    /// 当前的表达式，按添加顺序排列。
+    pub fn patterns(&self) -> Vec<String> {
+        let patterns = self.patterns.read().unwrap_or_else(|e| e.into_inner());
+        patterns.iter().map(|p| p.as_str().to_string()).collect()
+    }
+
+    /// 命令是否与任一表达式匹配。
+    pub fn matches(&self, command: &str) -> bool {
+        let patterns = self.patterns.read().unwrap_or_else(|e| e.into_inner());
+        patterns.iter().any(|p| p.is_match(command))
+    }
+}
+
+/// 命令是否以 `dangerous_commands` 中任一项开头，或与 `checker` 中任一表达式匹配。
+fn matches_dangerous(command: &str, security: &SecurityConfig, checker: &SecurityChecker) -> bool {
+    let trimmed = command.trim_start();
+    security
+        .dangerous_commands
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix.as_str()))
+        || checker.matches(command)
+}
+
+/// 按 `|`、`;`、`&` 和换行拆分命令，`2>&1` 之类的描述符重定向中的 `&` 不拆分。
+fn split_segments(command: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+    for (i, c) in command.char_indices() {
+        let separator = match c {
+            '|' | ';' | '\n' => true,
+            '&' => !matches!(previous, Some('>' | '<')),
+            _ => false,
+        };
+        if separator {
+            segments.push(&command[start..i]);
+            start = i + c.len_utf8();
+        }
+        previous = Some(c);
+    }
+    segments.push(&command[start..]);
+    segments
+}
+
+/// 命令中 `$(...)`、反引号、`<(...)` 和 `>(...)` 包含的命令，嵌套的替换留给递归处理。
+///
+/// 不考虑引号，单引号中的 `$(` 也会被当作替换，宁可多确认一次。未闭合的替换延续到命令末尾。
+fn substitutions(command: &str) -> Vec<&str> {
+    let bytes = command.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start;
+        let end;
+        match bytes[i] {
+            b'`' => {
+                start = i + 1;
+                end = command[start..].find('`').map_or(bytes.len(), |n| start + n);
+            }
+            b'$' | b'<' | b'>' if bytes.get(i + 1) == Some(&b'(') => {
+                start = i + 2;
+                let mut depth = 1;
+                let mut close = bytes.len();
+                for (j, byte) in bytes.iter().enumerate().skip(start) {
+                    match byte {
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                close = j;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                end = close;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        found.push(&command[start..end]);
+        i = end + 1;
+    }
+    found
+}
+
+/// 包装命令执行的命令：跳过包装命令自身的选项（及其参数），`timeout` 还要跳过时长。
+fn wrapped_command<'a>(program: &str, options: &[&str], args: &[&'a str]) -> Vec<&'a str> {
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        if options.contains(arg) {
+            i += 2;
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    if program == "timeout" {
+        i += 1;
+    }
+    args.get(i..).unwrap_or_default().to_vec()
+}
+
+/// 不会改变命令行为的环境变量，其余的赋值（`LD_PRELOAD`、`GIT_PAGER` 等）可能执行任意代码。
+fn is_harmless_variable(name: &str) -> bool {
+    matches!(name, "LANG" | "TZ") || name.starts_with("LC_")
+}
+
+/// 片段中是否有写入文件的输出重定向，忽略 `>&2` 和 `> /dev/null`。
+fn writes_file(segment: &str) -> bool {
+    segment.match_indices('>').any(|(i, _)| {
+        let target = segment[i + 1..].trim_start_matches('>').trim_start();
+        !target.starts_with('&') && !target.starts_with("/dev/null")
+    })
+}
+
+fn classify_segment(segment: &str) -> ImpactClass {
+    let redirect = writes_file(segment).then_some(ImpactClass::FileModification);
+
+    let mut words = segment.split_whitespace().peekable();
+    // 开头的 `VAR=value` 环境变量赋值
+    let mut assigned = None;
+    while let Some(word) = words.next_if(|word| word.contains('=') && !word.starts_with('-')) {
+        let name = word.split('=').next().unwrap_or_default();
+        if !is_harmless_variable(name) {
+            assigned = Some(ImpactClass::FileModification);
+        }
+    }
+    let redirect = redirect.max(assigned);
+    let Some(program) = words.next() else {
+        return redirect.unwrap_or(ImpactClass::ReadOnly);
+    };
+    // `sh -c 'rm ...'` 之类引号中的命令拆分后，程序名会带上引号
+    let program = program.trim_matches(['\'', '"']);
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let args: Vec<&str> = words.collect();
+
+    let class = if DESTRUCTIVE.contains(&program)
+        || program.starts_with("mkfs")
+        || segment.contains(":(){")
+    {
+        ImpactClass::Destructive
+    } else if let Some((_, options)) = WRAPPERS.iter().find(|(name, _)| *name == program) {
+        let class = classify_segment(&wrapped_command(program, options, &args).join(" "));
+        if matches!(program, "sudo" | "doas") {
+            // 提权执行的命令至少是 Privileged，被执行的命令更严重时取其类别
+            class.max(ImpactClass::Privileged)
+        } else {
+            class
+        }
+    } else if matches!(program, "sh" | "bash" | "zsh" | "dash") {
+        // 只有 `-c` 的脚本能够分类，执行脚本文件或交互式 shell 无从判断
+        match args.iter().position(|a| *a == "-c") {
+            Some(i) => CommandClassifier::impact(&args[i + 1..].join(" ")),
+            None => ImpactClass::FileModification,
+        }
+    } else if PRIVILEGED.contains(&program) {
+        ImpactClass::Privileged
+    } else if NETWORK.contains(&program) {
+        ImpactClass::Network
+    } else if program == "git" {
+        classify_git(&args)
+    } else if program == "find" {
+        classify_find(&args)
+    } else if matches!(program, "awk" | "gawk" | "mawk" | "nawk") {
+        // awk 脚本可以用 `system()` 或管道执行命令，脚本中的字符串按命令分类
+        segment
+            .split('"')
+            .skip(1)
+            .step_by(2)
+            .map(CommandClassifier::impact)
+            .fold(ImpactClass::FileModification, ImpactClass::max)
+    } else if program == "sed" {
+        // sed 脚本可以用 `e` 执行命令、用 `w` 写文件，无法可靠地判断为只读
+        ImpactClass::FileModification
+    } else if READ_ONLY.contains(&program) && !read_only_writes(program, &args) {
+        ImpactClass::ReadOnly
+    } else {
+        ImpactClass::FileModification
+    };
+
+    redirect.map_or(class, |r| class.max(r))
+}
+
+/// `READ_ONLY` 中的命令是否因选项或参数写入文件、执行其他程序或修改系统状态。
+fn read_only_writes(program: &str, args: &[&str]) -> bool {
+    // 单个 `-` 开头的选项可以合并，例如 `sort -ro out`
+    let short = |letter: char| {
+        args.iter()
+            .any(|a| a.len() > 1 && a.starts_with('-') && !a.starts_with("--") && a[1..].contains(letter))
+    };
+    let long = |name: &str| args.iter().any(|a| a.split('=').next() == Some(name));
+    let operands = |options: &[&str]| {
+        let mut count = 0;
+        let mut i = 0;
+        while let Some(arg) = args.get(i) {
+            if options.contains(arg) {
+                i += 1;
+            } else if !arg.starts_with('-') {
+                count += 1;
+            }
+            i += 1;
+        }
+        count
+    };
+    match program {
+        "sort" => short('o') || long("--output") || long("--compress-program"),
+        "tree" => short('o'),
+        // `uniq INPUT OUTPUT` 写入第二个文件
+        "uniq" => operands(&["-f", "-s", "-w"]) >= 2,
+        // `date MMDDhhmm` 与 `date -s` 设置系统时间
+        "date" => {
+            short('s')
+                || long("--set")
+                || args.iter().any(|a| a.chars().all(|c| c.is_ascii_digit() || c == '.'))
+        }
+        // `hostname NAME` 与 `hostname -F FILE` 设置主机名
+        "hostname" => short('F') || long("--file") || operands(&[]) > 0,
+        _ => false,
+    }
+}
+
+/// `-delete` 是 `Destructive`，`-exec` 等按执行的命令分类且至少为 `FileModification`。
+fn classify_find(args: &[&str]) -> ImpactClass {
+    if args.contains(&"-delete") {
+        return ImpactClass::Destructive;
+    }
+    let written = args
+        .iter()
+        .any(|a| FIND_WRITE.contains(a))
+        .then_some(ImpactClass::FileModification);
+    args.iter()
+        .enumerate()
+        .filter(|(_, a)| FIND_EXEC.contains(a))
+        .map(|(i, _)| {
+            // `\;` 在拆分片段时被截断为 `\`
+            let executed: Vec<&str> = args[i + 1..]
+                .iter()
+                .take_while(|a| !matches!(**a, ";" | "\\;" | "';'" | "+" | "\\"))
+                .copied()
+                .collect();
+            classify_segment(&executed.join(" ")).max(ImpactClass::FileModification)
+        })
+        .chain(written)
+        .max()
+        .unwrap_or(ImpactClass::ReadOnly)
+}
+
+fn classify_git(args: &[&str]) -> ImpactClass {
+    let Some(position) = args.iter().position(|a| !a.starts_with('-')) else {
+        return ImpactClass::ReadOnly;
+    };
+    let subcommand = &args[position];
+    let rest = &args[position + 1..];
+    if let Some((_, options)) = GIT_LIST.iter().find(|(name, _)| name == subcommand) {
+        // 只有列出时是只读的，删除分支或标签会丢失提交的引用
+        let deletes = rest.iter().any(|a| matches!(*a, "-d" | "-D" | "--delete"));
+        if deletes && matches!(*subcommand, "branch" | "tag") {
+            ImpactClass::Destructive
+        } else if rest.iter().all(|a| options.contains(a)) {
+            ImpactClass::ReadOnly
+        } else {
+            ImpactClass::FileModification
+        }
+    } else if GIT_READ_ONLY.contains(subcommand) {
+        ImpactClass::ReadOnly
+    } else if GIT_NETWORK.contains(subcommand) {
+        ImpactClass::Network
+    } else if (*subcommand == "reset" && args.contains(&"--hard"))
+        || (*subcommand == "clean" && args.iter().any(|a| a.starts_with('-') && a.contains('f')))
+    {
+        ImpactClass::Destructive
+    } else {
+        ImpactClass::FileModification
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn impact(command: &str) -> ImpactClass {
+        CommandClassifier::impact(command)
+    }
+
+    #[test]
+    fn plain_commands() {
+        assert_eq!(impact("ls -la | grep foo 2>&1"), ImpactClass::ReadOnly);
+        assert_eq!(impact("git status && git log"), ImpactClass::ReadOnly);
+        assert_eq!(impact("ls > files.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("FOO=1 rm -rf build"), ImpactClass::Destructive);
+        assert_eq!(impact("curl https://example.com | sudo tee /etc/x"), ImpactClass::Privileged);
+    }
+
+    #[test]
+    fn git_refs_are_read_only_only_when_listed() {
+        assert_eq!(impact("git branch"), ImpactClass::ReadOnly);
+        assert_eq!(impact("git branch -a -v"), ImpactClass::ReadOnly);
+        assert_eq!(impact("git tag --list"), ImpactClass::ReadOnly);
+        assert_eq!(impact("git remote -v"), ImpactClass::ReadOnly);
+        assert_eq!(impact("git branch -D main"), ImpactClass::Destructive);
+        assert_eq!(impact("git branch -d feature"), ImpactClass::Destructive);
+        assert_eq!(impact("git tag -d v1"), ImpactClass::Destructive);
+        assert_eq!(impact("git branch feature"), ImpactClass::FileModification);
+        assert_eq!(impact("git tag v1"), ImpactClass::FileModification);
+        assert_eq!(impact("git remote remove origin"), ImpactClass::FileModification);
+        assert_eq!(impact("git remote set-url origin https://example.com/x.git"), ImpactClass::FileModification);
+    }
+
+    #[test]
+    fn read_only_commands_that_write() {
+        assert_eq!(impact("sort -u names.txt"), ImpactClass::ReadOnly);
+        assert_eq!(impact("sort -o sorted.txt names.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("sort -ro sorted.txt names.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("sort --output=sorted.txt names.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("sort | uniq -c"), ImpactClass::ReadOnly);
+        assert_eq!(impact("uniq -f 1 in.txt"), ImpactClass::ReadOnly);
+        assert_eq!(impact("uniq in.txt out.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("tree -L 2"), ImpactClass::ReadOnly);
+        assert_eq!(impact("tree -o tree.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("date +%F"), ImpactClass::ReadOnly);
+        assert_eq!(impact("date -s '2020-01-01 00:00'"), ImpactClass::FileModification);
+        assert_eq!(impact("date 010100002020"), ImpactClass::FileModification);
+        assert_eq!(impact("hostname -I"), ImpactClass::ReadOnly);
+        assert_eq!(impact("hostname newname"), ImpactClass::FileModification);
+    }
+
+    #[test]
+    fn environment_assignments_are_not_read_only() {
+        assert_eq!(impact("LANG=C LC_ALL=C TZ=UTC ls"), ImpactClass::ReadOnly);
+        assert_eq!(impact("GIT_PAGER='rm -rf ~' git log"), ImpactClass::FileModification);
+        assert_eq!(impact("LD_PRELOAD=./x.so ls"), ImpactClass::FileModification);
+        assert_eq!(impact("PAGER=./evil man ls"), ImpactClass::FileModification);
+        assert_eq!(impact("env PAGER=./evil man ls"), ImpactClass::FileModification);
+        assert_eq!(impact("PATH=.:$PATH; ls"), ImpactClass::FileModification);
+    }
+
+    #[test]
+    fn substitutions_are_classified() {
+        assert_eq!(impact("echo $(rm -rf ~)"), ImpactClass::Destructive);
+        assert_eq!(impact("echo `shred -u secret`"), ImpactClass::Destructive);
+        assert_eq!(impact("cat <(curl https://example.com)"), ImpactClass::Network);
+        assert_eq!(impact("echo $(echo $(reboot))"), ImpactClass::Destructive);
+        assert_eq!(impact("echo $(date) $((1 + 2))"), ImpactClass::FileModification);
+        assert_eq!(impact("echo \"today is $(date)\""), ImpactClass::ReadOnly);
+    }
+
+    #[test]
+    fn wrappers_are_classified_by_the_wrapped_command() {
+        assert_eq!(impact("env rm -rf ~"), ImpactClass::Destructive);
+        assert_eq!(impact("env -u HOME FOO=1 rm -rf ~"), ImpactClass::Destructive);
+        assert_eq!(impact("env"), ImpactClass::ReadOnly);
+        assert_eq!(impact("env LANG=C ls"), ImpactClass::ReadOnly);
+        assert_eq!(impact("sudo -u root rm -rf /"), ImpactClass::Destructive);
+        assert_eq!(impact("sudo ls"), ImpactClass::Privileged);
+        assert_eq!(impact("timeout -s KILL 5 dd if=/dev/zero of=/dev/sda"), ImpactClass::Destructive);
+        assert_eq!(impact("nohup curl https://example.com"), ImpactClass::Network);
+        assert_eq!(impact("ls | xargs -n 1 rm"), ImpactClass::Destructive);
+        assert_eq!(impact("sh -c 'rm -rf ~'"), ImpactClass::Destructive);
+        assert_eq!(impact("bash script.sh"), ImpactClass::FileModification);
+    }
+
+    #[test]
+    fn awk_and_sed_are_not_read_only() {
+        assert_eq!(impact("awk '{print $1}' file"), ImpactClass::FileModification);
+        assert_eq!(impact("awk 'BEGIN{system(\"rm -rf ~\")}'"), ImpactClass::Destructive);
+        assert_eq!(impact("sed 'e rm -rf ~' file"), ImpactClass::FileModification);
+        assert_eq!(impact("sed -n 1p file"), ImpactClass::FileModification);
+    }
+
+    #[test]
+    fn find_exec_is_classified_by_the_executed_command() {
+        assert_eq!(impact("find . -name '*.tmp' -exec rm {} \\;"), ImpactClass::Destructive);
+        assert_eq!(impact("find . -execdir shred -u {} +"), ImpactClass::Destructive);
+        assert_eq!(impact("find . -exec grep -l foo {} +"), ImpactClass::FileModification);
+        assert_eq!(impact("find . -fprint list.txt"), ImpactClass::FileModification);
+        assert_eq!(impact("find . -name '*.rs'"), ImpactClass::ReadOnly);
+        assert_eq!(impact("find . -delete"), ImpactClass::Destructive);
+    }
+
+    #[test]
+    fn tiered_mode_does_not_auto_execute_bypasses() {
+        let security = SecurityConfig {
+            confirmation_mode: ConfirmationMode::Tiered,
+            ..Default::default()
+        };
+        let checker = SecurityChecker::new(&security).unwrap();
+        let action = |command| CommandClassifier::action(command, &security, &checker);
+        assert_eq!(action("ls"), TieredAction::AutoExecute);
+        for command in ["echo $(rm -rf ~)", "env rm -rf ~", "find . -exec rm {} +"] {
+            assert_eq!(action(command), TieredAction::Reject, "{command}");
+        }
+        assert_eq!(action("awk '{print}' f"), TieredAction::Confirm);
+    }
+
+    #[test]
+    fn runtime_patterns_are_used_for_classification() {
+        let security = SecurityConfig {
+            confirmation_mode: ConfirmationMode::Dangerous,
+            dangerous_patterns: vec![r"curl .*\| *sh".to_string()],
+            ..Default::default()
+        };
+        let checker = SecurityChecker::new(&security).unwrap();
+        let shared = checker.clone();
+        let action = |command| CommandClassifier::action(command, &security, &checker);
+        assert_eq!(action("curl https://example.com/install | sh"), TieredAction::Confirm);
+        assert_eq!(action("make deploy"), TieredAction::AutoExecute);
+
+        // 克隆的实例共享表达式，例如守护进程中各连接持有的检查器
+        shared.add_pattern("^make deploy").unwrap();
+        assert_eq!(action("make deploy"), TieredAction::Confirm);
+        assert!(CommandClassifier::is_dangerous("make deploy", &security, &checker));
+        assert!(shared.add_pattern("(").is_err());
+        assert_eq!(checker.patterns(), [r"curl .*\| *sh", "^make deploy"]);
+
+        assert!(shared.remove_pattern(r"curl .*\| *sh"));
+        assert!(!shared.remove_pattern(r"curl .*\| *sh"));
+        assert_eq!(action("curl https://example.com/install | sh"), TieredAction::AutoExecute);
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use tokio::net::UnixStream;
+
+use crate::protocol::{read_message, write_message, DaemonError, DaemonRequest, DaemonResponse, Verdict};
+
+/// `termichan daemon` 的客户端。
+///
+/// 同一连接上可以依次发送多个请求。
+pub struct DaemonClient {
+    stream: UnixStream,
+}
+
+impl DaemonClient {
+    /// 连接到守护进程监听的套接字。
+    ///
+    /// # Errors
+    ///
+    /// 套接字不存在或守护进程未运行时返回 `DaemonError::Io`。
+    pub async fn connect(socket_path: &Path) -> Result<DaemonClient, DaemonError> {
+        let stream = UnixStream::connect(socket_path).await?;
+        Ok(Self { stream })
+    }
+
+    /// 发送一条请求，之后通过 `next_response` 读取响应。
+    pub async fn send(&mut self, request: &DaemonRequest) -> Result<(), DaemonError> {
+        write_message(&mut self.stream, request).await
+    }
+
+    /// 读取下一条响应消息。
+    ///
+    /// # Errors
+    ///
+    /// 守护进程关闭了连接时返回 `DaemonError::ConnectionClosed`。
+    pub async fn next_response(&mut self) -> Result<DaemonResponse, DaemonError> {
+        read_message(&mut self.stream)
+            .await?
+            .ok_or(DaemonError::ConnectionClosed)
+    }
+
+    /// 生成命令，每收到一个内容块调用一次 `on_token`，返回完整的响应内容。
+    ///
+    /// # Errors
+    ///
+    /// 守护进程返回 `error` 消息时返回 `DaemonError::Remote`。
+    pub async fn generate(
+        &mut self,
+        query: impl Into<String>,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<String, DaemonError> {
+        self.send(&DaemonRequest::Generate { query: query.into() }).await?;
+        let mut response = String::new();
+        loop {
+            match self.next_response().await? {
+                DaemonResponse::Token { text } => {
+                    on_token(&text);
+                    response.push_str(&text);
+                }
+                DaemonResponse::Done => return Ok(response),
+                DaemonResponse::Error { message } => return Err(DaemonError::Remote(message)),
+                _ => return Err(DaemonError::UnexpectedResponse),
+            }
+        }
+    }
+
+    /// 按守护进程当前的安全规则判断 `command`。
+    pub async fn check(&mut self, command: impl Into<String>) -> Result<Verdict, DaemonError> {
+        self.send(&DaemonRequest::Check { command: command.into() }).await?;
+        match self.next_response().await? {
+            DaemonResponse::Verdict(verdict) => Ok(verdict),
+            DaemonResponse::Error { message } => Err(DaemonError::Remote(message)),
+            _ => Err(DaemonError::UnexpectedResponse),
+        }
+    }
+
+    /// 向守护进程添加识别危险命令的正则表达式，返回之后生效的全部表达式。
+    ///
+    /// # Errors
+    ///
+    /// 表达式无效时返回 `DaemonError::Remote`。
+    pub async fn add_pattern(&mut self, pattern: impl Into<String>) -> Result<Vec<String>, DaemonError> {
+        self.patterns_request(&DaemonRequest::AddPattern { pattern: pattern.into() }).await
+    }
+
+    /// 从守护进程删除识别危险命令的正则表达式，返回之后生效的全部表达式。
+    ///
+    /// # Errors
+    ///
+    /// 守护进程没有该表达式时返回 `DaemonError::Remote`。
+    pub async fn remove_pattern(&mut self, pattern: impl Into<String>) -> Result<Vec<String>, DaemonError> {
+        self.patterns_request(&DaemonRequest::RemovePattern { pattern: pattern.into() }).await
+    }
+
+    /// 守护进程当前生效的识别危险命令的正则表达式。
+    pub async fn patterns(&mut self) -> Result<Vec<String>, DaemonError> {
+        self.patterns_request(&DaemonRequest::ListPatterns).await
+    }
+
+    async fn patterns_request(&mut self, request: &DaemonRequest) -> Result<Vec<String>, DaemonError> {
+        self.send(request).await?;
+        match self.next_response().await? {
+            DaemonResponse::Patterns { patterns } => Ok(patterns),
+            DaemonResponse::Error { message } => Err(DaemonError::Remote(message)),
+            _ => Err(DaemonError::UnexpectedResponse),
+        }
+    }
+}
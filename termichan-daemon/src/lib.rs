@@ -0,0 +1,14 @@
+#[cfg(unix)]
+mod client;
+mod protocol;
+#[cfg(unix)]
+mod server;
+
+// 公开导出守护进程相关的类型，方便其他 crate 使用。
+#[cfg(unix)]
+pub use client::DaemonClient;
+pub use protocol::{
+    read_message, write_message, DaemonError, DaemonRequest, DaemonResponse, Verdict, MAX_MESSAGE_LEN,
+};
+#[cfg(unix)]
+pub use server::Daemon;
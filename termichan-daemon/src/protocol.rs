@@ -0,0 +1,105 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+use termichan_config::TieredAction;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// 单条消息的最大长度（字节），防止错误的长度前缀导致分配过多内存。
+pub const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// 守护进程及其客户端的错误类型。
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    #[error("Daemon I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed daemon message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Daemon message of {0} bytes exceeds the {MAX_MESSAGE_LEN} byte limit")]
+    MessageTooLarge(usize),
+    #[error("Daemon closed the connection before the response was complete")]
+    ConnectionClosed,
+    #[error("Daemon error: {0}")]
+    Remote(String),
+    #[error("Unexpected response from daemon")]
+    UnexpectedResponse,
+}
+
+/// 客户端发送给守护进程的请求。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    /// 根据自然语言查询生成命令。
+    Generate { query: String },
+    /// 按守护进程当前的安全规则判断命令，返回 `verdict`。
+    Check { command: String },
+    /// 添加识别危险命令的正则表达式，只在守护进程运行期间有效，返回 `patterns`。
+    AddPattern { pattern: String },
+    /// 删除识别危险命令的正则表达式，返回 `patterns`。
+    RemovePattern { pattern: String },
+    /// 列出当前生效的正则表达式，返回 `patterns`。
+    ListPatterns,
+}
+
+/// `check` 请求的结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    /// 命令是否危险，见 `CommandClassifier::is_dangerous`。
+    pub dangerous: bool,
+    /// 按确认模式应如何处理命令，见 `CommandClassifier::action`。
+    pub action: TieredAction,
+}
+
+/// 守护进程返回的消息。
+///
+/// 一次 `generate` 请求对应若干 `token` 消息，最后是一条 `done` 或 `error`；
+/// 其他请求各对应一条消息，失败时为 `error`。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    /// 响应内容块，拼接后与非流式响应相同。
+    Token { text: String },
+    /// 响应已结束。
+    Done,
+    /// 请求失败。
+    Error { message: String },
+    /// `check` 请求的结果。
+    Verdict(Verdict),
+    /// 修改或列出表达式后，当前生效的表达式。
+    Patterns { patterns: Vec<String> },
+}
+
+/// 写入一条消息：4 字节大端长度前缀，随后是 JSON 内容。
+pub async fn write_message<W, T>(writer: &mut W, message: &T) -> Result<(), DaemonError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let body = serde_json::to_vec(message)?;
+    if body.len() > MAX_MESSAGE_LEN {
+        return Err(DaemonError::MessageTooLarge(body.len()));
+    }
+    writer.write_u32(body.len() as u32).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// 读取一条消息，对端在消息边界处关闭连接时返回 `None`。
+pub async fn read_message<R, T>(reader: &mut R) -> Result<Option<T>, DaemonError>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let len = match reader.read_u32().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(DaemonError::MessageTooLarge(len));
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
@@ -0,0 +1,295 @@
+use futures::StreamExt;
+use std::fs::{self, Permissions};
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use termichan_config::{Config, ConfigError, WatchHandle};
+use termichan_core::{CommandClassifier, SecurityChecker};
+use termichan_llm::{LlmError, LlmService, PromptContext};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::protocol::{read_message, write_message, DaemonError, DaemonRequest, DaemonResponse, Verdict};
+
+/// 重新预热连接的间隔，短于 HTTP 客户端回收空闲连接的时间。
+const KEEP_WARM_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 通过 Unix 域套接字提供命令生成的守护进程。
+///
+/// 所有连接共享同一个 `LlmService`，并定期预热连接，
+/// 使 shell 小部件等频繁调用的客户端不必每次重新建立 TCP/TLS 连接。
+/// 所有连接也共享同一个 `SecurityChecker`，通过 `add_pattern` 等请求修改的规则立即对所有连接生效。
+pub struct Daemon {
+    config: RwLock<Config>,
+    llm: Arc<LlmService>,
+    checker: SecurityChecker,
+    config_file: Option<PathBuf>,
+}
+
+impl Daemon {
+    /// 使用 `config` 中的提示词和安全设置，通过 `llm` 生成命令，通过 `checker` 判断命令。
+    pub fn new(config: Config, llm: LlmService, checker: SecurityChecker) -> Self {
+        Self {
+            config: RwLock::new(config),
+            llm: Arc::new(llm),
+            checker,
+            config_file: None,
+        }
+    }
+
+    /// 运行期间监视 `path` 处的配置文件，文件变化时更新提示词和 LLM 设置，无需重启守护进程。
+    ///
+    /// 配置文件中新增的 `security.dangerous_patterns` 加入运行中的规则；从文件中删除的表达式
+    /// 需要通过 `remove_pattern` 请求删除，以免覆盖运行期间添加的表达式。
+    ///
+    /// 见 `Config::watch` 和 `LlmService::reload_config`。
+    pub fn with_config_file(mut self, path: PathBuf) -> Self {
+        self.config_file = Some(path);
+        self
+    }
+
+    /// 在 `socket_path` 上监听连接，直到出错。
+    ///
+    /// 套接字只允许当前用户连接，路径上遗留的套接字会被替换，见 `bind_private`。
+    ///
+    /// # Errors
+    ///
+    /// 路径上已有其他类型的文件、无法绑定套接字或接受连接失败时返回 `DaemonError::Io`。
+    pub async fn serve(self, socket_path: &Path) -> Result<(), DaemonError> {
+        let listener = bind_private(socket_path)?;
+
+        let llm = Arc::clone(&self.llm);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(KEEP_WARM_INTERVAL);
+            loop {
+                interval.tick().await;
+                // 预热失败只影响延迟，实际请求会返回具体错误
+                let _ = llm.prewarm().await;
+            }
+        });
+
+        let daemon = Arc::new(self);
+        // 句柄在监听期间一直存在；无法监视时守护进程照常运行，只是不会热重载
+        let _watch = daemon.config_file.as_deref().and_then(|path| {
+            Daemon::watch_config(&daemon, path)
+                .inspect_err(|e| log::warn!("Config hot-reload disabled: {e}"))
+                .ok()
+        });
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let daemon = Arc::clone(&daemon);
+            tokio::spawn(async move {
+                if let Err(e) = daemon.handle_connection(stream).await {
+                    log::warn!("Daemon connection failed: {e}");
+                }
+            });
+        }
+    }
+
+    /// 监视配置文件，回调只持有守护进程的弱引用。
+    fn watch_config(daemon: &Arc<Self>, path: &Path) -> Result<WatchHandle, ConfigError> {
+        let weak = Arc::downgrade(daemon);
+        Config::watch(path, move |config| {
+            let Some(daemon) = weak.upgrade() else {
+                return;
+            };
+            if let Err(e) = daemon.llm.reload_config(config.llm.clone(), &config.network) {
+                // LLM 设置无效时不更新任何设置，避免提示词与模型不一致
+                log::warn!("Failed to apply reloaded LLM config: {e}");
+                return;
+            }
+            for pattern in &config.security.dangerous_patterns {
+                // `Config::watch` 只传递通过验证的配置，这里不会失败
+                if let Err(e) = daemon.checker.add_pattern(pattern) {
+                    log::warn!("Ignoring invalid dangerous pattern `{pattern}`: {e}");
+                }
+            }
+            *daemon.config.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        })
+    }
+
+    /// 依次处理同一连接上的请求，直到客户端关闭连接。
+    async fn handle_connection(&self, mut stream: UnixStream) -> Result<(), DaemonError> {
+        while let Some(request) = read_message::<_, DaemonRequest>(&mut stream).await? {
+            let response = match request {
+                DaemonRequest::Generate { query } => {
+                    self.generate(&mut stream, &query).await?;
+                    continue;
+                }
+                DaemonRequest::Check { command } => self.check(&command),
+                DaemonRequest::AddPattern { pattern } => match self.checker.add_pattern(&pattern) {
+                    Ok(()) => {
+                        log::info!("Added dangerous pattern `{pattern}`");
+                        self.patterns()
+                    }
+                    Err(e) => DaemonResponse::Error {
+                        message: format!("Invalid pattern `{pattern}`: {e}"),
+                    },
+                },
+                DaemonRequest::RemovePattern { pattern } => {
+                    if self.checker.remove_pattern(&pattern) {
+                        log::info!("Removed dangerous pattern `{pattern}`");
+                        self.patterns()
+                    } else {
+                        DaemonResponse::Error {
+                            message: format!("Pattern `{pattern}` is not configured"),
+                        }
+                    }
+                }
+                DaemonRequest::ListPatterns => self.patterns(),
+            };
+            write_message(&mut stream, &response).await?;
+        }
+        Ok(())
+    }
+
+    /// 按当前的安全设置和表达式判断命令。
+    fn check(&self, command: &str) -> DaemonResponse {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+        DaemonResponse::Verdict(Verdict {
+            dangerous: CommandClassifier::is_dangerous(command, &config.security, &self.checker),
+            action: CommandClassifier::action(command, &config.security, &self.checker),
+        })
+    }
+
+    fn patterns(&self) -> DaemonResponse {
+        DaemonResponse::Patterns {
+            patterns: self.checker.patterns(),
+        }
+    }
+
+    /// 将流式响应逐块转发给客户端，LLM 错误作为 `error` 消息返回而不断开连接。
+    async fn generate(&self, stream: &mut UnixStream, query: &str) -> Result<(), DaemonError> {
+        let (prompt, llm) = {
+            let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+            (config.prompt.clone(), config.llm.clone())
+        };
+        let messages = PromptContext::detect()
+            .with_injection_guard(&llm)
+            .build_messages(&prompt, query);
+        let mut chunks = match self.llm.stream_chat_completion(messages, None).await {
+            Ok((_handle, chunks)) => chunks,
+            Err(e) => return write_message(stream, &error_response(&e)).await,
+        };
+
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(text) => write_message(stream, &DaemonResponse::Token { text }).await?,
+                // 只包含角色或结束原因的块没有内容
+                Err(LlmError::EmptyResponse) => {}
+                Err(e) => return write_message(stream, &error_response(&e)).await,
+            }
+        }
+        write_message(stream, &DaemonResponse::Done).await
+    }
+}
+
+fn error_response(error: &LlmError) -> DaemonResponse {
+    DaemonResponse::Error {
+        message: error.to_string(),
+    }
+}
+
+/// 在 `path` 上绑定只有当前用户可以连接的套接字。
+///
+/// 路径上已存在的套接字（例如上次异常退出留下的）会被删除；其他类型的文件不会删除，
+/// 以免配置错误的路径指向普通文件时造成数据丢失。
+fn bind_private(path: &Path) -> io::Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket, refusing to replace it", path.display()),
+            ));
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    // 连接上的请求会消耗用户的 API 额度，不允许其他用户连接
+    fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DaemonClient;
+    use termichan_config::{ConfirmationMode, LlmConfig, TieredAction};
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("termichan-daemon-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn bind_private_restricts_permissions_and_replaces_stale_sockets() {
+        let dir = scratch_dir("stale");
+        let path = dir.join("daemon.sock");
+        drop(bind_private(&path).unwrap());
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // 上次运行留下的套接字文件
+        let _listener = bind_private(&path).unwrap();
+        assert!(UnixStream::connect(&path).await.is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bind_private_keeps_other_files() {
+        let dir = scratch_dir("regular");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "keep me").unwrap();
+        let err = bind_private(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep me");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pattern_requests_change_the_running_rules() {
+        let dir = scratch_dir("patterns");
+        let path = dir.join("daemon.sock");
+        let mut config = Config::default();
+        config.security.confirmation_mode = ConfirmationMode::Dangerous;
+        config.llm = LlmConfig {
+            api_key: Some("test".to_string()),
+            // 预热请求发往不可连接的地址，立即失败
+            base_url: Some("http://127.0.0.1:9/v1".to_string()),
+            ..LlmConfig::default()
+        };
+        let checker = SecurityChecker::new(&config.security).unwrap();
+        let llm = LlmService::new(config.llm.clone()).unwrap();
+        let daemon = Daemon::new(config, llm, checker);
+        let socket = path.clone();
+        let server = tokio::spawn(async move { daemon.serve(&socket).await });
+
+        let mut client = loop {
+            match DaemonClient::connect(&path).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let verdict = client.check("make deploy").await.unwrap();
+        assert_eq!((verdict.dangerous, verdict.action), (false, TieredAction::AutoExecute));
+
+        assert_eq!(client.add_pattern("^make deploy").await.unwrap(), ["^make deploy"]);
+        let verdict = client.check("make deploy").await.unwrap();
+        assert_eq!((verdict.dangerous, verdict.action), (true, TieredAction::Confirm));
+        assert!(matches!(client.add_pattern("(").await, Err(DaemonError::Remote(_))));
+
+        // 其他连接看到同样的规则
+        let mut other = DaemonClient::connect(&path).await.unwrap();
+        assert_eq!(other.patterns().await.unwrap(), ["^make deploy"]);
+        assert!(other.remove_pattern("^make deploy").await.unwrap().is_empty());
+        assert!(matches!(other.remove_pattern("^make deploy").await, Err(DaemonError::Remote(_))));
+        assert!(!client.check("make deploy").await.unwrap().dangerous);
+
+        server.abort();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,371 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+mod prerequisites;
+mod process_group;
+mod pty;
+mod tools;
+
+pub use prerequisites::{PrerequisiteCheck, PrerequisiteRegistry};
+pub use tools::run_tool;
+
+/// `execute_with_limits` 检查命令是否结束和是否超出限制的间隔。
+const LIMIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// 命令结束后等待转发剩余输出的最长时间。
+const FORWARD_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// 执行命令时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum ExecError {
+    /// 无法启动 shell 进程。
+    #[error("Failed to start {shell}: {source}")]
+    Spawn {
+        shell: String,
+        #[source]
+        source: io::Error,
+    },
+    /// 命令已启动，但无法等待其结束。
+    #[error("Failed to wait for command: {0}")]
+    Wait(#[source] io::Error),
+    /// `PATH` 中没有找到可执行文件。
+    #[error("`{0}` was not found on PATH")]
+    BinaryNotFound(String),
+    /// 命令的输出超过了 `ExecLimits::max_output_bytes`，超出的部分被丢弃，命令被终止。
+    #[error("Command output exceeded {limit} bytes ({read} bytes read), the command was killed")]
+    OutputTooLarge { limit: u64, read: u64 },
+    /// 命令的运行时间超过了 `ExecLimits::wall_timeout`，命令被终止。
+    #[error("Command was still running after {} seconds and was killed", .0.as_secs())]
+    WallTimeout(Duration),
+    /// 模型请求了不存在的工具。
+    #[error("Unknown tool `{0}`")]
+    UnknownTool(String),
+    /// 工具调用的参数不是有效的 JSON 对象，或缺少必需的参数。
+    #[error("Invalid arguments for tool `{tool}`: {reason}")]
+    InvalidToolArguments { tool: String, reason: String },
+    /// 工具读取文件或目录失败。
+    #[error("Tool `{tool}` failed: {source}")]
+    Tool {
+        tool: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// 执行命令时的资源限制，见 `CommandExecutor::execute_with_limits`。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecLimits {
+    /// 标准输出和标准错误合计的最大字节数，`None` 表示不限制。
+    pub max_output_bytes: Option<u64>,
+    /// 命令的最长运行时间，`None` 表示不限制。
+    pub wall_timeout: Option<Duration>,
+}
+
+/// 在用户的 shell 中执行生成的命令。
+pub struct CommandExecutor;
+
+impl CommandExecutor {
+    /// 通过 shell 执行 `command`，继承当前进程的标准输入输出，等待其结束并返回退出状态。
+    ///
+    /// Unix 上使用 `sh -c`，Windows 上使用 `cmd /C`。
+    pub fn execute(command: &str) -> Result<ExitStatus, ExecError> {
+        let (shell, flag) = shell();
+        log::debug!("Executing via {shell} {flag}: {command}");
+        Command::new(shell)
+            .arg(flag)
+            .arg(command)
+            .status()
+            .map_err(|source| ExecError::Spawn {
+                shell: shell.to_string(),
+                source,
+            })
+    }
+
+    /// 与 `execute` 相同，但命令超出 `limits` 时将其终止。
+    ///
+    /// 命令在独立的进程组中运行，超出限制时终止整个进程组，包括命令启动的子进程和后台进程；
+    /// 终端仍交给命令使用，见 `process_group`。限制输出大小时，命令的标准输出和标准错误实时转发，
+    /// 转发到终端的输出经由伪终端，命令仍能检测到终端，见 `pty`。没有任何限制时等同于 `execute`。
+    ///
+    /// # Errors
+    ///
+    /// 输出超过限制时返回 `ExecError::OutputTooLarge`，超时返回 `ExecError::WallTimeout`。
+    pub fn execute_with_limits(command: &str, limits: &ExecLimits) -> Result<ExitStatus, ExecError> {
+        if *limits == ExecLimits::default() {
+            return Self::execute(command);
+        }
+        let (shell, flag) = shell();
+        log::debug!("Executing via {shell} {flag} with {limits:?}: {command}");
+        let mut process = Command::new(shell);
+        process.arg(flag).arg(command);
+        let (mut stdout_pty, mut stderr_pty) = (None, None);
+        if limits.max_output_bytes.is_some() {
+            match pty::open_like(&io::stdout()) {
+                Some((master, slave)) => {
+                    process.stdout(slave);
+                    stdout_pty = Some(master);
+                }
+                None => {
+                    process.stdout(Stdio::piped());
+                }
+            }
+            match pty::open_like(&io::stderr()) {
+                Some((master, slave)) => {
+                    process.stderr(slave);
+                    stderr_pty = Some(master);
+                }
+                None => {
+                    process.stderr(Stdio::piped());
+                }
+            }
+        }
+        let foreground = process_group::isolate(&mut process);
+        let spawned = process.spawn();
+        // 关闭 `termichan` 持有的伪终端从设备，命令结束后读取主设备才会结束
+        drop(process);
+        let mut child = spawned.map_err(|source| ExecError::Spawn {
+            shell: shell.to_string(),
+            source,
+        })?;
+        process_group::after_spawn(&child, foreground);
+
+        let read = Arc::new(AtomicU64::new(0));
+        let limit = limits.max_output_bytes.unwrap_or(u64::MAX);
+        let mut forwarders = Vec::new();
+        if let Some(stdout) = stdout_pty {
+            forwarders.push(forward(stdout, io::stdout(), Arc::clone(&read), limit));
+        } else if let Some(stdout) = child.stdout.take() {
+            forwarders.push(forward(stdout, io::stdout(), Arc::clone(&read), limit));
+        }
+        if let Some(stderr) = stderr_pty {
+            forwarders.push(forward(stderr, io::stderr(), Arc::clone(&read), limit));
+        } else if let Some(stderr) = child.stderr.take() {
+            forwarders.push(forward(stderr, io::stderr(), Arc::clone(&read), limit));
+        }
+
+        let deadline = limits.wall_timeout.map(|timeout| Instant::now() + timeout);
+        let outcome = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => {}
+                Err(e) => break Err(ExecError::Wait(e)),
+            }
+            let total = read.load(Ordering::Relaxed);
+            if total > limit {
+                process_group::kill(&mut child);
+                break Err(ExecError::OutputTooLarge { limit, read: total });
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                process_group::kill(&mut child);
+                break Err(ExecError::WallTimeout(limits.wall_timeout.unwrap_or_default()));
+            }
+            thread::sleep(LIMIT_POLL_INTERVAL);
+        };
+        process_group::restore_terminal(foreground);
+        let status = outcome?;
+
+        // 命令启动的后台进程可能一直持有管道，只等待剩余输出转发一小段时间
+        let grace = Instant::now() + FORWARD_GRACE_PERIOD;
+        while forwarders.iter().any(|f| !f.is_finished()) && Instant::now() < grace {
+            thread::sleep(LIMIT_POLL_INTERVAL);
+        }
+        // 命令可能在最后一次检查之后才输出超出限制的内容
+        let total = read.load(Ordering::Relaxed);
+        if total > limit {
+            return Err(ExecError::OutputTooLarge { limit, read: total });
+        }
+        Ok(status)
+    }
+
+    /// 与 `execute` 相同，但同时捕获命令的标准错误输出，返回退出状态和捕获的内容。
+    ///
+    /// 标准错误仍会实时转发到终端；转发或读取失败时停止捕获，不影响命令的执行。
+    pub fn execute_capturing_stderr(command: &str) -> Result<(ExitStatus, String), ExecError> {
+        let (shell, flag) = shell();
+        log::debug!("Executing via {shell} {flag} (capturing stderr): {command}");
+        let mut child = Command::new(shell)
+            .arg(flag)
+            .arg(command)
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| ExecError::Spawn {
+                shell: shell.to_string(),
+                source,
+            })?;
+
+        let mut captured = Vec::new();
+        if let Some(mut stderr) = child.stderr.take() {
+            let mut terminal = io::stderr();
+            let mut buf = [0u8; 4096];
+            loop {
+                match stderr.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let _ = terminal.write_all(&buf[..n]);
+                        captured.extend_from_slice(&buf[..n]);
+                    }
+                }
+            }
+        }
+
+        let status = child.wait().map_err(ExecError::Wait)?;
+        Ok((status, String::from_utf8_lossy(&captured).into_owned()))
+    }
+
+    /// 在 `PATH` 的各个目录中依次查找名为 `binary` 的可执行文件，返回第一个匹配的路径。
+    ///
+    /// `binary` 包含路径分隔符时直接检查该路径。Windows 上还会依次尝试 `PATHEXT` 中的扩展名。
+    /// 只读取文件元数据，不启动 shell。
+    pub fn which(binary: &str) -> Result<PathBuf, ExecError> {
+        let not_found = || ExecError::BinaryNotFound(binary.to_string());
+        if binary.is_empty() {
+            return Err(not_found());
+        }
+        if binary.contains(std::path::is_separator) {
+            return candidates(Path::new(binary)).find(|path| is_executable(path)).ok_or_else(not_found);
+        }
+        let path = env::var_os("PATH").ok_or_else(not_found)?;
+        env::split_paths(&path)
+            .flat_map(|dir| candidates(&dir.join(binary)).collect::<Vec<_>>())
+            .find(|path| is_executable(path))
+            .ok_or_else(not_found)
+    }
+
+    /// 按 `command` 的第一个程序运行内置的前提条件检查，返回不满足的条件。
+    ///
+    /// 内置检查见 `PrerequisiteRegistry`；需要自定义检查时使用 `PrerequisiteRegistry::check`。
+    pub fn check_prerequisites(command: &str) -> Vec<PrerequisiteCheck> {
+        PrerequisiteRegistry::default().check(command)
+    }
+
+    /// 描述 `execute` 将如何运行 `command`，但不执行。
+    pub fn dry_run(command: &str) -> String {
+        let (shell, flag) = shell();
+        format!("{shell} {flag} {command:?}")
+    }
+}
+
+/// 在后台线程中把 `reader` 的内容转发到 `writer`，并把读取的字节数累加到 `read`。
+///
+/// 累计字节数超过 `limit` 后只转发限制以内的部分，然后停止读取。
+fn forward<R, W>(mut reader: R, mut writer: W, read: Arc<AtomicU64>, limit: u64) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            let before = read.fetch_add(n as u64, Ordering::Relaxed);
+            let allowed = limit.saturating_sub(before).min(n as u64) as usize;
+            let _ = writer.write_all(&buf[..allowed]);
+            let _ = writer.flush();
+            if allowed < n {
+                break;
+            }
+        }
+    })
+}
+
+/// 可能的可执行文件路径：Windows 上依次加上 `PATHEXT` 中的扩展名，其他平台只有 `path` 本身。
+fn candidates(path: &Path) -> impl Iterator<Item = PathBuf> {
+    let mut paths = vec![path.to_path_buf()];
+    if cfg!(windows) && path.extension().is_none() {
+        let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        for extension in extensions.split(';').filter(|e| !e.is_empty()) {
+            let mut name = path.as_os_str().to_owned();
+            name.push(extension);
+            paths.push(PathBuf::from(name));
+        }
+    }
+    paths.into_iter()
+}
+
+/// `path` 是否为可执行的普通文件（跟随符号链接）。
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// 执行命令使用的 shell 及其参数。
+fn shell() -> (&'static str, &'static str) {
+    if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// 进程已不存在，或已结束只等待回收。
+    fn is_gone(pid: &str) -> bool {
+        match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+            Ok(stat) => stat.rsplit(')').next().is_some_and(|rest| rest.trim_start().starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[test]
+    fn wall_timeout_kills_background_processes() {
+        let pid_file = std::env::temp_dir().join(format!("termichan-exec-{}.pid", std::process::id()));
+        let command = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        let limits = ExecLimits {
+            max_output_bytes: None,
+            wall_timeout: Some(Duration::from_millis(300)),
+        };
+
+        let started = Instant::now();
+        let result = CommandExecutor::execute_with_limits(&command, &limits);
+        assert!(matches!(result, Err(ExecError::WallTimeout(_))), "{result:?}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        let pid = pid.trim();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !is_gone(pid) && Instant::now() < deadline {
+            thread::sleep(LIMIT_POLL_INTERVAL);
+        }
+        assert!(is_gone(pid), "background process {pid} survived the timeout");
+    }
+
+    #[test]
+    fn output_limit_stops_the_command() {
+        let limits = ExecLimits {
+            max_output_bytes: Some(1024),
+            wall_timeout: None,
+        };
+        let result = CommandExecutor::execute_with_limits("while :; do echo termichan; done", &limits);
+        assert!(matches!(result, Err(ExecError::OutputTooLarge { limit: 1024, .. })), "{result:?}");
+    }
+
+    #[test]
+    fn commands_within_limits_keep_their_status() {
+        let limits = ExecLimits {
+            max_output_bytes: Some(1024),
+            wall_timeout: Some(Duration::from_secs(30)),
+        };
+        assert!(CommandExecutor::execute_with_limits("true", &limits).unwrap().success());
+        assert_eq!(CommandExecutor::execute_with_limits("exit 3", &limits).unwrap().code(), Some(3));
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 检查 Docker 守护进程是否响应的最长等待时间。
+const DOCKER_PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// 等待检查命令结束时的轮询间隔。
+const PING_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 命令运行前需要满足的一个条件，例如 Docker 守护进程正在运行。
+#[derive(Debug, Clone)]
+pub struct PrerequisiteCheck {
+    /// 显示给用户的条件名称。
+    pub name: String,
+    /// 条件满足时返回 `true`。
+    pub check_fn: fn() -> bool,
+    /// 条件不满足时显示的说明和解决办法。
+    pub help_text: String,
+    /// 不需要此条件的子命令，例如 `git clone` 不需要当前目录是仓库。
+    pub skip_subcommands: Vec<String>,
+}
+
+impl PrerequisiteCheck {
+    /// 创建对所有子命令都生效的检查。
+    pub fn new(name: impl Into<String>, check_fn: fn() -> bool, help_text: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            check_fn,
+            help_text: help_text.into(),
+            skip_subcommands: Vec::new(),
+        }
+    }
+
+    /// 设置不需要此条件的子命令。
+    pub fn skip_subcommands(mut self, subcommands: &[&str]) -> Self {
+        self.skip_subcommands = subcommands.iter().map(|s| s.to_string()).collect();
+        self
+    }
+}
+
+/// 按程序名登记的前提条件检查，见 `CommandExecutor::check_prerequisites`。
+///
+/// `default()` 包含内置的检查：`docker`（守护进程响应）、`kubectl`（存在 kubeconfig）、
+/// `ssh`（SSH agent 可用）、`git`（当前目录位于仓库中）；`new()` 创建空的登记表。
+#[derive(Debug, Clone)]
+pub struct PrerequisiteRegistry {
+    checks: HashMap<String, Vec<PrerequisiteCheck>>,
+}
+
+impl PrerequisiteRegistry {
+    /// 创建没有任何检查的登记表。
+    pub fn new() -> Self {
+        Self { checks: HashMap::new() }
+    }
+
+    /// 为 `program` 添加一项检查，同一程序的检查按添加顺序运行。
+    pub fn register(&mut self, program: &str, check: PrerequisiteCheck) {
+        self.checks.entry(program.to_string()).or_default().push(check);
+    }
+
+    /// 按 `command` 的第一个程序运行登记的检查，返回不满足的条件。
+    ///
+    /// 跳过开头的 `VAR=value` 赋值和 `sudo`，程序名取路径的最后一部分。
+    pub fn check(&self, command: &str) -> Vec<PrerequisiteCheck> {
+        let mut words = command
+            .split_whitespace()
+            .skip_while(|word| is_assignment(word) || *word == "sudo");
+        let Some(program) = words.next() else {
+            return Vec::new();
+        };
+        let program = Path::new(program)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(program);
+        let Some(checks) = self.checks.get(program) else {
+            return Vec::new();
+        };
+        let subcommand = words.find(|word| !word.starts_with('-'));
+        checks
+            .iter()
+            .filter(|check| !subcommand.is_some_and(|sub| check.skip_subcommands.iter().any(|s| s == sub)))
+            .filter(|check| !(check.check_fn)())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for PrerequisiteRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            "docker",
+            PrerequisiteCheck::new(
+                "Docker daemon",
+                docker_daemon_running,
+                "the Docker daemon is not responding; start Docker or check DOCKER_HOST",
+            )
+            .skip_subcommands(&["help", "context"]),
+        );
+        registry.register(
+            "kubectl",
+            PrerequisiteCheck::new(
+                "kubeconfig",
+                kubeconfig_present,
+                "no kubeconfig found; set KUBECONFIG or create ~/.kube/config",
+            )
+            .skip_subcommands(&["help", "completion", "config"]),
+        );
+        registry.register(
+            "ssh",
+            PrerequisiteCheck::new(
+                "SSH agent",
+                ssh_agent_available,
+                "no SSH agent is available; run `eval \"$(ssh-agent)\"` and `ssh-add` to use key authentication",
+            ),
+        );
+        registry.register(
+            "git",
+            PrerequisiteCheck::new(
+                "Git repository",
+                in_git_repository,
+                "the current directory is not inside a Git repository",
+            )
+            .skip_subcommands(&["clone", "init", "config", "help", "version"]),
+        );
+        registry
+    }
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| !name.is_empty() && !name.contains('/'))
+}
+
+/// 运行 `docker version`，守护进程在 `DOCKER_PING_TIMEOUT` 内响应时返回 `true`。
+fn docker_daemon_running() -> bool {
+    let child = Command::new("docker")
+        .args(["version", "--format", "{{.Server.Version}}"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if started.elapsed() < DOCKER_PING_TIMEOUT => thread::sleep(PING_POLL_INTERVAL),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+}
+
+/// `KUBECONFIG` 中的某个文件或 `~/.kube/config` 存在。
+fn kubeconfig_present() -> bool {
+    if let Some(paths) = env::var_os("KUBECONFIG") {
+        return env::split_paths(&paths).any(|path| path.is_file());
+    }
+    home_dir().is_some_and(|home| home.join(".kube").join("config").is_file())
+}
+
+/// `SSH_AUTH_SOCK` 指向存在的套接字；Windows 的 OpenSSH agent 是系统服务，不做检查。
+fn ssh_agent_available() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    env::var_os("SSH_AUTH_SOCK").is_some_and(|sock| Path::new(&sock).exists())
+}
+
+/// 当前目录或其上级目录中有 `.git`，或设置了 `GIT_DIR`。
+fn in_git_repository() -> bool {
+    if env::var_os("GIT_DIR").is_some() {
+        return true;
+    }
+    let Ok(cwd) = env::current_dir() else {
+        return false;
+    };
+    cwd.ancestors().any(|dir| dir.join(".git").exists())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from)
+}
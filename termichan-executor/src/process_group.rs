@@ -0,0 +1,109 @@
+//! 受限命令的进程组管理。
+//!
+//! `execute_with_limits` 让命令在独立的进程组中运行，超出限制时终止整个进程组，
+//! 命令启动的子进程和后台进程也一并终止。与 shell 的作业控制一样，标准输入是终端且
+//! `termichan` 位于前台时，终端交给命令的进程组，命令仍可交互（例如 `sudo` 询问密码）
+//! 并接收 Ctrl+C；命令结束后再把终端交还给 `termichan`。
+
+use std::process::{Child, Command};
+
+/// 让 `command` 在新的进程组中启动，必要时在启动前把终端交给该进程组。
+///
+/// 返回是否交出了终端，传给 `after_spawn` 和 `restore_terminal`。
+#[cfg(unix)]
+pub(crate) fn isolate(command: &mut Command) -> bool {
+    use std::os::unix::process::CommandExt;
+
+    let foreground = is_foreground();
+    // SAFETY: 闭包在 fork 之后、exec 之前运行，只调用异步信号安全的系统调用
+    unsafe {
+        command.pre_exec(move || {
+            // 父进程启动后也会设置一次，先执行的一方生效，避免命令在交出终端前读取终端
+            libc::setpgid(0, 0);
+            if foreground {
+                give_terminal(libc::getpid());
+            }
+            Ok(())
+        });
+    }
+    foreground
+}
+
+/// 父进程一侧的设置：与 `isolate` 中的设置相同，确保无论父子进程谁先运行都已生效。
+#[cfg(unix)]
+pub(crate) fn after_spawn(child: &Child, foreground: bool) {
+    let Ok(pid) = libc::pid_t::try_from(child.id()) else {
+        return;
+    };
+    // SAFETY: 只调用不涉及内存安全的系统调用；子进程已 exec 时 `setpgid` 失败，此时子进程已自行设置
+    unsafe {
+        libc::setpgid(pid, pid);
+    }
+    if foreground {
+        give_terminal(pid);
+    }
+}
+
+/// 命令结束后把终端交还给 `termichan` 所在的进程组；启动时没有交出终端则不做任何事。
+#[cfg(unix)]
+pub(crate) fn restore_terminal(foreground: bool) {
+    if foreground {
+        // SAFETY: `getpgrp` 总是成功
+        give_terminal(unsafe { libc::getpgrp() });
+    }
+}
+
+/// 终止命令的整个进程组并回收命令进程；进程可能已经自行结束，因此忽略错误。
+#[cfg(unix)]
+pub(crate) fn kill(child: &mut Child) {
+    match libc::pid_t::try_from(child.id()) {
+        // SAFETY: 向进程组发送信号，不涉及内存安全
+        Ok(pid) => unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        },
+        Err(_) => {
+            let _ = child.kill();
+        }
+    }
+    let _ = child.wait();
+}
+
+/// 标准输入是否是终端，且当前进程组是终端的前台进程组。
+#[cfg(unix)]
+fn is_foreground() -> bool {
+    // SAFETY: 只查询标准输入的终端状态
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+}
+
+/// 把终端的前台进程组设为 `pgid`；标准输入不是终端时不做任何事。
+#[cfg(unix)]
+fn give_terminal(pgid: libc::pid_t) {
+    // SAFETY: 只调用异步信号安全的系统调用，可以在 `pre_exec` 中使用
+    unsafe {
+        if libc::isatty(libc::STDIN_FILENO) != 1 {
+            return;
+        }
+        // 后台进程组修改前台进程组时会收到 SIGTTOU 而被暂停，修改期间忽略该信号
+        let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+        libc::signal(libc::SIGTTOU, previous);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn isolate(_command: &mut Command) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub(crate) fn after_spawn(_child: &Child, _foreground: bool) {}
+
+#[cfg(not(unix))]
+pub(crate) fn restore_terminal(_foreground: bool) {}
+
+/// 没有进程组时只能终止命令进程本身。
+#[cfg(not(unix))]
+pub(crate) fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -0,0 +1,92 @@
+//! 受限命令的输出转发。
+//!
+//! 限制输出大小时，命令的标准输出和标准错误要经由 `termichan` 转发。转发的目标是终端时，
+//! 命令的输出接到一个与该终端大小、设置相同的伪终端上，命令仍能检测到终端，颜色、进度条等行为不变；
+//! 否则使用普通的管道。
+
+use std::fs::File;
+
+/// `terminal` 是终端时打开一个与其设置和窗口大小相同的伪终端，返回主设备和从设备。
+///
+/// 主设备由 `termichan` 读取并转发，从设备交给命令作为输出；`terminal` 不是终端或打开失败时返回 `None`。
+#[cfg(unix)]
+pub(crate) fn open_like(terminal: &impl std::os::fd::AsFd) -> Option<(File, File)> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    let fd = terminal.as_fd().as_raw_fd();
+    // SAFETY: 只查询 `fd` 的终端状态，并把结果写入本地变量
+    unsafe {
+        if libc::isatty(fd) != 1 {
+            return None;
+        }
+        let mut termios: libc::termios = std::mem::zeroed();
+        let termios = (libc::tcgetattr(fd, &mut termios) == 0).then_some(termios);
+        let mut size: libc::winsize = std::mem::zeroed();
+        let size = (libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) == 0).then_some(size);
+
+        let (mut master, mut slave) = (-1, -1);
+        let opened = libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            termios.as_ref().map_or(std::ptr::null(), |t| t as *const _),
+            size.as_ref().map_or(std::ptr::null(), |s| s as *const _),
+        );
+        if opened != 0 {
+            return None;
+        }
+        // 命令只应持有从设备，而且是作为标准输出或标准错误复制过去的那一份
+        libc::fcntl(master, libc::F_SETFD, libc::FD_CLOEXEC);
+        libc::fcntl(slave, libc::F_SETFD, libc::FD_CLOEXEC);
+        Some((File::from_raw_fd(master), File::from_raw_fd(slave)))
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn open_like<T>(_terminal: &T) -> Option<(File, File)> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::process::Command;
+
+    #[test]
+    fn commands_see_a_terminal() {
+        // 测试进程本身通常没有终端，用另一个伪终端代替
+        let (_outer_master, outer_slave) = open_like_any();
+        let (mut master, slave) = open_like(&outer_slave).expect("a pty slave is a terminal");
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("test -t 1 && echo terminal").stdout(slave);
+        let status = command.status().unwrap();
+        drop(command);
+        assert!(status.success());
+
+        let mut output = Vec::new();
+        // 从设备全部关闭后读取主设备返回 EIO，此前的输出已经读完
+        let _ = master.read_to_end(&mut output);
+        assert_eq!(String::from_utf8_lossy(&output).trim(), "terminal");
+    }
+
+    #[test]
+    fn pipes_are_not_terminals() {
+        let (reader, _writer) = std::io::pipe().unwrap();
+        assert!(open_like(&reader).is_none());
+    }
+
+    fn open_like_any() -> (File, File) {
+        use std::os::fd::FromRawFd;
+
+        let (mut master, mut slave) = (-1, -1);
+        // SAFETY: 参数都是有效的指针或空指针
+        let opened = unsafe {
+            libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), std::ptr::null())
+        };
+        assert_eq!(opened, 0);
+        // SAFETY: `openpty` 成功时返回两个新打开的描述符
+        unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) }
+    }
+}
@@ -0,0 +1,229 @@
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::ExecError;
+
+/// 返回给模型的工具输出的最大字符数，超出部分被截断。
+const MAX_TOOL_OUTPUT_CHARS: usize = 8000;
+/// `docker_logs` 读取的日志行数。
+const DOCKER_LOG_LINES: &str = "100";
+/// `get_env_vars` 会返回值的环境变量，其余变量只返回名称，避免把密钥发送给模型。
+const VISIBLE_ENV_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "SHELL",
+    "PWD",
+    "LANG",
+    "LC_ALL",
+    "TERM",
+    "EDITOR",
+    "VISUAL",
+    "VIRTUAL_ENV",
+    "CONDA_DEFAULT_ENV",
+    "KUBECONFIG",
+    "DOCKER_HOST",
+];
+/// `read_file` 和 `list_files` 拒绝访问的文件名：常见的私钥和凭据文件。
+///
+/// 以 `.` 开头的文件和目录（`.env`、`.ssh`、`.aws` 等）一律拒绝，不在此列出。
+const SECRET_FILE_NAMES: &[&str] = &[
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "credentials",
+    "credentials.json",
+    "secrets.json",
+    "secrets.toml",
+    "secrets.yaml",
+    "secrets.yml",
+];
+/// 拒绝访问的扩展名：私钥、证书库和密码库。
+const SECRET_EXTENSIONS: &[&str] = &["pem", "key", "p12", "pfx", "jks", "keystore", "kdbx", "gpg"];
+
+/// 执行模型请求的工具调用，返回发送给模型的文本结果。
+///
+/// `arguments` 是模型给出的 JSON 对象。工具只读取系统状态，不修改任何内容；
+/// 外部命令以非 0 状态退出时返回其错误输出，由模型自行判断。
+///
+/// # Errors
+///
+/// 工具名未知、参数缺失或无法启动外部命令时返回 `ExecError`。
+pub fn run_tool(name: &str, arguments: &str) -> Result<String, ExecError> {
+    let arguments: Value = if arguments.trim().is_empty() {
+        Value::Object(Default::default())
+    } else {
+        serde_json::from_str(arguments).map_err(|e| invalid_arguments(name, e.to_string()))?
+    };
+    let string_arg = |key: &str| {
+        arguments
+            .get(key)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_arguments(name, format!("missing string argument `{key}`")))
+    };
+    log::debug!("Running tool {name} with {arguments}");
+
+    let output = match name {
+        "git_status" => run_program("git", &["status", "--short", "--branch"])?,
+        "read_file" => read_file(string_arg("path")?, name)?,
+        "docker_ps" => run_program(
+            "docker",
+            &["ps", "--format", "{{.ID}}\t{{.Image}}\t{{.Status}}\t{{.Names}}"],
+        )?,
+        "docker_logs" => run_program(
+            "docker",
+            &["logs", "--tail", DOCKER_LOG_LINES, string_arg("container_id")?],
+        )?,
+        "get_env_vars" => env_vars(),
+        "list_files" => list_files(arguments.get("dir").and_then(Value::as_str).unwrap_or("."), name)?,
+        _ => return Err(ExecError::UnknownTool(name.to_string())),
+    };
+    Ok(truncate(output))
+}
+
+fn invalid_arguments(tool: &str, reason: String) -> ExecError {
+    ExecError::InvalidToolArguments {
+        tool: tool.to_string(),
+        reason,
+    }
+}
+
+/// 直接启动 `program`（不经过 shell），返回标准输出；失败时返回标准错误。
+fn run_program(program: &str, args: &[&str]) -> Result<String, ExecError> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|source| ExecError::Spawn {
+            shell: program.to_string(),
+            source,
+        })?;
+    let text = if output.status.success() {
+        &output.stdout
+    } else {
+        &output.stderr
+    };
+    Ok(String::from_utf8_lossy(text).into_owned())
+}
+
+/// 读取当前工作目录之内的文件，限制见 `resolve_allowed`。
+fn read_file(path: &str, tool: &str) -> Result<String, ExecError> {
+    let path = resolve_allowed(path, tool)?;
+    fs::read_to_string(&path).map_err(|source| ExecError::Tool {
+        tool: tool.to_string(),
+        source,
+    })
+}
+
+/// 解析 `path` 并检查模型能否访问。
+///
+/// 不允许通过 `..` 或符号链接访问当前工作目录之外的路径，也不允许访问隐藏文件、
+/// 隐藏目录中的文件和常见的私钥或凭据文件，避免把密钥发送给模型。
+fn resolve_allowed(path: &str, tool: &str) -> Result<PathBuf, ExecError> {
+    let io_error = |source| ExecError::Tool {
+        tool: tool.to_string(),
+        source,
+    };
+    let root = env::current_dir().and_then(fs::canonicalize).map_err(io_error)?;
+    let resolved = fs::canonicalize(path).map_err(io_error)?;
+    let Ok(relative) = resolved.strip_prefix(&root) else {
+        return Err(invalid_arguments(
+            tool,
+            format!("{} is outside the working directory", resolved.display()),
+        ));
+    };
+    if may_contain_secrets(relative) {
+        return Err(invalid_arguments(
+            tool,
+            format!("refusing to access {path}: hidden files and key or credential files may contain secrets"),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// 相对于工作目录的路径是否是隐藏文件、位于隐藏目录中，或是私钥、凭据文件。
+fn may_contain_secrets(relative: &Path) -> bool {
+    let hidden = relative
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+    let secret_name = relative
+        .file_name()
+        .is_some_and(|name| SECRET_FILE_NAMES.contains(&name.to_string_lossy().as_ref()));
+    let secret_extension = relative
+        .extension()
+        .is_some_and(|extension| SECRET_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str()));
+    hidden || secret_name || secret_extension
+}
+
+/// 所有环境变量的名称，`VISIBLE_ENV_VARS` 中的变量附带其值，按名称排序。
+fn env_vars() -> String {
+    let mut vars: Vec<String> = env::vars_os()
+        .map(|(name, value)| {
+            let name = name.to_string_lossy().into_owned();
+            if VISIBLE_ENV_VARS.contains(&name.as_str()) {
+                format!("{name}={}", value.to_string_lossy())
+            } else {
+                name
+            }
+        })
+        .collect();
+    vars.sort();
+    vars.join("\n")
+}
+
+/// 列出 `dir` 中的条目，目录名以 `/` 结尾，按名称排序。
+///
+/// 与 `read_file` 一样只能列出工作目录之内、不是隐藏目录的目录。
+fn list_files(dir: &str, tool: &str) -> Result<String, ExecError> {
+    let dir = resolve_allowed(dir, tool)?;
+    let io_error = |source| ExecError::Tool {
+        tool: tool.to_string(),
+        source,
+    };
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        let mut name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            name.push('/');
+        }
+        names.push(name);
+    }
+    names.sort();
+    Ok(names.join("\n"))
+}
+
+fn truncate(mut output: String) -> String {
+    if let Some((index, _)) = output.char_indices().nth(MAX_TOOL_OUTPUT_CHARS) {
+        output.truncate(index);
+        output.push_str("\n[output truncated]");
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_and_secret_files_are_refused() {
+        for path in [".env", ".ssh/config", "config/.aws/credentials", "deploy/server.PEM", "id_ed25519", "secrets.yaml"] {
+            assert!(may_contain_secrets(Path::new(path)), "{path}");
+        }
+        for path in ["", "src/main.rs", "README.md", "docs/keys.md", "env.example"] {
+            assert!(!may_contain_secrets(Path::new(path)), "{path}");
+        }
+    }
+
+    #[test]
+    fn read_file_stays_inside_the_working_directory() {
+        let result = run_tool("read_file", r#"{"path": "../Cargo.toml"}"#);
+        assert!(matches!(result, Err(ExecError::InvalidToolArguments { .. })), "{result:?}");
+        assert!(run_tool("read_file", r#"{"path": "Cargo.toml"}"#).is_ok());
+        assert!(run_tool("list_files", r#"{"dir": "/"}"#).is_err());
+        assert!(run_tool("list_files", "{}").unwrap().contains("Cargo.toml"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use termichan_config::PromptConfig;
+
+use crate::{LlmError, LlmService, PromptContext};
+
+/// 建议别名时使用的系统提示词，`{shell}` 替换为目标 shell，`{example}` 替换为该 shell 的别名语法示例
+const ALIAS_SYSTEM_PROMPT: &str = "You are a terminal expert. Suggest a concise, memorable alias name of 2 to 10 \
+characters for the user's command. The name must not shadow common commands or {shell} builtins. \
+Reply with only the {shell} alias definition on one line, without Markdown or explanation, for example: {example}";
+
+/// 别名定义的目标 shell，决定`LlmService::generate_alias`返回的语法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl ShellKind {
+    /// 按`$SHELL`（Windows 下为`$ComSpec`）检测当前 shell，无法识别时为`None`
+    pub fn detect() -> Option<Self> {
+        let path = env::var("SHELL").or_else(|_| env::var("ComSpec")).ok()?;
+        Path::new(&path).file_stem()?.to_str()?.parse().ok()
+    }
+
+    /// shell 的名称，与`FromStr`接受的名称一致
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::PowerShell => "powershell",
+        }
+    }
+
+    /// 该 shell 的别名定义示例，也写入提示词中
+    fn example(self) -> &'static str {
+        match self {
+            Self::Bash | Self::Zsh => "alias ll='ls -la'",
+            Self::Fish => "abbr -a ll 'ls -la'",
+            Self::PowerShell => "function ll { Get-ChildItem -Force @args }",
+        }
+    }
+}
+
+impl fmt::Display for ShellKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ShellKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bash" | "sh" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            "powershell" | "pwsh" => Ok(Self::PowerShell),
+            other => Err(format!("unsupported shell `{other}`: expected bash, zsh, fish or powershell")),
+        }
+    }
+}
+
+impl LlmService {
+    /// 请求 LLM 为经常使用的`command`建议一个简短的别名，返回`shell`语法的别名定义
+    ///
+    /// 例如 bash 返回`alias ll='ls -la'`，fish 返回`abbr -a ll 'ls -la'`。
+    /// 模型仍用 Markdown 代码块包裹定义时去掉代码块标记，返回的文本可以用`ResponseParser::parse`解析。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: API返回空响应
+    pub async fn generate_alias(&self, command: &str, shell: ShellKind) -> Result<String, LlmError> {
+        let prompt = PromptConfig {
+            system_prompt: ALIAS_SYSTEM_PROMPT.replace("{example}", shell.example()),
+            user_prompt_template: "{user_input}".to_string(),
+            ..PromptConfig::default()
+        };
+        let mut ctx = PromptContext::detect().with_injection_guard(&self.config());
+        ctx.shell = shell.name().to_string();
+        let messages = ctx.build_messages(&prompt, command);
+        let response = self.chat_completion_default(messages).await?;
+        response
+            .trim()
+            .lines()
+            .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with("```"))
+            .map(|line| line.trim().to_string())
+            .ok_or(LlmError::EmptyResponse)
+    }
+}
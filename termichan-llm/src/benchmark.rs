@@ -0,0 +1,128 @@
+use async_openai::types::ChatCompletionRequestUserMessageArgs;
+use futures::StreamExt;
+use std::fmt;
+use std::time::Instant;
+
+use crate::{LlmService, RequestOptions};
+
+/// `benchmark`默认使用的测试提示词
+pub const DEFAULT_BENCHMARK_PROMPT: &str = "echo hello";
+
+/// 一次基准测试的结果
+#[derive(Debug, Clone)]
+pub struct BenchmarkReport {
+    pub provider: String,
+    pub model: String,
+    /// 请求次数（包括失败的请求）
+    pub iterations: u32,
+    /// 成功请求的延迟中位数（毫秒）
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    /// 流式输出的平均速度，以收到的响应块数近似 token 数；没有成功请求时为`None`
+    pub tokens_per_second: Option<f64>,
+    /// 失败的请求数
+    pub errors: u32,
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Provider:   {}", self.provider)?;
+        writeln!(f, "Model:      {}", self.model)?;
+        writeln!(f, "Iterations: {} ({} errors)", self.iterations, self.errors)?;
+        writeln!(f, "Latency:    p50 {} ms, p95 {} ms, p99 {} ms", self.p50_ms, self.p95_ms, self.p99_ms)?;
+        match self.tokens_per_second {
+            Some(tps) => writeln!(f, "Throughput: {tps:.1} tokens/s"),
+            None => writeln!(f, "Throughput: -"),
+        }
+    }
+}
+
+impl LlmService {
+    /// 使用默认测试提示词对当前配置进行基准测试
+    ///
+    /// 等同于`benchmark_with_prompt(iterations, DEFAULT_BENCHMARK_PROMPT)`。
+    pub async fn benchmark(&self, iterations: u32) -> BenchmarkReport {
+        self.benchmark_with_prompt(iterations, DEFAULT_BENCHMARK_PROMPT)
+            .await
+    }
+
+    /// 将同一提示词以流式请求发送`iterations`次，统计延迟分位数和输出速度
+    ///
+    /// 请求按顺序发送，单次失败只计入`errors`，不会中断测试。
+    pub async fn benchmark_with_prompt(&self, iterations: u32, prompt: &str) -> BenchmarkReport {
+        self.benchmark_with_options(iterations, prompt, None).await
+    }
+
+    /// 与`benchmark_with_prompt`相同，但每个请求使用`opts`，例如用较短的超时让卡住的请求计为失败
+    pub async fn benchmark_with_options(
+        &self,
+        iterations: u32,
+        prompt: &str,
+        opts: Option<RequestOptions>,
+    ) -> BenchmarkReport {
+        let mut latencies = Vec::with_capacity(iterations as usize);
+        let mut total_chunks = 0u64;
+        let mut total_secs = 0f64;
+        let mut errors = 0;
+
+        for _ in 0..iterations {
+            let message = ChatCompletionRequestUserMessageArgs::default()
+                .content(prompt)
+                .build()
+                .expect("user message has all required fields")
+                .into();
+
+            let started = Instant::now();
+            let mut chunks = 0u64;
+            let mut failed = false;
+            match self.stream_chat_completion(vec![message], opts).await {
+                Ok((_handle, stream)) => {
+                    let mut stream = Box::pin(stream);
+                    while let Some(chunk) = stream.next().await {
+                        match chunk {
+                            Ok(_) => chunks += 1,
+                            // 部分服务会发送不含内容的块（例如结束标记），不视为失败
+                            Err(crate::LlmError::EmptyResponse) => {}
+                            Err(_) => {
+                                failed = true;
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(_) => failed = true,
+            }
+
+            if failed {
+                errors += 1;
+                continue;
+            }
+            let elapsed = started.elapsed();
+            latencies.push(elapsed.as_millis() as u64);
+            total_chunks += chunks;
+            total_secs += elapsed.as_secs_f64();
+        }
+
+        latencies.sort_unstable();
+        BenchmarkReport {
+            provider: self.config().provider.clone(),
+            model: self.config().model.clone(),
+            iterations,
+            p50_ms: percentile(&latencies, 50.0),
+            p95_ms: percentile(&latencies, 95.0),
+            p99_ms: percentile(&latencies, 99.0),
+            tokens_per_second: (total_secs > 0.0).then(|| total_chunks as f64 / total_secs),
+            errors,
+        }
+    }
+}
+
+/// 最近秩法计算已排序样本的百分位数，没有样本时返回 0
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,200 @@
+use async_openai::config::OpenAIConfig;
+use std::sync::{Arc, Mutex, RwLock};
+use termichan_config::{LlmConfig, NetworkConfig};
+
+use crate::tokenizer::{EstimateTokenizer, TiktokenTokenizer, Tokenizer};
+use crate::{
+    http, pool, request_id, retry, Cache, CachingMiddleware, Connection, CostTracker, LlmError, LlmMiddleware,
+    LlmService, PreProcessor, ProviderCapabilities, RateLimitMiddleware, RateLimitWait, RateLimiter, RetryMiddleware,
+};
+
+/// `LlmService`的构建器
+///
+/// 除配置外的依赖均可注入，便于在测试中替换 HTTP 客户端，
+/// 或在多个服务实例之间共享速率限制器、缓存和用量统计。
+#[derive(Default)]
+pub struct LlmServiceBuilder {
+    config: Option<LlmConfig>,
+    network: Option<NetworkConfig>,
+    http: Option<reqwest::Client>,
+    rate_limiter: Option<RateLimiter>,
+    rate_limit_wait: Option<RateLimitWait>,
+    cache: Option<Cache>,
+    cost_tracker: Option<CostTracker>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+    middlewares: Vec<Arc<dyn LlmMiddleware>>,
+    pre_processors: Vec<Arc<dyn PreProcessor>>,
+}
+
+impl LlmServiceBuilder {
+    /// 设置LLM配置，未设置时使用`LlmConfig::default()`
+    pub fn with_config(mut self, config: LlmConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// 设置用于构建默认HTTP客户端的网络配置
+    ///
+    /// 通过`with_http_client`注入客户端时忽略此项。
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// 使用指定的HTTP客户端，而不是根据配置构建
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// 在发送每个请求前通过`limiter`限制速率
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// 设置遇到服务端速率限制 (HTTP 429) 时的等待策略
+    pub fn with_rate_limit_wait(mut self, wait: RateLimitWait) -> Self {
+        self.rate_limit_wait = Some(wait);
+        self
+    }
+
+    /// 缓存非流式聊天补全的响应
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// 将API返回的token用量记录到`tracker`
+    pub fn with_cost_tracker(mut self, tracker: CostTracker) -> Self {
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// 使用`tokenizer`计算消息的 token 数
+    ///
+    /// 未设置时，OpenAI 兼容接口按模型名称使用 tiktoken 编码，
+    /// Anthropic 没有公开的 tiktoken 编码，按字符数估算。
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// 在中间件链的末尾添加`middleware`，中间件按添加顺序调用
+    ///
+    /// 缓存、速率限制和重试的默认中间件总是排在通过此方法添加的中间件之前，见`LlmMiddleware`。
+    pub fn with_middleware(mut self, middleware: impl LlmMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// 在查询预处理链的末尾添加`pre_processor`，在`PromptConfig::pre_processing_chain`之后按添加顺序调用
+    pub fn with_pre_processor(mut self, pre_processor: impl PreProcessor + 'static) -> Self {
+        self.pre_processors.push(Arc::new(pre_processor));
+        self
+    }
+
+    /// 创建`LlmService`
+    ///
+    /// # 错误
+    /// - `LlmError::ApiKeyMissing`: API密钥未配置
+    /// - `LlmError::InvalidNetworkConfig`: 代理、DNS或追踪请求头设置无效
+    /// - `LlmError::TlsConfig`: 无法初始化HTTP客户端的TLS后端
+    pub fn build(self) -> Result<LlmService, LlmError> {
+        let config = self.config.unwrap_or_default();
+        let openai_config = openai_config(&config)?;
+
+        let network = self.network.unwrap_or_default();
+        let injected_http = self.http.is_some();
+        let http = match self.http {
+            Some(http) => http,
+            None => http::build_http_client(&config, &network)?,
+        };
+        let network_retries = if network.retry_on_network_error {
+            network.network_retry_count
+        } else {
+            0
+        };
+        let dns_retries = if network.retry_on_dns_failure {
+            network.dns_retry_count
+        } else {
+            0
+        };
+
+        let request_id_header = config
+            .request_id_header
+            .as_deref()
+            .map(|header| request_id::parse_header(header, config.request_id_prefix.as_deref()))
+            .transpose()?;
+
+        let tokenizer = self.tokenizer.unwrap_or_else(|| {
+            if ProviderCapabilities::system_message_as_field(&config.provider) {
+                Arc::new(EstimateTokenizer)
+            } else {
+                Arc::new(TiktokenTokenizer::for_model(&config.model))
+            }
+        });
+
+        // 内置的缓存、速率限制和重试排在用户注册的中间件之前
+        let mut middlewares: Vec<Arc<dyn LlmMiddleware>> = Vec::new();
+        if let Some(cache) = &self.cache {
+            middlewares.push(Arc::new(CachingMiddleware::new(cache.clone())));
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            middlewares.push(Arc::new(RateLimitMiddleware::new(limiter.clone())));
+        }
+        let retry = RetryMiddleware::with_network(network_retries, dns_retries);
+        middlewares.push(Arc::new(retry));
+        middlewares.extend(self.middlewares);
+
+        let pool = pool::PoolTracker::new(config.pool_size);
+        Ok(LlmService {
+            config: RwLock::new(Arc::new(config)),
+            connection: RwLock::new(Connection {
+                openai_config,
+                http,
+                network,
+                injected_http,
+            }),
+            rate_limit_wait: self.rate_limit_wait.unwrap_or_else(retry::default_wait),
+            last_health: Mutex::new(None),
+            retry,
+            rate_limiter: self.rate_limiter,
+            cache: self.cache,
+            cost_tracker: self.cost_tracker,
+            pool,
+            request_id_header,
+            last_request_id: Mutex::new(None),
+            last_cache_usage: Mutex::new(None),
+            last_usage: Mutex::new(None),
+            tokenizer,
+            middlewares,
+            pre_processors: self.pre_processors,
+        })
+    }
+}
+
+/// 未配置`base_url`时 OpenAI 的默认地址
+pub(crate) const OPENAI_DEFAULT_BASE: &str = "https://api.openai.com/v1";
+
+/// 按`LlmConfig`中的 API 密钥、基础 URL 和组织 ID 创建 OpenAI 兼容接口的配置
+///
+/// # 错误
+/// API密钥未配置时返回`LlmError::ApiKeyMissing`
+pub(crate) fn openai_config(config: &LlmConfig) -> Result<OpenAIConfig, LlmError> {
+    let api_key = config
+        .api_key
+        .as_ref()
+        .ok_or(LlmError::ApiKeyMissing)?;
+    let base_url = config
+        .base_url
+        .as_deref()
+        .unwrap_or(OPENAI_DEFAULT_BASE);
+    let mut openai_config = OpenAIConfig::new()
+        .with_api_key(api_key)
+        .with_api_base(base_url);
+    if let Some(org_id) = &config.organization_id {
+        openai_config = openai_config.with_org_id(org_id);
+    }
+    Ok(openai_config)
+}
@@ -0,0 +1,208 @@
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use termichan_config::LlmConfig;
+
+/// 未指定时缓存条目的有效期
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// 聊天补全响应的缓存
+///
+/// 以模型、采样参数和消息内容为键，相同请求在有效期内直接返回缓存的响应。
+/// 通过`Cache::load`创建的缓存可以用`save`写回文件，在多次运行之间共享。
+/// 克隆得到的实例共享同一缓存。
+#[derive(Debug, Clone)]
+pub struct Cache {
+    ttl: Duration,
+    path: Option<PathBuf>,
+    data: Arc<Mutex<CacheData>>,
+}
+
+/// 缓存的命中统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    /// 命中缓存的请求数
+    pub hits: u64,
+    /// 未命中（或已过期）的请求数
+    pub misses: u64,
+    /// 缓存的条目数（包括已过期但尚未清理的条目）
+    pub entries: usize,
+    /// 所有缓存响应的总字节数
+    pub total_bytes: usize,
+}
+
+/// 缓存文件的内容，命中统计随条目一起保存
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    hits: u64,
+    misses: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    stored_at: SystemTime,
+    response: String,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        self.stored_at.elapsed().is_ok_and(|age| age < ttl)
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl Cache {
+    /// 创建条目有效期为`ttl`的内存缓存
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            path: None,
+            data: Arc::new(Mutex::new(CacheData::default())),
+        }
+    }
+
+    /// 从`path`加载使用默认有效期（一小时）的缓存，文件不存在时从空缓存开始
+    ///
+    /// # 错误
+    /// 无法读取文件或文件内容不是有效的缓存时返回`io::Error`
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let data = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => CacheData::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            ttl: DEFAULT_TTL,
+            path: Some(path),
+            data: Arc::new(Mutex::new(data)),
+        })
+    }
+
+    /// 将条目有效期改为`ttl`
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 缓存文件路径，内存缓存为`None`
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 将未过期的条目和命中统计写回`Cache::load`时的文件，内存缓存不做任何事
+    ///
+    /// 先写入临时文件再重命名，写入中断时不会损坏原有的缓存文件。
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = {
+            let mut data = self.lock();
+            data.entries.retain(|_, entry| entry.is_fresh(self.ttl));
+            serde_json::to_string(&*data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// 当前缓存的条目数（包括已过期但尚未清理的条目）
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 清空缓存，命中统计保持不变
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// 命中统计和缓存大小
+    pub fn stats(&self) -> CacheStats {
+        let data = self.lock();
+        CacheStats {
+            hits: data.hits,
+            misses: data.misses,
+            entries: data.entries.len(),
+            total_bytes: data.entries.values().map(|e| e.response.len()).sum(),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<String> {
+        let mut data = self.lock();
+        let response = match data.entries.get(key) {
+            Some(entry) if entry.is_fresh(self.ttl) => Some(entry.response.clone()),
+            Some(_) => {
+                data.entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        match response {
+            Some(_) => data.hits += 1,
+            None => data.misses += 1,
+        }
+        response
+    }
+
+    /// 是否有`key`的有效条目，不计入命中统计
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.lock()
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.is_fresh(self.ttl))
+    }
+
+    pub(crate) fn insert(&self, key: String, response: String) {
+        self.lock().entries.insert(
+            key,
+            CacheEntry {
+                stored_at: SystemTime::now(),
+                response,
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheData> {
+        self.data.lock().expect("response cache lock poisoned")
+    }
+}
+
+/// 响应缓存的键：提供商、模型、采样参数和消息内容的SHA-256摘要
+///
+/// 缓存会写入文件，键必须在不同的Rust版本之间保持稳定，因此不使用`DefaultHasher`。
+pub(crate) fn request_key(messages: &[ChatCompletionRequestMessage], model: &str, config: &LlmConfig) -> String {
+    // 按键排序，与`HashMap`的遍历顺序无关
+    let logit_bias: Option<BTreeMap<&String, &f32>> = config.logit_bias.as_ref().map(|bias| bias.iter().collect());
+    let request = serde_json::json!({
+        "provider": config.provider,
+        "model": model,
+        "temperature": config.temperature,
+        "top_p": config.top_p,
+        "logit_bias": logit_bias,
+        "max_tokens": config.max_tokens,
+        "messages": messages,
+    });
+    format!("{:x}", Sha256::digest(request.to_string()))
+}
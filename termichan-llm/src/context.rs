@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::LlmError;
+
+/// 附加了上下文说明的错误，保留原始错误的类型。
+///
+/// 显示为 `上下文: 原始错误`，`source()` 返回原始错误，
+/// 调用方仍可以通过 `get_ref` 或 `into_inner` 按原始错误类型分支。
+#[derive(Debug)]
+pub struct ContextError<E> {
+    context: String,
+    source: E,
+}
+
+impl<E> ContextError<E> {
+    /// 为 `source` 附加上下文说明。
+    pub fn new(context: impl Into<String>, source: E) -> Self {
+        Self {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// 附加的上下文说明。
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// 原始错误。
+    pub fn get_ref(&self) -> &E {
+        &self.source
+    }
+
+    /// 取出原始错误，丢弃上下文说明。
+    pub fn into_inner(self) -> E {
+        self.source
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ContextError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl<E: Error + 'static> Error for ContextError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl LlmError {
+    /// 为错误附加上下文说明，例如 `"while generating command for query: ..."`。
+    pub fn context(self, context: impl Into<String>) -> ContextError<LlmError> {
+        ContextError::new(context, self)
+    }
+}
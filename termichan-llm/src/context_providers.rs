@@ -0,0 +1,210 @@
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use termichan_config::{ContextProviderConfig, PromptConfig};
+
+use crate::prompt::DOCKER_SOCKETS;
+
+/// 自定义提供者命令的最长运行时间，超时的命令被终止并跳过
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+/// 识别项目类型的文件及对应的说明，按列出的顺序输出
+const PROJECT_MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust (cargo)"),
+    ("package.json", "Node.js (npm)"),
+    ("pyproject.toml", "Python"),
+    ("requirements.txt", "Python (pip)"),
+    ("go.mod", "Go"),
+    ("pom.xml", "Java (Maven)"),
+    ("build.gradle", "Java (Gradle)"),
+    ("build.gradle.kts", "Kotlin (Gradle)"),
+    ("Gemfile", "Ruby (Bundler)"),
+    ("composer.json", "PHP (Composer)"),
+    ("CMakeLists.txt", "C/C++ (CMake)"),
+    ("Makefile", "make"),
+    ("docker-compose.yml", "Docker Compose"),
+];
+
+/// 按`PromptConfig::context_providers`收集上下文，返回`(名称, 内容)`，按优先级从高到低排列
+///
+/// 只运行已启用的提供者；没有内容的提供者不出现在结果中，内容按各自的`max_chars`截断。
+pub(crate) fn collect(prompt: &PromptConfig, cwd: Option<&Path>) -> Vec<(String, String)> {
+    let mut providers: Vec<&ContextProviderConfig> =
+        prompt.context_providers.iter().filter(|p| p.enabled).collect();
+    // 稳定排序，优先级相同时保持配置中的顺序
+    providers.sort_by_key(|p| Reverse(p.priority));
+    providers
+        .into_iter()
+        .filter_map(|provider| {
+            let text = run(provider, cwd)?;
+            let text = text.trim();
+            if text.is_empty() {
+                return None;
+            }
+            let text: String = text.chars().take(provider.max_chars).collect();
+            Some((provider.name.clone(), text))
+        })
+        .collect()
+}
+
+fn run(provider: &ContextProviderConfig, cwd: Option<&Path>) -> Option<String> {
+    if let Some(command) = &provider.command {
+        return run_command(command);
+    }
+    match provider.name.as_str() {
+        "git" => git(),
+        "docker" => docker(),
+        "k8s" => k8s(),
+        "venv" => venv(),
+        "project" => project(cwd?),
+        "env_vars" => env_vars(),
+        "directory" => directory(cwd?),
+        name => {
+            log::warn!("Unknown context provider `{name}` without a command, skipping");
+            None
+        }
+    }
+}
+
+/// 当前分支和未提交的文件数，不在 Git 仓库中时为`None`
+fn git() -> Option<String> {
+    let branch = command_output(Command::new("git").args(["rev-parse", "--abbrev-ref", "HEAD"]))?;
+    let status = command_output(Command::new("git").args(["status", "--porcelain"]))?;
+    let changed = status.lines().filter(|line| !line.trim().is_empty()).count();
+    Some(match changed {
+        0 => format!("branch {}, clean", branch.trim()),
+        n => format!("branch {}, {n} uncommitted files", branch.trim()),
+    })
+}
+
+fn docker() -> Option<String> {
+    if let Some(host) = env::var_os("DOCKER_HOST") {
+        return Some(format!("available at {}", host.to_string_lossy()));
+    }
+    DOCKER_SOCKETS
+        .iter()
+        .any(|socket| Path::new(socket).exists())
+        .then(|| "available".to_string())
+}
+
+/// kubeconfig 中的当前上下文，不运行 kubectl
+fn k8s() -> Option<String> {
+    let path = match env::var_os("KUBECONFIG") {
+        Some(paths) => env::split_paths(&paths).next()?,
+        None => home_dir()?.join(".kube").join("config"),
+    };
+    let config = fs::read_to_string(path).ok()?;
+    config.lines().find_map(|line| {
+        let context = line.strip_prefix("current-context:")?.trim().trim_matches('"');
+        (!context.is_empty()).then(|| format!("kubectl context {context}"))
+    })
+}
+
+fn venv() -> Option<String> {
+    if let Some(venv) = env::var_os("VIRTUAL_ENV") {
+        let name = Path::new(&venv).file_name().unwrap_or(&venv).to_string_lossy().into_owned();
+        return Some(format!("Python virtualenv {name}"));
+    }
+    env::var("CONDA_DEFAULT_ENV").ok().map(|name| format!("conda environment {name}"))
+}
+
+fn project(cwd: &Path) -> Option<String> {
+    let mut kinds: Vec<&str> = Vec::new();
+    for (file, kind) in PROJECT_MARKERS {
+        if cwd.join(file).exists() && !kinds.contains(kind) {
+            kinds.push(kind);
+        }
+    }
+    (!kinds.is_empty()).then(|| kinds.join(", "))
+}
+
+/// 已设置的环境变量名，不包括值
+fn env_vars() -> Option<String> {
+    let mut names: Vec<String> = env::vars_os().map(|(name, _)| name.to_string_lossy().into_owned()).collect();
+    names.sort();
+    Some(names.join(", "))
+}
+
+/// 工作目录中的文件名，目录以`/`结尾
+fn directory(cwd: &Path) -> Option<String> {
+    let mut names: Vec<String> = fs::read_dir(cwd)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                format!("{name}/")
+            } else {
+                name
+            }
+        })
+        .collect();
+    names.sort();
+    Some(names.join(", "))
+}
+
+/// 通过 shell 运行自定义提供者的命令，失败或超时时记录日志并返回`None`
+fn run_command(command: &str) -> Option<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .inspect_err(|e| log::warn!("Failed to run context provider command `{command}`: {e}"))
+        .ok()?;
+
+    // 在另一个线程中读取输出，避免输出填满管道时命令阻塞
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+    let deadline = Instant::now() + COMMAND_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                log::warn!("Context provider command `{command}` timed out after {COMMAND_TIMEOUT:?}");
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(e) => {
+                log::warn!("Failed to wait for context provider command `{command}`: {e}");
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        log::debug!("Context provider command `{command}` exited with {status}");
+        return None;
+    }
+    reader.join().ok()?.ok()
+}
+
+/// 运行命令并返回标准输出，命令不存在或失败时为`None`
+fn command_output(command: &mut Command) -> Option<String> {
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from)
+}
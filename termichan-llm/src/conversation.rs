@@ -0,0 +1,143 @@
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+};
+
+use crate::tokens::message_text;
+use crate::{LlmError, LlmService};
+
+/// 请求 LLM 总结对话时使用的系统指令
+const SUMMARIZE_INSTRUCTION: &str = "Summarize this conversation in 3 bullet points, preserving all shell commands that were generated";
+/// 压缩对话时原样保留的最近消息数（最近两轮问答）
+const RECENT_MESSAGES_KEPT: usize = 4;
+
+/// 多轮对话的消息历史
+///
+/// 第一条`system`消息被视为系统提示词，压缩对话时会保留。
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    messages: Vec<ChatCompletionRequestMessage>,
+    /// 消息数超过此值时，`LlmService::chat_in_conversation`会在发送前自动总结并压缩历史
+    ///
+    /// 为`None`时不自动总结。
+    pub auto_summarize_at: Option<usize>,
+}
+
+impl Conversation {
+    /// 以已有的消息（通常是`PromptContext::build_messages`的结果）开始对话
+    pub fn new(messages: Vec<ChatCompletionRequestMessage>) -> Self {
+        Self {
+            messages,
+            auto_summarize_at: None,
+        }
+    }
+
+    /// 当前的消息历史
+    pub fn messages(&self) -> &[ChatCompletionRequestMessage] {
+        &self.messages
+    }
+
+    /// 追加一条用户消息
+    pub fn push_user(&mut self, content: impl Into<String>) {
+        self.messages.push(
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(content.into())
+                .build()
+                .expect("user message has all required fields")
+                .into(),
+        );
+    }
+
+    /// 追加一条助手消息
+    pub fn push_assistant(&mut self, content: impl Into<String>) {
+        self.messages.push(
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content(content.into())
+                .build()
+                .expect("assistant message has all required fields")
+                .into(),
+        );
+    }
+
+    /// 消息数是否超过了`auto_summarize_at`
+    pub fn needs_summary(&self) -> bool {
+        self.auto_summarize_at
+            .is_some_and(|threshold| self.messages.len() > threshold)
+    }
+
+    /// 将历史压缩为一条包含系统提示词和`summary`的`system`消息，加上最近的几条消息
+    fn compress(&mut self, summary: &str) {
+        let (system_prompt, history_start) = match self.messages.first() {
+            Some(message @ ChatCompletionRequestMessage::System(_)) => (message_text(message), 1),
+            _ => (String::new(), 0),
+        };
+        let content = if system_prompt.is_empty() {
+            format!("Summary of the conversation so far:\n{summary}")
+        } else {
+            format!("{system_prompt}\n\nSummary of the conversation so far:\n{summary}")
+        };
+
+        let recent_start = self
+            .messages
+            .len()
+            .saturating_sub(RECENT_MESSAGES_KEPT)
+            .max(history_start);
+        let recent = self.messages.split_off(recent_start);
+        self.messages = vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(content)
+                .build()
+                .expect("system message has all required fields")
+                .into(),
+        ];
+        self.messages.extend(recent);
+    }
+}
+
+impl LlmService {
+    /// 将对话总结为 3 个要点，并原样保留其中生成的所有命令
+    ///
+    /// # 错误
+    /// 与`chat_completion`相同
+    pub async fn summarize_conversation(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<String, LlmError> {
+        let mut request = messages.to_vec();
+        request.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(SUMMARIZE_INSTRUCTION)
+                .build()
+                .expect("system message has all required fields")
+                .into(),
+        );
+        self.chat_completion_default(request).await
+    }
+
+    /// 在对话中发送一条用户消息，并将回答追加到对话历史
+    ///
+    /// 如果追加用户消息后超过`Conversation::auto_summarize_at`，先总结并压缩历史再发送。
+    pub async fn chat_in_conversation(
+        &self,
+        conversation: &mut Conversation,
+        user_input: &str,
+    ) -> Result<String, LlmError> {
+        conversation.push_user(user_input);
+        if conversation.needs_summary() {
+            // 总结不包括刚追加的用户消息，压缩后它作为最近的消息保留
+            let history = &conversation.messages()[..conversation.messages().len() - 1];
+            let summary = self.summarize_conversation(history).await?;
+            log::debug!(
+                "Summarized {} messages of conversation history",
+                conversation.messages().len()
+            );
+            conversation.compress(&summary);
+        }
+
+        let response = self
+            .chat_completion_default(conversation.messages().to_vec())
+            .await?;
+        conversation.push_assistant(response.clone());
+        Ok(response)
+    }
+}
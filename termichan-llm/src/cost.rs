@@ -0,0 +1,94 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// 一个模型累计的 token 用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenUsage {
+    /// 请求次数
+    pub requests: u64,
+    /// 输入（提示词）token 数
+    pub prompt_tokens: u64,
+    /// 输出 token 数；请求多个候选回答时包含所有候选
+    pub completion_tokens: u64,
+    /// 从提示词缓存读取的输入 token 数（不计入`prompt_tokens`）
+    pub cache_read_tokens: u64,
+    /// 写入提示词缓存的输入 token 数（不计入`prompt_tokens`）
+    pub cache_creation_tokens: u64,
+}
+
+/// 一次请求中与提示词缓存相关的 token 数，见`LlmConfig::enable_prompt_cache`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheUsage {
+    /// 从缓存读取的输入 token 数
+    pub read_tokens: u64,
+    /// 写入缓存的输入 token 数
+    pub creation_tokens: u64,
+}
+
+/// 读取缓存的输入 token 相对于普通输入 token 的价格（Anthropic）
+const CACHE_READ_PRICE_RATIO: f64 = 0.1;
+/// 写入缓存的输入 token 相对于普通输入 token 的价格（Anthropic）
+const CACHE_CREATION_PRICE_RATIO: f64 = 1.25;
+
+impl TokenUsage {
+    /// 输入与输出 token 的总和，包括读写缓存的输入 token
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.cache_read_tokens + self.cache_creation_tokens + self.completion_tokens
+    }
+
+    /// 按普通输入 token 的价格折算的输入 token 数，用于估算费用
+    ///
+    /// 读取缓存的 token 按 0.1 倍、写入缓存的 token 按 1.25 倍计算。
+    pub fn billable_prompt_tokens(&self) -> f64 {
+        self.prompt_tokens as f64
+            + self.cache_read_tokens as f64 * CACHE_READ_PRICE_RATIO
+            + self.cache_creation_tokens as f64 * CACHE_CREATION_PRICE_RATIO
+    }
+}
+
+/// 按模型统计 API 返回的 token 用量
+///
+/// 克隆得到的实例共享同一统计，可在多个`LlmService`之间汇总用量。
+#[derive(Debug, Clone, Default)]
+pub struct CostTracker {
+    usage: Arc<Mutex<HashMap<String, TokenUsage>>>,
+}
+
+impl CostTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次请求的用量
+    pub fn record(&self, model: &str, prompt_tokens: u64, completion_tokens: u64) {
+        self.record_with_cache(model, prompt_tokens, completion_tokens, CacheUsage::default());
+    }
+
+    /// 记录一次使用了提示词缓存的请求的用量
+    pub fn record_with_cache(&self, model: &str, prompt_tokens: u64, completion_tokens: u64, cache: CacheUsage) {
+        let mut usage = self.usage.lock().expect("cost tracker lock poisoned");
+        let entry = usage.entry(model.to_string()).or_default();
+        entry.requests += 1;
+        entry.prompt_tokens += prompt_tokens;
+        entry.completion_tokens += completion_tokens;
+        entry.cache_read_tokens += cache.read_tokens;
+        entry.cache_creation_tokens += cache.creation_tokens;
+    }
+
+    /// 各模型的累计用量
+    pub fn usage(&self) -> HashMap<String, TokenUsage> {
+        self.usage.lock().expect("cost tracker lock poisoned").clone()
+    }
+
+    /// 所有模型的累计用量之和
+    pub fn total(&self) -> TokenUsage {
+        self.usage().values().fold(TokenUsage::default(), |acc, u| TokenUsage {
+            requests: acc.requests + u.requests,
+            prompt_tokens: acc.prompt_tokens + u.prompt_tokens,
+            completion_tokens: acc.completion_tokens + u.completion_tokens,
+            cache_read_tokens: acc.cache_read_tokens + u.cache_read_tokens,
+            cache_creation_tokens: acc.cache_creation_tokens + u.cache_creation_tokens,
+        })
+    }
+}
@@ -0,0 +1,117 @@
+use async_openai::error::OpenAIError;
+use async_openai::types::{CreateEmbeddingRequestArgs, CreateEmbeddingResponse};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{LlmError, LlmService, ProviderCapabilities};
+
+/// 嵌入向量缓存所在的目录，位于响应缓存文件旁
+const EMBEDDINGS_DIR: &str = "embeddings";
+
+impl LlmService {
+    /// 使用`LlmConfig::embedding_model`为`text`生成嵌入向量，返回的向量已归一化（L2 范数为 1）
+    ///
+    /// 通过 OpenAI 兼容接口的`/embeddings`端点请求，遇到速率限制时与聊天补全一样重试。
+    /// 配置了`LlmConfig::cache_file`时，向量以`SHA-256(text + model)`为键缓存在其旁边的
+    /// `embeddings`目录中；同一模型对同一文本的结果不变，缓存不会过期。
+    ///
+    /// # 错误
+    /// - `LlmError::EmbeddingsUnsupported`: 提供商没有嵌入接口（Anthropic），或 Ollama 中还没有拉取该模型
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: API没有返回嵌入向量
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        let config = self.config();
+        if ProviderCapabilities::system_message_as_field(&config.provider) {
+            return Err(LlmError::EmbeddingsUnsupported(config.provider.clone()));
+        }
+        let cache_path = self.embedding_cache_path(text, &config.embedding_model);
+        if let Some(embedding) = cache_path.as_ref().and_then(|path| read_cached(path)) {
+            return Ok(embedding);
+        }
+
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(&config.embedding_model)
+            .input(text)
+            .build()?;
+        let result = self
+            .with_retry(|| self.openai_post::<_, CreateEmbeddingResponse>("/embeddings", &request))
+            .await;
+        let response = match result {
+            Err(e) if config.provider.eq_ignore_ascii_case("ollama") && is_model_missing(&e) => {
+                return Err(LlmError::EmbeddingsUnsupported(format!(
+                    "{} (run `ollama pull {}` first)",
+                    config.provider, config.embedding_model
+                )));
+            }
+            result => result?,
+        };
+        self.record_usage(&config.embedding_model, response.usage.prompt_tokens.into(), 0);
+
+        let mut embedding = response
+            .data
+            .into_iter()
+            .next()
+            .map(|embedding| embedding.embedding)
+            .ok_or(LlmError::EmptyResponse)?;
+        normalize(&mut embedding);
+        if let Some(path) = &cache_path {
+            write_cached(path, &embedding);
+        }
+        Ok(embedding)
+    }
+
+    /// `text`的嵌入向量缓存文件，没有配置响应缓存文件时为`None`
+    fn embedding_cache_path(&self, text: &str, model: &str) -> Option<PathBuf> {
+        let cache_file = self.config().cache_file.clone()?;
+        let dir = cache_file
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from(EMBEDDINGS_DIR), |p| p.join(EMBEDDINGS_DIR));
+        let key = Sha256::new()
+            .chain_update(text.as_bytes())
+            .chain_update(model.as_bytes())
+            .finalize();
+        Some(dir.join(format!("{key:x}.json")))
+    }
+}
+
+/// 将向量缩放为单位长度，零向量保持不变
+fn normalize(embedding: &mut [f32]) {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Ollama 对没有拉取的模型返回“model ... not found”
+fn is_model_missing(error: &LlmError) -> bool {
+    match error {
+        LlmError::WithRequestId { source, .. } => is_model_missing(source),
+        LlmError::ApiError(OpenAIError::ApiError(api_error)) => api_error.message.contains("not found"),
+        _ => false,
+    }
+}
+
+/// 读取缓存的向量，文件不存在或已损坏时返回`None`
+fn read_cached(path: &Path) -> Option<Vec<f32>> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content)
+        .inspect_err(|e| log::warn!("Ignoring corrupt embedding cache {}: {e}", path.display()))
+        .ok()
+}
+
+/// 写入缓存失败只记录警告，不影响返回的向量
+fn write_cached(path: &Path, embedding: &[f32]) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| {
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string(embedding).unwrap_or_default())?;
+            fs::rename(&tmp, path)
+        });
+    if let Err(e) = result {
+        log::warn!("Failed to cache embedding in {}: {e}", path.display());
+    }
+}
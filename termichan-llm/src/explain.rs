@@ -0,0 +1,104 @@
+use termichan_config::PromptConfig;
+
+use crate::prompt::{tail, wrap_user_content, MAX_LAST_ERROR_CHARS};
+use crate::{LlmError, LlmService, PromptContext};
+
+/// 解释命令时使用的系统提示词，`{shell}` 和 `{os}` 按当前环境替换
+const EXPLAIN_SYSTEM_PROMPT: &str = "You are a terminal expert. Explain concisely what the following \
+{shell} command does on {os}, including the effect of each option and any risk of data loss. \
+Reply in plain text without Markdown.";
+
+/// 诊断失败命令时使用的系统提示词，比默认的生成提示词短，以减少 token 消耗
+///
+/// 要求的输出格式与默认提示词一致，可以用`ResponseParser`解析出修正后的命令和解释。
+const FIX_SYSTEM_PROMPT: &str = "You are a terminal expert. The user ran a {shell} command on {os} and it failed. \
+Reply with only the corrected command on the first line, without Markdown, then a line starting with \
+`# Explanation:` that explains the cause of the error and what was changed.";
+
+/// 按用户要求修改命令时使用的系统提示词，只要求返回修改后的命令
+const REWRITE_SYSTEM_PROMPT: &str = "You are a terminal expert. Modify the user's {shell} command on {os} as requested. \
+Reply with only the modified command, without Markdown or explanation.";
+
+impl LlmService {
+    /// 请求 LLM 解释一条已有的命令
+    ///
+    /// 用于在执行危险命令前补充生成结果中缺少的解释，也用于 gRPC 的`ExplainCommand`。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: API返回空解释
+    pub async fn generate_explanation(&self, command: &str) -> Result<String, LlmError> {
+        let prompt = PromptConfig {
+            system_prompt: EXPLAIN_SYSTEM_PROMPT.to_string(),
+            user_prompt_template: "{user_input}".to_string(),
+            ..PromptConfig::default()
+        };
+        let messages = PromptContext::detect()
+            .with_injection_guard(&self.config())
+            .build_messages(&prompt, command);
+        let explanation = self.chat_completion_default(messages).await?;
+        Some(explanation.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .ok_or(LlmError::EmptyResponse)
+    }
+
+    /// 请求 LLM 解释失败命令的错误输出并给出修正后的命令
+    ///
+    /// 使用比`PromptConfig::system_prompt`更短的专用系统提示词；错误输出只保留末尾部分。
+    /// 返回的文本包含修正后的命令和`# Explanation:`解释，可用`ResponseParser::parse`解析。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: API返回空响应
+    pub async fn explain_error(&self, error: &str, command: &str) -> Result<String, LlmError> {
+        let prompt = PromptConfig {
+            system_prompt: FIX_SYSTEM_PROMPT.to_string(),
+            user_prompt_template: "{user_input}".to_string(),
+            ..PromptConfig::default()
+        };
+        let error = tail(error.trim(), MAX_LAST_ERROR_CHARS);
+        let error = if self.config().system_prompt_injection_guard {
+            wrap_user_content(error)
+        } else {
+            error.to_string()
+        };
+        let query = format!("Command: {command}\nError: {error}\nExplain this error and provide a corrected command.");
+        let messages = PromptContext::detect()
+            .with_injection_guard(&self.config())
+            .build_messages(&prompt, &query);
+        let response = self.chat_completion_default(messages).await?;
+        Some(response.trim().to_string())
+            .filter(|r| !r.is_empty())
+            .ok_or(LlmError::EmptyResponse)
+    }
+
+    /// 请求 LLM 按`instruction`（例如"make it recursive"）修改一条已有的命令，返回修改后的命令
+    ///
+    /// 使用比`PromptConfig::system_prompt`更短的专用系统提示词。模型仍用 Markdown 代码块包裹命令时去掉代码块标记。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: API返回空命令
+    pub async fn rewrite_command(&self, command: &str, instruction: &str) -> Result<String, LlmError> {
+        let prompt = PromptConfig {
+            system_prompt: REWRITE_SYSTEM_PROMPT.to_string(),
+            user_prompt_template: "{user_input}".to_string(),
+            ..PromptConfig::default()
+        };
+        let query = format!(
+            "Given this shell command: {command}\nApply this modification: {instruction}\nReturn only the modified command."
+        );
+        let messages = PromptContext::detect()
+            .with_injection_guard(&self.config())
+            .build_messages(&prompt, &query);
+        let response = self.chat_completion_default(messages).await?;
+        let rewritten: Vec<&str> = response
+            .trim()
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("```"))
+            .collect();
+        Some(rewritten.join("\n").trim().to_string())
+            .filter(|r| !r.is_empty())
+            .ok_or(LlmError::EmptyResponse)
+    }
+}
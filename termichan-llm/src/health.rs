@@ -0,0 +1,224 @@
+use async_openai::config::Config as _;
+use serde::Deserialize;
+use std::fmt;
+use std::time::{Duration, Instant};
+use termichan_config::{LlmConfig, NetworkConfig};
+
+use crate::builder::OPENAI_DEFAULT_BASE;
+use crate::provider::ANTHROPIC_DEFAULT_BASE;
+use crate::{http, LlmError, LlmService, ProviderCapabilities};
+
+/// 健康检查结果在此时长内有效，期间不会重复检查。
+const HEALTH_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// 未配置`base_url`时 Ollama 的默认地址
+const OLLAMA_DEFAULT_BASE: &str = "http://localhost:11434";
+
+/// LLM 服务的健康检查结果
+#[derive(Debug, Clone)]
+pub struct HealthStatus {
+    /// 服务提供商，取自`LlmConfig::provider`
+    pub provider: String,
+    /// 配置的模型名称
+    pub model: String,
+    /// 健康检查请求的往返延迟（毫秒）
+    pub latency_ms: u64,
+    /// 服务端报告的 API 版本（如果提供）
+    pub api_version: Option<String>,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}) reachable in {} ms", self.provider, self.model, self.latency_ms)?;
+        if let Some(version) = &self.api_version {
+            write!(f, ", API version {version}")?;
+        }
+        Ok(())
+    }
+}
+
+/// 最近一次健康检查的时间和结果（失败时为`None`）
+pub(crate) struct HealthRecord {
+    checked_at: Instant,
+    status: Option<HealthStatus>,
+}
+
+#[derive(Deserialize)]
+struct OllamaVersion {
+    version: String,
+}
+
+impl LlmService {
+    /// 发送一个轻量请求检查 LLM 服务是否可用
+    ///
+    /// OpenAI 兼容服务和 Anthropic 请求模型列表接口，Ollama 请求版本接口。
+    /// 结果（无论成功与否）会缓存在服务中，供`chat_completion`判断是否需要重新检查。
+    ///
+    /// # 错误
+    /// - `LlmError::NetworkError`: 无法连接到服务
+    /// - `LlmError::UnexpectedStatus`: 服务返回非成功状态码
+    pub async fn health_check(&self) -> Result<HealthStatus, LlmError> {
+        let started = Instant::now();
+        let result = self.probe().await;
+
+        let status = result.map(|api_version| HealthStatus {
+            provider: self.config().provider.clone(),
+            model: self.config().model.clone(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            api_version,
+        });
+
+        *self.last_health.lock().unwrap_or_else(|e| e.into_inner()) = Some(HealthRecord {
+            checked_at: Instant::now(),
+            status: status.as_ref().ok().cloned(),
+        });
+
+        status
+    }
+
+    /// 预先建立到服务的连接，使之后的第一个请求可以复用连接池中的 TCP/TLS 连接
+    ///
+    /// 发送与`health_check`相同的轻量请求，但不记录健康状态，
+    /// 因此预热失败不会影响之后的`chat_completion`。
+    pub async fn prewarm(&self) -> Result<(), LlmError> {
+        let started = Instant::now();
+        let result = self.probe().await.map(|_| ());
+        match &result {
+            Ok(()) => log::debug!("Connection prewarmed in {} ms", started.elapsed().as_millis()),
+            Err(e) => log::debug!("Connection prewarm failed after {} ms: {e}", started.elapsed().as_millis()),
+        }
+        result
+    }
+
+    /// 通过模型列表等轻量接口检查 API 密钥是否有效，不消耗生成请求
+    ///
+    /// Ollama 不需要认证，直接返回`Ok(())`而不发送请求。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiKeyInvalid`: 服务返回 401
+    /// - `LlmError::NetworkError`: 无法连接到服务
+    /// - `LlmError::UnexpectedStatus`: 服务返回其他非成功状态码
+    pub async fn validate_api_key(&self) -> Result<(), LlmError> {
+        if self.config().provider.eq_ignore_ascii_case("ollama") {
+            return Ok(());
+        }
+        let _active = self.pool.track_active();
+        let result = if ProviderCapabilities::system_message_as_field(&self.config().provider) {
+            self.anthropic_models().await
+        } else {
+            self.openai_models().await
+        };
+        match result {
+            Ok(_) => Ok(()),
+            Err(LlmError::UnexpectedStatus { status: 401, .. }) => Err(LlmError::ApiKeyInvalid),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 按提供商发送轻量请求，返回服务端报告的 API 版本
+    async fn probe(&self) -> Result<Option<String>, LlmError> {
+        let _active = self.pool.track_active();
+        if self.config().provider.eq_ignore_ascii_case("ollama") {
+            self.ollama_version().await
+        } else if ProviderCapabilities::system_message_as_field(&self.config().provider) {
+            self.anthropic_models().await
+        } else {
+            self.openai_models().await
+        }
+    }
+
+    /// 返回最近一次成功且仍在有效期内的健康检查结果
+    pub fn cached_health(&self) -> Option<HealthStatus> {
+        let record = self.last_health.lock().unwrap_or_else(|e| e.into_inner());
+        record
+            .as_ref()
+            .filter(|r| r.checked_at.elapsed() < HEALTH_CACHE_TTL)
+            .and_then(|r| r.status.clone())
+    }
+
+    /// 如果之前做过健康检查，但结果已过期或检查失败，则重新检查
+    ///
+    /// 从未检查过时直接返回，避免单次调用额外增加一次往返。
+    pub(crate) async fn recheck_health_if_stale(&self) -> Result<(), LlmError> {
+        let checked = self
+            .last_health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some();
+        if checked && self.cached_health().is_none() {
+            self.health_check().await?;
+        }
+        Ok(())
+    }
+
+    /// 请求 OpenAI 的模型列表接口，返回`openai-version`响应头
+    async fn openai_models(&self) -> Result<Option<String>, LlmError> {
+        let config = self.openai_config();
+        let response = self
+            .http()
+            .get(config.url("/models"))
+            .headers(config.headers())
+            .send()
+            .await?;
+        let response = error_for_status(response).await?;
+
+        Ok(response
+            .headers()
+            .get("openai-version")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string))
+    }
+
+    /// 请求 Ollama 的版本接口
+    async fn ollama_version(&self) -> Result<Option<String>, LlmError> {
+        let root = self.ollama_root();
+        let response = self.http().get(format!("{root}/api/version")).send().await?;
+        let version: OllamaVersion = error_for_status(response).await?.json().await?;
+        Ok(Some(version.version))
+    }
+
+    /// Ollama 原生接口的根地址，不带末尾的`/`
+    pub(crate) fn ollama_root(&self) -> String {
+        let config = self.config();
+        let base = config
+            .base_url
+            .as_deref()
+            .unwrap_or(OLLAMA_DEFAULT_BASE)
+            .trim_end_matches('/');
+        // OpenAI 兼容接口位于`/v1`下，原生接口位于根路径
+        base.strip_suffix("/v1").unwrap_or(base).to_string()
+    }
+}
+
+/// 向`base_url`（未设置时为提供商的默认地址）发送 HEAD 请求，检查能否连接到服务，返回响应的状态码
+///
+/// 不需要 API 密钥，任何 HTTP 响应（包括 401 和 404）都视为可以连接。
+/// 使用与`LlmService`相同的代理、DNS 和超时设置。
+///
+/// # 错误
+/// - `LlmError::NetworkError`: 无法连接到服务或代理
+/// - `LlmError::InvalidNetworkConfig`: 代理、DNS或`provider_headers`设置无效
+/// - `LlmError::TlsConfig`: 无法初始化HTTP客户端的TLS后端
+pub async fn check_reachable(llm: &LlmConfig, network: &NetworkConfig) -> Result<u16, LlmError> {
+    let url = match llm.base_url.as_deref() {
+        Some(url) => url,
+        None if llm.provider.eq_ignore_ascii_case("ollama") => OLLAMA_DEFAULT_BASE,
+        None if ProviderCapabilities::system_message_as_field(&llm.provider) => ANTHROPIC_DEFAULT_BASE,
+        None => OPENAI_DEFAULT_BASE,
+    };
+    let response = http::build_http_client(llm, network)?.head(url).send().await?;
+    Ok(response.status().as_u16())
+}
+
+/// 将非成功状态码转换为`LlmError::UnexpectedStatus`
+pub(crate) async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response, LlmError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(LlmError::UnexpectedStatus {
+        status: status.as_u16(),
+        body,
+    })
+}
@@ -0,0 +1,140 @@
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termichan_config::{LlmConfig, NetworkConfig};
+
+use crate::{LlmError, ProviderCapabilities};
+
+/// OpenAI 按项目计费时使用的请求头
+const OPENAI_PROJECT_HEADER: &str = "openai-project";
+
+/// 根据网络配置构建发送 API 请求使用的 HTTP 客户端
+///
+/// 应用代理、证书校验、超时和`provider_headers`设置；配置了`dns_override`或
+/// `dns_over_https_url`时使用自定义 DNS 解析器，否则使用系统解析器。
+pub(crate) fn build_http_client(
+    llm: &LlmConfig,
+    network: &NetworkConfig,
+) -> Result<reqwest::Client, LlmError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(llm.timeout_secs))
+        .pool_max_idle_per_host(llm.pool_size)
+        .danger_accept_invalid_certs(network.trust_invalid_certs)
+        .default_headers(provider_headers(llm)?);
+
+    if let Some(proxy) = &network.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| LlmError::InvalidNetworkConfig(format!("invalid proxy `{proxy}`: {e}")))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(resolver) = CustomResolver::from_config(network)? {
+        builder = builder.dns_resolver(Arc::new(resolver));
+    }
+
+    // 客户端配置已在上面校验，构建失败几乎都来自 TLS 后端的初始化
+    builder.build().map_err(LlmError::TlsConfig)
+}
+
+/// 将`provider_headers`转换为随每个请求发送的默认请求头，`env:`引用在这里解析
+///
+/// OpenAI 兼容接口设置了`project_id`时加上`OpenAI-Project`，`provider_headers`中的同名请求头优先。
+/// 凭据类请求头的值不会写入日志。
+fn provider_headers(llm: &LlmConfig) -> Result<HeaderMap, LlmError> {
+    let invalid = |e: &dyn std::fmt::Display| LlmError::InvalidNetworkConfig(e.to_string());
+    let mut headers = HeaderMap::new();
+    let project_id = llm
+        .project_id
+        .as_deref()
+        .filter(|_| !ProviderCapabilities::system_message_as_field(&llm.provider));
+    if let Some(project_id) = project_id {
+        let value = HeaderValue::from_str(project_id)
+            .map_err(|_| invalid(&format!("invalid llm.project_id `{project_id}`")))?;
+        headers.insert(OPENAI_PROJECT_HEADER, value);
+    }
+    for (name, value) in llm.resolved_provider_headers().map_err(|e| invalid(&e))? {
+        let sensitive = LlmConfig::is_sensitive_header(&name);
+        let shown = if sensitive { "***" } else { value.as_str() };
+        log::debug!("Sending provider header {name}: {shown}");
+        let header = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| invalid(&format!("invalid provider header name `{name}`")))?;
+        let mut value = HeaderValue::from_str(&value)
+            .map_err(|_| invalid(&format!("invalid value for provider header `{name}`")))?;
+        value.set_sensitive(sensitive);
+        headers.insert(header, value);
+    }
+    Ok(headers)
+}
+
+/// 使用自定义 DNS 服务器或 DNS over HTTPS 的解析器
+struct CustomResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl CustomResolver {
+    /// 根据网络配置创建解析器，两者都未配置时返回`None`
+    ///
+    /// 同时配置时优先使用`dns_over_https_url`。
+    fn from_config(network: &NetworkConfig) -> Result<Option<Self>, LlmError> {
+        let name_servers = match (&network.dns_over_https_url, network.dns_override) {
+            (Some(url), _) => doh_name_servers(url)?,
+            (None, Some(addr)) => NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true),
+            (None, None) => return Ok(None),
+        };
+
+        let config = ResolverConfig::from_parts(None, vec![], name_servers);
+        Ok(Some(Self {
+            resolver: TokioAsyncResolver::tokio(config, ResolverOpts::default()),
+        }))
+    }
+}
+
+impl Resolve for CustomResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.resolver.clone();
+        Box::pin(async move {
+            let started = Instant::now();
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            log::debug!("Resolved {} in {:?}", name.as_str(), started.elapsed());
+
+            let addrs: Addrs = Box::new(
+                lookup
+                    .iter()
+                    .map(|ip| SocketAddr::new(ip, 0))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            );
+            Ok(addrs)
+        })
+    }
+}
+
+/// 解析 DoH URL（例如`https://1.1.1.1/dns-query`）为名称服务器配置
+///
+/// 如果 URL 中的主机是域名，则使用系统解析器获取其地址。
+fn doh_name_servers(url: &str) -> Result<NameServerConfigGroup, LlmError> {
+    let invalid = |reason: &str| LlmError::InvalidNetworkConfig(format!("invalid DNS over HTTPS URL `{url}`: {reason}"));
+
+    let parsed = reqwest::Url::parse(url).map_err(|e| invalid(&e.to_string()))?;
+    if parsed.scheme() != "https" {
+        return Err(invalid("scheme must be https"));
+    }
+    let host = parsed.host_str().ok_or_else(|| invalid("missing host"))?;
+    let port = parsed.port().unwrap_or(443);
+
+    let ips: Vec<IpAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => (host, port)
+            .to_socket_addrs()
+            .map_err(|e| invalid(&e.to_string()))?
+            .map(|addr| addr.ip())
+            .collect(),
+    };
+
+    Ok(NameServerConfigGroup::from_ips_https(&ips, port, host.to_string(), true))
+}
@@ -0,0 +1,95 @@
+use futures::stream::BoxStream;
+use std::sync::{Arc, OnceLock};
+use termichan_config::{LlmConfig, NetworkConfig};
+
+use crate::{
+    ChatCompletionRequestMessage, LlmError, LlmService, LlmServiceBuilder, MetadataStream, RequestOptions,
+    StreamHandle,
+};
+
+/// 创建`LlmService`的函数，由`LazyLlmService`在首次使用时调用
+pub type LlmServiceFactory = Arc<dyn Fn() -> Result<LlmService, LlmError> + Send + Sync>;
+
+/// 首次使用时才创建的`LlmService`
+///
+/// 构建 HTTP 客户端和 DNS 解析器需要一定时间，只在真正发送请求时才付出这部分开销。
+/// 克隆得到的实例共享同一个服务。
+///
+/// 创建失败时错误返回给调用方，不会缓存，下一次调用会重新尝试。
+/// 多个任务同时首次调用时可能各自创建服务，但只有一个会被保留。
+#[derive(Clone)]
+pub struct LazyLlmService {
+    factory: LlmServiceFactory,
+    service: Arc<OnceLock<LlmService>>,
+}
+
+impl LazyLlmService {
+    /// 使用`factory`在首次使用时创建服务
+    pub fn new(factory: impl Fn() -> Result<LlmService, LlmError> + Send + Sync + 'static) -> Self {
+        Self {
+            factory: Arc::new(factory),
+            service: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// 首次使用时按LLM配置和网络配置创建服务，等同于`LlmService::with_network`
+    pub fn from_config(config: LlmConfig, network: NetworkConfig) -> Self {
+        Self::new(move || {
+            LlmServiceBuilder::default()
+                .with_config(config.clone())
+                .with_network(network.clone())
+                .build()
+        })
+    }
+
+    /// 获取服务，尚未创建时立即创建
+    ///
+    /// # 错误
+    /// 返回创建服务时的错误，例如`LlmError::ApiKeyMissing`
+    pub fn get(&self) -> Result<&LlmService, LlmError> {
+        if let Some(service) = self.service.get() {
+            return Ok(service);
+        }
+        let service = (self.factory)()?;
+        Ok(self.service.get_or_init(|| service))
+    }
+
+    /// 服务是否已经创建
+    pub fn is_initialized(&self) -> bool {
+        self.service.get().is_some()
+    }
+
+    /// 创建服务（如果尚未创建）并预热连接池
+    ///
+    /// # 错误
+    /// 返回创建服务或预热请求的错误
+    pub async fn prewarm(&self) -> Result<(), LlmError> {
+        self.get()?.prewarm().await
+    }
+
+    /// 见`LlmService::chat_completion`
+    pub async fn chat_completion(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        opts: Option<RequestOptions>,
+    ) -> Result<String, LlmError> {
+        self.get()?.chat_completion(messages, opts).await
+    }
+
+    /// 见`LlmService::stream_chat_completion`
+    pub async fn stream_chat_completion(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        opts: Option<RequestOptions>,
+    ) -> Result<(StreamHandle, BoxStream<'static, Result<String, LlmError>>), LlmError> {
+        self.get()?.stream_chat_completion(messages, opts).await
+    }
+
+    /// 见`LlmService::streaming_with_metadata`
+    pub async fn streaming_with_metadata(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<(StreamHandle, MetadataStream), LlmError> {
+        self.get()?.streaming_with_metadata(messages).await
+    }
+}
@@ -1,24 +1,162 @@
 use async_openai::{
     config::OpenAIConfig,
     types::{
-        ChatCompletionRequestMessage,
-        CreateChatCompletionRequestArgs
+        ChatCompletionResponseStream, CreateChatCompletionRequestArgs, CreateChatCompletionResponse, FinishReason,
     },
-    Client,
 };
+use reqwest::header::HeaderName;
+use futures::stream::{BoxStream, Stream};
 use futures::StreamExt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use termichan_config::LlmConfig;
+use termichan_config::{LlmConfig, NetworkConfig};
+
+use crate::request::ChatRequest;
+
+mod alias;
+mod benchmark;
+mod builder;
+mod cache;
+mod context;
+mod context_providers;
+mod conversation;
+mod cost;
+mod embeddings;
+mod explain;
+mod health;
+mod http;
+mod lazy;
+mod middleware;
+mod models;
+mod options;
+mod pool;
+mod preprocess;
+mod prompt;
+mod provider;
+mod rate_limit;
+mod reload;
+mod request;
+mod request_id;
+mod retry;
+mod stream;
+mod tokenizer;
+mod tokens;
+mod tools;
+mod warm;
+
+// 消息类型出现在公开接口中，重新导出以免调用方直接依赖 async-openai
+pub use async_openai::types::ChatCompletionRequestMessage;
+pub use alias::ShellKind;
+pub use benchmark::{BenchmarkReport, DEFAULT_BENCHMARK_PROMPT};
+pub use builder::LlmServiceBuilder;
+pub use cache::{Cache, CacheStats};
+pub use context::ContextError;
+pub use conversation::Conversation;
+pub use cost::{CacheUsage, CostTracker, TokenUsage};
+pub use health::{check_reachable, HealthStatus};
+pub use lazy::{LazyLlmService, LlmServiceFactory};
+pub use middleware::{
+    CachingMiddleware, CostTrackingMiddleware, LlmMiddleware, LoggingMiddleware, RateLimitMiddleware, RequestContext,
+    ResponseContext, RetryMiddleware,
+};
+pub use options::RequestOptions;
+// 模型上限表定义在配置 crate 中，供`Config::validate`使用
+pub use termichan_config::ModelLimits;
+pub use pool::PoolMetrics;
+pub use preprocess::{NormalizeWhitespace, PreProcessError, PreProcessor, StripSecrets, TruncateLong};
+pub use prompt::{PromptContext, LAST_EXIT_CODE_ENV, PROMPT_PLACEHOLDERS};
+pub use provider::ProviderCapabilities;
+pub use rate_limit::RateLimiter;
+pub use retry::RateLimitWait;
+pub use stream::{MetadataStream, StreamEvent, StreamHandle};
+pub use tokenizer::{EstimateTokenizer, TiktokenTokenizer, Tokenizer};
+pub use tokens::estimate_text_tokens;
+pub use tools::{ToolCall, ToolDefinition};
+pub use warm::WarmCacheReport;
+
+/// `stream_with_buffering`最多累积这么久就输出一次
+const STREAM_BUFFER_INTERVAL: Duration = Duration::from_millis(50);
 
 /// OpenAI LLM 服务错误类型
 #[derive(Error, Debug)]
 pub enum LlmError {
     #[error("OpenAI API key not configured")]
     ApiKeyMissing,
+    #[error("API key was rejected by the provider (401 Unauthorized)")]
+    ApiKeyInvalid,
     #[error("OpenAI API error: {0}")]
     ApiError(#[from] async_openai::error::OpenAIError),
     #[error("Empty response from OpenAI")]
     EmptyResponse,
+    #[error("Rate limit exceeded{}", match .retry_after {
+        Some(wait) => format!(", retry after {}s", wait.as_secs_f64().ceil()),
+        None => String::new(),
+    })]
+    QuotaExceeded { retry_after: Option<Duration> },
+    #[error("Invalid network configuration: {0}")]
+    InvalidNetworkConfig(String),
+    #[error("Network error: {0}")]
+    NetworkError(#[from] reqwest::Error),
+    #[error("Failed to initialize HTTP client (TLS configuration): {0}")]
+    TlsConfig(#[source] reqwest::Error),
+    #[error("Unexpected HTTP status {status}: {body}")]
+    UnexpectedStatus { status: u16, body: String },
+    #[error("Response stream aborted")]
+    Aborted(String),
+    #[error("Request timed out after {}s", .0.as_secs_f64())]
+    Timeout(Duration),
+    #[error("Response cache is not enabled")]
+    CacheDisabled,
+    #[error("Provider {0} does not support embeddings")]
+    EmbeddingsUnsupported(String),
+    #[error("{source} (request ID: {request_id})")]
+    WithRequestId {
+        request_id: String,
+        #[source]
+        source: Box<LlmError>,
+    },
+}
+
+impl LlmError {
+    /// 失败请求的追踪 ID（配置了`LlmConfig::request_id_header`时）
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::WithRequestId { request_id, .. } => Some(request_id),
+            _ => None,
+        }
+    }
+
+    /// 去掉追踪 ID 后的原始错误，用于按错误类型分支
+    pub fn root(&self) -> &LlmError {
+        match self {
+            Self::WithRequestId { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// 附加发送请求时使用的追踪 ID
+    pub(crate) fn with_request_id(self, request_id: Option<&request_id::RequestId>) -> Self {
+        match request_id {
+            Some(id) => Self::WithRequestId {
+                request_id: id.as_str().to_string(),
+                source: Box::new(self),
+            },
+            None => self,
+        }
+    }
+}
+
+/// 单个模型的比较结果
+#[derive(Debug)]
+pub struct ModelComparison {
+    /// 模型名称
+    pub model: String,
+    /// 模型的响应内容或请求错误
+    pub response: Result<String, LlmError>,
+    /// 从发送请求到收到完整响应所用的时间
+    pub latency: Duration,
 }
 
 /// 提供与OpenAI API交互的服务
@@ -26,8 +164,31 @@ pub enum LlmError {
 /// 该服务封装了OpenAI的聊天补全API，支持流式和非流式响应。
 /// 使用前需要通过`LlmConfig`配置API密钥和模型参数。
 pub struct LlmService {
-    client: Client<OpenAIConfig>,
-    config: LlmConfig,
+    config: RwLock<Arc<LlmConfig>>,
+    connection: RwLock<Connection>,
+    rate_limit_wait: RateLimitWait,
+    last_health: Mutex<Option<health::HealthRecord>>,
+    retry: RetryMiddleware,
+    rate_limiter: Option<RateLimiter>,
+    cache: Option<Cache>,
+    cost_tracker: Option<CostTracker>,
+    pool: pool::PoolTracker,
+    request_id_header: Option<HeaderName>,
+    last_request_id: Mutex<Option<String>>,
+    last_cache_usage: Mutex<Option<CacheUsage>>,
+    last_usage: Mutex<Option<(u32, u32)>>,
+    tokenizer: Arc<dyn Tokenizer>,
+    middlewares: Vec<Arc<dyn LlmMiddleware>>,
+    pre_processors: Vec<Arc<dyn PreProcessor>>,
+}
+
+/// 依赖配置的连接设置，配置热重载时按需替换，见`LlmService::reload_config`
+struct Connection {
+    openai_config: OpenAIConfig,
+    http: reqwest::Client,
+    network: NetworkConfig,
+    /// HTTP 客户端是否由`LlmServiceBuilder::with_http_client`注入，注入的客户端不会被替换
+    injected_http: bool,
 }
 
 impl LlmService {
@@ -39,25 +200,45 @@ impl LlmService {
     /// # 错误
     /// 如果API密钥未配置，返回`LlmError::ApiKeyMissing`
     pub fn new(config: LlmConfig) -> Result<Self, LlmError> {
-        let api_key = config
-            .api_key
-            .as_ref()
-            .ok_or(LlmError::ApiKeyMissing)?;
+        LlmServiceBuilder::default().with_config(config).build()
+    }
 
-        let base_url = config
-            .base_url
-            .as_deref()
-            .unwrap_or("https://api.openai.com/v1")
-            .to_string();
+    /// 从LLM配置和网络配置创建新的LLM服务
+    ///
+    /// 网络配置决定HTTP客户端使用的代理、证书校验和DNS解析器。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiKeyMissing`: API密钥未配置
+    /// - `LlmError::InvalidNetworkConfig`: 代理或DNS设置无效
+    /// - `LlmError::TlsConfig`: 无法初始化HTTP客户端的TLS后端
+    pub fn with_network(config: LlmConfig, network: &NetworkConfig) -> Result<Self, LlmError> {
+        LlmServiceBuilder::default()
+            .with_config(config)
+            .with_network(network.clone())
+            .build()
+    }
+
+    /// 创建构建器，用于注入HTTP客户端、速率限制器、缓存等依赖
+    pub fn builder() -> LlmServiceBuilder {
+        LlmServiceBuilder::default()
+    }
 
-        // 使用OpenAIConfig构建客户端
-        let openai_config = OpenAIConfig::new()
-            .with_api_key(api_key)
-            .with_api_base(base_url);
+    /// 服务使用的响应缓存（如果有）
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
 
-        let client = Client::with_config(openai_config);
+    /// 服务使用的用量统计（如果有）
+    pub fn cost_tracker(&self) -> Option<&CostTracker> {
+        self.cost_tracker.as_ref()
+    }
 
-        Ok(Self { client, config })
+    /// 设置遇到速率限制时的等待策略
+    ///
+    /// 默认策略只是休眠；界面层可以借此显示倒计时等提示。
+    pub fn with_rate_limit_wait(mut self, wait: RateLimitWait) -> Self {
+        self.rate_limit_wait = wait;
+        self
     }
 
     /// 执行聊天补全请求（非流式）
@@ -66,45 +247,350 @@ impl LlmService {
     ///
     /// # 参数
     /// - `messages`: 聊天消息列表，包含用户和系统的对话历史
+    /// - `opts`: 只作用于这次调用的选项，为`None`时全部使用`LlmConfig`中的设置
     ///
     /// # 返回
     /// 返回完整的响应内容字符串
     ///
     /// # 错误
     /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::QuotaExceeded`: 触发速率限制且重试次数已用尽
     /// - `LlmError::EmptyResponse`: API返回空响应
+    /// - `LlmError::Timeout`: 超过了`RequestOptions::timeout`
     pub async fn chat_completion(
         &self,
         messages: Vec<ChatCompletionRequestMessage>,
+        opts: Option<RequestOptions>,
     ) -> Result<String, LlmError> {
-        // 创建请求构建器并设置必要参数
-        let mut request_builder = CreateChatCompletionRequestArgs::default();
-        request_builder
-            .model(&self.config.model)
-            .messages(messages)
-            .temperature(self.config.temperature);
-
-        // 条件设置可选参数（使用可变引用）
-        if let Some(top_p) = self.config.top_p {
-            request_builder.top_p(top_p);
-        }
-        if let Some(max_tokens) = self.config.max_tokens {
-            request_builder.max_tokens(max_tokens as u16);
+        let opts = opts.unwrap_or_default();
+        let config = opts.apply(self.config());
+        let request = async {
+            self.recheck_health_if_stale().await?;
+            self.chat_completion_with_model(messages, &config.model, &config)
+                .await
+        };
+        match opts.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, request)
+                .await
+                .unwrap_or(Err(LlmError::Timeout(timeout))),
+            None => request.await,
         }
+    }
 
-        let request = request_builder.build()?;
+    /// 使用`LlmConfig`中的设置执行聊天补全请求，等同于`chat_completion(messages, None)`
+    pub async fn chat_completion_default(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, LlmError> {
+        self.chat_completion(messages, None).await
+    }
 
+    /// 使用指定模型发送聊天补全请求（非流式）并返回`n`个候选中非空的回答，不经过中间件
+    ///
+    /// 除模型名称外，其余参数均取自`config`。
+    /// 根据`ProviderCapabilities`选择 OpenAI 兼容接口或 Anthropic 接口，不支持`n`的 Anthropic 接口顺序发送`n`次。
+    /// 缓存、速率限制和重试由`LlmServiceBuilder`默认注册的中间件负责，这里只发送一次。
+    async fn send_chat_completion(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        model: &str,
+        config: &LlmConfig,
+        n: u8,
+    ) -> Result<Vec<String>, LlmError> {
+        let _active = self.pool.track_active();
+        if ProviderCapabilities::system_message_as_field(&config.provider) {
+            return self.anthropic_completions(messages, model, config, n).await;
+        }
+        let request = openai_request(messages, model, n, config)?;
         let response = self
-            .client
-            .chat()
-            .create(request)
+            .openai_post::<_, CreateChatCompletionResponse>("/chat/completions", &request)
             .await?;
+        self.completion_choices(model, response)
+    }
 
-        response.choices[0]
-            .message
-            .content
+    /// 通过 Anthropic 接口顺序请求`n`次，`last_usage`记录所有请求的用量之和
+    async fn anthropic_completions(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        model: &str,
+        config: &LlmConfig,
+        n: u8,
+    ) -> Result<Vec<String>, LlmError> {
+        let mut choices = Vec::with_capacity(n.into());
+        let (mut prompt, mut completion) = (0u32, 0u32);
+        for _ in 0..n {
+            choices.push(self.anthropic_completion(messages.clone(), model, config).await?);
+            if let Some((p, c)) = self.last_usage() {
+                prompt = prompt.saturating_add(p);
+                completion = completion.saturating_add(c);
+            }
+        }
+        if n > 1 {
+            *self.last_usage.lock().unwrap_or_else(|e| e.into_inner()) = Some((prompt, completion));
+        }
+        Ok(choices)
+    }
+
+    /// 最近一次补全请求的追踪 ID，未配置`LlmConfig::request_id_header`时为`None`
+    ///
+    /// 多个任务共享同一服务时，只反映最后发出的请求。
+    pub fn last_request_id(&self) -> Option<String> {
+        self.last_request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 最近一次 Anthropic 补全请求的提示词缓存用量，API 未返回用量时为`None`
+    ///
+    /// 与`last_request_id`一样，多个任务共享同一服务时只反映最后完成的请求。
+    pub fn last_cache_usage(&self) -> Option<CacheUsage> {
+        *self.last_cache_usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 最近一次请求 API 报告的（输入 token 数, 输出 token 数），用于`StreamEvent::Usage`
+    fn last_usage(&self) -> Option<(u32, u32)> {
+        *self.last_usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 为一次补全请求生成追踪 ID（如果已配置），并记录为`last_request_id`
+    pub(crate) fn next_request_id(&self) -> Option<request_id::RequestId> {
+        let id = self
+            .request_id_header
+            .as_ref()
+            .map(|header| request_id::RequestId::generate(header, self.config().request_id_prefix.as_deref()));
+        *self.last_request_id.lock().unwrap_or_else(|e| e.into_inner()) =
+            id.as_ref().map(|id| id.as_str().to_string());
+        id
+    }
+
+    /// 向 OpenAI 兼容接口的`path`发送 POST 请求，与服务共享 HTTP 连接池
+    ///
+    /// 由服务自行读取响应而不经过`async-openai`，以便从`Retry-After`响应头取得速率限制的等待时间，
+    /// 见`retry::read_openai_response`。错误中附带本次请求的追踪 ID。
+    pub(crate) async fn openai_post<I, O>(&self, path: &str, request: &I) -> Result<O, LlmError>
+    where
+        I: serde::Serialize,
+        O: serde::de::DeserializeOwned,
+    {
+        use async_openai::config::Config as _;
+
+        let request_id = self.next_request_id();
+        let config = request_id::TracedConfig::new(self.openai_config(), request_id.clone());
+        let result = async {
+            let response = self
+                .http()
+                .post(config.url(path))
+                .query(&config.query())
+                .headers(config.headers())
+                .json(request)
+                .send()
+                .await
+                .map_err(async_openai::error::OpenAIError::Reqwest)?;
+            retry::read_openai_response(response).await
+        };
+        result.await.map_err(|e| e.with_request_id(request_id.as_ref()))
+    }
+
+    /// 向 OpenAI 兼容接口的`path`发送流式 POST 请求，请求头和地址与`openai_post`相同
+    ///
+    /// 响应流中的错误见`request::completion_stream`。
+    fn openai_post_stream<I: serde::Serialize>(
+        &self,
+        path: &str,
+        request: &I,
+    ) -> Result<ChatCompletionResponseStream, LlmError> {
+        use async_openai::config::Config as _;
+        use reqwest_eventsource::RequestBuilderExt;
+
+        let request_id = self.next_request_id();
+        let config = request_id::TracedConfig::new(self.openai_config(), request_id.clone());
+        let events = self
+            .http()
+            .post(config.url(path))
+            .query(&config.query())
+            .headers(config.headers())
+            .json(request)
+            .eventsource()
+            .map_err(|e| {
+                retry::classify(async_openai::error::OpenAIError::StreamError(e.to_string()))
+                    .with_request_id(request_id.as_ref())
+            })?;
+        Ok(request::completion_stream(events))
+    }
+
+    /// 当前使用的LLM配置，`reload_config`之后返回新的配置
+    ///
+    /// 一次请求中应只取一次，避免请求过程中配置变化导致前后不一致。
+    pub(crate) fn config(&self) -> Arc<LlmConfig> {
+        Arc::clone(&self.config.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// 当前使用的HTTP客户端，与服务共享连接池
+    pub(crate) fn http(&self) -> reqwest::Client {
+        self.connection.read().unwrap_or_else(|e| e.into_inner()).http.clone()
+    }
+
+    /// 当前使用的 OpenAI 兼容接口配置
+    pub(crate) fn openai_config(&self) -> OpenAIConfig {
+        self.connection
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .openai_config
             .clone()
-            .ok_or(LlmError::EmptyResponse)
+    }
+
+    /// 记录一次请求的 token 用量（如果配置了`CostTracker`）
+    pub(crate) fn record_usage(&self, model: &str, prompt_tokens: u64, completion_tokens: u64) {
+        self.record_usage_with_cache(model, prompt_tokens, completion_tokens, CacheUsage::default());
+    }
+
+    /// 记录一次请求的 token 用量和提示词缓存用量
+    pub(crate) fn record_usage_with_cache(
+        &self,
+        model: &str,
+        prompt_tokens: u64,
+        completion_tokens: u64,
+        cache: CacheUsage,
+    ) {
+        let saturate = |tokens: u64| u32::try_from(tokens).unwrap_or(u32::MAX);
+        *self.last_usage.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((saturate(prompt_tokens), saturate(completion_tokens)));
+        if let Some(tracker) = &self.cost_tracker {
+            tracker.record_with_cache(model, prompt_tokens, completion_tokens, cache);
+        }
+    }
+
+    /// 对同一组消息请求`n`个候选回答
+    ///
+    /// 对应 OpenAI 请求中的`n`字段，一次请求返回多个补全，按服务商计费方式
+    /// 会消耗`n`倍的输出 token。不支持`n`的提供商（Anthropic）会顺序发送`n`次请求。
+    /// 与`chat_completion`一样经过中间件，但`n`大于 1 时不使用响应缓存。
+    ///
+    /// # 返回
+    /// 返回所有非空的候选回答，顺序与 API 返回的顺序一致
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: 所有候选回答均为空
+    pub async fn chat_completion_n(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        n: u8,
+    ) -> Result<Vec<String>, LlmError> {
+        self.recheck_health_if_stale().await?;
+        let config = self.config();
+        self.chat_completion_choices(messages, &config.model, &config, n.max(1))
+            .await
+    }
+
+    /// 记录补全响应的用量并取出每个非空候选的内容，没有非空候选时返回`LlmError::EmptyResponse`
+    fn completion_choices(
+        &self,
+        model: &str,
+        response: CreateChatCompletionResponse,
+    ) -> Result<Vec<String>, LlmError> {
+        // 请求多个候选时，`completion_tokens`已包含所有候选的输出
+        if let Some(usage) = &response.usage {
+            self.record_usage(model, usage.prompt_tokens.into(), usage.completion_tokens.into());
+        }
+        let choices: Vec<String> = response
+            .choices
+            .into_iter()
+            .filter_map(|choice| choice.message.content)
+            .collect();
+        if choices.is_empty() {
+            return Err(LlmError::EmptyResponse);
+        }
+        Ok(choices)
+    }
+
+    /// 执行聊天补全以外的请求（嵌入、工具调用），按默认注册的`RetryMiddleware`的策略重试
+    ///
+    /// 这些请求不经过中间件链，这里代替链中的`RateLimitMiddleware`和`RetryMiddleware`：
+    /// 发送前按`LlmServiceBuilder::with_rate_limiter`限速，速率限制的等待同样交给`RateLimitWait`。
+    async fn with_retry<T, F, Fut>(&self, mut request: F) -> Result<T, LlmError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = &self.rate_limiter {
+                let _queued = self.pool.track_queued();
+                limiter.acquire().await;
+            }
+            let result = {
+                let _active = self.pool.track_active();
+                request().await
+            };
+            let Err(e) = &result else {
+                return result;
+            };
+            let Some(wait) = self.retry.retry_delay(e, attempt, &self.config()) else {
+                return result;
+            };
+            if matches!(e.root(), LlmError::QuotaExceeded { .. }) {
+                (self.rate_limit_wait)(wait).await;
+            } else {
+                tokio::time::sleep(wait).await;
+            }
+            attempt += 1;
+        }
+    }
+
+    /// 使用多个模型回答同一组消息，便于比较输出
+    ///
+    /// 按顺序（而非并发）对每个模型调用聊天补全，以免触发速率限制。
+    ///
+    /// # 参数
+    /// - `messages`: 聊天消息列表，对每个模型发送相同的内容
+    /// - `models`: 参与比较的模型名称列表
+    ///
+    /// # 返回
+    /// 返回`(模型名称, 响应内容)`列表，顺序与`models`一致
+    ///
+    /// # 错误
+    /// 任一模型请求失败时返回该错误
+    pub async fn compare_models(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        models: Vec<String>,
+    ) -> Result<Vec<(String, String)>, LlmError> {
+        self.compare_models_with_latency(messages, models)
+            .await
+            .into_iter()
+            .map(|comparison| {
+                comparison
+                    .response
+                    .map(|response| (comparison.model, response))
+            })
+            .collect()
+    }
+
+    /// 使用多个模型回答同一组消息，并记录每个模型的延迟
+    ///
+    /// 与`compare_models`不同，单个模型失败不会中断比较，
+    /// 错误会保存在对应的`ModelComparison::response`中。
+    pub async fn compare_models_with_latency(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        models: Vec<String>,
+    ) -> Vec<ModelComparison> {
+        let config = self.config();
+        let mut comparisons = Vec::with_capacity(models.len());
+        for model in models {
+            let started = Instant::now();
+            let response = self
+                .chat_completion_with_model(messages.clone(), &model, &config)
+                .await;
+            comparisons.push(ModelComparison {
+                model,
+                response,
+                latency: started.elapsed(),
+            });
+        }
+        comparisons
     }
 
     /// 执行流式聊天补全请求
@@ -113,39 +599,78 @@ impl LlmService {
     ///
     /// # 参数
     /// - `messages`: 聊天消息列表，包含用户和系统的对话历史
+    /// - `opts`: 只作用于这次调用的选项，为`None`时全部使用`LlmConfig`中的设置
     ///
     /// # 返回
-    /// 返回一个流，每个元素是响应内容块或错误
+    /// 返回`(StreamHandle, 流)`，流的每个元素是响应内容块或错误。
+    /// 调用`StreamHandle::abort`后，流以`LlmError::Aborted(已收到的内容)`结束。
+    /// 流不借用服务本身，可以转交给其他任务（例如 gRPC 响应流）继续读取。
+    /// 设置了`RequestOptions::timeout`时，超时后流以`LlmError::Timeout`结束。
     ///
     /// # 错误
     /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::Timeout`: 收到响应之前就超过了`RequestOptions::timeout`
+    ///
+    /// 对于要求顶层`system`字段的提供商（Anthropic），
+    /// 退化为一次非流式请求，整个响应作为唯一的块返回。
     pub async fn stream_chat_completion(
         &self,
         messages: Vec<ChatCompletionRequestMessage>,
-    ) -> Result<impl futures::Stream<Item = Result<String, LlmError>>, LlmError> {
-        // 创建请求构建器并设置必要参数
-        let mut request_builder = CreateChatCompletionRequestArgs::default();
-        request_builder
-            .model(&self.config.model)
-            .messages(messages)
-            .temperature(self.config.temperature);
+        opts: Option<RequestOptions>,
+    ) -> Result<(StreamHandle, BoxStream<'static, Result<String, LlmError>>), LlmError> {
+        let opts = opts.unwrap_or_default();
+        let Some(timeout) = opts.timeout else {
+            return self.send_stream_chat_completion(messages, opts).await;
+        };
+        // 超时从发送请求时开始计算，读取响应流的时间也计入其中
+        let deadline = tokio::time::Instant::now() + timeout;
+        let (handle, stream) = tokio::time::timeout_at(deadline, self.send_stream_chat_completion(messages, opts))
+            .await
+            .unwrap_or(Err(LlmError::Timeout(timeout)))?;
+        Ok((handle, stream::with_deadline(stream, deadline, timeout)))
+    }
 
-        // 条件设置可选参数（使用可变引用）
-        if let Some(top_p) = self.config.top_p {
-            request_builder.top_p(top_p);
-        }
-        if let Some(max_tokens) = self.config.max_tokens {
-            request_builder.max_tokens(max_tokens as u16);
-        }
+    /// 使用`LlmConfig`中的设置执行流式聊天补全请求，等同于`stream_chat_completion(messages, None)`
+    pub async fn stream_chat_completion_default(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<(StreamHandle, BoxStream<'static, Result<String, LlmError>>), LlmError> {
+        self.stream_chat_completion(messages, None).await
+    }
+
+    /// 执行流式聊天补全请求，把响应中的小块合并后再输出，减少界面重绘
+    ///
+    /// 收到的内容累积到`buffer_size`个字符，或距上次输出经过 50 毫秒时合并为一个元素输出；
+    /// 响应结束时总会输出剩余的内容。需要逐块实时显示时使用`stream_chat_completion`。
+    ///
+    /// # 错误
+    /// 与`stream_chat_completion`相同
+    pub async fn stream_with_buffering(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        buffer_size: usize,
+    ) -> Result<impl Stream<Item = Result<String, LlmError>> + Send + 'static, LlmError> {
+        let (_handle, stream) = self.stream_chat_completion(messages, None).await?;
+        Ok(stream::buffered(stream, buffer_size, STREAM_BUFFER_INTERVAL))
+    }
 
-        let request = request_builder.build()?;
+    /// `stream_chat_completion`中不计超时的部分
+    async fn send_stream_chat_completion(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        opts: RequestOptions,
+    ) -> Result<(StreamHandle, BoxStream<'static, Result<String, LlmError>>), LlmError> {
+        let handle = StreamHandle::default();
+        let config = opts.apply(self.config());
+        if ProviderCapabilities::system_message_as_field(&config.provider) {
+            let response = self.chat_completion(messages, Some(RequestOptions { timeout: None, ..opts })).await?;
+            let stream: BoxStream<'static, Result<String, LlmError>> =
+                futures::stream::once(async move { Ok(response) }).boxed();
+            let stream = stream::abortable(stream, &handle);
+            return Ok((handle, stream.boxed()));
+        }
 
-        let stream = self
-            .client
-            .chat()
-            .create_stream(request)
-            .await
-            .map_err(LlmError::ApiError)?;
+        let (stream, _throttled) = self.openai_stream(messages, &config).await?;
 
         // 将响应流映射为字符串流
         let mapped_stream = stream.map(|chunk| {
@@ -161,10 +686,140 @@ impl LlmService {
                         Err(LlmError::EmptyResponse)
                     }
                 }
-                Err(e) => Err(LlmError::ApiError(e)),
+                Err(e) => Err(retry::classify(e)),
+            }
+        });
+
+        let stream = stream::abortable(mapped_stream.boxed(), &handle);
+        Ok((handle, stream.boxed()))
+    }
+
+    /// 与`stream_chat_completion`相同，但流的元素是带有元数据的`StreamEvent`
+    ///
+    /// 除响应内容外，流中还包括客户端速率限制的等待时间、结束原因，以及 API 报告用量时的 token 用量。
+    /// 只包含角色等信息、没有内容的块不产生事件。
+    ///
+    /// 当前使用的 async-openai 版本不在流式响应中返回用量，OpenAI 兼容接口不会产生`Usage`事件。
+    /// Anthropic 退化为非流式请求，整个响应作为一个`Token`事件，之后是`Usage`事件。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    pub async fn streaming_with_metadata(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<(StreamHandle, MetadataStream), LlmError> {
+        let handle = StreamHandle::default();
+        if ProviderCapabilities::system_message_as_field(&self.config().provider) {
+            *self.last_usage.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            let response = self.chat_completion_default(messages).await?;
+            let mut events = vec![Ok(StreamEvent::Token(response))];
+            if let Some((prompt_tokens, completion_tokens)) = self.last_usage() {
+                events.push(Ok(StreamEvent::Usage {
+                    prompt_tokens,
+                    completion_tokens,
+                }));
             }
+            let stream = stream::abortable(futures::stream::iter(events).boxed(), &handle);
+            return Ok((handle, stream.boxed()));
+        }
+
+        let (stream, throttled) = self.openai_stream(messages, &self.config()).await?;
+        let throttle = (throttled.as_millis() > 0).then(|| {
+            Ok(StreamEvent::ThrottleWarning {
+                wait_ms: throttled.as_millis() as u64,
+            })
+        });
+        let events = stream.flat_map(|chunk| {
+            let events: Vec<Result<StreamEvent, LlmError>> = match chunk {
+                Ok(chunk) => chunk
+                    .choices
+                    .into_iter()
+                    .take(1)
+                    .flat_map(|choice| {
+                        let token = choice.delta.content.filter(|c| !c.is_empty()).map(StreamEvent::Token);
+                        let finish = choice.finish_reason.map(|r| StreamEvent::FinishReason(finish_reason_name(&r)));
+                        token.into_iter().chain(finish).map(Ok)
+                    })
+                    .collect(),
+                Err(e) => vec![Err(retry::classify(e))],
+            };
+            futures::stream::iter(events)
         });
+        let events = futures::stream::iter(throttle).chain(events);
+
+        let stream = stream::abortable(events.boxed(), &handle);
+        Ok((handle, stream.boxed()))
+    }
+
+    /// 按`config`发送流式补全请求，返回响应流和因客户端速率限制等待的时间
+    async fn openai_stream(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        config: &LlmConfig,
+    ) -> Result<(ChatCompletionResponseStream, Duration), LlmError> {
+        // 创建请求构建器并设置必要参数
+        let mut request_builder = CreateChatCompletionRequestArgs::default();
+        request_builder
+            .model(&config.model)
+            .messages(messages)
+            .temperature(config.temperature);
+
+        // 条件设置可选参数（使用可变引用）
+        if let Some(top_p) = config.top_p {
+            request_builder.top_p(top_p);
+        }
+        if let Some(bias) = &config.logit_bias {
+            request_builder.logit_bias(tokenizer::logit_bias_token_ids(&config.model, bias));
+        }
+        let mut request = request_builder.build()?;
+        request.stream = Some(true);
+        let request = ChatRequest::new(request, config.max_tokens);
+
+        let mut throttled = Duration::ZERO;
+        if let Some(limiter) = &self.rate_limiter {
+            let _queued = self.pool.track_queued();
+            let started = Instant::now();
+            limiter.acquire().await;
+            throttled = started.elapsed();
+        }
+        let _active = self.pool.track_active();
+        let stream = self.openai_post_stream("/chat/completions", &request)?;
+        Ok((stream, throttled))
+    }
+}
+
+/// OpenAI 兼容接口请求`n`个补全的请求体，除模型名称外的参数取自`config`
+fn openai_request(
+    messages: Vec<ChatCompletionRequestMessage>,
+    model: &str,
+    n: u8,
+    config: &LlmConfig,
+) -> Result<ChatRequest, LlmError> {
+    // 创建请求构建器并设置必要参数
+    let mut request_builder = CreateChatCompletionRequestArgs::default();
+    request_builder
+        .model(model)
+        .messages(messages)
+        .temperature(config.temperature);
 
-        Ok(mapped_stream)
+    // 条件设置可选参数（使用可变引用）
+    if let Some(top_p) = config.top_p {
+        request_builder.top_p(top_p);
     }
-}
\ No newline at end of file
+    if let Some(bias) = &config.logit_bias {
+        request_builder.logit_bias(tokenizer::logit_bias_token_ids(model, bias));
+    }
+    if n > 1 {
+        request_builder.n(n);
+    }
+    Ok(ChatRequest::new(request_builder.build()?, config.max_tokens))
+}
+
+/// `finish_reason`在 API 中的名称，例如`content_filter`
+fn finish_reason_name(reason: &FinishReason) -> String {
+    serde_json::to_value(reason)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{reason:?}").to_lowercase())
+}
+
@@ -0,0 +1,473 @@
+use async_openai::types::ChatCompletionRequestMessage;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termichan_config::LlmConfig;
+
+use crate::{cache, retry, Cache, CostTracker, LlmError, LlmService, RateLimiter, TokenUsage};
+
+/// 发送聊天补全请求前传给中间件的上下文
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// 请求使用的模型
+    pub model: String,
+    /// 发送的消息，中间件可以修改
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    /// 除模型名称外的请求参数（温度等采样参数），中间件可以替换
+    pub config: Arc<LlmConfig>,
+    /// 本次请求是第几次重试，首次请求为 0
+    pub attempt: u32,
+    /// 请求的候选回答数，见`LlmService::chat_completion_n`；其余请求为 1
+    pub n: u8,
+    /// 设置后不发送请求，直接以此作为响应（例如缓存命中），每个候选回答一项
+    pub response: Option<Vec<String>>,
+    /// 设置后等待这段时间再发送请求；多个中间件设置时取最长的一个
+    pub delay: Option<Duration>,
+}
+
+/// 收到聊天补全响应（或错误）后传给中间件的上下文
+#[derive(Debug)]
+pub struct ResponseContext {
+    /// 请求使用的模型
+    pub model: String,
+    /// 实际发送的消息
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    /// 实际使用的请求参数
+    pub config: Arc<LlmConfig>,
+    /// 本次请求是第几次重试，首次请求为 0
+    pub attempt: u32,
+    /// 请求的候选回答数，其余请求为 1
+    pub n: u8,
+    /// 非空的候选回答或请求错误，中间件可以替换
+    pub result: Result<Vec<String>, LlmError>,
+    /// API 报告的 token 用量；响应来自中间件或 API 未返回用量时为`None`
+    pub usage: Option<TokenUsage>,
+    /// 响应是否由中间件在`before_request`中给出，没有实际发送请求
+    pub short_circuited: bool,
+    /// 从发送请求到收到响应所用的时间
+    pub latency: Duration,
+    /// 设置后等待这段时间并重新发送请求，重新经过所有中间件
+    pub retry_after: Option<Duration>,
+}
+
+/// 聊天补全请求的中间件
+///
+/// 中间件可以修改请求、直接给出响应、延迟发送或要求重试，通过`LlmServiceBuilder::with_middleware`
+/// 注册。`before_request`和`after_response`都按注册顺序调用。
+/// 服务的响应缓存、客户端速率限制和重试也以中间件实现，由`LlmServiceBuilder::build`排在链的最前面，
+/// 依次为`CachingMiddleware`（`with_cache`）、`RateLimitMiddleware`（`with_rate_limiter`）和`RetryMiddleware`。
+/// 只作用于非流式的聊天补全（包括`chat_completion`、`chat_completion_n`和`compare_models`），
+/// 流式请求不经过中间件。
+pub trait LlmMiddleware: Send + Sync {
+    /// 发送请求前调用
+    fn before_request(&self, _req: &mut RequestContext) {}
+
+    /// 收到响应或请求失败后调用
+    fn after_response(&self, _res: &mut ResponseContext) {}
+}
+
+/// 记录每次请求和响应的中间件
+///
+/// 请求记录在`debug`级别，失败的请求记录在`warn`级别；只记录模型、消息数和长度，不记录内容。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingMiddleware;
+
+impl LlmMiddleware for LoggingMiddleware {
+    fn before_request(&self, req: &mut RequestContext) {
+        log::debug!(
+            "Sending chat completion to {} with {} messages (attempt {})",
+            req.model,
+            req.messages.len(),
+            req.attempt + 1
+        );
+    }
+
+    fn after_response(&self, res: &mut ResponseContext) {
+        match &res.result {
+            Ok(choices) => log::debug!(
+                "Received {} bytes from {} in {:?}",
+                choices.iter().map(String::len).sum::<usize>(),
+                res.model,
+                res.latency
+            ),
+            Err(e) => log::warn!(
+                "Chat completion with {} failed after {:?}: {e}",
+                res.model,
+                res.latency
+            ),
+        }
+    }
+}
+
+/// 在客户端限制每分钟请求数的中间件
+///
+/// 与`LlmServiceBuilder::with_rate_limiter`共用滑动窗口的实现，窗口已满时延迟发送。
+#[derive(Debug, Clone)]
+pub struct RateLimitMiddleware {
+    limiter: RateLimiter,
+}
+
+impl RateLimitMiddleware {
+    /// 每分钟最多`requests_per_minute`个请求
+    pub fn per_minute(requests_per_minute: usize) -> Self {
+        Self::new(RateLimiter::per_minute(requests_per_minute))
+    }
+
+    /// 使用`limiter`限制速率，克隆的限制器可在多个服务之间共享限额
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self { limiter }
+    }
+}
+
+impl LlmMiddleware for RateLimitMiddleware {
+    fn before_request(&self, req: &mut RequestContext) {
+        // 响应已由之前的中间件给出（例如缓存命中）时不会发送请求，不占用限额
+        if req.response.is_some() {
+            return;
+        }
+        let wait = self.limiter.reserve();
+        if !wait.is_zero() {
+            req.delay = req.delay.max(Some(wait));
+        }
+    }
+}
+
+/// 遇到速率限制或连接失败时按指数退避重试的中间件
+///
+/// 重试次数按请求的总重试次数（`ResponseContext::attempt`）计算，多个重试中间件同时注册时
+/// 不会叠加重试，而是取各自的次数上限和等待时间中较大的一个。
+#[derive(Debug, Clone, Copy)]
+pub struct RetryMiddleware {
+    /// 速率限制的重试次数，`None`表示使用请求参数中的`LlmConfig::max_retries`
+    max_retries: Option<u32>,
+    /// 无法建立连接时的重试次数
+    network_retries: u32,
+    /// 域名解析失败时以固定间隔重试的次数，用完后按连接失败处理
+    dns_retries: u32,
+}
+
+impl RetryMiddleware {
+    /// 最多重试`max_retries`次
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries: Some(max_retries),
+            network_retries: max_retries,
+            dns_retries: 0,
+        }
+    }
+
+    /// `LlmService`默认注册的重试策略：速率限制按`LlmConfig::max_retries`重试，配置热重载后立即生效；
+    /// 连接失败和域名解析失败按`NetworkConfig`中的次数重试
+    pub(crate) fn with_network(network_retries: u32, dns_retries: u32) -> Self {
+        Self {
+            max_retries: None,
+            network_retries,
+            dns_retries,
+        }
+    }
+
+    /// 第`attempt`次重试（首次请求为 0）失败并返回`error`后，应等待多久再重试；不应重试时返回`None`
+    ///
+    /// 不经过中间件的请求（嵌入、工具调用）也按此决定是否重试，见`LlmService::with_retry`。
+    pub(crate) fn retry_delay(&self, error: &LlmError, attempt: u32, config: &LlmConfig) -> Option<Duration> {
+        // 优先使用API建议的等待时间，否则指数退避
+        let (wait, limit) = match error.root() {
+            LlmError::QuotaExceeded { retry_after } => (
+                retry_after.unwrap_or_else(|| retry::backoff_delay(attempt)),
+                self.max_retries.unwrap_or(config.max_retries),
+            ),
+            _ if attempt < self.dns_retries && retry::dns_failure(error).is_some() => {
+                (retry::DNS_RETRY_DELAY, self.dns_retries)
+            }
+            _ if retry::connect_failure(error).is_some() => (retry::backoff_delay(attempt), self.network_retries),
+            _ => return None,
+        };
+        if attempt >= limit {
+            return None;
+        }
+        log::warn!(
+            "Request failed ({error}), retrying in {wait:?} (attempt {}/{limit})",
+            attempt + 1
+        );
+        Some(wait)
+    }
+}
+
+impl LlmMiddleware for RetryMiddleware {
+    fn after_response(&self, res: &mut ResponseContext) {
+        if let Err(e) = &res.result {
+            let wait = self.retry_delay(e, res.attempt, &res.config);
+            res.retry_after = res.retry_after.max(wait);
+        }
+    }
+}
+
+/// 缓存响应的中间件
+///
+/// 以提供商、模型、采样参数和消息内容为键，与`LlmServiceBuilder::with_cache`相同。
+/// 请求多个候选回答（`RequestContext::n`大于 1）时既不读取也不写入缓存。
+#[derive(Debug, Clone)]
+pub struct CachingMiddleware {
+    cache: Cache,
+}
+
+impl CachingMiddleware {
+    /// 使用`cache`保存响应，克隆的缓存可在多个服务之间共享
+    pub fn new(cache: Cache) -> Self {
+        Self { cache }
+    }
+}
+
+impl LlmMiddleware for CachingMiddleware {
+    fn before_request(&self, req: &mut RequestContext) {
+        if req.response.is_none() && req.n == 1 {
+            req.response = self
+                .cache
+                .get(&cache::request_key(&req.messages, &req.model, &req.config))
+                .map(|response| vec![response]);
+            if req.response.is_some() {
+                log::debug!("Using cached response for model {}", req.model);
+            }
+        }
+    }
+
+    fn after_response(&self, res: &mut ResponseContext) {
+        if res.n != 1 || res.short_circuited {
+            return;
+        }
+        if let Ok([response]) = res.result.as_deref() {
+            self.cache.insert(
+                cache::request_key(&res.messages, &res.model, &res.config),
+                response.clone(),
+            );
+        }
+    }
+}
+
+/// 将响应的 token 用量记录到`CostTracker`的中间件
+///
+/// `LlmServiceBuilder::with_cost_tracker`设置的统计已经记录所有请求的用量，不要再把同一个统计交给此中间件。
+#[derive(Debug, Clone)]
+pub struct CostTrackingMiddleware {
+    tracker: CostTracker,
+}
+
+impl CostTrackingMiddleware {
+    /// 把用量记录到`tracker`，克隆的统计可在多个服务之间汇总
+    pub fn new(tracker: CostTracker) -> Self {
+        Self { tracker }
+    }
+}
+
+impl LlmMiddleware for CostTrackingMiddleware {
+    fn after_response(&self, res: &mut ResponseContext) {
+        if let Some(usage) = res.usage {
+            self.tracker
+                .record(&res.model, usage.prompt_tokens, usage.completion_tokens);
+        }
+    }
+}
+
+impl LlmService {
+    /// 使用指定模型、经过中间件执行一次非流式聊天补全
+    ///
+    /// 除模型名称外的请求参数取自`config`。
+    pub(crate) async fn chat_completion_with_model(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        model: &str,
+        config: &LlmConfig,
+    ) -> Result<String, LlmError> {
+        let choices = self.chat_completion_choices(messages, model, config, 1).await?;
+        choices.into_iter().next().ok_or(LlmError::EmptyResponse)
+    }
+
+    /// 使用指定模型、经过中间件请求`n`个候选回答，返回其中非空的回答
+    ///
+    /// 除模型名称外的请求参数取自`config`。
+    pub(crate) async fn chat_completion_choices(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        model: &str,
+        config: &LlmConfig,
+        n: u8,
+    ) -> Result<Vec<String>, LlmError> {
+        let config = Arc::new(config.clone());
+        let mut attempt = 0;
+        loop {
+            let mut req = RequestContext {
+                model: model.to_string(),
+                messages: messages.clone(),
+                config: Arc::clone(&config),
+                attempt,
+                n,
+                response: None,
+                delay: None,
+            };
+            for middleware in &self.middlewares {
+                middleware.before_request(&mut req);
+            }
+            if let Some(delay) = req.delay {
+                let _queued = self.pool.track_queued();
+                tokio::time::sleep(delay).await;
+            }
+
+            let started = Instant::now();
+            let short_circuited = req.response.is_some();
+            let (result, usage) = match req.response {
+                Some(response) => (Ok(response), None),
+                None => {
+                    let result = self
+                        .send_chat_completion(req.messages.clone(), &req.model, &req.config, n)
+                        .await;
+                    // 与`last_request_id`一样，多个任务共享同一服务时可能读到其他请求的用量
+                    let usage =
+                        self.last_usage()
+                            .filter(|_| result.is_ok())
+                            .map(|(prompt, completion)| TokenUsage {
+                                requests: 1,
+                                prompt_tokens: prompt.into(),
+                                completion_tokens: completion.into(),
+                                ..TokenUsage::default()
+                            });
+                    (result, usage)
+                }
+            };
+            let mut res = ResponseContext {
+                model: req.model,
+                messages: req.messages,
+                config: req.config,
+                attempt,
+                n,
+                result,
+                usage,
+                short_circuited,
+                latency: started.elapsed(),
+                retry_after: None,
+            };
+            for middleware in &self.middlewares {
+                middleware.after_response(&mut res);
+            }
+
+            match res.retry_after {
+                Some(wait) => {
+                    // 速率限制的等待交给`RateLimitWait`，界面层可以显示倒计时
+                    match &res.result {
+                        Err(e) if matches!(e.root(), LlmError::QuotaExceeded { .. }) => {
+                            (self.rate_limit_wait)(wait).await
+                        }
+                        _ => tokio::time::sleep(wait).await,
+                    }
+                    attempt += 1;
+                }
+                None => return res.result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    fn request(config: LlmConfig) -> RequestContext {
+        let message = ChatCompletionRequestUserMessageArgs::default()
+            .content("list files")
+            .build()
+            .unwrap()
+            .into();
+        RequestContext {
+            model: config.model.clone(),
+            messages: vec![message],
+            config: Arc::new(config),
+            attempt: 0,
+            n: 1,
+            response: None,
+            delay: None,
+        }
+    }
+
+    fn response(req: RequestContext, attempt: u32, result: Result<Vec<String>, LlmError>) -> ResponseContext {
+        ResponseContext {
+            model: req.model,
+            messages: req.messages,
+            config: req.config,
+            attempt,
+            n: req.n,
+            result,
+            usage: None,
+            short_circuited: false,
+            latency: Duration::ZERO,
+            retry_after: None,
+        }
+    }
+
+    #[test]
+    fn cache_keys_include_sampling_parameters() {
+        let middleware = CachingMiddleware::new(Cache::default());
+        let config = LlmConfig::default();
+        middleware.after_response(&mut response(request(config.clone()), 0, Ok(vec!["ls".to_string()])));
+
+        let mut hit = request(config.clone());
+        middleware.before_request(&mut hit);
+        assert_eq!(hit.response, Some(vec!["ls".to_string()]));
+
+        let mut other = request(LlmConfig {
+            temperature: config.temperature + 0.5,
+            ..config
+        });
+        middleware.before_request(&mut other);
+        assert_eq!(other.response, None);
+    }
+
+    #[test]
+    fn multiple_choices_bypass_the_cache() {
+        let middleware = CachingMiddleware::new(Cache::default());
+        let config = LlmConfig::default();
+        let several = || RequestContext {
+            n: 3,
+            ..request(config.clone())
+        };
+        middleware.after_response(&mut response(several(), 0, Ok(vec!["ls".to_string()])));
+        let mut miss = request(config.clone());
+        middleware.before_request(&mut miss);
+        assert_eq!(miss.response, None);
+
+        middleware.after_response(&mut response(request(config.clone()), 0, Ok(vec!["ls".to_string()])));
+        let mut other = several();
+        middleware.before_request(&mut other);
+        assert_eq!(other.response, None);
+    }
+
+    #[test]
+    fn retry_middlewares_share_the_attempt_count() {
+        let builtin = RetryMiddleware::with_network(0, 0);
+        let extra = RetryMiddleware::new(1);
+        let config = LlmConfig {
+            max_retries: 2,
+            ..LlmConfig::default()
+        };
+        let quota = || {
+            Err(LlmError::QuotaExceeded {
+                retry_after: Some(Duration::from_secs(3)),
+            })
+        };
+
+        let mut first = response(request(config.clone()), 0, quota());
+        builtin.after_response(&mut first);
+        extra.after_response(&mut first);
+        assert_eq!(first.retry_after, Some(Duration::from_secs(3)));
+
+        // 第二次重试只有内置中间件（`max_retries = 2`）还允许，总共只重试两次
+        let mut second = response(request(config.clone()), 1, quota());
+        extra.after_response(&mut second);
+        assert_eq!(second.retry_after, None);
+        builtin.after_response(&mut second);
+        assert_eq!(second.retry_after, Some(Duration::from_secs(3)));
+
+        let mut third = response(request(config), 2, quota());
+        builtin.after_response(&mut third);
+        extra.after_response(&mut third);
+        assert_eq!(third.retry_after, None);
+    }
+}
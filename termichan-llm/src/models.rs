@@ -0,0 +1,62 @@
+use async_openai::config::Config as _;
+use serde::Deserialize;
+
+use crate::health::error_for_status;
+use crate::{LlmError, LlmService, ProviderCapabilities};
+
+/// OpenAI 和 Anthropic 模型列表接口的响应
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// Ollama`/api/tags`接口的响应
+#[derive(Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+impl LlmService {
+    /// 列出服务提供的模型名称，按名称排序
+    ///
+    /// OpenAI 兼容服务和 Anthropic 请求模型列表接口，Ollama 列出本地已下载的模型。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiKeyMissing`: Anthropic 的API密钥未配置
+    /// - `LlmError::NetworkError`: 无法连接到服务或响应格式不正确
+    /// - `LlmError::UnexpectedStatus`: 服务返回非成功状态码
+    pub async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let provider = self.config().provider.clone();
+        let mut models: Vec<String> = if provider.eq_ignore_ascii_case("ollama") {
+            let response = self
+                .http()
+                .get(format!("{}/api/tags", self.ollama_root()))
+                .send()
+                .await?;
+            let tags: OllamaTags = error_for_status(response).await?.json().await?;
+            tags.models.into_iter().map(|m| m.name).collect()
+        } else {
+            let request = if ProviderCapabilities::system_message_as_field(&provider) {
+                // 默认每页只有 20 个模型
+                self.anthropic_request(reqwest::Method::GET, "/models?limit=100")?
+            } else {
+                let config = self.openai_config();
+                self.http().get(config.url("/models")).headers(config.headers())
+            };
+            let list: ModelList = error_for_status(request.send().await?).await?.json().await?;
+            list.data.into_iter().map(|m| m.id).collect()
+        };
+        models.sort();
+        Ok(models)
+    }
+}
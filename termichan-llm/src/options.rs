@@ -0,0 +1,35 @@
+use std::sync::Arc;
+use std::time::Duration;
+use termichan_config::LlmConfig;
+
+/// 单次请求的选项，设置的字段覆盖`LlmConfig`中的默认值
+///
+/// 传给`LlmService::chat_completion`或`stream_chat_completion`，只影响这一次调用。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RequestOptions {
+    /// 整个调用的超时时间，包括等待速率限制和重试；流式请求包括读取整个响应流
+    ///
+    /// HTTP 客户端的`LlmConfig::timeout_secs`仍然作用于每个 HTTP 请求，因此比它更长的值不会延长单个请求。
+    pub timeout: Option<Duration>,
+    /// 覆盖`LlmConfig::max_tokens`
+    pub max_tokens: Option<u32>,
+    /// 覆盖`LlmConfig::temperature`
+    pub temperature: Option<f32>,
+}
+
+impl RequestOptions {
+    /// 应用覆盖后的配置，没有覆盖请求参数时直接返回`config`
+    pub(crate) fn apply(&self, config: Arc<LlmConfig>) -> Arc<LlmConfig> {
+        if self.max_tokens.is_none() && self.temperature.is_none() {
+            return config;
+        }
+        let mut config = LlmConfig::clone(&config);
+        if let Some(max_tokens) = self.max_tokens {
+            config.max_tokens = Some(max_tokens);
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        Arc::new(config)
+    }
+}
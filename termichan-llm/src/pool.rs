@@ -0,0 +1,87 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::LlmService;
+
+/// HTTP 连接池的使用情况
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// 估算的空闲连接数
+    pub idle_connections: usize,
+    /// 正在进行的请求数
+    pub active_connections: usize,
+    /// 正在等待速率限制器放行的请求数
+    pub queued_requests: usize,
+}
+
+impl fmt::Display for PoolMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} active, {} idle, {} queued",
+            self.active_connections, self.idle_connections, self.queued_requests
+        )
+    }
+}
+
+/// 通过原子计数器跟踪经过`LlmService`的请求
+///
+/// `reqwest`不公开连接池的内部状态，因此空闲连接数是估算值：
+/// 连接池最多保留`pool_size`个空闲连接，并发请求的峰值近似于已建立的连接数。
+#[derive(Debug)]
+pub(crate) struct PoolTracker {
+    pool_size: usize,
+    active: AtomicUsize,
+    peak: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+impl PoolTracker {
+    pub(crate) fn new(pool_size: usize) -> Self {
+        Self {
+            pool_size,
+            active: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// 标记一个请求开始，返回的守卫被丢弃时标记结束
+    pub(crate) fn track_active(&self) -> CounterGuard<'_> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(active, Ordering::SeqCst);
+        CounterGuard(&self.active)
+    }
+
+    /// 标记一个请求开始排队，返回的守卫被丢弃时标记离开队列
+    pub(crate) fn track_queued(&self) -> CounterGuard<'_> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        CounterGuard(&self.queued)
+    }
+
+    fn metrics(&self) -> PoolMetrics {
+        let active = self.active.load(Ordering::SeqCst);
+        let established = self.peak.load(Ordering::SeqCst).min(self.pool_size.max(active));
+        PoolMetrics {
+            idle_connections: established.saturating_sub(active).min(self.pool_size),
+            active_connections: active,
+            queued_requests: self.queued.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// 被丢弃时将计数器减一
+pub(crate) struct CounterGuard<'a>(&'a AtomicUsize);
+
+impl Drop for CounterGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl LlmService {
+    /// 当前的连接池使用情况
+    pub fn pool_metrics(&self) -> PoolMetrics {
+        self.pool.metrics()
+    }
+}
@@ -0,0 +1,190 @@
+use regex::Regex;
+use std::sync::{Arc, LazyLock};
+use termichan_config::{PreProcessorConfig, PromptConfig};
+use thiserror::Error;
+
+use crate::LlmService;
+
+/// 形如 API 密钥的连续大写字母和数字
+static SECRET_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[A-Z0-9]{20,}\b").expect("secret pattern is valid"));
+
+/// 查询预处理错误类型
+#[derive(Error, Debug)]
+pub enum PreProcessError {
+    #[error("Query is empty after pre-processing")]
+    EmptyQuery,
+    #[error("Pre-processor failed: {0}")]
+    Failed(String),
+}
+
+/// 在构建提示词之前转换用户查询，例如解码 URL、删除敏感信息或展开缩写
+///
+/// 内置的预处理器由`PromptConfig::pre_processing_chain`配置，
+/// 自定义预处理器通过`LlmServiceBuilder::with_pre_processor`注册。
+pub trait PreProcessor: Send + Sync {
+    /// 返回处理后的查询
+    fn process(&self, input: &str) -> Result<String, PreProcessError>;
+}
+
+/// 删除形如 API 密钥的连续 20 个以上大写字母和数字
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripSecrets;
+
+impl PreProcessor for StripSecrets {
+    fn process(&self, input: &str) -> Result<String, PreProcessError> {
+        Ok(SECRET_PATTERN.replace_all(input, "").into_owned())
+    }
+}
+
+/// 只保留前指定数量的字符
+#[derive(Debug, Clone, Copy)]
+pub struct TruncateLong(pub usize);
+
+impl PreProcessor for TruncateLong {
+    fn process(&self, input: &str) -> Result<String, PreProcessError> {
+        Ok(input.chars().take(self.0).collect())
+    }
+}
+
+/// 去掉首尾空白，并把连续的空白字符合并为一个空格
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeWhitespace;
+
+impl PreProcessor for NormalizeWhitespace {
+    fn process(&self, input: &str) -> Result<String, PreProcessError> {
+        Ok(input.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// 按配置创建内置的预处理器
+fn from_config(config: &PreProcessorConfig) -> Arc<dyn PreProcessor> {
+    match config {
+        PreProcessorConfig::StripSecrets => Arc::new(StripSecrets),
+        PreProcessorConfig::TruncateLong { max_chars } => Arc::new(TruncateLong(*max_chars)),
+        PreProcessorConfig::NormalizeWhitespace => Arc::new(NormalizeWhitespace),
+    }
+}
+
+impl LlmService {
+    /// 依次用`prompt.pre_processing_chain`和通过构建器注册的预处理器处理`query`
+    ///
+    /// # Errors
+    ///
+    /// - `PreProcessError::EmptyQuery`: 处理后的查询为空
+    /// - 预处理器返回的其他错误
+    pub fn pre_process(&self, query: &str, prompt: &PromptConfig) -> Result<String, PreProcessError> {
+        let configured = prompt.pre_processing_chain.iter().map(from_config);
+        let mut processed = query.to_string();
+        for pre_processor in configured.chain(self.pre_processors.iter().cloned()) {
+            processed = pre_processor.process(&processed)?;
+        }
+        if processed.trim().is_empty() {
+            return Err(PreProcessError::EmptyQuery);
+        }
+        Ok(processed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LlmServiceBuilder;
+    use termichan_config::LlmConfig;
+
+    /// 在查询末尾追加固定文本，用于检查调用顺序
+    struct Append(&'static str);
+
+    impl PreProcessor for Append {
+        fn process(&self, input: &str) -> Result<String, PreProcessError> {
+            Ok(format!("{input}{}", self.0))
+        }
+    }
+
+    struct Reject;
+
+    impl PreProcessor for Reject {
+        fn process(&self, _input: &str) -> Result<String, PreProcessError> {
+            Err(PreProcessError::Failed("rejected".to_string()))
+        }
+    }
+
+    fn service(builder: LlmServiceBuilder) -> LlmService {
+        let config = LlmConfig {
+            api_key: Some("test".to_string()),
+            ..LlmConfig::default()
+        };
+        builder.with_config(config).build().unwrap()
+    }
+
+    fn chain(pre_processing_chain: Vec<PreProcessorConfig>) -> PromptConfig {
+        PromptConfig {
+            pre_processing_chain,
+            ..PromptConfig::default()
+        }
+    }
+
+    #[test]
+    fn built_in_pre_processors() {
+        assert_eq!(
+            StripSecrets.process("export KEY=ABCDEF0123456789ABCD now").unwrap(),
+            "export KEY= now"
+        );
+        // 不足 20 个字符或含有小写字母的不视为密钥
+        assert_eq!(StripSecrets.process("git show ABCDEF0123").unwrap(), "git show ABCDEF0123");
+        assert_eq!(
+            StripSecrets.process("cd Abcdef0123456789abcd").unwrap(),
+            "cd Abcdef0123456789abcd"
+        );
+
+        assert_eq!(TruncateLong(4).process("列出所有文件").unwrap(), "列出所有");
+        assert_eq!(TruncateLong(100).process("ls").unwrap(), "ls");
+
+        assert_eq!(
+            NormalizeWhitespace.process("  list\n\tfiles   here ").unwrap(),
+            "list files here"
+        );
+    }
+
+    #[test]
+    fn configured_chain_runs_before_registered_pre_processors() {
+        let service = service(
+            LlmServiceBuilder::default()
+                .with_pre_processor(Append("!"))
+                .with_pre_processor(Append("?")),
+        );
+        let prompt = chain(vec![
+            PreProcessorConfig::StripSecrets,
+            PreProcessorConfig::NormalizeWhitespace,
+            PreProcessorConfig::TruncateLong { max_chars: 9 },
+        ]);
+        let processed = service
+            .pre_process("list ABCDEF0123456789ABCD   files  now", &prompt)
+            .unwrap();
+        assert_eq!(processed, "list file!?");
+
+        // 先截断再合并空白时结果不同
+        let prompt = chain(vec![
+            PreProcessorConfig::TruncateLong { max_chars: 9 },
+            PreProcessorConfig::NormalizeWhitespace,
+        ]);
+        let processed = service.pre_process("list     files", &prompt).unwrap();
+        assert_eq!(processed, "list!?");
+    }
+
+    #[test]
+    fn empty_queries_are_rejected() {
+        let plain = service(LlmServiceBuilder::default());
+        let prompt = chain(vec![PreProcessorConfig::StripSecrets]);
+        let result = plain.pre_process(" ABCDEF0123456789ABCD ", &prompt);
+        assert!(matches!(result, Err(PreProcessError::EmptyQuery)), "{result:?}");
+        assert!(matches!(
+            plain.pre_process("   ", &PromptConfig::default()),
+            Err(PreProcessError::EmptyQuery)
+        ));
+
+        let rejecting = service(LlmServiceBuilder::default().with_pre_processor(Reject));
+        let result = rejecting.pre_process("list files", &PromptConfig::default());
+        assert!(matches!(result, Err(PreProcessError::Failed(reason)) if reason == "rejected"));
+    }
+}
@@ -0,0 +1,342 @@
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPart,
+    ChatCompletionRequestUserMessageContent,
+};
+use serde::{Deserialize, Serialize, Serializer};
+use std::ops::Deref;
+use termichan_config::LlmConfig;
+
+use crate::request_id::RequestId;
+use crate::{CacheUsage, LlmError, LlmService};
+
+/// 未配置`base_url`时 Anthropic 的默认地址
+pub(crate) const ANTHROPIC_DEFAULT_BASE: &str = "https://api.anthropic.com";
+/// Anthropic Messages API 版本
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Anthropic 要求必须指定`max_tokens`，配置未设置时使用此值
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// 不同服务提供商在请求格式上的差异
+pub struct ProviderCapabilities;
+
+impl ProviderCapabilities {
+    /// 该提供商是否要求系统提示词放在顶层`system`字段，而不是`messages`数组中
+    ///
+    /// Anthropic 不接受`system`角色的消息；OpenAI 及其兼容服务保持默认行为。
+    pub fn system_message_as_field(provider: &str) -> bool {
+        provider.eq_ignore_ascii_case("anthropic")
+    }
+}
+
+/// Anthropic Messages API 的请求体
+#[derive(Debug, Serialize)]
+pub(crate) struct AnthropicRequest {
+    pub model: String,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<SystemPrompt>,
+    pub messages: Vec<AnthropicMessage>,
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+}
+
+/// Anthropic 请求的系统提示词
+///
+/// 启用提示词缓存时序列化为带`cache_control`的文本内容块，否则为普通字符串。
+#[derive(Debug)]
+pub(crate) struct SystemPrompt {
+    text: String,
+    cache: bool,
+}
+
+impl Deref for SystemPrompt {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Serialize for SystemPrompt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct CacheControl {
+            r#type: &'static str,
+        }
+        #[derive(Serialize)]
+        struct TextBlock<'a> {
+            r#type: &'static str,
+            text: &'a str,
+            cache_control: CacheControl,
+        }
+
+        if !self.cache {
+            return serializer.serialize_str(&self.text);
+        }
+        [TextBlock {
+            r#type: "text",
+            text: &self.text,
+            cache_control: CacheControl { r#type: "ephemeral" },
+        }]
+        .serialize(serializer)
+    }
+}
+
+/// Anthropic 消息，角色只能是`user`或`assistant`
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct AnthropicMessage {
+    pub role: &'static str,
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicContent>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    /// 只在请求使用了提示词缓存时出现
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicContent {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl AnthropicRequest {
+    /// 将 OpenAI 格式的消息转换为 Anthropic 请求
+    ///
+    /// 所有`system`消息被移出`messages`，按顺序合并到顶层`system`字段；
+    /// 启用`LlmConfig::enable_prompt_cache`时该字段标记为可缓存。
+    pub(crate) fn new(
+        messages: Vec<ChatCompletionRequestMessage>,
+        config: &LlmConfig,
+        model: &str,
+    ) -> Self {
+        let mut system_parts = Vec::new();
+        let mut converted = Vec::new();
+
+        for message in messages {
+            match message {
+                ChatCompletionRequestMessage::System(m) => {
+                    system_parts.extend(m.content);
+                }
+                ChatCompletionRequestMessage::User(m) => converted.push(AnthropicMessage {
+                    role: "user",
+                    content: m.content.map(user_content_text).unwrap_or_default(),
+                }),
+                ChatCompletionRequestMessage::Assistant(m) => converted.push(AnthropicMessage {
+                    role: "assistant",
+                    content: m.content.unwrap_or_default(),
+                }),
+                // 工具调用结果作为用户提供的内容传递
+                ChatCompletionRequestMessage::Tool(m) => converted.push(AnthropicMessage {
+                    role: "user",
+                    content: m.content.unwrap_or_default(),
+                }),
+                ChatCompletionRequestMessage::Function(m) => converted.push(AnthropicMessage {
+                    role: "user",
+                    content: m.content.unwrap_or_default(),
+                }),
+            }
+        }
+
+        Self {
+            model: model.to_string(),
+            max_tokens: config.max_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_TOKENS),
+            system: Some(system_parts.join("\n\n"))
+                .filter(|s| !s.is_empty())
+                .map(|text| SystemPrompt {
+                    text,
+                    cache: config.enable_prompt_cache,
+                }),
+            messages: converted,
+            temperature: config.temperature,
+            top_p: config.top_p,
+        }
+    }
+}
+
+fn user_content_text(content: ChatCompletionRequestUserMessageContent) -> String {
+    match content {
+        ChatCompletionRequestUserMessageContent::Text(text) => text,
+        ChatCompletionRequestUserMessageContent::Array(parts) => parts
+            .into_iter()
+            .filter_map(|part| match part {
+                ChatCompletionRequestMessageContentPart::Text(t) => Some(t.text),
+                ChatCompletionRequestMessageContentPart::Image(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+impl LlmService {
+    /// 请求 Anthropic 的模型列表接口，用于健康检查
+    pub(crate) async fn anthropic_models(&self) -> Result<Option<String>, LlmError> {
+        let response = self
+            .anthropic_request(reqwest::Method::GET, "/models")?
+            .send()
+            .await?;
+        crate::health::error_for_status(response).await?;
+        Ok(Some(ANTHROPIC_VERSION.to_string()))
+    }
+
+    /// 构建带有认证和版本请求头的 Anthropic API 请求，`path`相对于`/v1`
+    pub(crate) fn anthropic_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<reqwest::RequestBuilder, LlmError> {
+        let config = self.config();
+        let api_key = config.api_key.as_deref().ok_or(LlmError::ApiKeyMissing)?;
+        let base = config
+            .base_url
+            .as_deref()
+            .unwrap_or(ANTHROPIC_DEFAULT_BASE)
+            .trim_end_matches('/');
+        let root = base.strip_suffix("/v1").unwrap_or(base);
+
+        Ok(self
+            .http()
+            .request(method, format!("{root}/v1{path}"))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION))
+    }
+
+    /// 通过 Anthropic Messages API 执行一次聊天补全请求，除模型名称外的参数取自`config`
+    ///
+    /// 配置了`request_id_header`时，错误中附带本次请求的追踪 ID。
+    pub(crate) async fn anthropic_completion(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        model: &str,
+        config: &LlmConfig,
+    ) -> Result<String, LlmError> {
+        let request_id = self.next_request_id();
+        self.send_anthropic_completion(messages, model, config, request_id.as_ref())
+            .await
+            .map_err(|e| e.with_request_id(request_id.as_ref()))
+    }
+
+    async fn send_anthropic_completion(
+        &self,
+        messages: Vec<ChatCompletionRequestMessage>,
+        model: &str,
+        config: &LlmConfig,
+        request_id: Option<&RequestId>,
+    ) -> Result<String, LlmError> {
+        let request = AnthropicRequest::new(messages, config, model);
+        let mut builder = self.anthropic_request(reqwest::Method::POST, "/messages")?;
+        if let Some(id) = request_id {
+            builder = id.apply(builder);
+        }
+        let response = builder.json(&request).send().await?;
+
+        let status = response.status();
+        if status.as_u16() == 429 {
+            let retry_after = crate::retry::retry_after_header(response.headers());
+            return Err(LlmError::QuotaExceeded { retry_after });
+        }
+        if !status.is_success() {
+            return Err(LlmError::UnexpectedStatus {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        let body: AnthropicResponse = response.json().await?;
+        if let Some(usage) = &body.usage {
+            let cache = CacheUsage {
+                read_tokens: usage.cache_read_input_tokens,
+                creation_tokens: usage.cache_creation_input_tokens,
+            };
+            *self.last_cache_usage.lock().unwrap_or_else(|e| e.into_inner()) = Some(cache);
+            self.record_usage_with_cache(model, usage.input_tokens, usage.output_tokens, cache);
+        }
+        let text: String = body.content.into_iter().filter_map(|c| c.text).collect();
+        Some(text).filter(|t| !t.is_empty()).ok_or(LlmError::EmptyResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::{
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestUserMessageArgs,
+    };
+
+    #[test]
+    fn anthropic_request_moves_system_prompt_out_of_messages() {
+        let system_prompt = "You are termichan.";
+        let messages = vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system_prompt)
+                .build()
+                .unwrap()
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content("list files")
+                .build()
+                .unwrap()
+                .into(),
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content("ls")
+                .build()
+                .unwrap()
+                .into(),
+        ];
+
+        let request = AnthropicRequest::new(messages, &LlmConfig::default(), "claude-3-haiku");
+
+        assert_eq!(request.system.as_deref(), Some(system_prompt));
+        assert_eq!(
+            request.messages,
+            vec![
+                AnthropicMessage { role: "user", content: "list files".to_string() },
+                AnthropicMessage { role: "assistant", content: "ls".to_string() },
+            ]
+        );
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(body.to_string().matches(system_prompt).count(), 1);
+    }
+
+    #[test]
+    fn anthropic_request_marks_system_prompt_cacheable() {
+        let messages = vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content("You are termichan.")
+                .build()
+                .unwrap()
+                .into(),
+        ];
+        let config = LlmConfig {
+            enable_prompt_cache: true,
+            ..LlmConfig::default()
+        };
+
+        let request = AnthropicRequest::new(messages, &config, "claude-3-5-sonnet");
+
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            body["system"],
+            serde_json::json!([{
+                "type": "text",
+                "text": "You are termichan.",
+                "cache_control": { "type": "ephemeral" },
+            }])
+        );
+    }
+}
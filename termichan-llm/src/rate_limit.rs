@@ -0,0 +1,66 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 客户端侧的请求速率限制器
+///
+/// 在滑动时间窗口内最多允许`max_requests`个请求，超出时`acquire`会等待到窗口内有空位。
+/// 克隆得到的实例共享同一状态，可用于在多个`LlmService`之间共享限额。
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    max_requests: usize,
+    window: Duration,
+    sent: Arc<Mutex<VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    /// 创建一个在`window`时间内最多允许`max_requests`个请求的限制器
+    pub fn new(max_requests: usize, window: Duration) -> Self {
+        Self {
+            max_requests: max_requests.max(1),
+            window,
+            sent: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// 每分钟最多`requests_per_minute`个请求
+    pub fn per_minute(requests_per_minute: usize) -> Self {
+        Self::new(requests_per_minute, Duration::from_secs(60))
+    }
+
+    /// 等待直到可以发送下一个请求，并将该请求计入窗口
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().expect("rate limiter lock poisoned");
+                let now = Instant::now();
+                while sent.front().is_some_and(|t| now.duration_since(*t) >= self.window) {
+                    sent.pop_front();
+                }
+                if sent.len() < self.max_requests {
+                    sent.push_back(now);
+                    return;
+                }
+                self.window - now.duration_since(sent[0])
+            };
+            log::debug!("Client-side rate limit reached, waiting {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 不等待，直接为下一个请求预留窗口中的位置，返回发送前需要等待的时间
+    pub(crate) fn reserve(&self) -> Duration {
+        let mut sent = self.sent.lock().expect("rate limiter lock poisoned");
+        let now = Instant::now();
+        while sent.front().is_some_and(|t| now.saturating_duration_since(*t) >= self.window) {
+            sent.pop_front();
+        }
+        // 已预留的位置可能在将来，按窗口内第`len - max_requests`个请求推算可发送的时间
+        let at = match sent.len().checked_sub(self.max_requests) {
+            Some(index) => (sent[index] + self.window).max(now),
+            None => now,
+        };
+        sent.push_back(at);
+        at - now
+    }
+}
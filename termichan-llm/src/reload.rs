@@ -0,0 +1,77 @@
+use std::path::Path;
+use std::sync::{Arc, Weak};
+use termichan_config::{Config, ConfigError, LlmConfig, NetworkConfig, WatchHandle};
+
+use crate::{builder, http, LlmError, LlmService};
+
+impl LlmService {
+    /// 用新的配置替换服务使用的 LLM 配置，之后的请求使用新的模型、温度等设置
+    ///
+    /// `base_url`、网络配置、超时、`provider_headers`或`project_id`变化时重新创建 HTTP 客户端
+    /// （通过`LlmServiceBuilder::with_http_client`注入的客户端除外），API 密钥、`base_url`
+    /// 或`organization_id`变化时重新创建 OpenAI 兼容接口的配置。进行中的请求继续使用原来的配置。
+    ///
+    /// 追踪请求头、分词器、连接池统计和重试次数在创建服务时确定，修改后需要重启。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiKeyMissing`: API密钥未配置
+    /// - `LlmError::InvalidNetworkConfig`: 代理、DNS或`provider_headers`设置无效
+    /// - `LlmError::TlsConfig`: 无法初始化HTTP客户端的TLS后端
+    ///
+    /// 出错时保留原来的配置。
+    pub fn reload_config(&self, config: LlmConfig, network: &NetworkConfig) -> Result<(), LlmError> {
+        let previous = self.config();
+        let mut connection = self.connection.write().unwrap_or_else(|e| e.into_inner());
+
+        let rebuild_http = !connection.injected_http
+            && (previous.base_url != config.base_url
+                || connection.network != *network
+                || previous.timeout_secs != config.timeout_secs
+                || previous.pool_size != config.pool_size
+                || previous.provider_headers != config.provider_headers
+                || previous.project_id != config.project_id);
+        let http = if rebuild_http {
+            Some(http::build_http_client(&config, network)?)
+        } else {
+            None
+        };
+        let openai_config =
+            if previous.api_key != config.api_key
+                || previous.base_url != config.base_url
+                || previous.organization_id != config.organization_id
+            {
+                Some(builder::openai_config(&config)?)
+            } else {
+                None
+            };
+
+        if let Some(http) = http {
+            log::info!("Recreated HTTP client after configuration change");
+            connection.http = http;
+        }
+        if let Some(openai_config) = openai_config {
+            connection.openai_config = openai_config;
+        }
+        connection.network = network.clone();
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+        Ok(())
+    }
+
+    /// 监视`path`处的配置文件，文件变化时调用`reload_config`
+    ///
+    /// 回调只持有服务的弱引用，服务被释放后不再重新加载。返回的句柄被丢弃时停止监视。
+    ///
+    /// # 错误
+    /// 无法监视配置文件时返回`ConfigError::Watch`
+    pub fn watch_config(self: &Arc<Self>, path: &Path) -> Result<WatchHandle, ConfigError> {
+        let service: Weak<Self> = Arc::downgrade(self);
+        Config::watch(path, move |config| {
+            let Some(service) = service.upgrade() else {
+                return;
+            };
+            if let Err(e) = service.reload_config(config.llm.clone(), &config.network) {
+                log::warn!("Failed to apply reloaded LLM config: {e}");
+            }
+        })
+    }
+}
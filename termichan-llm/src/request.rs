@@ -0,0 +1,76 @@
+use async_openai::error::OpenAIError;
+use async_openai::types::{
+    ChatCompletionResponseStream, CreateChatCompletionRequest, CreateChatCompletionStreamResponse,
+};
+use futures::StreamExt;
+use reqwest_eventsource::{Event, EventSource};
+use serde::Serialize;
+
+/// 发送给 OpenAI 兼容接口的补全请求体
+///
+/// `async-openai` 0.16 的`CreateChatCompletionRequest::max_tokens`是`u16`，无法表示 65535 以上的输出上限，
+/// 因此请求中不设置该字段，由这里以`u32`序列化。请求体由服务自行发送，见`LlmService::openai_post`
+/// 和`LlmService::openai_post_stream`。
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ChatRequest {
+    #[serde(flatten)]
+    pub(crate) request: CreateChatCompletionRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_tokens: Option<u32>,
+}
+
+impl ChatRequest {
+    /// `request`中的`max_tokens`应为`None`，否则请求体中会出现两个`max_tokens`
+    pub(crate) fn new(request: CreateChatCompletionRequest, max_tokens: Option<u32>) -> Self {
+        debug_assert!(request.max_tokens.is_none(), "max_tokens is sent by ChatRequest");
+        Self { request, max_tokens }
+    }
+}
+
+/// 把服务器发送事件（SSE）转换为流式补全的响应流，收到`[DONE]`时结束
+///
+/// 与`async-openai`对流式请求的处理相同，错误以`OpenAIError::StreamError`返回，见`retry::classify`。
+/// 出错后不再重新连接，重试由调用方决定。
+pub(crate) fn completion_stream(mut events: EventSource) -> ChatCompletionResponseStream {
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let (item, last) = match event {
+                Ok(Event::Open) => continue,
+                Ok(Event::Message(message)) if message.data == "[DONE]" => break,
+                Ok(Event::Message(message)) => {
+                    let parsed = serde_json::from_str::<CreateChatCompletionStreamResponse>(&message.data);
+                    (parsed.map_err(OpenAIError::JSONDeserialize), false)
+                }
+                Err(e) => (Err(OpenAIError::StreamError(e.to_string())), true),
+            };
+            // 接收方已丢弃响应流
+            if tx.unbounded_send(item).is_err() || last {
+                break;
+            }
+        }
+        events.close();
+    });
+    Box::pin(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::CreateChatCompletionRequestArgs;
+
+    #[test]
+    fn max_tokens_above_u16_are_sent_unchanged() {
+        let request = CreateChatCompletionRequestArgs::default()
+            .model("o1")
+            .messages(Vec::new())
+            .build()
+            .unwrap();
+        let body = serde_json::to_value(ChatRequest::new(request.clone(), Some(100_000))).unwrap();
+        assert_eq!(body["max_tokens"], 100_000);
+        assert_eq!(body["model"], "o1");
+
+        let body = serde_json::to_value(ChatRequest::new(request, None)).unwrap();
+        assert!(body.get("max_tokens").is_none());
+    }
+}
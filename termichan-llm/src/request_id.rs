@@ -0,0 +1,92 @@
+use async_openai::config::{Config, OpenAIConfig};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::LlmError;
+
+/// 一次 API 请求的追踪 ID
+///
+/// 由`LlmConfig::request_id_prefix`和 UUID v4 组成，作为`LlmConfig::request_id_header`
+/// 请求头发送，便于在 API 网关或代理的日志中找到对应的请求。
+#[derive(Debug, Clone)]
+pub(crate) struct RequestId {
+    header: HeaderName,
+    value: HeaderValue,
+}
+
+impl RequestId {
+    /// 生成新的追踪 ID，`prefix`已在构建服务时校验过
+    pub(crate) fn generate(header: &HeaderName, prefix: Option<&str>) -> Self {
+        let value = format!("{}{}", prefix.unwrap_or_default(), Uuid::new_v4());
+        Self {
+            header: header.clone(),
+            value: HeaderValue::from_str(&value).expect("prefix is a valid header value"),
+        }
+    }
+
+    /// 追踪 ID 的文本
+    pub(crate) fn as_str(&self) -> &str {
+        self.value.to_str().expect("request ID is visible ASCII")
+    }
+
+    /// 为直接通过`reqwest`发送的请求加上追踪请求头
+    pub(crate) fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request.header(self.header.clone(), self.value.clone())
+    }
+}
+
+/// 校验`LlmConfig`中的请求头名称和前缀
+///
+/// # 错误
+/// 名称或前缀不能用作 HTTP 请求头时返回`LlmError::InvalidNetworkConfig`
+pub(crate) fn parse_header(header: &str, prefix: Option<&str>) -> Result<HeaderName, LlmError> {
+    let name = HeaderName::from_bytes(header.trim().as_bytes())
+        .map_err(|_| LlmError::InvalidNetworkConfig(format!("invalid request ID header `{header}`")))?;
+    if let Some(prefix) = prefix {
+        HeaderValue::from_str(prefix)
+            .map_err(|_| LlmError::InvalidNetworkConfig(format!("invalid request ID prefix `{prefix}`")))?;
+    }
+    Ok(name)
+}
+
+/// 在`OpenAIConfig`的请求头之外附加追踪 ID 的配置
+///
+/// 每个请求使用带有各自追踪 ID 的配置，见`LlmService::openai_post`。
+#[derive(Debug, Clone)]
+pub(crate) struct TracedConfig {
+    inner: OpenAIConfig,
+    request_id: Option<RequestId>,
+}
+
+impl TracedConfig {
+    pub(crate) fn new(inner: OpenAIConfig, request_id: Option<RequestId>) -> Self {
+        Self { inner, request_id }
+    }
+}
+
+impl Config for TracedConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = self.inner.headers();
+        if let Some(id) = &self.request_id {
+            headers.insert(id.header.clone(), id.value.clone());
+        }
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        self.inner.url(path)
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        self.inner.query()
+    }
+
+    fn api_base(&self) -> &str {
+        self.inner.api_base()
+    }
+
+    fn api_key(&self) -> &Secret<String> {
+        self.inner.api_key()
+    }
+}
@@ -0,0 +1,158 @@
+use async_openai::error::{ApiError, OpenAIError};
+use futures::future::BoxFuture;
+use hickory_resolver::error::ResolveError;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::LlmError;
+
+/// 遇到速率限制时的等待策略，参数为本次需要等待的时长。
+///
+/// 默认实现只是休眠；界面层可以替换为带倒计时提示的实现。
+pub type RateLimitWait = Arc<dyn Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// 没有建议等待时间时，指数退避的初始等待时长。
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 域名解析失败后重试前的等待时长。
+pub(crate) const DNS_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// 默认的等待策略：直接休眠指定时长。
+pub(crate) fn default_wait() -> RateLimitWait {
+    Arc::new(|wait| Box::pin(tokio::time::sleep(wait)))
+}
+
+/// 第 `attempt` 次重试（从 0 开始）的指数退避时长。
+pub(crate) fn backoff_delay(attempt: u32) -> Duration {
+    INITIAL_BACKOFF * 2u32.saturating_pow(attempt)
+}
+
+/// 未能建立连接（连接被拒绝、网络不可达等）时，返回底层的 HTTP 错误。
+///
+/// 这类失败发生在收到任何 HTTP 响应之前，与速率限制等 API 层面的错误分开重试。
+pub(crate) fn connect_failure(error: &LlmError) -> Option<&reqwest::Error> {
+    let error = match error.root() {
+        LlmError::NetworkError(e) | LlmError::ApiError(OpenAIError::Reqwest(e)) => e,
+        _ => return None,
+    };
+    error.is_connect().then_some(error)
+}
+
+/// 连接失败的原因是域名解析失败时，返回底层的 HTTP 错误。
+///
+/// hyper 将系统解析器的失败包装为消息为 "dns error" 的错误，
+/// 自定义解析器 (DNS over HTTPS 等) 的失败则以 `ResolveError` 出现在错误链中。
+pub(crate) fn dns_failure(error: &LlmError) -> Option<&reqwest::Error> {
+    let error = connect_failure(error)?;
+    let mut source = error.source();
+    while let Some(cause) = source {
+        if cause.is::<ResolveError>() || cause.to_string() == "dns error" {
+            return Some(error);
+        }
+        source = cause.source();
+    }
+    None
+}
+
+/// OpenAI 兼容接口的错误响应体
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ApiError,
+}
+
+/// 读取 OpenAI 兼容接口的响应，成功时解析为`O`
+///
+/// 429 响应转换为`LlmError::QuotaExceeded`，等待时间取自`Retry-After`响应头；
+/// 账户余额不足 (`insufficient_quota`) 无法通过等待恢复，与其他错误响应一样作为`LlmError::ApiError`返回。
+/// 响应体不是 OpenAI 格式的错误时返回`LlmError::UnexpectedStatus`。
+pub(crate) async fn read_openai_response<O: DeserializeOwned>(response: reqwest::Response) -> Result<O, LlmError> {
+    let status = response.status();
+    let retry_after = retry_after_header(response.headers());
+    let bytes = response.bytes().await.map_err(OpenAIError::Reqwest)?;
+    if status.is_success() {
+        return serde_json::from_slice(&bytes).map_err(|e| OpenAIError::JSONDeserialize(e).into());
+    }
+    let error = serde_json::from_slice::<ErrorBody>(&bytes).ok().map(|body| body.error);
+    let insufficient_quota = error.as_ref().is_some_and(|e| {
+        e.r#type.as_deref() == Some("insufficient_quota")
+            || e.code.as_ref().and_then(|c| c.as_str()) == Some("insufficient_quota")
+    });
+    match error {
+        _ if status.as_u16() == 429 && !insufficient_quota => Err(LlmError::QuotaExceeded { retry_after }),
+        Some(error) => Err(LlmError::ApiError(OpenAIError::ApiError(error))),
+        None => Err(LlmError::UnexpectedStatus {
+            status: status.as_u16(),
+            body: String::from_utf8_lossy(&bytes).into_owned(),
+        }),
+    }
+}
+
+/// `Retry-After`响应头中以秒为单位的等待时间
+pub(crate) fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// 将 OpenAI 错误转换为 `LlmError`，并识别速率限制错误。
+///
+/// 只用于流式请求，其错误不包含 HTTP 响应头，因此建议的等待时间从错误消息
+/// （例如 "Please try again in 6.5s."）中解析；其他请求见`read_openai_response`。账户余额不足
+/// (`insufficient_quota`) 无法通过等待恢复，仍作为普通 API 错误返回。
+pub(crate) fn classify(error: OpenAIError) -> LlmError {
+    if let OpenAIError::ApiError(api_error) = &error {
+        let code = api_error.code.as_ref().and_then(|c| c.as_str());
+        let is_rate_limit = code == Some("rate_limit_exceeded")
+            || (code != Some("insufficient_quota")
+                && api_error.message.to_ascii_lowercase().contains("rate limit"));
+        if is_rate_limit {
+            return LlmError::QuotaExceeded {
+                retry_after: parse_retry_after(&api_error.message),
+            };
+        }
+    }
+    LlmError::ApiError(error)
+}
+
+/// 从形如 "try again in 1m30s"、"try again in 6.5s"、"try again in 20ms" 的消息中解析等待时间。
+pub(crate) fn parse_retry_after(message: &str) -> Option<Duration> {
+    let lower = message.to_ascii_lowercase();
+    let start = lower.find("try again in ")? + "try again in ".len();
+    let rest = &lower[start..];
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+        .unwrap_or(rest.len());
+    let spec = rest[..end].trim_end_matches('.');
+
+    let mut total = Duration::ZERO;
+    let mut number = String::new();
+    let mut chars = spec.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let value: f64 = number.parse().ok()?;
+        number.clear();
+        let secs = match c {
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                value / 1000.0
+            }
+            'm' => value * 60.0,
+            'h' => value * 3600.0,
+            's' => value,
+            _ => return None,
+        };
+        total += Duration::from_secs_f64(secs);
+    }
+
+    // 没有单位的数字无法确定含义
+    (number.is_empty() && !total.is_zero()).then_some(total)
+}
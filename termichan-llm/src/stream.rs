@@ -0,0 +1,164 @@
+use futures::stream::{BoxStream, Stream};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::LlmError;
+
+/// `LlmService::streaming_with_metadata`返回的流中的事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamEvent {
+    /// 一块响应内容
+    Token(String),
+    /// 发送请求前因客户端速率限制（`LlmServiceBuilder::with_rate_limiter`）等待的时间
+    ThrottleWarning { wait_ms: u64 },
+    /// 模型停止生成的原因，例如`stop`、`length`、`content_filter`
+    FinishReason(String),
+    /// API 报告的 token 用量，在流的末尾给出；API 未返回用量时没有此事件
+    Usage {
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    },
+}
+
+/// 带有元数据的响应流，见`LlmService::streaming_with_metadata`
+pub type MetadataStream = BoxStream<'static, Result<StreamEvent, LlmError>>;
+
+/// 流中可以累积为已收到内容的元素，用于中止时返回`LlmError::Aborted`
+pub(crate) trait PartialText {
+    fn partial_text(&self) -> Option<&str>;
+}
+
+impl PartialText for String {
+    fn partial_text(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl PartialText for StreamEvent {
+    fn partial_text(&self) -> Option<&str> {
+        match self {
+            StreamEvent::Token(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// 用于从轮询任务之外中止流式响应的句柄
+///
+/// 由`LlmService::stream_chat_completion`或`streaming_with_metadata`与响应流一同返回，克隆得到的句柄控制同一个流。
+#[derive(Debug, Clone, Default)]
+pub struct StreamHandle {
+    cancel: CancellationToken,
+}
+
+impl StreamHandle {
+    /// 中止对应的响应流
+    ///
+    /// 流的下一个元素为`LlmError::Aborted`，其中包含已收到的内容，之后流结束。
+    pub fn abort(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 是否已调用过`abort`
+    pub fn is_aborted(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
+
+/// 为响应流加上中止支持，并累积已收到的内容
+///
+/// 中止时立即丢弃底层流，从而关闭对应的 HTTP 连接。
+pub(crate) fn abortable<T: PartialText + Send + 'static>(
+    stream: BoxStream<'static, Result<T, LlmError>>,
+    handle: &StreamHandle,
+) -> impl Stream<Item = Result<T, LlmError>> + Send + 'static {
+    let state = (Some(stream), handle.cancel.clone(), String::new());
+    futures::stream::unfold(state, |(inner, cancel, mut partial)| async move {
+        let mut inner = inner?;
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                let aborted = LlmError::Aborted(std::mem::take(&mut partial));
+                Some((Err(aborted), (None, cancel, partial)))
+            }
+            chunk = inner.next() => {
+                let chunk = chunk?;
+                if let Some(text) = chunk.as_ref().ok().and_then(PartialText::partial_text) {
+                    partial.push_str(text);
+                }
+                Some((chunk, (Some(inner), cancel, partial)))
+            }
+        }
+    })
+}
+
+/// 到达`deadline`时以`LlmError::Timeout(timeout)`结束流，见`RequestOptions::timeout`
+pub(crate) fn with_deadline(
+    stream: BoxStream<'static, Result<String, LlmError>>,
+    deadline: tokio::time::Instant,
+    timeout: Duration,
+) -> BoxStream<'static, Result<String, LlmError>> {
+    futures::stream::unfold(Some(stream), move |stream| async move {
+        let mut stream = stream?;
+        match tokio::time::timeout_at(deadline, stream.next()).await {
+            Ok(Some(item)) => Some((item, Some(stream))),
+            Ok(None) => None,
+            Err(_) => Some((Err(LlmError::Timeout(timeout)), None)),
+        }
+    })
+    .boxed()
+}
+
+/// 把响应流中的小块合并：累积到`buffer_size`个字符或距上次输出经过`flush_interval`时输出一次
+///
+/// 上游出错时先输出已累积的内容，再输出错误；上游结束时总会输出剩余的内容。
+pub(crate) fn buffered(
+    stream: BoxStream<'static, Result<String, LlmError>>,
+    buffer_size: usize,
+    flush_interval: Duration,
+) -> BoxStream<'static, Result<String, LlmError>> {
+    let start = tokio::time::Instant::now() + flush_interval;
+    let mut interval = tokio::time::interval_at(start, flush_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let state = (Some(stream), interval, String::new(), None::<LlmError>);
+    futures::stream::unfold(state, move |(inner, mut interval, mut buffer, pending)| async move {
+        if let Some(error) = pending {
+            return Some((Err(error), (inner, interval, buffer, None)));
+        }
+        let mut inner = inner?;
+        loop {
+            tokio::select! {
+                chunk = inner.next() => match chunk {
+                    Some(Ok(text)) => {
+                        buffer.push_str(&text);
+                        if buffer.chars().count() >= buffer_size {
+                            interval.reset();
+                            let text = std::mem::take(&mut buffer);
+                            return Some((Ok(text), (Some(inner), interval, buffer, None)));
+                        }
+                    }
+                    Some(Err(error)) if buffer.is_empty() => {
+                        return Some((Err(error), (Some(inner), interval, buffer, None)));
+                    }
+                    Some(Err(error)) => {
+                        let text = std::mem::take(&mut buffer);
+                        return Some((Ok(text), (Some(inner), interval, buffer, Some(error))));
+                    }
+                    None if buffer.is_empty() => return None,
+                    None => {
+                        let text = std::mem::take(&mut buffer);
+                        return Some((Ok(text), (None, interval, buffer, None)));
+                    }
+                },
+                _ = interval.tick() => {
+                    if !buffer.is_empty() {
+                        let text = std::mem::take(&mut buffer);
+                        return Some((Ok(text), (Some(inner), interval, buffer, None)));
+                    }
+                }
+            }
+        }
+    })
+    .boxed()
+}
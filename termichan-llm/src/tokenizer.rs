@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
+
+use crate::tokens::estimate_text_tokens;
+
+/// 计算文本 token 数的分词器
+///
+/// `LlmService`按提供商选择默认实现，也可以通过`LlmServiceBuilder::with_tokenizer`替换，
+/// 例如为 Anthropic 接入专用的分词器。
+pub trait Tokenizer: Send + Sync {
+    /// `text`编码后的 token 数
+    fn count(&self, text: &str) -> u64;
+}
+
+/// 使用 tiktoken 编码的分词器，适用于 OpenAI 及兼容接口
+///
+/// 按模型名称选择编码（例如 GPT-4 使用`cl100k_base`，较早的模型使用`p50k_base`），
+/// 无法识别的模型使用`cl100k_base`。编码表在第一次计数时才加载。
+pub struct TiktokenTokenizer {
+    model: String,
+    bpe: OnceLock<CoreBPE>,
+}
+
+impl TiktokenTokenizer {
+    /// 为`model`创建分词器
+    pub fn for_model(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            bpe: OnceLock::new(),
+        }
+    }
+
+    fn bpe(&self) -> &CoreBPE {
+        self.bpe.get_or_init(|| {
+            tiktoken_rs::get_bpe_from_model(&self.model).unwrap_or_else(|_| {
+                log::debug!("No tiktoken encoding known for model {}, using cl100k_base", self.model);
+                tiktoken_rs::cl100k_base().expect("cl100k_base is bundled with tiktoken-rs")
+            })
+        })
+    }
+}
+
+impl Tokenizer for TiktokenTokenizer {
+    fn count(&self, text: &str) -> u64 {
+        self.bpe().encode_with_special_tokens(text).len() as u64
+    }
+}
+
+/// 将`LlmConfig::logit_bias`中的 token 文本按`model`的编码转换为 OpenAI 接口使用的 token ID
+///
+/// 一段文本编码为多个 token 时，每个 token 都使用该文本的偏置。
+pub(crate) fn logit_bias_token_ids(model: &str, bias: &HashMap<String, f32>) -> HashMap<String, serde_json::Value> {
+    let tokenizer = TiktokenTokenizer::for_model(model);
+    let mut ids = HashMap::new();
+    for (text, &value) in bias {
+        for id in tokenizer.bpe().encode_ordinary(text) {
+            ids.insert(id.to_string(), serde_json::Value::from(value));
+        }
+    }
+    ids
+}
+
+/// 按字符数粗略估算的分词器，用于没有公开编码的提供商
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EstimateTokenizer;
+
+impl Tokenizer for EstimateTokenizer {
+    fn count(&self, text: &str) -> u64 {
+        estimate_text_tokens(text)
+    }
+}
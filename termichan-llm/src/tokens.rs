@@ -0,0 +1,101 @@
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPart,
+    ChatCompletionRequestUserMessageContent,
+};
+use termichan_core::HistoryManager;
+
+use crate::LlmService;
+
+/// 粗略估算时每个 token 对应的字符数
+const CHARS_PER_TOKEN: usize = 4;
+/// 每条消息的角色和分隔符等格式开销
+const TOKENS_PER_MESSAGE: u64 = 4;
+/// 按 OpenAI 的计数规则，每条消息除角色和内容外的格式开销
+const FRAMING_TOKENS_PER_MESSAGE: u64 = 3;
+/// 按 OpenAI 的计数规则，回复开头`<|start|>assistant<|message|>`的开销
+const REPLY_PRIMING_TOKENS: u64 = 3;
+
+impl LlmService {
+    /// 不调用 API，粗略估算消息列表的 token 数
+    ///
+    /// 按约 4 个字符一个 token 计算，并为每条消息加上固定的格式开销。
+    /// 结果只用于提示用户，不应作为计费依据。
+    pub fn estimate_tokens(&self, messages: &[ChatCompletionRequestMessage]) -> u64 {
+        messages
+            .iter()
+            .map(|message| estimate_text_tokens(&message_text(message)) + TOKENS_PER_MESSAGE)
+            .sum()
+    }
+
+    /// 模型的上下文窗口大小，见`LlmConfig::effective_context_window`
+    ///
+    /// 供调用方决定是否需要裁剪消息，不必自行查询模型上限表。
+    pub fn effective_context_window(&self) -> usize {
+        self.config().effective_context_window()
+    }
+
+    /// 使用服务的分词器计算消息列表的 token 数
+    ///
+    /// 按 OpenAI 的规则计入每条消息的角色、内容和格式开销，以及回复开头的开销。
+    /// 分词器由`LlmServiceBuilder::with_tokenizer`决定，只在第一次计数时加载编码表。
+    pub fn count_messages_tokens(&self, messages: &[ChatCompletionRequestMessage]) -> u64 {
+        if messages.is_empty() {
+            return 0;
+        }
+        messages
+            .iter()
+            .map(|message| {
+                FRAMING_TOKENS_PER_MESSAGE
+                    + self.tokenizer.count(message_role(message))
+                    + self.tokenizer.count(&message_text(message))
+            })
+            .sum::<u64>()
+            + REPLY_PRIMING_TOKENS
+    }
+
+    /// 作为多轮对话上下文注入历史记录时消耗的 token 数
+    ///
+    /// 取最近`LlmConfig::max_context_messages`条执行成功的记录，每条转换为一对查询和命令消息后用
+    /// `count_messages_tokens`计数，供调用方在发送请求前与上下文窗口比较。没有可用的记录时为 0。
+    pub fn count_history_tokens(&self, manager: &HistoryManager) -> u64 {
+        let messages = manager.build_context_from_recent_successes(self.config().max_context_messages);
+        self.count_messages_tokens(&messages)
+    }
+}
+
+/// 粗略估算一段文本的 token 数
+pub fn estimate_text_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+fn message_role(message: &ChatCompletionRequestMessage) -> &'static str {
+    match message {
+        ChatCompletionRequestMessage::System(_) => "system",
+        ChatCompletionRequestMessage::User(_) => "user",
+        ChatCompletionRequestMessage::Assistant(_) => "assistant",
+        ChatCompletionRequestMessage::Tool(_) => "tool",
+        ChatCompletionRequestMessage::Function(_) => "function",
+    }
+}
+
+pub(crate) fn message_text(message: &ChatCompletionRequestMessage) -> String {
+    match message {
+        ChatCompletionRequestMessage::System(m) => m.content.clone().unwrap_or_default(),
+        ChatCompletionRequestMessage::User(m) => match &m.content {
+            Some(ChatCompletionRequestUserMessageContent::Text(text)) => text.clone(),
+            // 图片等非文本部分无法按字符估算，只计文本
+            Some(ChatCompletionRequestUserMessageContent::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ChatCompletionRequestMessageContentPart::Text(t) => Some(t.text.as_str()),
+                    ChatCompletionRequestMessageContentPart::Image(_) => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => String::new(),
+        },
+        ChatCompletionRequestMessage::Assistant(m) => m.content.clone().unwrap_or_default(),
+        ChatCompletionRequestMessage::Tool(m) => m.content.clone().unwrap_or_default(),
+        ChatCompletionRequestMessage::Function(m) => m.content.clone().unwrap_or_default(),
+    }
+}
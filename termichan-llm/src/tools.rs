@@ -0,0 +1,224 @@
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestToolMessageArgs, ChatCompletionTool,
+    ChatCompletionToolArgs, ChatCompletionToolType, CreateChatCompletionRequestArgs,
+    CreateChatCompletionResponse, FinishReason, ChatCompletionFunctionsArgs,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::prompt::wrap_user_content;
+use crate::{
+    request::ChatRequest, tokenizer, LlmError, LlmService, PromptContext, ProviderCapabilities,
+};
+
+/// 一次生成中最多进行的请求轮数，最后一轮不再提供工具，要求模型直接回答
+const MAX_TOOL_ROUNDS: usize = 5;
+
+/// 提供给模型的工具（函数）定义
+///
+/// 工具的实现在`termichan_executor::run_tool`中，名称与之一一对应。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolDefinition {
+    /// 工具名称，模型按此名称调用
+    pub name: String,
+    /// 告诉模型工具用途的说明
+    pub description: String,
+    /// 参数的 JSON Schema
+    pub parameters: Value,
+}
+
+impl ToolDefinition {
+    fn new(name: &str, description: &str, parameters: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+        }
+    }
+
+    fn to_openai(&self) -> Result<ChatCompletionTool, LlmError> {
+        let function = ChatCompletionFunctionsArgs::default()
+            .name(&self.name)
+            .description(&self.description)
+            .parameters(self.parameters.clone())
+            .build()?;
+        Ok(ChatCompletionToolArgs::default()
+            .r#type(ChatCompletionToolType::Function)
+            .function(function)
+            .build()?)
+    }
+}
+
+/// 模型请求的一次工具调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCall {
+    /// 调用 ID，工具结果需要带上相同的 ID
+    pub id: String,
+    /// 工具名称
+    pub name: String,
+    /// 模型给出的参数，JSON 对象的文本
+    pub arguments: String,
+}
+
+/// 没有参数的工具使用的 JSON Schema
+fn no_parameters() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+/// 只有一个字符串参数的工具使用的 JSON Schema
+fn string_parameter(name: &str, description: &str, required: bool) -> Value {
+    let required: Vec<&str> = if required { vec![name] } else { vec![] };
+    json!({
+        "type": "object",
+        "properties": { name: { "type": "string", "description": description } },
+        "required": required,
+    })
+}
+
+impl LlmService {
+    /// 按检测到的运行环境选择提供给模型的工具
+    ///
+    /// 总是包括`get_env_vars`和`list_files`；位于 Git 仓库中时加上`git_status`和`read_file`，
+    /// 检测到 Docker 时加上`docker_ps`和`docker_logs`。
+    pub fn function_definitions_from_context(ctx: &PromptContext) -> Vec<ToolDefinition> {
+        let mut tools = vec![
+            ToolDefinition::new(
+                "get_env_vars",
+                "List the names of the environment variables; common non-secret variables include their values.",
+                no_parameters(),
+            ),
+            ToolDefinition::new(
+                "list_files",
+                "List the entries of a directory inside the working directory. Directory names end with `/`.",
+                string_parameter("dir", "Directory to list, defaults to the working directory", false),
+            ),
+        ];
+        if ctx.in_git_repo {
+            tools.push(ToolDefinition::new(
+                "git_status",
+                "Show the current branch and the changed files of the Git repository.",
+                no_parameters(),
+            ));
+            tools.push(ToolDefinition::new(
+                "read_file",
+                "Read a text file inside the working directory. Hidden files and key or credential files cannot be read.",
+                string_parameter(
+                    "path",
+                    "Path of the file, relative to the working directory",
+                    true,
+                ),
+            ));
+        }
+        if ctx.docker_available {
+            tools.push(ToolDefinition::new(
+                "docker_ps",
+                "List the running Docker containers with their IDs, images, status and names.",
+                no_parameters(),
+            ));
+            tools.push(ToolDefinition::new(
+                "docker_logs",
+                "Show the last lines of a Docker container's logs.",
+                string_parameter("container_id", "ID or name of the container", true),
+            ));
+        }
+        tools
+    }
+
+    /// 提供`tools`并执行聊天补全请求，模型以`tool_calls`结束时调用`run_tool`并把结果发回给模型
+    ///
+    /// 最多进行 5 轮请求，最后一轮不再提供工具。工具结果依赖系统状态，不使用响应缓存。
+    /// 只支持 OpenAI 兼容接口，其他提供商或`tools`为空时等同于`chat_completion`。
+    ///
+    /// # 错误
+    /// - `LlmError::ApiError`: API请求失败
+    /// - `LlmError::EmptyResponse`: API返回空响应
+    pub async fn chat_completion_with_tools(
+        &self,
+        mut messages: Vec<ChatCompletionRequestMessage>,
+        tools: &[ToolDefinition],
+        mut run_tool: impl FnMut(&ToolCall) -> String,
+    ) -> Result<String, LlmError> {
+        let config = self.config();
+        if tools.is_empty() || ProviderCapabilities::system_message_as_field(&config.provider) {
+            log::debug!("Function calling is not used for provider {}", config.provider);
+            return self.chat_completion_default(messages).await;
+        }
+        let tools = tools
+            .iter()
+            .map(ToolDefinition::to_openai)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for round in 1..=MAX_TOOL_ROUNDS {
+            let mut request_builder = CreateChatCompletionRequestArgs::default();
+            request_builder
+                .model(&config.model)
+                .messages(messages.clone())
+                .temperature(config.temperature);
+            if round < MAX_TOOL_ROUNDS {
+                request_builder.tools(tools.clone());
+            }
+            if let Some(top_p) = config.top_p {
+                request_builder.top_p(top_p);
+            }
+            if let Some(bias) = &config.logit_bias {
+                request_builder
+                    .logit_bias(tokenizer::logit_bias_token_ids(&config.model, bias));
+            }
+            let request = ChatRequest::new(request_builder.build()?, config.max_tokens);
+
+            let response = self
+                .with_retry(|| {
+                    self.openai_post::<_, CreateChatCompletionResponse>("/chat/completions", &request)
+                })
+                .await?;
+            if let Some(usage) = &response.usage {
+                self.record_usage(
+                    &config.model,
+                    usage.prompt_tokens.into(),
+                    usage.completion_tokens.into(),
+                );
+            }
+
+            let choice = response
+                .choices
+                .into_iter()
+                .next()
+                .ok_or(LlmError::EmptyResponse)?;
+            let tool_calls = choice.message.tool_calls.unwrap_or_default();
+            if !matches!(choice.finish_reason, Some(FinishReason::ToolCalls)) || tool_calls.is_empty() {
+                return choice.message.content.ok_or(LlmError::EmptyResponse);
+            }
+
+            messages.push(
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .tool_calls(tool_calls.clone())
+                    .build()?
+                    .into(),
+            );
+            for call in tool_calls {
+                let mut output = run_tool(&tool_call(&call));
+                if config.system_prompt_injection_guard {
+                    output = wrap_user_content(&output);
+                }
+                messages.push(
+                    ChatCompletionRequestToolMessageArgs::default()
+                        .content(output)
+                        .tool_call_id(call.id)
+                        .build()?
+                        .into(),
+                );
+            }
+        }
+        // 最后一轮没有提供工具，模型不会再以`tool_calls`结束
+        Err(LlmError::EmptyResponse)
+    }
+}
+
+fn tool_call(call: &ChatCompletionMessageToolCall) -> ToolCall {
+    ToolCall {
+        id: call.id.clone(),
+        name: call.function.name.clone(),
+        arguments: call.function.arguments.clone(),
+    }
+}
@@ -0,0 +1,51 @@
+use serde::Serialize;
+use termichan_config::PromptConfig;
+
+use crate::{cache, LlmError, LlmService, PromptContext};
+
+/// `LlmService::warm_cache`的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WarmCacheReport {
+    /// 新写入缓存的查询数
+    pub warmed: usize,
+    /// 已有有效缓存而跳过的查询数
+    pub already_cached: usize,
+    /// 请求失败的查询数
+    pub failed: usize,
+}
+
+impl LlmService {
+    /// 依次请求`queries`中的每个查询，把响应写入响应缓存
+    ///
+    /// 提示词按`prompt`和`ctx`构建，与实际查询时相同，之后的相同查询可以直接命中缓存。
+    /// 请求按顺序发送并遵循速率限制；单个查询失败只计入`failed`，不会中断预热。
+    /// 调用方负责在之后调用`Cache::save`保存结果。
+    ///
+    /// # 错误
+    /// 服务没有配置响应缓存时返回`LlmError::CacheDisabled`
+    pub async fn warm_cache(
+        &self,
+        queries: Vec<String>,
+        ctx: &PromptContext,
+        prompt: &PromptConfig,
+    ) -> Result<WarmCacheReport, LlmError> {
+        let cache = self.cache.as_ref().ok_or(LlmError::CacheDisabled)?;
+        let mut report = WarmCacheReport::default();
+        let config = self.config();
+        for query in queries {
+            let messages = ctx.build_messages(prompt, &query);
+            if cache.contains(&cache::request_key(&messages, &config.model, &config)) {
+                report.already_cached += 1;
+                continue;
+            }
+            match self.chat_completion_default(messages).await {
+                Ok(_) => report.warmed += 1,
+                Err(e) => {
+                    log::warn!("Failed to warm cache for `{query}`: {e}");
+                    report.failed += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
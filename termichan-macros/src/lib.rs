@@ -0,0 +1,129 @@
+//! `termichan` 的过程宏。
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Expr, ExprLit, Fields, ItemStruct, Lit, LitStr, Meta};
+
+/// 为配置结构体生成字段文档，供 `Config::documentation` 使用。
+///
+/// 从每个字段的文档注释中提取说明，并记录字段类型和默认值。字段上可以使用辅助属性：
+///
+/// - `#[termichan_doc(nested)]`：字段本身是带有 `#[termichan_doc]` 的结构体，展开其字段。
+/// - `#[termichan_doc(example = "...")]`：字段的示例值。
+///
+/// 生成的代码引用 `crate::docs`，因此只能在 `termichan-config` 中使用。
+#[proc_macro_attribute]
+pub fn termichan_doc(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(input as ItemStruct);
+
+    let Fields::Named(fields) = &mut item.fields else {
+        return syn::Error::new_spanned(&item, "#[termichan_doc] requires a struct with named fields")
+            .to_compile_error()
+            .into();
+    };
+
+    let mut entries = Vec::new();
+    for field in &mut fields.named {
+        let options = match FieldOptions::take(&mut field.attrs) {
+            Ok(options) => options,
+            Err(e) => return e.to_compile_error().into(),
+        };
+        let ident = field.ident.as_ref().expect("named field");
+        let name = ident.to_string();
+
+        if options.nested {
+            entries.push(quote! {
+                crate::docs::Documented::collect_docs(
+                    &self.#ident,
+                    &crate::docs::join_path(prefix, #name),
+                    out,
+                );
+            });
+            continue;
+        }
+
+        let ty = &field.ty;
+        let type_name = quote!(#ty).to_string().replace(' ', "");
+        let description = doc_comment(&field.attrs);
+        let example = match options.example {
+            Some(example) => quote!(Some(#example.to_string())),
+            None => quote!(None),
+        };
+        entries.push(quote! {
+            out.push(crate::docs::FieldDoc {
+                path: crate::docs::join_path(prefix, #name),
+                type_name: #type_name.to_string(),
+                default_value: crate::docs::format_value(&self.#ident),
+                description: #description.to_string(),
+                example: #example,
+            });
+        });
+    }
+
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    quote! {
+        #item
+
+        impl #impl_generics crate::docs::Documented for #name #ty_generics #where_clause {
+            fn collect_docs(&self, prefix: &str, out: &mut Vec<crate::docs::FieldDoc>) {
+                #(#entries)*
+            }
+        }
+    }
+    .into()
+}
+
+/// 字段上的 `#[termichan_doc(...)]` 辅助属性。
+#[derive(Default)]
+struct FieldOptions {
+    nested: bool,
+    example: Option<LitStr>,
+}
+
+impl FieldOptions {
+    /// 解析并移除字段上的辅助属性，避免编译器报告未知属性。
+    fn take(attrs: &mut Vec<Attribute>) -> syn::Result<Self> {
+        let mut options = Self::default();
+        let mut result = Ok(());
+        attrs.retain(|attr| {
+            if !attr.path().is_ident("termichan_doc") {
+                return true;
+            }
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("nested") {
+                    options.nested = true;
+                    Ok(())
+                } else if meta.path.is_ident("example") {
+                    options.example = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `nested` or `example = \"...\"`"))
+                }
+            });
+            if let Err(e) = parsed {
+                result = Err(e);
+            }
+            false
+        });
+        result.map(|()| options)
+    }
+}
+
+/// 合并文档注释，去掉每行开头的一个空格。
+fn doc_comment(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,155 @@
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use termichan_config::Config;
+use termichan_core::{CommandClassifier, HistoryError, HistoryManager};
+use termichan_llm::{LlmError, LlmService, PromptContext};
+use thiserror::Error;
+use tonic::{Request, Response, Status};
+
+/// 由 `proto/termichan.proto` 生成的消息和服务类型。
+pub mod proto {
+    tonic::include_proto!("termichan");
+}
+
+use proto::termichan_server::{Termichan, TermichanServer};
+use proto::{
+    ExplainRequest, ExplainResponse, GenerateRequest, GenerateToken, HistoryRequest, HistoryResponse,
+};
+
+/// `termichan server --grpc` 的默认端口。
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
+
+/// gRPC 服务相关的错误类型。
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("gRPC transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+}
+
+/// 通过 gRPC 提供命令生成、命令解释和历史记录查询。
+///
+/// 所有请求共享同一个 `LlmService`，因此也共享其连接池、缓存和速率限制。
+pub struct TermichanService {
+    config: Config,
+    llm: Arc<LlmService>,
+}
+
+impl TermichanService {
+    /// 使用 `config` 中的提示词和历史记录设置，通过 `llm` 处理请求。
+    pub fn new(config: Config, llm: LlmService) -> Self {
+        Self {
+            config,
+            llm: Arc::new(llm),
+        }
+    }
+
+    /// 在 `addr` 上监听 gRPC 请求，直到服务出错。
+    ///
+    /// # Errors
+    ///
+    /// 无法绑定地址或连接层出错时返回 `ServerError::Transport`。
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), ServerError> {
+        tonic::transport::Server::builder()
+            .add_service(TermichanServer::new(self))
+            .serve(addr)
+            .await?;
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Termichan for TermichanService {
+    type GenerateCommandStream = BoxStream<'static, Result<GenerateToken, Status>>;
+
+    async fn generate_command(
+        &self,
+        request: Request<GenerateRequest>,
+    ) -> Result<Response<Self::GenerateCommandStream>, Status> {
+        let request = request.into_inner();
+        let mut context = PromptContext::detect().with_injection_guard(&self.config.llm);
+        if let Some(pwd) = request.pwd {
+            context.pwd = pwd;
+        }
+        if let Some(shell) = request.shell {
+            context.shell = shell;
+        }
+        let messages = context.build_messages(&self.config.prompt, &request.query);
+
+        // 客户端断开时 tonic 丢弃响应流，底层的 HTTP 连接随之关闭，不需要保留句柄
+        let (_handle, stream) = self.llm.stream_chat_completion(messages, None).await.map_err(llm_status)?;
+        let tokens = stream.filter_map(|chunk| async move {
+            match chunk {
+                Ok(text) => Some(Ok(GenerateToken { text })),
+                // 只包含角色或结束原因的块没有内容
+                Err(LlmError::EmptyResponse) => None,
+                Err(e) => Some(Err(llm_status(e))),
+            }
+        });
+        Ok(Response::new(tokens.boxed()))
+    }
+
+    async fn explain_command(
+        &self,
+        request: Request<ExplainRequest>,
+    ) -> Result<Response<ExplainResponse>, Status> {
+        let command = request.into_inner().command;
+        if command.trim().is_empty() {
+            return Err(Status::invalid_argument("command must not be empty"));
+        }
+
+        let explanation = self.llm.generate_explanation(&command).await.map_err(llm_status)?;
+
+        Ok(Response::new(ExplainResponse {
+            explanation,
+            impact: CommandClassifier::impact(&command).as_str().to_string(),
+        }))
+    }
+
+    async fn get_history(
+        &self,
+        request: Request<HistoryRequest>,
+    ) -> Result<Response<HistoryResponse>, Status> {
+        let limit = request.into_inner().limit as usize;
+        let manager = HistoryManager::load(&self.config.history).map_err(history_status)?;
+        let entries = manager.entries();
+        let skip = if limit == 0 { 0 } else { entries.len().saturating_sub(limit) };
+
+        Ok(Response::new(HistoryResponse {
+            entries: entries[skip..]
+                .iter()
+                .map(|entry| proto::HistoryEntry {
+                    id: entry.id,
+                    timestamp: entry.timestamp.to_rfc3339(),
+                    query: entry.query.clone(),
+                    generated_command: entry.generated_command.clone(),
+                    executed: entry.executed,
+                    exit_code: entry.exit_code,
+                    provider: entry.provider.clone(),
+                    model: entry.model.clone(),
+                    latency_ms: entry.latency_ms,
+                })
+                .collect(),
+        }))
+    }
+}
+
+/// 将 LLM 错误映射为最接近的 gRPC 状态码，消息中保留请求的追踪 ID。
+fn llm_status(error: LlmError) -> Status {
+    match error.root() {
+        LlmError::ApiKeyMissing | LlmError::InvalidNetworkConfig(_) | LlmError::TlsConfig(_) => {
+            Status::failed_precondition(error.to_string())
+        }
+        LlmError::ApiKeyInvalid => Status::unauthenticated(error.to_string()),
+        LlmError::QuotaExceeded { .. } => Status::resource_exhausted(error.to_string()),
+        LlmError::Aborted(_) => Status::cancelled(error.to_string()),
+        LlmError::Timeout(_) => Status::deadline_exceeded(error.to_string()),
+        LlmError::NetworkError(_) | LlmError::UnexpectedStatus { .. } => Status::unavailable(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+fn history_status(error: HistoryError) -> Status {
+    Status::internal(error.to_string())
+}
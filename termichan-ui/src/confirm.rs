@@ -0,0 +1,102 @@
+use base64::Engine;
+use std::io::{self, BufRead, Write};
+use termichan_config::{UiConfig, KEYBINDING_ACTIONS};
+
+use crate::render::Layout;
+
+/// 用户在确认提示中选择的操作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationChoice {
+    /// 执行命令。
+    Confirm,
+    /// 不执行。
+    Reject,
+    /// 编辑命令后再次确认。
+    Edit,
+    /// 描述要做的修改，由 LLM 改写命令后再次确认。
+    Rewrite,
+    /// 只显示将要执行的内容。
+    DryRun,
+    /// 复制命令到剪贴板。
+    Copy,
+}
+
+impl ConfirmationChoice {
+    fn from_action(action: &str) -> Option<Self> {
+        match action {
+            "confirm" => Some(Self::Confirm),
+            "reject" => Some(Self::Reject),
+            "edit" => Some(Self::Edit),
+            "rewrite" => Some(Self::Rewrite),
+            "dry_run" => Some(Self::DryRun),
+            "copy" => Some(Self::Copy),
+            _ => None,
+        }
+    }
+}
+
+/// 执行命令前的确认提示，按键取自 `UiConfig::keybindings`。
+pub struct ConfirmationPrompt;
+
+impl ConfirmationPrompt {
+    /// 提示文本，包含由按键配置生成的按键说明。
+    pub fn legend(config: &UiConfig) -> String {
+        let keys = KEYBINDING_ACTIONS
+            .iter()
+            .map(|action| (*action, config.keybinding(action).unwrap_or_default()));
+        match Layout::from_config(config) {
+            Layout::Standard => {
+                let legend: Vec<String> = keys
+                    .map(|(action, key)| format!("[{key}] {}", action.replace('_', " ")))
+                    .collect();
+                format!("Execute this command? {}: ", legend.join(", "))
+            }
+            Layout::Compact => {
+                let legend: Vec<&str> = keys.map(|(_, key)| key).collect();
+                format!("execute? [{}] ", legend.join("/"))
+            }
+        }
+    }
+
+    /// 在标准错误显示提示并从标准输入读取选择。
+    ///
+    /// 空输入或输入结束视为 `Reject`；无法识别的输入会重新提示。按键不区分大小写。
+    pub fn ask(config: &UiConfig) -> io::Result<ConfirmationChoice> {
+        let stdin = io::stdin();
+        loop {
+            eprint!("{}", Self::legend(config));
+            io::stderr().flush()?;
+
+            let mut answer = String::new();
+            if stdin.lock().read_line(&mut answer)? == 0 {
+                return Ok(ConfirmationChoice::Reject);
+            }
+            let answer = answer.trim().to_lowercase();
+            if answer.is_empty() {
+                return Ok(ConfirmationChoice::Reject);
+            }
+
+            let choice = KEYBINDING_ACTIONS
+                .iter()
+                .find(|action| {
+                    config
+                        .keybinding(action)
+                        .is_some_and(|key| key.trim().to_lowercase() == answer)
+                })
+                .and_then(|action| ConfirmationChoice::from_action(action));
+            if let Some(choice) = choice {
+                return Ok(choice);
+            }
+        }
+    }
+}
+
+/// 通过 OSC 52 终端转义序列将 `text` 复制到剪贴板。
+///
+/// 不需要访问系统剪贴板，通过 SSH 连接时同样有效；终端不支持时没有效果。
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stderr = io::stderr();
+    write!(stderr, "\x1b]52;c;{encoded}\x07")?;
+    stderr.flush()
+}
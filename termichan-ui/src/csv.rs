@@ -0,0 +1,116 @@
+use termichan_core::CommandResponse;
+
+/// CSV 输出的表头，列的顺序与每行数据一致。
+const HEADER: &[&str] = &["query", "command", "explanation", "provider", "model", "latency_ms", "tokens"];
+
+/// 一次查询的结果，CSV 输出中的一行。
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    /// 用户的查询。
+    pub query: String,
+    /// 生成的命令响应。
+    pub response: CommandResponse,
+    /// 请求使用的 token 总数，未知时为 `None`，输出为空字段。
+    pub tokens: Option<u64>,
+}
+
+/// 以 CSV 格式输出命令响应，供表格软件和脚本处理。
+pub struct CsvRenderer;
+
+impl CsvRenderer {
+    /// 输出表头和每个结果一行，行以 `\n` 结尾。
+    ///
+    /// 含有逗号、引号或换行的字段用双引号包裹，字段中的双引号写成两个。
+    pub fn render_batch(results: Vec<BatchResult>) -> String {
+        let mut out = row(HEADER.iter().map(|s| s.to_string()));
+        for result in results {
+            let response = result.response;
+            out.push_str(&row([
+                result.query,
+                response.parsed.command,
+                response.parsed.explanation.unwrap_or_default(),
+                response.provider,
+                response.model,
+                response.latency_ms.to_string(),
+                result.tokens.map(|t| t.to_string()).unwrap_or_default(),
+            ]));
+        }
+        out
+    }
+
+    /// 单个查询的输出：表头加一行数据，与批量输出的格式相同。
+    pub fn render(query: &str, response: &CommandResponse, tokens: Option<u64>) -> String {
+        Self::render_batch(vec![BatchResult {
+            query: query.to_string(),
+            response: response.clone(),
+            tokens,
+        }])
+    }
+}
+
+fn row(fields: impl IntoIterator<Item = String>) -> String {
+    let mut line = fields.into_iter().map(|f| quote(&f)).collect::<Vec<_>>().join(",");
+    line.push('\n');
+    line
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termichan_core::ParsedResponse;
+
+    fn response(command: &str, explanation: Option<&str>) -> CommandResponse {
+        CommandResponse {
+            parsed: ParsedResponse {
+                command: command.to_string(),
+                explanation: explanation.map(str::to_string),
+                ..ParsedResponse::default()
+            },
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            latency_ms: 120,
+        }
+    }
+
+    #[test]
+    fn render_writes_the_header_and_one_row() {
+        let csv = CsvRenderer::render("list files", &response("ls -la", None), Some(42));
+        assert_eq!(
+            csv,
+            "query,command,explanation,provider,model,latency_ms,tokens\n\
+             list files,ls -la,,openai,gpt-4o,120,42\n"
+        );
+    }
+
+    #[test]
+    fn render_batch_quotes_special_characters() {
+        let results = vec![
+            BatchResult {
+                query: "count lines, words".to_string(),
+                response: response("wc -l -w", Some("Counts lines\nand words")),
+                tokens: None,
+            },
+            BatchResult {
+                query: "say hi".to_string(),
+                response: response("echo \"hi\"", Some("Prints \"hi\"")),
+                tokens: Some(7),
+            },
+        ];
+        let csv = CsvRenderer::render_batch(results);
+        assert_eq!(
+            csv,
+            "query,command,explanation,provider,model,latency_ms,tokens\n\
+             \"count lines, words\",wc -l -w,\"Counts lines\nand words\",openai,gpt-4o,120,\n\
+             say hi,\"echo \"\"hi\"\"\",\"Prints \"\"hi\"\"\",openai,gpt-4o,120,7\n"
+        );
+        assert_eq!(CsvRenderer::render_batch(Vec::new()), format!("{}\n", HEADER.join(",")));
+    }
+}
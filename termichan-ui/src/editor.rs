@@ -0,0 +1,64 @@
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{DefaultEditor, Editor, Helper};
+use std::borrow::Cow;
+
+/// 单行编辑器，用于在执行前修改命令。
+pub struct LineEditor;
+
+impl LineEditor {
+    /// 显示 `prompt` 并以 `initial` 作为可编辑的初始内容读取一行。
+    ///
+    /// 用户按 Ctrl-C 或 Ctrl-D 取消时返回 `Ok(None)`。
+    pub fn edit(prompt: &str, initial: &str) -> Result<Option<String>, ReadlineError> {
+        let mut editor = DefaultEditor::new()?;
+        match editor.readline_with_initial(prompt, (initial, "")) {
+            Ok(line) => Ok(Some(line)),
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 显示 `prompt` 并读取一行，输入的每个字符显示为 `*`，用于读取 API 密钥等机密内容。
+    ///
+    /// 输入不会加入编辑历史。用户按 Ctrl-C 或 Ctrl-D 取消时返回 `Ok(None)`。
+    pub fn read_secret(prompt: &str) -> Result<Option<String>, ReadlineError> {
+        let mut editor: Editor<MaskingHelper, DefaultHistory> = Editor::new()?;
+        editor.set_helper(Some(MaskingHelper));
+        match editor.readline(prompt) {
+            Ok(line) => Ok(Some(line)),
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// 把输入内容显示为 `*` 的编辑器辅助类型，不提供补全和提示。
+struct MaskingHelper;
+
+impl Highlighter for MaskingHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned("*".repeat(line.chars().count()))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _forced: bool) -> bool {
+        // 每次输入都重新绘制，避免显示原始字符
+        true
+    }
+}
+
+impl Completer for MaskingHelper {
+    type Candidate = String;
+}
+
+impl Hinter for MaskingHelper {
+    type Hint = String;
+}
+
+impl Validator for MaskingHelper {}
+
+impl Helper for MaskingHelper {}
@@ -0,0 +1,18 @@
+mod confirm;
+mod csv;
+mod editor;
+mod pager;
+mod progress;
+mod render;
+mod spinner;
+mod stream;
+
+// 公开导出终端输出相关的类型，方便其他 crate 使用。
+pub use confirm::{copy_to_clipboard, ConfirmationChoice, ConfirmationPrompt};
+pub use csv::{BatchResult, CsvRenderer};
+pub use editor::LineEditor;
+pub use pager::{Pager, PagerError};
+pub use progress::{rate_limit_countdown, StreamProgress};
+pub use render::Renderer;
+pub use spinner::{AsyncSpinner, SpinnerHandle};
+pub use stream::StreamBuffer;
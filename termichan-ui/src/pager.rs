@@ -0,0 +1,107 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use termichan_config::UiConfig;
+use thiserror::Error;
+
+/// 未配置 `pager_command` 且 `$PAGER` 未设置时依次尝试的分页器。
+const FALLBACK_PAGERS: &[&str] = &["less", "more", "more.exe"];
+
+/// 分页器错误类型
+#[derive(Error, Debug)]
+pub enum PagerError {
+    #[error("Pager command is empty")]
+    EmptyCommand,
+    #[error("Failed to run pager `{command}`: {source}")]
+    Spawn {
+        command: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("I/O error while paging output: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// 将较长的输出交给外部分页器显示。
+pub struct Pager;
+
+impl Pager {
+    /// 显示 `content`，在需要时通过分页器滚动浏览。
+    ///
+    /// 仅当标准输出是终端且内容行数超过终端高度时才会启动分页器，
+    /// 否则直接写入标准输出。分页器依次取自 `UiConfig::pager_command`、
+    /// `$PAGER`，最后尝试 `less`、`more`、`more.exe`。
+    ///
+    /// # Errors
+    ///
+    /// 如果配置的分页器无法启动，或写入输出失败，返回 `PagerError`。
+    pub fn display(content: &str, config: &UiConfig) -> Result<(), PagerError> {
+        if !Self::should_page(content) {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(content.as_bytes())?;
+            stdout.flush()?;
+            return Ok(());
+        }
+
+        let explicit = config
+            .pager_command
+            .clone()
+            .or_else(|| env::var("PAGER").ok().filter(|p| !p.trim().is_empty()));
+
+        match explicit {
+            Some(command) => Self::run(&command, content),
+            None => {
+                for command in FALLBACK_PAGERS {
+                    match Self::run(command, content) {
+                        Err(PagerError::Spawn { source, .. })
+                            if source.kind() == io::ErrorKind::NotFound =>
+                        {
+                            continue;
+                        }
+                        result => return result,
+                    }
+                }
+                // 没有可用的分页器时直接输出
+                io::stdout().write_all(content.as_bytes())?;
+                Ok(())
+            }
+        }
+    }
+
+    /// 判断是否需要分页：标准输出是终端且内容超过终端高度。
+    fn should_page(content: &str) -> bool {
+        if !io::stdout().is_terminal() {
+            return false;
+        }
+        match terminal_size::terminal_size() {
+            Some((_, terminal_size::Height(height))) => content.lines().count() > height as usize,
+            None => false,
+        }
+    }
+
+    /// 启动分页器，将 `content` 写入其标准输入并等待其退出。
+    fn run(command: &str, content: &str) -> Result<(), PagerError> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or(PagerError::EmptyCommand)?;
+
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|source| PagerError::Spawn {
+                command: command.to_string(),
+                source,
+            })?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // 用户可能在读完全部内容之前退出分页器，此时忽略管道断开错误
+            match stdin.write_all(content.as_bytes()) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+        }
+
+        child.wait()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use std::time::Duration;
+use termichan_config::SpinnerStyle;
+use tokio::time::Instant;
+
+use crate::spinner::spinner_bar;
+
+/// 因速率限制需要等待时，显示倒计时并休眠 `wait`。
+///
+/// 提示信息输出到标准错误；标准错误不是终端时只休眠而不显示。
+pub async fn rate_limit_countdown(wait: Duration) {
+    let bar = ProgressBar::new_spinner();
+    let deadline = Instant::now() + wait;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        bar.set_message(format!(
+            "Rate limited. Waiting {}s before retrying...",
+            remaining.as_secs_f64().ceil()
+        ));
+        bar.tick();
+        tokio::time::sleep(remaining.min(Duration::from_secs(1))).await;
+    }
+
+    bar.finish_and_clear();
+}
+
+/// 接近 `max_tokens` 的比例，超过后显示警告。
+const TOKEN_WARNING_RATIO: f64 = 0.9;
+
+/// 流式接收响应时显示的 token 计数器。
+///
+/// 输出到标准错误；标准错误不是终端时不显示。
+pub struct StreamProgress {
+    bar: ProgressBar,
+    max_tokens: Option<u32>,
+    received: u64,
+    finish_reason: Option<String>,
+}
+
+impl StreamProgress {
+    /// 显示请求前的 token 估算，并开始计数。`max_tokens` 为 `None` 时不显示接近上限的警告。
+    ///
+    /// 计数器使用 `spinner_style` 动画；`SpinnerStyle::None` 时不显示计数器，只显示估算和最终用量。
+    pub fn start(estimated_prompt_tokens: u64, max_tokens: Option<u32>, spinner_style: SpinnerStyle) -> Self {
+        let animated = spinner_bar(spinner_style);
+        let bar = animated.clone().unwrap_or_else(ProgressBar::new_spinner);
+        bar.println(format!("Estimated: ~{estimated_prompt_tokens} tokens"));
+        match animated {
+            Some(_) => bar.enable_steady_tick(Duration::from_millis(100)),
+            None => bar.set_draw_target(ProgressDrawTarget::hidden()),
+        }
+        let progress = Self {
+            bar,
+            max_tokens,
+            received: 0,
+            finish_reason: None,
+        };
+        progress.update();
+        progress
+    }
+
+    /// 记录收到的一个响应块，`tokens` 为该块的 token 数。
+    pub fn on_chunk(&mut self, tokens: u64) {
+        self.received += tokens;
+        self.update();
+    }
+
+    /// 显示发送请求前因客户端速率限制等待的时间。
+    pub fn on_throttle(&self, wait_ms: u64) {
+        let message = format!("Throttled by the client-side rate limit for {wait_ms} ms");
+        // 计数器隐藏时（例如实时输出响应内容）直接写到标准错误
+        if self.bar.is_hidden() {
+            eprintln!("{message}");
+        } else {
+            self.bar.println(message);
+        }
+    }
+
+    /// 记录模型停止生成的原因，显示在计数器和最终用量中。
+    pub fn on_finish_reason(&mut self, reason: impl Into<String>) {
+        self.finish_reason = Some(reason.into());
+        self.update();
+    }
+
+    /// 不再显示计数器，只在结束时输出用量；在终端中实时输出响应内容时使用，以免两者交错。
+    pub fn hide(&self) {
+        self.bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    /// 已收到的 token 数。
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// 结束计数并显示最终用量。
+    ///
+    /// `actual_completion_tokens` 为 API 报告的实际输出 token 数，未提供时显示计数值。
+    ///
+    /// 结束原因为 `length` 时提示响应因达到 `max_tokens` 被截断。
+    pub fn finish(self, actual_completion_tokens: Option<u64>) {
+        let mut summary = match actual_completion_tokens {
+            Some(tokens) => format!("Used: {tokens} completion tokens"),
+            None => format!("Received: ~{} tokens", self.received),
+        };
+        if let Some(reason) = &self.finish_reason {
+            summary.push_str(&format!(" (finish: {reason})"));
+        }
+        self.bar.finish_and_clear();
+        eprintln!("{summary}");
+        if self.finish_reason.as_deref() == Some("length") {
+            eprintln!("\x1b[33mThe response was cut off by max_tokens\x1b[0m");
+        }
+    }
+
+    fn update(&self) {
+        let near_limit = self
+            .max_tokens
+            .is_some_and(|max| self.received as f64 >= f64::from(max) * TOKEN_WARNING_RATIO);
+        let mut counter = match self.max_tokens {
+            Some(max) => format!("Receiving: {} / {max} tokens", self.received),
+            None => format!("Receiving: {} tokens", self.received),
+        };
+        if let Some(reason) = &self.finish_reason {
+            counter.push_str(&format!("  finish: {reason}"));
+        }
+        if near_limit {
+            self.bar.set_message(format!("{counter}  \x1b[33mApproaching token limit\x1b[0m"));
+        } else {
+            self.bar.set_message(counter);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::iter;
+use terminal_size::{terminal_size, Width};
+use termichan_config::{OutputFormat, UiConfig};
+use termichan_core::CommandResponse;
+
+/// 紧凑模式下命令行的前缀。
+const COMPACT_PREFIX: &str = "[termichan] ► ";
+/// 标准布局中命令行的前缀。
+const BOX_PREFIX: &str = "│ ";
+/// 截断的命令行末尾的省略号。
+const ELLIPSIS: char = '…';
+
+/// 输出布局。新增渲染模式时在此添加变体，并在 `Renderer::render` 中分派。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Layout {
+    /// 带边框的多行布局，各部分之间以空行分隔。
+    Standard,
+    /// 单行命令，不显示解释，不输出空行。
+    Compact,
+}
+
+impl Layout {
+    pub(crate) fn from_config(config: &UiConfig) -> Self {
+        if config.compact_mode {
+            Layout::Compact
+        } else {
+            Layout::Standard
+        }
+    }
+}
+
+/// 将命令响应渲染为终端输出。
+pub struct Renderer;
+
+impl Renderer {
+    /// 按照 `UiConfig` 渲染 `response`，返回以换行结尾的文本。
+    pub fn render(response: &CommandResponse, config: &UiConfig) -> String {
+        match Layout::from_config(config) {
+            Layout::Standard => render_standard(response, config),
+            Layout::Compact => render_compact(response, config),
+        }
+    }
+
+    /// 将多个候选响应渲染为编号列表，编号从 1 开始。
+    ///
+    /// 每个候选只显示命令和安全提示，不显示解释，以便在一屏内比较。
+    pub fn render_choices(responses: &[CommandResponse], config: &UiConfig) -> String {
+        let layout = Layout::from_config(config);
+        let mut out = String::new();
+        for (index, response) in responses.iter().enumerate() {
+            let parsed = &response.parsed;
+            let label = format!("[{}] ", index + 1);
+            let indent = " ".repeat(label.chars().count());
+
+            let (command, truncated) = display_command(&parsed.command, config, indent.len());
+            let mut lines = command.lines();
+            out.push_str(&format!("{label}{}\n", lines.next().unwrap_or_default()));
+            for line in lines {
+                out.push_str(&format!("{indent}{line}\n"));
+            }
+            if truncated {
+                out.push_str(&format!("{indent}{}\n", truncation_notice(config)));
+            }
+            if let Some(note) = &parsed.safety_note {
+                out.push_str(&format!("{indent}# Be careful: {note}\n"));
+            }
+            if layout == Layout::Standard && index + 1 < responses.len() {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// 从多个候选中选择命令时的提示，`count` 为候选数量。
+    pub fn choice_prompt(count: usize, config: &UiConfig) -> String {
+        match Layout::from_config(config) {
+            Layout::Standard => format!("Choose a command [1-{count}, default 1]: "),
+            Layout::Compact => format!("choose [1-{count}]: "),
+        }
+    }
+
+}
+
+fn render_standard(response: &CommandResponse, config: &UiConfig) -> String {
+    let parsed = &response.parsed;
+    let (command, truncated) = display_command(&parsed.command, config, BOX_PREFIX.chars().count());
+    let width = command
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(20);
+
+    let mut sections = Vec::new();
+
+    let mut command_box = format!("╭─ termichan {}\n", "─".repeat(width.saturating_sub(10)));
+    for line in command.lines() {
+        command_box.push_str(&format!("{BOX_PREFIX}{line}\n"));
+    }
+    command_box.push_str(&format!("╰{}", "─".repeat(width + 2)));
+    if truncated {
+        command_box.push_str(&format!("\n{}", truncation_notice(config)));
+    }
+    sections.push(command_box);
+
+    if let Some(note) = &parsed.safety_note {
+        sections.push(format!("# Be careful: {note}"));
+    }
+    if let Some(explanation) = parsed.explanation.as_ref().filter(|_| config.show_explanation) {
+        sections.push(format!("# Explanation: {explanation}"));
+    }
+
+    format!("{}\n", sections.join("\n\n"))
+}
+
+fn render_compact(response: &CommandResponse, config: &UiConfig) -> String {
+    let parsed = &response.parsed;
+    let mut out = String::new();
+
+    let (command, truncated) = display_command(&parsed.command, config, COMPACT_PREFIX.chars().count());
+    let mut lines = command.lines();
+    out.push_str(&format!("{COMPACT_PREFIX}{}\n", lines.next().unwrap_or_default()));
+    let indent = " ".repeat(COMPACT_PREFIX.chars().count());
+    for line in lines {
+        out.push_str(&format!("{indent}{line}\n"));
+    }
+    if truncated {
+        out.push_str(&format!("{indent}{}\n", truncation_notice(config)));
+    }
+
+    if let Some(note) = &parsed.safety_note {
+        out.push_str(&format!("# Be careful: {note}\n"));
+    }
+    out
+}
+
+/// 按 `UiConfig::truncate_command_at` 截断每一行后用于显示的命令，以及是否有行被截断。
+///
+/// `prefix_width` 是每行前缀占用的列数，`Rich` 输出时截断后的行连同前缀不超过终端宽度。
+fn display_command(command: &str, config: &UiConfig, prefix_width: usize) -> (String, bool) {
+    if config.truncate_command_at == 0 {
+        return (command.to_string(), false);
+    }
+    let mut limit = config.truncate_command_at;
+    if config.output_format == OutputFormat::Rich
+        && let Some((Width(columns), _)) = terminal_size()
+    {
+        // 留出前缀和省略号的位置
+        limit = limit.min((columns as usize).saturating_sub(prefix_width + 1).max(1));
+    }
+
+    let mut truncated = false;
+    let lines: Vec<String> = command
+        .lines()
+        .map(|line| {
+            if line.chars().count() <= limit {
+                return line.to_string();
+            }
+            truncated = true;
+            line.chars().take(limit).chain(iter::once(ELLIPSIS)).collect()
+        })
+        .collect();
+    (lines.join("\n"), truncated)
+}
+
+/// 命令被截断时的提示，按键取自 `UiConfig::keybindings` 中的 `edit`。
+fn truncation_notice(config: &UiConfig) -> String {
+    format!("[truncated, press {} to see full]", config.keybinding("edit").unwrap_or("e"))
+}
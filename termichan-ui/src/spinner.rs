@@ -0,0 +1,86 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+use termichan_config::SpinnerStyle;
+
+/// 加载动画的刷新间隔。
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 等待异步操作时在标准错误显示的加载动画。
+///
+/// 动画在后台线程中刷新，不会阻塞 Tokio 运行时；标准错误不是终端时不显示。
+pub struct AsyncSpinner {
+    style: SpinnerStyle,
+    message: String,
+}
+
+/// 正在显示的加载动画，`finish` 或离开作用域时清除。
+pub struct SpinnerHandle {
+    bar: Option<ProgressBar>,
+}
+
+impl AsyncSpinner {
+    /// 创建以 `style` 动画显示 `message` 的加载动画。
+    pub fn new(style: SpinnerStyle, message: &str) -> Self {
+        Self {
+            style,
+            message: message.to_string(),
+        }
+    }
+
+    /// 开始显示动画；`SpinnerStyle::None` 时立即返回不显示任何内容的句柄。
+    pub fn start(self) -> SpinnerHandle {
+        let bar = spinner_bar(self.style).inspect(|bar| {
+            bar.set_message(self.message);
+            bar.enable_steady_tick(TICK_INTERVAL);
+        });
+        SpinnerHandle { bar }
+    }
+}
+
+impl SpinnerHandle {
+    /// 更新动画旁的提示文字。
+    pub fn set_message(&self, message: impl Into<String>) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(message.into());
+        }
+    }
+
+    /// 停止并清除动画。
+    pub fn finish(self) {}
+}
+
+impl Drop for SpinnerHandle {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// 使用 `style` 动画的进度条；`SpinnerStyle::None` 返回 `None`。
+pub(crate) fn spinner_bar(style: SpinnerStyle) -> Option<ProgressBar> {
+    let frames = frames(style)?;
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {msg}")
+            .expect("valid template")
+            .tick_strings(frames),
+    );
+    Some(bar)
+}
+
+/// 各样式的动画帧；按 indicatif 的约定，最后一帧在结束时显示。
+fn frames(style: SpinnerStyle) -> Option<&'static [&'static str]> {
+    let frames: &[&str] = match style {
+        SpinnerStyle::Braille => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏", "⠿"],
+        SpinnerStyle::Dots => &[".  ", ".. ", "...", " ..", "  .", "   ", "..."],
+        SpinnerStyle::Bar => &["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█", "▇", "▆", "▅", "▄", "▃", "▂", "█"],
+        SpinnerStyle::Arc => &["◜", "◠", "◝", "◞", "◡", "◟", "○"],
+        SpinnerStyle::Clock => &[
+            "🕛", "🕐", "🕑", "🕒", "🕓", "🕔", "🕕", "🕖", "🕗", "🕘", "🕙", "🕚", "🕛",
+        ],
+        SpinnerStyle::Pipe => &["|", "/", "-", "\\", "|"],
+        SpinnerStyle::None => return None,
+    };
+    Some(frames)
+}
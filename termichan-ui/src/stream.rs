@@ -0,0 +1,81 @@
+use futures::{Stream, StreamExt};
+use std::io::{self, Write};
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// 缓存达到此字节数时立即写出，避免快速模型的输出延迟显示。
+const FLUSH_THRESHOLD_BYTES: usize = 512;
+
+/// 合并流式响应的内容块，按固定间隔批量写出。
+///
+/// 逐字写入终端会让部分终端闪烁；`StreamBuffer` 最多每隔 `flush_interval` 写出一次，
+/// 缓存超过 512 字节时立即写出。`flush_interval` 为 0 时每块内容都立即写出。
+pub struct StreamBuffer<W: Write> {
+    writer: W,
+    pending: String,
+    interval: Option<Interval>,
+}
+
+impl<W: Write> StreamBuffer<W> {
+    /// 创建写入 `writer` 的缓冲区。需要在 Tokio 运行时中调用。
+    pub fn new(flush_interval: Duration, writer: W) -> Self {
+        let interval = (!flush_interval.is_zero()).then(|| {
+            let mut interval = tokio::time::interval(flush_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        Self {
+            writer,
+            pending: String::new(),
+            interval,
+        }
+    }
+
+    /// 缓存一块内容；缓存达到阈值或未设置刷新间隔时立即写出。
+    pub fn push(&mut self, chunk: &str) -> io::Result<()> {
+        self.pending.push_str(chunk);
+        if self.interval.is_none() || self.pending.len() >= FLUSH_THRESHOLD_BYTES {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// 等待 `stream` 的下一项，期间每到刷新间隔就写出已缓存的内容。
+    ///
+    /// 流结束时返回 `Ok(None)`，已缓存的内容由 `finish` 写出。
+    pub async fn next_from<S>(&mut self, stream: &mut S) -> io::Result<Option<S::Item>>
+    where
+        S: Stream + Unpin,
+    {
+        let Some(interval) = &mut self.interval else {
+            return Ok(stream.next().await);
+        };
+        loop {
+            tokio::select! {
+                item = stream.next() => return Ok(item),
+                _ = interval.tick() => write_pending(&mut self.writer, &mut self.pending)?,
+            }
+        }
+    }
+
+    /// 立即写出已缓存的内容。
+    pub fn flush(&mut self) -> io::Result<()> {
+        write_pending(&mut self.writer, &mut self.pending)
+    }
+
+    /// 写出剩余内容并返回 `writer`，在流结束时调用，不等待下一次刷新。
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn write_pending(writer: &mut impl Write, pending: &mut String) -> io::Result<()> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+    writer.write_all(pending.as_bytes())?;
+    writer.flush()?;
+    pending.clear();
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use termichan_config::OutputFormat;
+use termichan_llm::ShellKind;
+use uuid::Uuid;
+
+use crate::commands::cheatsheet::CheatsheetFormat;
+use crate::commands::history::{parse_from_date, parse_to_date, HistoryExportFormat};
+use crate::output::OutputFileFormat;
+
+/// termichan 的命令行参数。
+#[derive(Debug, Parser)]
+#[command(name = "termichan", version, about = "Generate terminal commands from natural language")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// 使用多个模型（逗号分隔）回答同一查询并比较结果。
+    #[arg(long, value_delimiter = ',', value_name = "MODELS")]
+    pub compare: Option<Vec<String>>,
+
+    /// 在处理查询前检查 LLM 服务的连通性。
+    #[arg(long)]
+    pub health: bool,
+
+    /// 以流式方式接收响应，显示 token 估算和实时计数。
+    #[arg(long)]
+    pub stream: bool,
+
+    /// 将生成的命令和解释同时写入文件。
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// `--output` 文件的格式。
+    #[arg(long, value_enum, default_value_t, requires = "output")]
+    pub output_format: OutputFileFormat,
+
+    /// 覆盖配置中的 `ui.output_format`：plain、markdown、rich、json 或 csv。
+    ///
+    /// `json` 向标准输出写出单个 JSON 对象，`csv` 写出表头和一行数据，两者都不显示确认提示，也不执行命令。
+    #[arg(long, value_name = "FORMAT")]
+    pub format: Option<OutputFormat>,
+
+    /// 不在终端显示响应，仅与 `--output` 一起使用。
+    #[arg(long, requires = "output")]
+    pub quiet: bool,
+
+    /// 不读取配置文件，仅从 `TERMICHAN_*` 环境变量构建配置。
+    #[arg(long, global = true)]
+    pub no_config_file: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// 用自然语言描述的查询。
+    #[arg(value_name = "QUERY")]
+    pub query: Vec<String>,
+}
+
+/// termichan 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 管理响应缓存（需要设置 `llm.cache_file`）。
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// 管理模型比较结果。
+    #[command(subcommand)]
+    Compare(CompareCommand),
+    /// 查看和管理配置。
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// 通过交互式向导创建配置文件。
+    Init {
+        /// 覆盖已有的配置文件，不再询问。
+        #[arg(long)]
+        overwrite: bool,
+        /// 不提问，直接写入默认配置；没有终端或在 CI 环境中时自动启用。
+        #[arg(long)]
+        non_interactive: bool,
+    },
+    /// 诊断配置、网络和运行环境的常见问题。
+    Doctor,
+    /// 检查 API 密钥和 LLM 服务的连通性。
+    Test {
+        /// 同时显示连接池的使用情况。
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// 查看命令历史记录。
+    #[command(subcommand)]
+    History(HistoryCommand),
+    /// 管理识别危险命令的正则表达式。
+    #[command(subcommand)]
+    Security(SecurityCommand),
+    /// 查看、切换和添加助手风格。
+    #[command(subcommand)]
+    Persona(PersonaCommand),
+    /// 测量当前提供商和模型的延迟与吞吐量。
+    Benchmark {
+        /// 请求次数。
+        #[arg(long, default_value_t = 10)]
+        iterations: u32,
+        /// 测试使用的提示词。
+        #[arg(long, default_value = termichan_llm::DEFAULT_BENCHMARK_PROMPT)]
+        prompt: String,
+        /// 单个请求的超时时间（秒），超时的请求计为失败；不超过 `llm.timeout_secs`。
+        #[arg(long, value_name = "SECONDS")]
+        timeout_secs: Option<u64>,
+    },
+    /// 以服务方式运行，供 IDE 和编辑器插件调用。
+    Server {
+        /// 提供 gRPC 接口（见 `termichan-server/proto/termichan.proto`）。
+        #[arg(long, required = true)]
+        grpc: bool,
+        /// 监听的本机端口。
+        #[arg(long, default_value_t = termichan_server::DEFAULT_GRPC_PORT)]
+        port: u16,
+    },
+    /// 运行命令，失败时请 LLM 解释错误并给出修正后的命令。
+    Fix {
+        /// 要运行的命令，例如 `termichan fix git psuh`。
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// 请 LLM 为经常使用的命令建议简短的别名，确认后追加到 shell 的配置文件。
+    Alias {
+        /// 别名语法的目标 shell：bash、zsh、fish 或 powershell，默认按 `$SHELL` 检测。
+        #[arg(long)]
+        shell: Option<ShellKind>,
+        /// 要起别名的命令，例如 `termichan alias ls -la`。
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// 打印子命令、配置项和提示词占位符的速查表。
+    Cheatsheet {
+        /// 输出格式。
+        #[arg(long, value_enum, default_value_t)]
+        format: CheatsheetFormat,
+    },
+    /// 以 Markdown 格式打印问题报告：隐藏密钥的配置、系统信息和最近 5 条历史记录。
+    Bugreport,
+    /// 清理过期的历史记录、多余的配置备份和过期的响应缓存。
+    Gc {
+        /// 只显示将要清理的内容，不删除任何文件。
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 在 Unix 域套接字上运行守护进程，供 shell 小部件低延迟调用。
+    #[cfg(unix)]
+    Daemon {
+        /// 监听的套接字路径。
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+        /// 同时在 `127.0.0.1` 的此端口上提供 Prometheus 格式的 `/metrics`。
+        #[arg(long, value_name = "PORT")]
+        metrics_port: Option<u16>,
+    },
+}
+
+/// `termichan cache` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// 预先请求文件中的查询并写入缓存。
+    Warm {
+        /// 每行一个查询的文本文件。
+        #[arg(long, value_name = "PATH")]
+        file: PathBuf,
+    },
+    /// 显示缓存的命中次数、未命中次数和大小。
+    Stats,
+}
+
+/// `termichan compare` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum CompareCommand {
+    /// 将最近一次比较结果保存为 JSON 报告。
+    Save {
+        /// 报告名称。
+        name: String,
+    },
+}
+
+/// `termichan config` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// 显示当前生效的配置和 LLM 服务状态。
+    Show {
+        /// 以 JSON 格式输出配置，不检查 LLM 服务状态。
+        #[arg(long)]
+        json: bool,
+        /// 将 API 密钥等敏感字段整体替换为 `[REDACTED]`，适合附在问题报告中。
+        #[arg(long, requires = "json")]
+        redacted: bool,
+    },
+    /// 打印所有受支持的环境变量及其默认值和说明。
+    EnvTemplate,
+    /// 管理配置文件的备份。
+    #[command(subcommand)]
+    Backup(BackupCommand),
+    /// 显示配置项的说明、类型和默认值。
+    Docs {
+        /// 只显示指定路径下的配置项，例如 `llm.temperature` 或 `network`。
+        #[arg(long)]
+        field: Option<String>,
+    },
+    /// 将另一个配置文件中的设置合并到当前配置，写入前显示差异并请求确认。
+    Import {
+        /// 要导入的 TOML 配置文件。
+        path: PathBuf,
+        /// 只导入指定的配置段，例如 `llm` 或 `network`。
+        #[arg(long)]
+        section: Option<String>,
+    },
+}
+
+/// `termichan config backup` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum BackupCommand {
+    /// 列出所有配置备份。
+    List,
+    /// 用指定时间戳的备份替换当前配置文件。
+    Restore {
+        /// 备份的时间戳，格式为 `YYYY-MM-DD_HH-MM-SS`，同一秒内的后续备份带有 `_1` 等序号（见 `config backup list`）。
+        timestamp: String,
+    },
+}
+
+/// `termichan history` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// 显示历史记录统计信息。
+    Stats {
+        /// 仅统计指定提供商生成的记录。
+        #[arg(long)]
+        provider: Option<String>,
+        /// 显示长期使用统计，例如最常使用的星期和最长连续使用天数。
+        #[arg(long, conflicts_with = "provider")]
+        all_time: bool,
+        /// 按提示词变体（见 `prompt.ab_test`）比较成功率和耗时。
+        #[arg(long, conflicts_with_all = ["provider", "all_time"])]
+        ab_test: bool,
+    },
+    /// 列出历史记录，可按时间范围和提供商筛选。
+    List {
+        /// 只列出此时间之后的记录：RFC 3339 时间、`2024-01-01` 这样的日期，或 `7d ago` 这样的相对时间。
+        #[arg(long, value_name = "DATE", value_parser = parse_from_date)]
+        from: Option<DateTime<Utc>>,
+        /// 只列出此时间之前的记录，格式与 `--from` 相同；只有日期时包括当天。
+        #[arg(long, value_name = "DATE", value_parser = parse_to_date)]
+        to: Option<DateTime<Utc>>,
+        /// 只列出指定提供商生成的记录。
+        #[arg(long)]
+        provider: Option<String>,
+    },
+    /// 列出耗时较长的请求。
+    Slow {
+        /// 耗时阈值（毫秒），默认使用 `llm.slow_query_warn_ms`，未设置时为 5000。
+        #[arg(long)]
+        threshold_ms: Option<u64>,
+    },
+    /// 找出经常连续使用的命令，并建议对应的 shell 别名或函数。
+    Patterns,
+    /// 把历史记录中的命令分为若干组相关的命令，例如 Docker 命令和 Git 命令。
+    Cluster {
+        /// 最多分为的组数。
+        #[arg(long, short, default_value_t = 10)]
+        n: usize,
+    },
+    /// 从终端录制文件中导入以前执行过的命令。
+    Import {
+        /// asciinema v2 录制文件（`.cast`）。
+        #[arg(long, value_name = "PATH")]
+        asciinema: PathBuf,
+    },
+    /// 合并另一个历史文件（例如在其他机器上生成的）中的记录，跳过重复的记录。
+    Merge {
+        /// 要合并的历史文件（JSON Lines 格式）。
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+    /// 编辑并重新执行一条历史记录中的命令。
+    Replay {
+        /// 历史记录编号。
+        id: u64,
+    },
+    /// 列出所有终端会话及其记录数和时间范围。
+    Sessions,
+    /// 依次编辑并重新执行一个会话中的所有命令。
+    ReplaySession {
+        /// 会话 ID（见 `history sessions`）。
+        id: Uuid,
+    },
+    /// 按含义搜索历史记录中的查询，需要启用 `llm.enable_embeddings`。
+    Search {
+        /// 要搜索的查询。
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// 最多显示的记录数。
+        #[arg(long, short = 'k', default_value_t = 5)]
+        limit: usize,
+    },
+    /// 按 `history.retention_policy` 立即删除历史记录。
+    Purge {
+        /// 删除早于此天数的记录，覆盖 `retention_policy.max_age_days`。
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u32>,
+        /// 只显示将要删除的记录数，不修改历史文件。
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 删除历史记录中的查询、IP 地址、密钥等敏感信息，以便分享历史文件。
+    Anonymize {
+        /// 直接修改历史文件。
+        #[arg(long, conflicts_with = "output")]
+        in_place: bool,
+        /// 写入的文件（JSON Lines 格式），默认输出到标准输出。
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// 导出所有历史记录或统计指标。
+    Export {
+        /// 导出格式。
+        #[arg(long, value_enum, default_value_t)]
+        format: HistoryExportFormat,
+        /// 写入的文件，默认输出到标准输出。
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// `termichan security` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum SecurityCommand {
+    /// 添加识别危险命令的正则表达式。
+    ///
+    /// 至少需要 `--socket` 和 `--persist` 之一：前者立即作用于运行中的守护进程，后者写入配置文件，
+    /// 之后启动的 `termichan` 都会使用。
+    AddPattern {
+        /// 与整条命令匹配的正则表达式，例如 `curl .*\| *sh`。
+        pattern: String,
+        /// 写入配置文件的 `security.dangerous_patterns`。
+        #[arg(long)]
+        persist: bool,
+        /// 添加到监听此套接字的守护进程，无需重启即生效。
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// 删除识别危险命令的正则表达式，选项与 `add-pattern` 相同。
+    RemovePattern {
+        /// 要删除的正则表达式，须与添加时完全相同。
+        pattern: String,
+        /// 从配置文件的 `security.dangerous_patterns` 中删除。
+        #[arg(long)]
+        persist: bool,
+        /// 从监听此套接字的守护进程中删除。
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// 列出当前生效的识别危险命令的正则表达式。
+    ListPatterns {
+        /// 列出监听此套接字的守护进程中生效的表达式，而不是配置中的 `security.dangerous_patterns`。
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+}
+
+/// `termichan persona` 的子命令。
+#[derive(Debug, Subcommand)]
+pub enum PersonaCommand {
+    /// 列出内置和自定义的风格，当前使用的风格以 `*` 标记。
+    List,
+    /// 把 `llm.active_persona` 设为指定的风格并写入配置文件。
+    Use {
+        /// 风格名称，例如 `expert`、`teacher` 或 `safety-first`。
+        name: String,
+    },
+    /// 在配置文件的 `prompt.role_play_personas` 中添加或替换一个风格。
+    Add {
+        /// 风格名称，与内置风格同名时替换内置风格。
+        name: String,
+        /// 加在系统提示词之前的文本。
+        #[arg(long)]
+        prefix: String,
+        /// 使用这个风格时代替 `llm.temperature` 的值。
+        #[arg(long)]
+        temperature: Option<f32>,
+    },
+}
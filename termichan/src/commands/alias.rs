@@ -0,0 +1,68 @@
+use std::env;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use termichan_config::Config;
+use termichan_core::ResponseParser;
+use termichan_llm::ShellKind;
+use termichan_ui::AsyncSpinner;
+
+/// 执行 `termichan alias`：请 LLM 为命令建议别名，确认后追加到 shell 的配置文件。
+///
+/// 没有指定 `shell` 时按 `$SHELL` 检测。标准输入不是终端时只打印别名定义。
+pub async fn run(config: &Config, command: &str, shell: Option<ShellKind>) -> Result<(), Box<dyn Error>> {
+    let shell = shell
+        .or_else(ShellKind::detect)
+        .ok_or("Cannot detect the current shell, pass --shell")?;
+    let service = super::build_service(config)?;
+    let raw = {
+        let _spinner = AsyncSpinner::new(config.ui.spinner_style, "Suggesting alias...").start();
+        service.generate_alias(command, shell).await?
+    };
+    let alias = ResponseParser::parse(&raw).command;
+    println!("{alias}");
+
+    let Some(path) = config_file(shell) else {
+        return Ok(());
+    };
+    if !io::stdin().is_terminal() {
+        return Ok(());
+    }
+    print!("Add this alias to your shell config? [y/n] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "\n# Added by termichan for: {}\n{alias}", command.replace('\n', " "))?;
+    println!("Added to {}. Open a new shell to use it.", path.display());
+    Ok(())
+}
+
+/// shell 启动时读取的用户配置文件。
+fn config_file(shell: ShellKind) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let xdg_config = || {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".config"))
+    };
+    Some(match shell {
+        ShellKind::Bash => home.join(".bashrc"),
+        ShellKind::Zsh => env::var_os("ZDOTDIR")
+            .map_or_else(|| home.clone(), PathBuf::from)
+            .join(".zshrc"),
+        ShellKind::Fish => xdg_config().join("fish").join("config.fish"),
+        ShellKind::PowerShell if cfg!(windows) => dirs::document_dir()?
+            .join("PowerShell")
+            .join("Microsoft.PowerShell_profile.ps1"),
+        ShellKind::PowerShell => xdg_config().join("powershell").join("Microsoft.PowerShell_profile.ps1"),
+    })
+}
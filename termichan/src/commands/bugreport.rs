@@ -0,0 +1,45 @@
+use std::env;
+use termichan_config::Config;
+use termichan_core::HistoryManager;
+use termichan_llm::PromptContext;
+
+/// 问题报告中包含的最近历史记录数。
+const RECENT_ENTRIES: usize = 5;
+
+/// 生成 Markdown 格式的问题报告。
+///
+/// 配置使用 `Config::to_redacted_json`，历史记录只包含命令的摘要，不包含查询和命令原文。
+pub fn render(config: &Config) -> String {
+    let ctx = PromptContext::detect();
+    let mut out = String::from("## termichan bug report\n\n### System\n\n");
+    out.push_str(&format!("- termichan: {}\n", env!("CARGO_PKG_VERSION")));
+    out.push_str(&format!("- OS: {} ({})\n", ctx.os, env::consts::ARCH));
+    out.push_str(&format!("- Shell: {}\n", ctx.shell));
+    out.push_str(&format!("- Provider: {} / {}\n\n", config.llm.provider, config.llm.model));
+
+    let redacted = serde_json::to_string_pretty(&config.to_redacted_json()).unwrap_or_default();
+    out.push_str(&format!("### Config\n\n```json\n{redacted}\n```\n\n"));
+
+    out.push_str("### Recent history\n\n");
+    match HistoryManager::load(&config.history) {
+        Ok(history) if history.entries().is_empty() => out.push_str("No history entries.\n"),
+        Ok(history) => {
+            out.push_str("| Time | Provider | Model | Command SHA-256 | Executed | Exit code |\n");
+            out.push_str("|---|---|---|---|---|---|\n");
+            let start = history.entries().len().saturating_sub(RECENT_ENTRIES);
+            for entry in &history.entries()[start..] {
+                let exit_code = entry.exit_code.map(|code| code.to_string()).unwrap_or_default();
+                out.push_str(&format!(
+                    "| {} | {} | {} | `{}` | {} | {exit_code} |\n",
+                    config.ui.format_timestamp(&entry.timestamp),
+                    entry.provider,
+                    entry.model,
+                    entry.command_hash(),
+                    entry.executed,
+                ));
+            }
+        }
+        Err(e) => out.push_str(&format!("History unavailable: {e}\n")),
+    }
+    out
+}
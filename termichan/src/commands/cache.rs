@@ -0,0 +1,56 @@
+use std::error::Error;
+use std::fs;
+use termichan_config::Config;
+use termichan_llm::{Cache, LlmService, PromptContext};
+
+use crate::cli::CacheCommand;
+
+/// 执行 `termichan cache` 子命令。
+pub async fn run(command: CacheCommand, config: &Config) -> Result<(), Box<dyn Error>> {
+    let path = config
+        .llm
+        .cache_file
+        .as_ref()
+        .ok_or("The response cache is disabled, set llm.cache_file to enable it")?;
+    match command {
+        CacheCommand::Warm { file } => {
+            let queries: Vec<String> = fs::read_to_string(&file)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            let service = super::build_service(config)?;
+            // 与实际查询使用相同的上下文，预热的条目才能被命中
+            let ctx = PromptContext::detect()
+                .with_injection_guard(&config.llm)
+                .with_recent_errors(&config.prompt)
+                .with_context_providers(&config.prompt);
+            let report = service.warm_cache(queries, &ctx, &config.prompt).await?;
+            save_cache(&service);
+            println!(
+                "Warmed {}, already cached {}, failed {}",
+                report.warmed, report.already_cached, report.failed
+            );
+        }
+        CacheCommand::Stats => {
+            let stats = Cache::load(path)?.stats();
+            println!("Cache file: {}", path.display());
+            println!("Entries:    {}", stats.entries);
+            println!("Size:       {} bytes", stats.total_bytes);
+            println!("Hits:       {}", stats.hits);
+            println!("Misses:     {}", stats.misses);
+        }
+    }
+    Ok(())
+}
+
+/// 将服务的响应缓存写回 `llm.cache_file`；失败只记录警告，不影响本次查询的结果。
+pub fn save_cache(service: &LlmService) {
+    let Some(cache) = service.cache() else {
+        return;
+    };
+    if let Err(e) = cache.save() {
+        log::warn!("Failed to save response cache: {e}");
+    }
+}
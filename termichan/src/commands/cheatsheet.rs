@@ -0,0 +1,180 @@
+use clap::{CommandFactory, ValueEnum};
+use serde::Serialize;
+use termichan_config::{Config, ConfirmationMode, OutputFormat};
+use termichan_llm::PROMPT_PLACEHOLDERS;
+
+use crate::cli::Cli;
+
+/// `termichan cheatsheet` 的输出格式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CheatsheetFormat {
+    /// Markdown，适合保存到 wiki。
+    #[default]
+    Md,
+    /// 纯文本，适合在终端中查看。
+    Plain,
+    /// 独立的 HTML 页面。
+    Html,
+}
+
+/// 速查表中的一节，每行为`(条目, 说明)`。
+struct Section {
+    title: String,
+    rows: Vec<(String, String)>,
+}
+
+/// 生成速查表。
+///
+/// 子命令取自 clap 的命令定义，配置项取自 `Config::documentation()`，
+/// 与 `--help` 和 `termichan config docs` 的内容保持一致。
+pub fn render(format: CheatsheetFormat) -> String {
+    let sections = sections();
+    match format {
+        CheatsheetFormat::Md => render_markdown(&sections),
+        CheatsheetFormat::Plain => render_plain(&sections),
+        CheatsheetFormat::Html => render_html(&sections),
+    }
+}
+
+fn sections() -> Vec<Section> {
+    let cli = Cli::command();
+    let mut sections = vec![Section {
+        title: "Flags".to_string(),
+        rows: flags(&cli),
+    }];
+
+    let mut commands = Vec::new();
+    collect_commands(&cli, "termichan", &mut commands);
+    sections.push(Section {
+        title: "Commands".to_string(),
+        rows: commands,
+    });
+
+    // 按配置项路径的第一段分节，例如 `llm`、`security`
+    let documentation = Config::documentation();
+    for doc in &documentation.fields {
+        let section = doc.path.split('.').next().unwrap_or_default();
+        let title = format!("Config: [{section}]");
+        let default = if doc.default_value.is_empty() { "none" } else { &doc.default_value };
+        let summary = doc.description.lines().next().unwrap_or_default();
+        let row = (format!("{} = {default}", doc.path), summary.to_string());
+        match sections.last_mut() {
+            Some(last) if last.title == title => last.rows.push(row),
+            _ => sections.push(Section { title, rows: vec![row] }),
+        }
+    }
+
+    sections.push(Section {
+        title: "security.confirmation_mode values".to_string(),
+        rows: ConfirmationMode::ALL.iter().map(|mode| (variant_name(mode), String::new())).collect(),
+    });
+    sections.push(Section {
+        title: "ui.output_format values".to_string(),
+        rows: OutputFormat::ALL.iter().map(|format| (variant_name(format), String::new())).collect(),
+    });
+    sections.push(Section {
+        title: "Prompt placeholders".to_string(),
+        rows: PROMPT_PLACEHOLDERS
+            .iter()
+            .map(|(placeholder, description)| (placeholder.to_string(), description.to_string()))
+            .collect(),
+    });
+    sections
+}
+
+/// 命令的选项，例如 `--stream`、`--output <PATH>`；不含 `--help` 和 `--version`。
+fn flags(command: &clap::Command) -> Vec<(String, String)> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !matches!(arg.get_id().as_str(), "help" | "version"))
+        .filter_map(|arg| {
+            let long = arg.get_long()?;
+            let value = if arg.get_action().takes_values() {
+                let name = arg.get_value_names().and_then(|names| names.first());
+                name.map_or_else(|| format!(" <{}>", arg.get_id().as_str().to_uppercase()), |name| format!(" <{name}>"))
+            } else {
+                String::new()
+            };
+            let help = arg.get_help().map(ToString::to_string).unwrap_or_default();
+            Some((format!("--{long}{value}"), help))
+        })
+        .collect()
+}
+
+/// 递归列出子命令，每个子命令一行，附带其选项。
+fn collect_commands(command: &clap::Command, prefix: &str, out: &mut Vec<(String, String)>) {
+    for sub in command.get_subcommands().filter(|sub| sub.get_name() != "help") {
+        let name = format!("{prefix} {}", sub.get_name());
+        if sub.has_subcommands() {
+            collect_commands(sub, &name, out);
+            continue;
+        }
+        let flags: Vec<String> = flags(sub).into_iter().map(|(flag, _)| flag).collect();
+        let usage = if flags.is_empty() {
+            name
+        } else {
+            format!("{name} [{}]", flags.join("] ["))
+        };
+        let about = sub.get_about().map(ToString::to_string).unwrap_or_default();
+        out.push((usage, about));
+    }
+}
+
+/// 枚举值在配置文件中的写法。
+fn variant_name(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn render_markdown(sections: &[Section]) -> String {
+    let escape = |text: &str| text.replace('|', "\\|");
+    let mut out = "# termichan cheatsheet\n".to_string();
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n| | |\n|---|---|\n", section.title));
+        for (item, description) in &section.rows {
+            out.push_str(&format!("| `{}` | {} |\n", escape(item), escape(description)));
+        }
+    }
+    out
+}
+
+fn render_plain(sections: &[Section]) -> String {
+    let mut out = String::new();
+    for section in sections {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n", section.title.to_uppercase()));
+        let width = section.rows.iter().map(|(item, _)| item.chars().count()).max().unwrap_or_default();
+        for (item, description) in &section.rows {
+            out.push_str(format!("  {item:<width$}  {description}").trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn render_html(sections: &[Section]) -> String {
+    let escape = |text: &str| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let mut out = "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>termichan cheatsheet</title></head>\n<body>\n<h1>termichan cheatsheet</h1>\n".to_string();
+    for section in sections {
+        out.push_str(&format!("<h2>{}</h2>\n<table>\n", escape(&section.title)));
+        for (item, description) in &section.rows {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td></tr>\n",
+                escape(item),
+                escape(description)
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
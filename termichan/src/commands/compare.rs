@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use termichan_config::Config;
+use termichan_llm::ModelComparison;
+use termichan_ui::Pager;
+
+use crate::cli::CompareCommand;
+
+/// 执行 `termichan compare` 子命令。
+pub fn run(command: CompareCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        CompareCommand::Save { name } => {
+            let path = save_last(&name)?;
+            println!("Comparison saved to {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// 使用多个模型回答 `query` 并以表格显示结果，同时保存为最近一次比较结果。
+pub async fn compare(config: &Config, query: String, models: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let service = super::build_service(config)?;
+    let messages = termichan_llm::PromptContext::detect()
+        .with_injection_guard(&config.llm)
+        .with_recent_errors(&config.prompt)
+        .with_context_providers(&config.prompt)
+        .build_messages(&config.prompt, &query);
+
+    let comparisons = service.compare_models_with_latency(messages, models).await;
+    let report = ComparisonReport::new(query, &comparisons);
+    Pager::display(&report.render_table(), &config.ui)?;
+    report.save_as_last()?;
+    Ok(())
+}
+
+/// 一次模型比较的可持久化报告。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComparisonReport {
+    /// 参与比较的用户查询。
+    pub query: String,
+    /// 比较完成时的 Unix 时间戳（秒）。
+    pub created_at: u64,
+    /// 每个模型的比较结果，顺序与命令行中的模型列表一致。
+    pub results: Vec<ComparisonEntry>,
+}
+
+/// 单个模型在报告中的结果。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComparisonEntry {
+    pub model: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+impl ComparisonReport {
+    pub fn new(query: String, comparisons: &[ModelComparison]) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let results = comparisons
+            .iter()
+            .map(|c| ComparisonEntry {
+                model: c.model.clone(),
+                response: c.response.as_ref().ok().cloned(),
+                error: c.response.as_ref().err().map(|e| e.to_string()),
+                latency_ms: c.latency.as_millis() as u64,
+            })
+            .collect();
+
+        Self {
+            query,
+            created_at,
+            results,
+        }
+    }
+
+    /// 以表格形式渲染比较结果，每个模型一行，多行响应在响应列中续行。
+    pub fn render_table(&self) -> String {
+        let model_width = self
+            .results
+            .iter()
+            .map(|r| r.model.len())
+            .chain(std::iter::once("MODEL".len()))
+            .max()
+            .unwrap_or_default();
+        let latency_width = "LATENCY".len().max(10);
+
+        let mut out = format!(
+            "{:<model_width$}  {:>latency_width$}  RESPONSE\n",
+            "MODEL", "LATENCY"
+        );
+        for entry in &self.results {
+            let latency = format!("{} ms", entry.latency_ms);
+            let body = match (&entry.response, &entry.error) {
+                (Some(response), _) => response.trim().to_string(),
+                (None, Some(error)) => format!("error: {error}"),
+                (None, None) => String::new(),
+            };
+            let mut lines = body.lines();
+            out.push_str(&format!(
+                "{:<model_width$}  {:>latency_width$}  {}\n",
+                entry.model,
+                latency,
+                lines.next().unwrap_or_default()
+            ));
+            for line in lines {
+                out.push_str(&format!(
+                    "{:<model_width$}  {:>latency_width$}  {}\n",
+                    "", "", line
+                ));
+            }
+        }
+        out
+    }
+
+    /// 保存为最近一次比较结果，供 `termichan compare save` 使用。
+    pub fn save_as_last(&self) -> Result<(), Box<dyn Error>> {
+        let path = last_comparison_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 将最近一次比较结果保存为名为 `name` 的 JSON 报告，返回报告路径。
+///
+/// `name` 只能是文件名，不能含有路径分隔符或 `..`，报告总是写在比较结果目录中。
+pub fn save_last(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(format!("invalid comparison name `{name}`: use a file name without `/`, `\\` or `..`").into());
+    }
+    let last = last_comparison_path()?;
+    let content = fs::read_to_string(&last)
+        .map_err(|e| format!("no comparison to save ({}): {e}", last.display()))?;
+
+    let dir = dirs::data_dir()
+        .ok_or("unable to determine data directory")?
+        .join("termichan")
+        .join("comparisons");
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!("{name}.json"));
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+fn last_comparison_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(dirs::cache_dir()
+        .ok_or("unable to determine cache directory")?
+        .join("termichan")
+        .join("last_comparison.json"))
+}
@@ -0,0 +1,153 @@
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use termichan_config::{config_file_path, Config, FieldDoc, ENV_VARS};
+
+use crate::cli::{BackupCommand, ConfigCommand};
+
+/// 执行 `termichan config` 子命令。
+pub async fn run(command: ConfigCommand, config: &Config) -> Result<(), Box<dyn Error>> {
+    match command {
+        ConfigCommand::Show { json: true, redacted } => {
+            let value = if redacted {
+                config.to_redacted_json()
+            } else {
+                serde_json::to_value(config.sensitive_fields_masked())?
+            };
+            println!("{}", serde_json::to_string_pretty(&value)?);
+            Ok(())
+        }
+        ConfigCommand::Show { json: false, .. } => show(config).await,
+        ConfigCommand::EnvTemplate => {
+            print!("{}", env_template());
+            Ok(())
+        }
+        ConfigCommand::Backup(command) => backup(command, config),
+        ConfigCommand::Docs { field } => {
+            print!("{}", docs(field.as_deref())?);
+            Ok(())
+        }
+        ConfigCommand::Import { path, section } => import(&path, section.as_deref()),
+    }
+}
+
+/// 将 `path` 处的配置合并到当前配置文件，显示差异并在用户确认后写入。
+fn import(path: &Path, section: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut imported = Config::load_file(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    imported
+        .validate()
+        .map_err(|e| format!("{} is not a valid config: {e}", path.display()))?;
+
+    let mut locked = Config::lock(&config_file_path()?)?;
+    let mut merged = (*locked).clone();
+    merged.merge_from(&imported, section)?;
+    merged
+        .validate()
+        .map_err(|e| format!("Merged config is not valid, nothing was written: {e}"))?;
+
+    let diffs = locked.sensitive_diff(&merged);
+    if diffs.is_empty() {
+        println!("No changes to import from {}", path.display());
+        return Ok(());
+    }
+    for diff in &diffs {
+        println!("{diff}");
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err("Refusing to import without confirmation: stdin is not a terminal".into());
+    }
+    print!("Apply {} change(s) to {}? [y/N] ", diffs.len(), locked.path().display());
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        println!("Import cancelled");
+        return Ok(());
+    }
+
+    *locked = merged;
+    locked.store()?;
+    println!("Imported {} setting(s) into {}", diffs.len(), locked.path().display());
+    Ok(())
+}
+
+/// 渲染所有配置项或 `field` 路径下配置项的说明。
+fn docs(field: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let documentation = Config::documentation();
+    let fields: Vec<&FieldDoc> = match field {
+        Some(path) => documentation.find(path),
+        None => documentation.fields.iter().collect(),
+    };
+    if fields.is_empty() {
+        return Err(format!("Unknown config field: {}", field.unwrap_or_default()).into());
+    }
+
+    let mut out = String::new();
+    for doc in fields {
+        let default = if doc.default_value.is_empty() { "none" } else { &doc.default_value };
+        out.push_str(&format!("{} ({}, default: {default})\n", doc.path, doc.type_name));
+        for line in doc.description.lines() {
+            out.push_str(&format!("    {line}\n").replace("    \n", "\n"));
+        }
+        if let Some(example) = &doc.example {
+            out.push_str(&format!("    Example: {example}\n"));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// 执行 `termichan config backup` 子命令。
+fn backup(command: BackupCommand, config: &Config) -> Result<(), Box<dyn Error>> {
+    let backup_dir = Config::default_backup_dir()?;
+    match command {
+        BackupCommand::List => {
+            let backups = Config::list_backups(&backup_dir)?;
+            if backups.is_empty() {
+                println!("No config backups in {}", backup_dir.display());
+            }
+            for backup in backups {
+                println!("{}  {}", backup.timestamp, backup.path.display());
+            }
+        }
+        BackupCommand::Restore { timestamp } => {
+            // 替换文件期间阻止其他 termichan 进程写入配置
+            let _lock = Config::lock(&config_file_path()?)?;
+            let path = config.restore_backup(&backup_dir, &timestamp)?;
+            println!("Restored {} from backup {timestamp}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// 打印当前生效的配置，以及 LLM 服务的健康状态。
+async fn show(config: &Config) -> Result<(), Box<dyn Error>> {
+    println!("{config}");
+
+    let health = match super::build_service(config) {
+        Ok(service) => service.health_check().await.map(|s| s.to_string()),
+        Err(e) => Ok(format!("unavailable ({e})")),
+    };
+    match health {
+        Ok(status) => println!("# LLM service: {status}"),
+        Err(e) => println!("# LLM service: unreachable ({e})"),
+    }
+    Ok(())
+}
+
+/// 生成列出所有 `TERMICHAN_*` 环境变量的 dotenv 格式模板，值为默认配置。
+fn env_template() -> String {
+    let defaults = Config::default();
+    let mut out = String::new();
+    for spec in ENV_VARS {
+        let value = spec.value(&defaults);
+        let value = if value.contains(['\n', '"', '#', ' ']) {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            value
+        };
+        out.push_str(&format!("# {}\n{}={}\n\n", spec.description, spec.name, value));
+    }
+    out
+}
@@ -0,0 +1,237 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+use termichan_config::{config_file_disabled, config_file_path, load_or_create_config, Config};
+use termichan_llm::{check_reachable, PromptContext};
+
+/// 连接代理时的超时时间。
+const PROXY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 一项检查的结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Warn => "warn",
+            Self::Fail => "fail",
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// 执行 `termichan doctor`：检查安装和配置，打印每项检查的结果和建议的后续步骤。
+///
+/// 在加载配置之前运行，配置文件无效时也能给出诊断。有检查失败时返回错误，退出码非 0。
+pub async fn run() -> Result<(), Box<dyn Error>> {
+    let (file_check, file_ok) = check_config_file();
+    let mut checks = vec![file_check];
+
+    // 配置文件不存在时不创建它，只从环境变量构建配置
+    let loaded = if file_ok {
+        load_or_create_config(None)
+    } else {
+        Config::from_env_only()
+    };
+    let mut config = match loaded {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(Check::new("Config values", Status::Fail, e.to_string()));
+            Config::default()
+        }
+    };
+    if checks.len() == 1 {
+        checks.push(match config.validate() {
+            Ok(()) => Check::new("Config values", Status::Pass, "valid"),
+            Err(e) => Check::new("Config values", Status::Fail, e.to_string()),
+        });
+    }
+
+    checks.push(check_api_key(&config));
+    checks.push(check_base_url(&config).await);
+    checks.push(check_proxy(&config));
+    checks.push(check_history_dir(&config));
+    checks.push(check_shell());
+    checks.push(check_color());
+
+    print!("{}", render(&checks));
+
+    let config_failed = checks
+        .iter()
+        .any(|c| c.status == Status::Fail && matches!(c.name, "Config file" | "Config values" | "API key"));
+    println!();
+    if config_failed {
+        println!("Run `termichan init` to create or repair the config file.");
+    }
+    println!("Run `termichan test` to check that the API key is accepted.");
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        return Err(format!("{failed} of {} checks failed", checks.len()).into());
+    }
+    Ok(())
+}
+
+/// 配置文件存在且是有效的 TOML，第二个值表示是否可以从文件加载配置。
+fn check_config_file() -> (Check, bool) {
+    const NAME: &str = "Config file";
+    if config_file_disabled() {
+        return (Check::new(NAME, Status::Warn, "skipped, TERMICHAN_NO_CONFIG_FILE is set"), false);
+    }
+    let path = match config_file_path() {
+        Ok(path) => path,
+        Err(e) => return (Check::new(NAME, Status::Fail, e.to_string()), false),
+    };
+    if !path.exists() {
+        return (Check::new(NAME, Status::Fail, format!("{} does not exist", path.display())), false);
+    }
+    match Config::load_file(&path) {
+        Ok(_) => (Check::new(NAME, Status::Pass, path.display().to_string()), true),
+        Err(e) => (Check::new(NAME, Status::Fail, format!("{}: {e}", path.display())), false),
+    }
+}
+
+/// 只检查是否设置了密钥，密钥是否有效由 `termichan test` 检查。
+fn check_api_key(config: &Config) -> Check {
+    const NAME: &str = "API key";
+    if config.llm.provider.eq_ignore_ascii_case("ollama") {
+        Check::new(NAME, Status::Pass, "not required for ollama")
+    } else if config.llm.api_key.as_deref().is_some_and(|key| !key.trim().is_empty()) {
+        Check::new(NAME, Status::Pass, "set")
+    } else {
+        Check::new(NAME, Status::Fail, "not set, set llm.api_key or TERMICHAN_LLM_API_KEY")
+    }
+}
+
+async fn check_base_url(config: &Config) -> Check {
+    const NAME: &str = "API endpoint";
+    let url = config.llm.base_url.as_deref().unwrap_or("default endpoint");
+    match check_reachable(&config.llm, &config.network).await {
+        Ok(status) => Check::new(NAME, Status::Pass, format!("{url} answered with HTTP {status}")),
+        Err(e) => Check::new(NAME, Status::Fail, format!("{url}: {e}")),
+    }
+}
+
+fn check_proxy(config: &Config) -> Check {
+    const NAME: &str = "Proxy";
+    let Some(proxy) = &config.network.proxy else {
+        return Check::new(NAME, Status::Pass, "not configured");
+    };
+    let Some(address) = proxy_address(proxy) else {
+        return Check::new(NAME, Status::Fail, format!("cannot parse {proxy}"));
+    };
+    let connected = address
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())
+        .and_then(|mut addrs| addrs.next().ok_or_else(|| "no address found".to_string()))
+        .and_then(|addr| TcpStream::connect_timeout(&addr, PROXY_TIMEOUT).map_err(|e| e.to_string()));
+    match connected {
+        Ok(_) => Check::new(NAME, Status::Pass, format!("{address} is reachable")),
+        Err(e) => Check::new(NAME, Status::Fail, format!("{address}: {e}")),
+    }
+}
+
+/// 代理 URL 中的 `主机:端口`，省略端口时按协议补上默认端口。
+fn proxy_address(proxy: &str) -> Option<String> {
+    let (scheme, rest) = proxy.split_once("://").unwrap_or(("http", proxy));
+    let authority = rest.split('/').next()?;
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    if authority.is_empty() {
+        return None;
+    }
+    if authority.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+        return Some(authority.to_string());
+    }
+    let port = match scheme.to_ascii_lowercase().as_str() {
+        "https" => 443,
+        s if s.starts_with("socks") => 1080,
+        _ => 80,
+    };
+    Some(format!("{authority}:{port}"))
+}
+
+/// 在历史文件所在目录中写入并删除一个临时文件。
+fn check_history_dir(config: &Config) -> Check {
+    const NAME: &str = "History directory";
+    if !config.history.enabled {
+        return Check::new(NAME, Status::Pass, "history is disabled");
+    }
+    let dir = config
+        .history
+        .file_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let probe = dir.join(".termichan-doctor");
+    let result = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&probe, b""))
+        .and_then(|()| fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::new(NAME, Status::Pass, format!("{} is writable", dir.display())),
+        Err(e) => Check::new(NAME, Status::Fail, format!("{}: {e}", dir.display())),
+    }
+}
+
+fn check_shell() -> Check {
+    const NAME: &str = "Shell";
+    let shell = PromptContext::detect().shell;
+    if shell == "unknown" {
+        Check::new(NAME, Status::Warn, "cannot detect the shell, set $SHELL")
+    } else {
+        Check::new(NAME, Status::Pass, shell)
+    }
+}
+
+fn check_color() -> Check {
+    const NAME: &str = "Terminal colors";
+    if !io::stdout().is_terminal() {
+        Check::new(NAME, Status::Warn, "standard output is not a terminal")
+    } else if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        Check::new(NAME, Status::Warn, "disabled by NO_COLOR")
+    } else if env::var("TERM").is_ok_and(|term| term == "dumb") {
+        Check::new(NAME, Status::Warn, "TERM=dumb does not support colors")
+    } else {
+        Check::new(NAME, Status::Pass, "supported")
+    }
+}
+
+fn render(checks: &[Check]) -> String {
+    let width = checks.iter().map(|c| c.name.len()).max().unwrap_or_default();
+    let mut out = format!("{:<width$}  STATUS  DETAILS\n", "CHECK");
+    for check in checks {
+        out.push_str(&format!("{:<width$}  {:<6}  {}\n", check.name, check.status.as_str(), check.detail));
+    }
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    out.push_str(&format!(
+        "\n{} passed, {} warnings, {} failed\n",
+        count(Status::Pass),
+        count(Status::Warn),
+        count(Status::Fail)
+    ));
+    out
+}
@@ -0,0 +1,511 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, Utc};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use termichan_config::{Config, UiConfig};
+use termichan_core::{
+    CommandCluster, CommandPattern, CrossSessionStats, HistoryEntry, HistoryManager, HistoryStats, ScoredEntry,
+    SecurityChecker, SessionSummary,
+};
+use termichan_ui::LineEditor;
+
+use crate::cli::HistoryCommand;
+
+/// `termichan history export` 的输出格式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HistoryExportFormat {
+    /// 所有记录组成的 JSON 数组。
+    #[default]
+    Json,
+    /// Prometheus 文本格式的统计指标。
+    Prometheus,
+}
+
+/// `history cluster` 为每组显示的代表性命令数。
+const REPRESENTATIVE_COMMANDS: usize = 3;
+/// 未配置 `llm.slow_query_warn_ms` 时 `history slow` 的默认阈值。
+const DEFAULT_SLOW_THRESHOLD_MS: u64 = 5000;
+
+/// `history list --from/--to` 的时间无法解析。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ParseError {}
+
+/// 执行 `termichan history` 子命令。
+pub async fn run(command: HistoryCommand, config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut manager = HistoryManager::load(&config.history)?;
+    match command {
+        HistoryCommand::Stats { all_time: true, .. } => {
+            print!("{}", render_all_time(&manager.cross_session_statistics()));
+        }
+        HistoryCommand::Stats { ab_test: true, .. } => {
+            print!("{}", render_variants(&manager.variant_statistics()));
+        }
+        HistoryCommand::Stats { provider, .. } => {
+            let stats = match provider {
+                Some(provider) => manager.provider_statistics(&provider),
+                None => manager.statistics(),
+            };
+            print!("{}", render_stats(&stats));
+            // 计数只需要分词器，但无法创建服务（例如没有 API 密钥）时不显示
+            match super::build_service(config) {
+                Ok(service) => println!(
+                    "Context injection budget: {} / {} tokens",
+                    format_thousands(service.count_history_tokens(&manager)),
+                    format_thousands(service.effective_context_window() as u64)
+                ),
+                Err(e) => log::debug!("Skipping context injection budget: {e}"),
+            }
+        }
+        HistoryCommand::List { from, to, provider } => {
+            let entries: Vec<_> = manager
+                .search_by_date_range(from.unwrap_or(DateTime::<Utc>::MIN_UTC), to.unwrap_or(DateTime::<Utc>::MAX_UTC))
+                .into_iter()
+                .filter(|e| provider.as_ref().is_none_or(|p| e.provider.eq_ignore_ascii_case(p)))
+                .collect();
+            print!("{}", render_list(&entries, &config.ui));
+        }
+        HistoryCommand::Slow { threshold_ms } => {
+            let threshold = threshold_ms
+                .or(config.llm.slow_query_warn_ms)
+                .unwrap_or(DEFAULT_SLOW_THRESHOLD_MS);
+            print!("{}", render_slow(&manager.slow_queries(threshold), threshold));
+        }
+        HistoryCommand::Patterns => print!("{}", render_patterns(&manager.analyze_patterns())),
+        HistoryCommand::Cluster { n } => print!("{}", render_clusters(&manager.cluster_commands(n))),
+        HistoryCommand::Import { asciinema } => {
+            let count = manager.import_from_asciinema(&asciinema)?;
+            manager.save()?;
+            println!("Imported {count} commands from {}.", asciinema.display());
+        }
+        HistoryCommand::Merge { path } => {
+            let report = manager.merge(&path)?;
+            println!(
+                "Merged {} entries from {} ({} duplicates skipped, {} with conflicting timestamps).",
+                report.added,
+                path.display(),
+                report.duplicates_skipped,
+                report.conflicts
+            );
+        }
+        HistoryCommand::Replay { id } => replay(&mut manager, id, config).await?,
+        HistoryCommand::Sessions => print!("{}", render_sessions(&manager.sessions(), &config.ui)),
+        HistoryCommand::Search { query, limit } => {
+            let embedding = super::build_service(config)?.embed(&query.join(" ")).await?;
+            print!("{}", render_search(&manager.semantic_search(&embedding, limit)));
+        }
+        HistoryCommand::Purge { older_than, dry_run } => {
+            let mut policy = config.history.retention_policy.clone();
+            if older_than.is_some() {
+                policy.max_age_days = older_than;
+            }
+            // 只修改内存中的记录，不保存即为预演
+            let count = manager.apply_retention_policy(&policy);
+            if dry_run {
+                println!("Would delete {count} history entries.");
+            } else {
+                manager.save()?;
+                println!("Deleted {count} history entries.");
+            }
+        }
+        HistoryCommand::Export { format, output } => {
+            let mut content = Vec::new();
+            match format {
+                HistoryExportFormat::Json => {
+                    serde_json::to_writer_pretty(&mut content, manager.entries())?;
+                    content.push(b'\n');
+                }
+                HistoryExportFormat::Prometheus => manager.export_prometheus_metrics(&mut content)?,
+            }
+            match output {
+                Some(path) => fs::write(path, content)?,
+                None => io::stdout().write_all(&content)?,
+            }
+        }
+        HistoryCommand::Anonymize { in_place, output } => {
+            let count = manager.anonymize()?;
+            if in_place {
+                manager.save()?;
+                println!("Anonymized {count} history entries in {}.", manager.path().display());
+                return Ok(());
+            }
+            let mut content = Vec::new();
+            for entry in manager.entries() {
+                serde_json::to_writer(&mut content, entry)?;
+                content.push(b'\n');
+            }
+            match output {
+                Some(path) => {
+                    fs::write(&path, content)?;
+                    println!("Anonymized {count} history entries into {}.", path.display());
+                }
+                None => io::stdout().write_all(&content)?,
+            }
+        }
+        HistoryCommand::ReplaySession { id } => {
+            // 重放会追加新记录，先取出编号
+            let ids: Vec<u64> = manager.session_entries(id).iter().map(|e| e.id).collect();
+            if ids.is_empty() {
+                return Err(format!("No history entries in session {id}").into());
+            }
+            for entry_id in ids {
+                replay(&mut manager, entry_id, config).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 将历史命令放入编辑缓冲区供用户修改，然后按确认策略执行，并记录为新的历史条目。
+async fn replay(manager: &mut HistoryManager, id: u64, config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut entry = manager
+        .replay(id)?
+        .ok_or_else(|| format!("No history entry with id {id}"))?;
+
+    println!("# {}", entry.query);
+    let Some(command) = LineEditor::edit("> ", &entry.generated_command)? else {
+        return Ok(());
+    };
+    if command.trim().is_empty() {
+        return Ok(());
+    }
+    entry.generated_command = command.trim().to_string();
+
+    let checker = SecurityChecker::new(&config.security)?;
+    let service = super::lazy_service(config);
+    let status =
+        super::confirm_and_execute(config, &checker, &service, &entry.query, &mut entry.generated_command).await?;
+    entry.executed = status.is_some();
+    entry.exit_code = status.and_then(|s| s.code());
+    if config.history.enabled {
+        manager.add(entry);
+        manager.save()?;
+    }
+    Ok(())
+}
+
+/// 解析 `--from`：只有日期时取当天本地时间的开始。
+pub fn parse_from_date(s: &str) -> Result<DateTime<Utc>, ParseError> {
+    parse_date(s, NaiveTime::MIN)
+}
+
+/// 解析 `--to`：只有日期时取当天本地时间的结束，使范围包括这一天。
+pub fn parse_to_date(s: &str) -> Result<DateTime<Utc>, ParseError> {
+    let end_of_day = NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).expect("end of day is a valid time");
+    parse_date(s, end_of_day)
+}
+
+/// 依次尝试 RFC 3339 时间、`%Y-%m-%d` 日期（时间取 `time_of_day`）和 `parse_relative` 的相对时间。
+fn parse_date(s: &str, time_of_day: NaiveTime) -> Result<DateTime<Utc>, ParseError> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.to_utc());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date
+            .and_time(time_of_day)
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|time| time.to_utc())
+            .ok_or_else(|| ParseError(format!("`{s}` does not exist in the local time zone")));
+    }
+    let ago = parse_relative(s)?;
+    Utc::now()
+        .checked_sub_signed(ago)
+        .ok_or_else(|| ParseError(format!("invalid date `{s}`: too far in the past")))
+}
+
+/// 解析 `7d ago` 这样的相对时间，返回距今的时长。
+///
+/// 单位为 `s`、`m`、`h`、`d`、`w`（秒、分钟、小时、天、周），`ago` 可以省略。
+pub fn parse_relative(s: &str) -> Result<TimeDelta, ParseError> {
+    let invalid = || {
+        ParseError(format!(
+            "invalid date `{s}`: expected RFC 3339, YYYY-MM-DD or a duration like `7d ago`"
+        ))
+    };
+    let amount = s.trim().strip_suffix("ago").unwrap_or(s).trim();
+    let unit_start = amount.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (number, unit) = amount.split_at(unit_start);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    let delta = match unit.trim() {
+        "s" => TimeDelta::try_seconds(number),
+        "m" => TimeDelta::try_minutes(number),
+        "h" => TimeDelta::try_hours(number),
+        "d" => TimeDelta::try_days(number),
+        "w" => TimeDelta::try_weeks(number),
+        _ => None,
+    };
+    delta.ok_or_else(invalid)
+}
+
+fn render_list(entries: &[&HistoryEntry], ui: &UiConfig) -> String {
+    if entries.is_empty() {
+        return "No matching history entries.\n".to_string();
+    }
+    let rows: Vec<_> = entries.iter().map(|e| (e, ui.format_timestamp(&e.timestamp))).collect();
+    let width = rows
+        .iter()
+        .map(|(_, time)| time.chars().count())
+        .chain(std::iter::once("TIME".len()))
+        .max()
+        .unwrap_or_default();
+    let mut out = format!("{:>6}  {:<width$}  {:<10}  COMMAND\n", "ID", "TIME", "PROVIDER");
+    for (entry, time) in rows {
+        out.push_str(&format!(
+            "{:>6}  {:<width$}  {:<10}  {}\n",
+            entry.id, time, entry.provider, entry.generated_command
+        ));
+    }
+    out
+}
+
+fn render_slow(entries: &[&HistoryEntry], threshold_ms: u64) -> String {
+    if entries.is_empty() {
+        return format!("No queries took longer than {threshold_ms} ms.\n");
+    }
+    let mut out = format!("{:>6}  {:>9}  {:<20}  QUERY\n", "ID", "LATENCY", "MODEL");
+    for entry in entries {
+        out.push_str(&format!(
+            "{:>6}  {:>6} ms  {:<20}  {}\n",
+            entry.id,
+            entry.latency_ms.unwrap_or_default(),
+            entry.model,
+            entry.query
+        ));
+    }
+    out
+}
+
+fn render_search(results: &[ScoredEntry]) -> String {
+    if results.is_empty() {
+        return "No history entries with embeddings. Set llm.enable_embeddings = true to record them.\n".to_string();
+    }
+    let mut out = format!("{:>6}  {:>5}  {:<40}  COMMAND\n", "ID", "SCORE", "QUERY");
+    for result in results {
+        out.push_str(&format!(
+            "{:>6}  {:>5.2}  {:<40}  {}\n",
+            result.entry.id, result.score, result.entry.query, result.entry.generated_command
+        ));
+    }
+    out
+}
+
+fn render_sessions(sessions: &[SessionSummary], ui: &UiConfig) -> String {
+    if sessions.is_empty() {
+        return "No sessions recorded yet.\n".to_string();
+    }
+    let rows: Vec<_> = sessions
+        .iter()
+        .map(|s| (s, ui.format_timestamp(&s.first), ui.format_timestamp(&s.last)))
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(_, first, _)| first.chars().count())
+        .chain(std::iter::once("FIRST".len()))
+        .max()
+        .unwrap_or_default();
+    let mut out = format!("{:<36}  {:>7}  {:<width$}  LAST\n", "SESSION", "ENTRIES", "FIRST");
+    for (session, first, last) in rows {
+        out.push_str(&format!(
+            "{:<36}  {:>7}  {:<width$}  {}\n",
+            session.session_id, session.entry_count, first, last
+        ));
+    }
+    out
+}
+
+fn render_patterns(patterns: &[CommandPattern]) -> String {
+    if patterns.is_empty() {
+        return "No recurring command sequences found.\n".to_string();
+    }
+    let mut out = String::new();
+    for pattern in patterns {
+        out.push_str(&format!("{}x  {}\n    {}\n", pattern.frequency, pattern.template, pattern.suggested_alias));
+    }
+    out.push_str("\nAdd the suggested aliases to your shell's rc file to use them.\n");
+    out
+}
+
+fn render_clusters(clusters: &[CommandCluster]) -> String {
+    if clusters.is_empty() {
+        return "No history entries to cluster.\n".to_string();
+    }
+    let mut out = String::new();
+    for cluster in clusters {
+        out.push_str(&format!("{} ({} commands)\n", cluster.centroid_description, cluster.entries.len()));
+        let mut shown = Vec::new();
+        for entry in &cluster.entries {
+            if shown.len() == REPRESENTATIVE_COMMANDS {
+                break;
+            }
+            if !shown.contains(&&entry.generated_command) {
+                shown.push(&entry.generated_command);
+                out.push_str(&format!("    {}\n", entry.generated_command));
+            }
+        }
+    }
+    out
+}
+
+fn render_stats(stats: &HistoryStats) -> String {
+    let mut out = format!(
+        "Total entries: {}\nExecuted:      {}\nSuccess rate:  {}\nLatency:       avg {}, p95 {}, p99 {}\n",
+        stats.total_entries,
+        stats.executed_count,
+        format_rate(stats.success_rate),
+        stats.avg_latency_ms.map_or_else(|| "-".to_string(), |ms| format!("{ms:.0} ms")),
+        format_ms(stats.p95_latency_ms),
+        format_ms(stats.p99_latency_ms),
+    );
+
+    if stats.provider_breakdown.is_empty() {
+        return out;
+    }
+
+    let mut providers: Vec<_> = stats.provider_breakdown.iter().collect();
+    providers.sort_by(|a, b| b.1.request_count.cmp(&a.1.request_count).then(a.0.cmp(b.0)));
+    let width = providers
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(std::iter::once("PROVIDER".len()))
+        .max()
+        .unwrap_or_default();
+
+    out.push_str(&format!(
+        "\n{:<width$}  {:>8}  {:>11}  {:>7}\n",
+        "PROVIDER", "REQUESTS", "AVG LATENCY", "SUCCESS"
+    ));
+    for (name, provider) in providers {
+        let latency = provider
+            .avg_latency_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{ms:.0} ms"));
+        out.push_str(&format!(
+            "{:<width$}  {:>8}  {:>11}  {:>7}\n",
+            name,
+            provider.request_count,
+            latency,
+            format_rate(provider.success_rate)
+        ));
+    }
+    out
+}
+
+fn render_variants(variants: &BTreeMap<String, HistoryStats>) -> String {
+    if variants.is_empty() {
+        return "No A/B test entries yet. Set [prompt.ab_test] in the config file to start one.\n".to_string();
+    }
+    let mut out = format!(
+        "{:<7}  {:>8}  {:>8}  {:>11}  {:>7}\n",
+        "VARIANT", "REQUESTS", "EXECUTED", "AVG LATENCY", "SUCCESS"
+    );
+    for (variant, stats) in variants {
+        let latency = stats
+            .avg_latency_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{ms:.0} ms"));
+        out.push_str(&format!(
+            "{:<7}  {:>8}  {:>8}  {:>11}  {:>7}\n",
+            variant,
+            stats.total_entries,
+            stats.executed_count,
+            latency,
+            format_rate(stats.success_rate)
+        ));
+    }
+    out
+}
+
+fn render_all_time(stats: &CrossSessionStats) -> String {
+    format!(
+        "Total commands:      {}\nSuccess rate:        {}\nMost productive day: {}\nLongest streak:      {} day(s)\nLast 30 days:        {:.1} commands/day\nDiversity:           {}\n",
+        stats.total_commands,
+        format_rate(stats.success_rate),
+        stats
+            .most_productive_weekday
+            .map_or_else(|| "-".to_string(), |day| day.to_string()),
+        stats.longest_daily_streak,
+        stats.avg_commands_per_day_30d,
+        stats.vocabulary_diversity.map_or_else(|| "-".to_string(), |d| format!("{d:.2}")),
+    )
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    ms.map_or_else(|| "-".to_string(), |ms| format!("{ms} ms"))
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "-".to_string(), |r| format!("{:.1}%", r * 100.0))
+}
+
+/// 每三位用逗号分隔，例如 `8,192`。
+fn format_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_times_use_each_unit() {
+        assert_eq!(parse_relative("30s ago"), Ok(TimeDelta::seconds(30)));
+        assert_eq!(parse_relative("5m ago"), Ok(TimeDelta::minutes(5)));
+        assert_eq!(parse_relative("2h ago"), Ok(TimeDelta::hours(2)));
+        assert_eq!(parse_relative(" 7d  ago "), Ok(TimeDelta::days(7)));
+        assert_eq!(parse_relative("1w ago"), Ok(TimeDelta::weeks(1)));
+        // `ago` 可以省略
+        assert_eq!(parse_relative("7d"), Ok(TimeDelta::days(7)));
+    }
+
+    #[test]
+    fn invalid_relative_times_are_rejected() {
+        for input in ["", "ago", "7", "7 ago", "d ago", "7y ago", "-7d ago", "seven days ago", "99999999999999999999d"] {
+            assert!(parse_relative(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn dates_cover_the_whole_day() {
+        let start = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap().and_time(NaiveTime::MIN);
+        let start = start.and_local_timezone(Local).earliest().unwrap().to_utc();
+        assert_eq!(parse_from_date("2024-03-05"), Ok(start));
+        let end = parse_to_date("2024-03-05").unwrap();
+        assert_eq!(end - start, TimeDelta::days(1) - TimeDelta::nanoseconds(1));
+        assert_eq!(
+            parse_from_date("2024-03-05T10:00:00+08:00").map(|t| t.to_rfc3339()),
+            Ok("2024-03-05T02:00:00+00:00".to_string())
+        );
+        assert!(parse_from_date("2024-13-05").is_err());
+    }
+
+    #[test]
+    fn relative_dates_are_measured_from_now() {
+        let from = parse_from_date("1h ago").unwrap();
+        let expected = Utc::now() - TimeDelta::hours(1);
+        assert!((expected - from).abs() < TimeDelta::seconds(5), "{from}");
+    }
+
+    #[test]
+    fn relative_dates_before_the_earliest_time_are_rejected() {
+        assert!(parse_from_date("99999999d ago").is_err());
+        assert!(parse_to_date("9999999999999w ago").is_err());
+    }
+}
@@ -0,0 +1,331 @@
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal, Write};
+use termichan_config::{config_file_path, Config, ConfirmationMode};
+use termichan_llm::LlmService;
+use termichan_ui::LineEditor;
+
+/// 向导提供的服务商：名称、默认模型和默认基础 URL。
+const PROVIDERS: &[(&str, &str, Option<&str>)] = &[
+    ("openai", "gpt-4o", None),
+    ("anthropic", "claude-3-5-sonnet-latest", None),
+    ("ollama", "llama3.1", Some("http://localhost:11434/v1")),
+];
+/// Ollama 不校验 API 密钥，但 OpenAI 兼容接口的客户端要求设置一个值。
+const OLLAMA_API_KEY: &str = "ollama";
+/// 设置了其中任一变量时视为运行在 CI 环境中。
+const CI_ENV_VARS: &[&str] = &["CI", "GITHUB_ACTIONS", "GITLAB_CI", "BUILDKITE", "JENKINS_URL"];
+/// 向导的步骤，按顺序执行，输入 `b` 返回上一步。
+const STEPS: &[Step] = &[
+    Step::Provider,
+    Step::ApiKey,
+    Step::Connectivity,
+    Step::Model,
+    Step::Confirmation,
+    Step::History,
+    Step::Write,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Provider,
+    ApiKey,
+    Connectivity,
+    Model,
+    Confirmation,
+    History,
+    Write,
+}
+
+/// 一个步骤结束后向导的去向。
+enum Outcome {
+    /// 进入下一步。
+    Next,
+    /// 返回上一步。
+    Back,
+    /// 输入无效，重新执行当前步骤。
+    Retry,
+    /// 当前步骤不适用，沿原来的方向继续。
+    Skip,
+    /// 配置已写入。
+    Done,
+}
+
+/// 用户对一个问题的回答。
+enum Answer {
+    Value(String),
+    Back,
+}
+
+/// 向导在步骤之间传递的状态。
+struct Wizard {
+    config: Config,
+    /// 连通性检查时获取的模型列表，检查被跳过时为空。
+    models: Vec<String>,
+}
+
+/// 执行 `termichan init`：交互式地创建配置文件。
+///
+/// 已有配置文件时，除非指定 `overwrite`，否则先询问是否覆盖（非交互模式下直接报错）。
+/// 没有终端或检测到 CI 环境时按 `non_interactive` 处理，直接写入默认配置。
+pub async fn run(overwrite: bool, non_interactive: bool) -> Result<(), Box<dyn Error>> {
+    let path = config_file_path()?;
+    let interactive = !non_interactive && io::stdin().is_terminal() && io::stderr().is_terminal() && !in_ci();
+
+    if path.exists() && !overwrite {
+        if !interactive {
+            return Err(format!(
+                "Config file {} already exists. Run `termichan init --overwrite` to replace it.",
+                path.display()
+            )
+            .into());
+        }
+        eprintln!("Warning: config file {} already exists.", path.display());
+        match ask("Overwrite it? [y/N]")? {
+            Answer::Value(answer) if is_yes(&answer, false) => {}
+            _ => {
+                eprintln!("Setup cancelled, the existing config file was not changed.");
+                return Ok(());
+            }
+        }
+    }
+
+    if !interactive {
+        Config::default().lock_replacing(&path)?.store()?;
+        println!("Wrote default config to {}.", path.display());
+        println!("Edit the file or set TERMICHAN_* environment variables (e.g. TERMICHAN_LLM_API_KEY) to finish setup.");
+        return Ok(());
+    }
+
+    eprintln!("Welcome to termichan! Answer a few questions to create {}.", path.display());
+    eprintln!("Press Enter to accept the value in brackets, or type `b` to go back.\n");
+    let mut wizard = Wizard {
+        config: Config::default(),
+        models: Vec::new(),
+    };
+    let mut index = 0;
+    let mut forward = true;
+    loop {
+        let outcome = match STEPS[index] {
+            Step::Provider => wizard.provider()?,
+            Step::ApiKey => wizard.api_key()?,
+            Step::Connectivity => wizard.connectivity().await?,
+            Step::Model => wizard.model()?,
+            Step::Confirmation => wizard.confirmation()?,
+            Step::History => wizard.history()?,
+            Step::Write => wizard.write()?,
+        };
+        match outcome {
+            Outcome::Next => forward = true,
+            Outcome::Back => forward = false,
+            Outcome::Retry => continue,
+            Outcome::Skip => {}
+            Outcome::Done => return Ok(()),
+        }
+        index = if forward {
+            (index + 1).min(STEPS.len() - 1)
+        } else if index == 0 {
+            // 第一步没有上一步，重新开始
+            forward = true;
+            0
+        } else {
+            index - 1
+        };
+    }
+}
+
+impl Wizard {
+    fn provider(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        eprintln!("Which LLM provider do you use?");
+        for (i, (name, _, _)) in PROVIDERS.iter().enumerate() {
+            eprintln!("  {}) {name}", i + 1);
+        }
+        let Answer::Value(answer) = ask(&format!("Provider [{}]", self.config.llm.provider))? else {
+            return Ok(Outcome::Back);
+        };
+        let answer = if answer.is_empty() { self.config.llm.provider.clone() } else { answer };
+        let Some(&(name, model, base_url)) = choose(&answer, PROVIDERS.iter().map(|p| p.0))
+            .map(|index| &PROVIDERS[index])
+        else {
+            eprintln!("Please enter a number between 1 and {} or a provider name.", PROVIDERS.len());
+            return Ok(Outcome::Retry);
+        };
+        if name != self.config.llm.provider {
+            self.config.llm.provider = name.to_string();
+            self.config.llm.model = model.to_string();
+            self.config.llm.base_url = base_url.map(str::to_string);
+            self.config.llm.api_key = None;
+        }
+        Ok(Outcome::Next)
+    }
+
+    fn api_key(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        if self.config.llm.provider == "ollama" {
+            self.config.llm.api_key = Some(OLLAMA_API_KEY.to_string());
+            return Ok(Outcome::Skip);
+        }
+        let key = LineEditor::read_secret(&format!("{} API key: ", self.config.llm.provider))?
+            .ok_or("Setup cancelled")?;
+        let key = key.trim();
+        if key == "b" {
+            return Ok(Outcome::Back);
+        }
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            eprintln!("The API key must not be empty or contain spaces.");
+            return Ok(Outcome::Retry);
+        }
+        self.config.llm.api_key = Some(key.to_string());
+        Ok(Outcome::Next)
+    }
+
+    async fn connectivity(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        eprintln!("Testing the connection to {}...", self.config.llm.provider);
+        let result = match LlmService::new(self.config.llm.clone()) {
+            Ok(service) => match service.validate_api_key().await {
+                Ok(()) => service.list_models().await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(models) => {
+                eprintln!("Connected, {} models available.\n", models.len());
+                self.models = models;
+                Ok(Outcome::Next)
+            }
+            Err(e) => {
+                eprintln!("Connection failed: {e}");
+                match ask("Press Enter to retry, `s` to skip the check, or `b` to change the API key")? {
+                    Answer::Back => Ok(Outcome::Back),
+                    Answer::Value(answer) if answer.eq_ignore_ascii_case("s") => {
+                        self.models.clear();
+                        Ok(Outcome::Next)
+                    }
+                    Answer::Value(_) => Ok(Outcome::Retry),
+                }
+            }
+        }
+    }
+
+    fn model(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        if self.models.is_empty() {
+            eprintln!("Which model should termichan use?");
+        } else {
+            eprintln!("Which model should termichan use? Available models:");
+            for (i, model) in self.models.iter().enumerate() {
+                eprintln!("  {}) {model}", i + 1);
+            }
+        }
+        let Answer::Value(answer) = ask(&format!("Model [{}]", self.config.llm.model))? else {
+            return Ok(Outcome::Back);
+        };
+        if answer.is_empty() {
+            return Ok(Outcome::Next);
+        }
+        let model = if self.models.is_empty() {
+            Some(answer)
+        } else {
+            choose(&answer, self.models.iter().map(String::as_str)).map(|index| self.models[index].clone())
+        };
+        let Some(model) = model else {
+            eprintln!("Please enter a number between 1 and {} or a model name from the list.", self.models.len());
+            return Ok(Outcome::Retry);
+        };
+        self.config.llm.model = model;
+        Ok(Outcome::Next)
+    }
+
+    fn confirmation(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        eprintln!("When should termichan ask before running a generated command?");
+        eprintln!("  1) always     ask every time (recommended)");
+        eprintln!("  2) never      run commands without asking");
+        eprintln!("  3) dangerous  ask only for commands in security.dangerous_commands");
+        eprintln!("  4) tiered     decide by the command's impact, see security.tiered_thresholds");
+        let current = format!("{:?}", self.config.security.confirmation_mode).to_lowercase();
+        let Answer::Value(answer) = ask(&format!("Confirmation mode [{current}]"))? else {
+            return Ok(Outcome::Back);
+        };
+        let answer = if answer.is_empty() { current } else { answer };
+        let mode = match answer.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| ConfirmationMode::ALL.get(i)).cloned(),
+            Err(_) => answer.parse::<ConfirmationMode>().ok(),
+        };
+        let Some(mode) = mode else {
+            eprintln!("Please enter a number between 1 and 4 or one of: always, never, dangerous, tiered.");
+            return Ok(Outcome::Retry);
+        };
+        self.config.security.confirmation_mode = mode;
+        Ok(Outcome::Next)
+    }
+
+    fn history(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        let default = self.config.history.enabled;
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        let Answer::Value(answer) = ask(&format!("Keep a history of generated commands? {hint}"))? else {
+            return Ok(Outcome::Back);
+        };
+        if !answer.is_empty() && !is_yes(&answer, false) && !answer.eq_ignore_ascii_case("n") {
+            eprintln!("Please answer y or n.");
+            return Ok(Outcome::Retry);
+        }
+        self.config.history.enabled = is_yes(&answer, default);
+        Ok(Outcome::Next)
+    }
+
+    fn write(&mut self) -> Result<Outcome, Box<dyn Error>> {
+        let llm = &self.config.llm;
+        eprintln!("\nProvider:          {}", llm.provider);
+        eprintln!("Model:             {}", llm.model);
+        eprintln!("Confirmation mode: {:?}", self.config.security.confirmation_mode);
+        eprintln!("History:           {}", if self.config.history.enabled { "enabled" } else { "disabled" });
+        let Answer::Value(answer) = ask("Write this configuration? [Y/n]")? else {
+            return Ok(Outcome::Back);
+        };
+        if !is_yes(&answer, true) {
+            // 不写入时返回上一步，用户可以继续修改或按 Ctrl-D 退出
+            return Ok(Outcome::Back);
+        }
+        let locked = self.config.clone().lock_replacing(&config_file_path()?)?;
+        locked.store()?;
+        eprintln!("Wrote {}. Try it out: termichan list the largest files here", locked.path().display());
+        Ok(Outcome::Done)
+    }
+}
+
+/// 在标准错误显示 `question` 并读取一行回答；输入结束（Ctrl-D）时取消向导。
+fn ask(question: &str) -> Result<Answer, Box<dyn Error>> {
+    eprint!("{question}: ");
+    io::stderr().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Err("Setup cancelled".into());
+    }
+    let line = line.trim();
+    if line.eq_ignore_ascii_case("b") {
+        return Ok(Answer::Back);
+    }
+    Ok(Answer::Value(line.to_string()))
+}
+
+/// 按从 1 开始的编号或名称（不区分大小写）在 `options` 中选择，返回下标。
+fn choose<'a>(answer: &str, options: impl Iterator<Item = &'a str>) -> Option<usize> {
+    let options: Vec<&str> = options.collect();
+    match answer.parse::<usize>() {
+        Ok(n) => n.checked_sub(1).filter(|&i| i < options.len()),
+        Err(_) => options.iter().position(|o| o.eq_ignore_ascii_case(answer)),
+    }
+}
+
+/// `y`/`yes` 为真，`n`/`no` 为假，空回答使用 `default`。
+fn is_yes(answer: &str, default: bool) -> bool {
+    match answer.to_ascii_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    }
+}
+
+/// 是否运行在常见的 CI 环境中。
+fn in_ci() -> bool {
+    CI_ENV_VARS.iter().any(|name| env::var_os(name).is_some_and(|v| !v.is_empty()))
+}
@@ -0,0 +1,71 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use termichan_config::HistoryConfig;
+use termichan_core::HistoryManager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 读取请求的最大字节数，只需要请求行。
+const MAX_REQUEST_BYTES: usize = 8192;
+/// Prometheus 文本格式的内容类型。
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 在 `127.0.0.1:port` 上监听 `/metrics` 请求。
+pub async fn bind(port: u16) -> io::Result<TcpListener> {
+    TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await
+}
+
+/// 逐个处理连接，每次请求重新读取历史文件，见 `HistoryManager::export_prometheus_metrics`。
+///
+/// 单个连接出错只记录日志，不影响之后的请求。
+pub async fn serve(listener: TcpListener, history: HistoryConfig) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                log::warn!("Failed to accept metrics connection: {e}");
+                continue;
+            }
+        };
+        if let Err(e) = handle(stream, &history).await {
+            log::debug!("Metrics connection failed: {e}");
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, history: &HistoryConfig) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    let (status, body) = match (method, path) {
+        ("GET", "/metrics") => match render(history) {
+            Ok(body) => ("200 OK", body),
+            Err(e) => ("500 Internal Server Error", format!("{e}\n")),
+        },
+        ("GET", _) => ("404 Not Found", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method not allowed\n".to_string()),
+    };
+    let header = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn render(history: &HistoryConfig) -> Result<String, termichan_core::HistoryError> {
+    let mut out = Vec::new();
+    HistoryManager::load(history)?.export_prometheus_metrics(&mut out)?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
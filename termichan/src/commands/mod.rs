@@ -0,0 +1,438 @@
+pub mod alias;
+pub mod bugreport;
+pub mod cache;
+pub mod cheatsheet;
+pub mod compare;
+pub mod config;
+pub mod doctor;
+pub mod history;
+pub mod init;
+#[cfg(unix)]
+pub mod metrics;
+pub mod persona;
+pub mod security;
+
+use std::error::Error;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termichan_config::{Config, ConfirmationMode, SecurityConfig, TieredAction, UiConfig};
+use termichan_core::{
+    AuditEvent, AuditEventType, AuditLog, CommandClassifier, CommandResponse, HistoryManager, ResponseParser,
+    SecurityChecker,
+};
+use termichan_executor::{CommandExecutor, ExecError, ExecLimits};
+use termichan_llm::{Cache, LazyLlmService, LlmError, LlmService, LlmServiceBuilder, RequestOptions};
+use termichan_server::TermichanService;
+use termichan_ui::{
+    copy_to_clipboard, rate_limit_countdown, AsyncSpinner, ConfirmationChoice, ConfirmationPrompt, LineEditor, Pager,
+    Renderer,
+};
+
+use crate::cli::Command;
+
+/// 执行子命令。
+///
+/// `config_file` 是 `config` 读取的配置文件，只从环境变量构建配置时为 `None`。
+pub async fn dispatch(command: Command, config: &Config, config_file: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Cache(command) => cache::run(command, config).await,
+        Command::Compare(command) => compare::run(command),
+        Command::Config(command) => config::run(command, config).await,
+        Command::Doctor => doctor::run().await,
+        Command::Init { overwrite, non_interactive } => init::run(overwrite, non_interactive).await,
+        Command::Test { verbose } => test(config, verbose).await,
+        Command::History(command) => history::run(command, config).await,
+        Command::Security(command) => security::run(command, config, config_file).await,
+        Command::Persona(command) => persona::run(command, config),
+        Command::Benchmark {
+            iterations,
+            prompt,
+            timeout_secs,
+        } => benchmark(config, iterations, &prompt, timeout_secs).await,
+        Command::Server { grpc: _, port } => server(config, port).await,
+        Command::Fix { command } => fix(config, &command.join(" ")).await,
+        Command::Alias { shell, command } => alias::run(config, &command.join(" "), shell).await,
+        Command::Cheatsheet { format } => {
+            print!("{}", cheatsheet::render(format));
+            Ok(())
+        }
+        Command::Bugreport => {
+            print!("{}", bugreport::render(config));
+            Ok(())
+        }
+        Command::Gc { dry_run } => gc(config, dry_run),
+        #[cfg(unix)]
+        Command::Daemon { socket, metrics_port } => daemon(config, &socket, metrics_port, config_file).await,
+    }
+}
+
+/// 根据配置创建 LLM 服务，遇到速率限制时显示倒计时。
+pub fn build_service(config: &Config) -> Result<LlmService, Box<dyn Error>> {
+    Ok(service_builder(config).build()?)
+}
+
+/// 与 `build_service` 相同，但推迟到第一次发送请求时才创建服务。
+pub fn lazy_service(config: &Config) -> LazyLlmService {
+    let config = config.clone();
+    LazyLlmService::new(move || service_builder(&config).build())
+}
+
+fn service_builder(config: &Config) -> LlmServiceBuilder {
+    let builder = LlmService::builder()
+        .with_config(config.llm.clone())
+        .with_network(config.network.clone())
+        .with_rate_limit_wait(Arc::new(|wait| -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            Box::pin(rate_limit_countdown(wait))
+        }));
+    // 缓存文件损坏时不使用缓存，而不是让查询失败
+    match config.llm.cache_file.as_ref().map(Cache::load) {
+        Some(Ok(cache)) => builder.with_cache(cache.with_ttl(Duration::from_secs(config.llm.cache_ttl_secs))),
+        Some(Err(e)) => {
+            log::warn!("Ignoring unreadable response cache: {e}");
+            builder
+        }
+        None => builder,
+    }
+}
+
+/// 根据 `SecurityConfig` 的确认策略决定是否执行命令，返回执行后的退出状态。
+///
+/// 用户在确认提示中编辑命令或让 `service` 改写命令时，`command` 会被更新为修改后的内容，
+/// 并重新按策略判断。危险命令的表达式取自 `checker`。需要确认但标准输入不是终端时不执行，只显示命令。
+/// `query` 只用于审计日志。
+pub async fn confirm_and_execute(
+    config: &Config,
+    checker: &SecurityChecker,
+    service: &LazyLlmService,
+    query: &str,
+    command: &mut String,
+) -> Result<Option<ExitStatus>, Box<dyn Error>> {
+    loop {
+        let trimmed = command.trim();
+        if trimmed.is_empty() {
+            return Ok(None);
+        }
+        if let Err(e) = CommandClassifier::check_limits(trimmed, &config.security) {
+            audit(config, AuditEventType::PromptInjectionDetected, query, trimmed);
+            eprintln!("Warning: {e}");
+            return Ok(None);
+        }
+        if CommandClassifier::is_dangerous(trimmed, &config.security, checker) {
+            audit(config, AuditEventType::DangerousDetected, query, trimmed);
+        }
+        if let Some(e) = missing_binary(trimmed) {
+            eprintln!("Warning: {e}");
+        }
+        let placeholders = unfilled_placeholders(trimmed, &config.security);
+        if !placeholders.is_empty() {
+            let message = format!("Command contains unfilled placeholders: {}", placeholders.join(", "));
+            // 用户明确关闭了确认，只记录警告，由调用方在历史记录中注明
+            if config.security.confirmation_mode == ConfirmationMode::Never {
+                log::warn!("{message}");
+            } else {
+                eprintln!("Warning: {message}");
+                if !io::stdin().is_terminal() || !ask_edit(&config.ui)? {
+                    return Ok(None);
+                }
+                match LineEditor::edit("> ", trimmed)? {
+                    Some(edited) => *command = edited,
+                    None => return Ok(None),
+                }
+                continue;
+            }
+        }
+        for check in CommandExecutor::check_prerequisites(trimmed) {
+            eprintln!("Warning: {}: {}", check.name, check.help_text);
+        }
+
+        match CommandClassifier::action(trimmed, &config.security, checker) {
+            TieredAction::Reject => {
+                audit(config, AuditEventType::WhitelistViolation, query, trimmed);
+                let impact = CommandClassifier::impact(trimmed);
+                eprintln!("Not executing: {} commands are rejected by the confirmation policy.", impact.as_str());
+                return Ok(None);
+            }
+            TieredAction::AutoExecute => {
+                audit(config, AuditEventType::CommandConfirmed, query, trimmed);
+                return Ok(Some(CommandExecutor::execute_with_limits(trimmed, &exec_limits(config))?));
+            }
+            TieredAction::Confirm if !io::stdin().is_terminal() => return Ok(None),
+            TieredAction::Confirm => {}
+        }
+
+        match ConfirmationPrompt::ask(&config.ui)? {
+            ConfirmationChoice::Confirm => {
+                audit(config, AuditEventType::CommandConfirmed, query, trimmed);
+                return Ok(Some(CommandExecutor::execute_with_limits(trimmed, &exec_limits(config))?));
+            }
+            ConfirmationChoice::Reject => {
+                audit(config, AuditEventType::CommandRejected, query, trimmed);
+                return Ok(None);
+            }
+            ConfirmationChoice::Edit => {
+                if let Some(edited) = LineEditor::edit("> ", trimmed)? {
+                    *command = edited;
+                }
+            }
+            ConfirmationChoice::Rewrite => {
+                if let Some(rewritten) = rewrite(config, service, trimmed).await? {
+                    eprintln!("{rewritten}");
+                    *command = rewritten;
+                }
+            }
+            ConfirmationChoice::DryRun => eprintln!("Would run: {}", CommandExecutor::dry_run(trimmed)),
+            ConfirmationChoice::Copy => {
+                copy_to_clipboard(trimmed)?;
+                eprintln!("Copied to clipboard.");
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// 启用 `security.placeholder_detection` 时 `command` 中未替换的占位符。
+pub fn unfilled_placeholders(command: &str, security: &SecurityConfig) -> Vec<String> {
+    if !security.placeholder_detection {
+        return Vec::new();
+    }
+    ResponseParser::placeholders(command)
+}
+
+/// 命令含有占位符时只提供编辑和放弃两个选择，按键取自 `ui.keybindings`；选择编辑时返回 `true`。
+fn ask_edit(ui: &UiConfig) -> io::Result<bool> {
+    let edit = ui.keybinding("edit").unwrap_or_default();
+    let reject = ui.keybinding("reject").unwrap_or_default();
+    eprint!("[{edit}] edit, [{reject}] reject: ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case(edit.trim()))
+}
+
+/// 询问要做的修改并请 LLM 改写 `command`，用户取消或改写失败时返回 `None`。
+async fn rewrite(
+    config: &Config,
+    service: &LazyLlmService,
+    command: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(instruction) = LineEditor::edit("Modification: ", "")? else {
+        return Ok(None);
+    };
+    if instruction.trim().is_empty() {
+        return Ok(None);
+    }
+    let result = {
+        let _spinner = AsyncSpinner::new(config.ui.spinner_style, "Rewriting command...").start();
+        match service.get() {
+            Ok(service) => service.rewrite_command(command, instruction.trim()).await,
+            Err(e) => Err(e),
+        }
+    };
+    match result {
+        Ok(rewritten) => Ok(Some(rewritten)),
+        Err(e) => {
+            eprintln!("Failed to rewrite the command: {e}");
+            Ok(None)
+        }
+    }
+}
+
+/// 按 `SecurityConfig` 中的输出大小和运行时间限制执行命令。
+fn exec_limits(config: &Config) -> ExecLimits {
+    let wall_seconds = config.security.max_execution_wall_seconds;
+    ExecLimits {
+        max_output_bytes: config.security.max_command_output_bytes,
+        wall_timeout: (wall_seconds > 0).then(|| Duration::from_secs(wall_seconds)),
+    }
+}
+
+/// 不在 `PATH` 上的 shell 内置命令和关键字，不检查其可执行文件。
+const SHELL_BUILTINS: &[&str] = &[
+    "cd", "export", "unset", "alias", "unalias", "source", ".", "eval", "exec", "exit", "set", "shift", "trap",
+    "ulimit", "umask", "wait", "jobs", "fg", "bg", "read", "local", "declare", "typeset", "readonly", "builtin",
+    "command", "hash", "pushd", "popd", "dirs", "history", "type", "time", "if", "for", "while", "until", "case",
+    "function", "!", "[[", "{", "(",
+];
+
+/// 命令的第一个程序不在 `PATH` 上时返回对应的错误，用于在确认前提示用户。
+///
+/// 跳过开头的 `VAR=value` 赋值；shell 内置命令、含有变量或子 shell 的程序名不检查。
+/// Windows 的 `cmd` 内置命令太多，不做检查。
+fn missing_binary(command: &str) -> Option<ExecError> {
+    if cfg!(windows) {
+        return None;
+    }
+    let program = command
+        .split_whitespace()
+        .find(|word| !word.split_once('=').is_some_and(|(name, _)| !name.is_empty() && !name.contains('/')))?;
+    if SHELL_BUILTINS.contains(&program) || program.contains(['$', '`', '(', '"', '\'', '~']) {
+        return None;
+    }
+    CommandExecutor::which(program).err()
+}
+
+/// 在启用审计时记录安全事件，见 `SecurityConfig::audit_log_path`。
+///
+/// 写入失败不影响命令的生成和执行，只记录警告。
+pub fn audit(config: &Config, event_type: AuditEventType, query: &str, command: &str) {
+    let Some(audit_log) = AuditLog::from_config(&config.security) else {
+        return;
+    };
+    let mut event = AuditEvent::new(event_type, query, command, &config.security);
+    event.organization_id = config.llm.organization_id.clone();
+    event.project_id = config.llm.project_id.clone();
+    if let Err(e) = audit_log.record(&event) {
+        log::warn!("Failed to write audit log {}: {e}", audit_log.path().display());
+    }
+}
+
+/// 执行 `termichan test`：检查 API 密钥和 LLM 服务的连通性。
+async fn test(config: &Config, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let service = build_service(config)?;
+    // 先检查密钥，否则密钥无效时只会看到健康检查的 401 错误
+    match service.validate_api_key().await {
+        Ok(()) if config.llm.provider.eq_ignore_ascii_case("ollama") => println!("API key: not required"),
+        Ok(()) => println!("API key: valid"),
+        Err(e @ LlmError::ApiKeyInvalid) => {
+            println!("API key: invalid");
+            return Err(e.into());
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let status = service.health_check().await?;
+    println!("OK: {status}");
+    let limits = exec_limits(config);
+    match limits.max_output_bytes {
+        Some(bytes) => println!("Command output limit: {bytes} bytes"),
+        None => println!("Command output limit: none"),
+    }
+    match limits.wall_timeout {
+        Some(timeout) => println!("Command time limit: {}s", timeout.as_secs()),
+        None => println!("Command time limit: none"),
+    }
+    if verbose {
+        println!("Connection pool: {}", service.pool_metrics());
+    }
+    Ok(())
+}
+
+/// 执行 `termichan fix`：运行命令，失败时将错误输出交给 LLM 诊断，并按确认策略执行修正后的命令。
+async fn fix(config: &Config, command: &str) -> Result<(), Box<dyn Error>> {
+    let (status, stderr) = CommandExecutor::execute_capturing_stderr(command)?;
+    if status.success() {
+        eprintln!("Command succeeded, nothing to fix.");
+        return Ok(());
+    }
+    // 部分命令失败时不输出任何错误信息，至少告诉模型退出状态
+    let error = if stderr.trim().is_empty() {
+        format!("(no error output, {status})")
+    } else {
+        stderr
+    };
+
+    let service = lazy_service(config);
+    let started = Instant::now();
+    let raw = {
+        let _spinner = AsyncSpinner::new(config.ui.spinner_style, "Diagnosing error...").start();
+        service.get()?.explain_error(&error, command).await?
+    };
+    let mut response = CommandResponse {
+        parsed: ResponseParser::parse(&raw),
+        provider: config.llm.provider.clone(),
+        model: config.llm.model.clone(),
+        latency_ms: started.elapsed().as_millis() as u64,
+    };
+    audit(config, AuditEventType::CommandGenerated, command, &response.parsed.command);
+    Pager::display(&Renderer::render(&response, &config.ui), &config.ui)?;
+    let checker = SecurityChecker::new(&config.security)?;
+    confirm_and_execute(config, &checker, &service, command, &mut response.parsed.command).await?;
+    Ok(())
+}
+
+/// 执行 `termichan benchmark`：多次发送同一提示词并报告延迟分位数。
+async fn benchmark(
+    config: &Config,
+    iterations: u32,
+    prompt: &str,
+    timeout_secs: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    let service = build_service(config)?;
+    let opts = timeout_secs.map(|secs| RequestOptions {
+        timeout: Some(Duration::from_secs(secs)),
+        ..RequestOptions::default()
+    });
+    let report = service.benchmark_with_options(iterations, prompt, opts).await;
+    print!("{report}");
+    Ok(())
+}
+
+/// 执行 `termichan server --grpc`：在本机端口上提供 gRPC 接口。
+///
+/// 只监听回环地址，服务没有鉴权，不应暴露到网络上。
+async fn server(config: &Config, port: u16) -> Result<(), Box<dyn Error>> {
+    // 服务端没有终端可以显示倒计时，使用默认的等待策略
+    let service = LlmService::with_network(config.llm.clone(), &config.network)?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    eprintln!("Listening for gRPC requests on {addr}");
+    TermichanService::new(config.clone(), service).serve(addr).await?;
+    Ok(())
+}
+
+/// 执行 `termichan gc`：清理历史记录、配置备份和响应缓存，并打印清理结果。
+fn gc(config: &Config, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let report = HistoryManager::load(&config.history)?.garbage_collect(config, dry_run)?;
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    println!(
+        "{verb} {} file(s) and {} history entries, freeing {} bytes",
+        report.files_deleted, report.entries_pruned, report.bytes_freed
+    );
+    Ok(())
+}
+
+/// 守护进程自动清理的间隔。
+#[cfg(unix)]
+const DAEMON_GC_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 执行 `termichan daemon`：在 Unix 域套接字上处理生成请求。
+///
+/// 守护进程每运行 24 小时自动执行一次 `termichan gc`。
+#[cfg(unix)]
+async fn daemon(
+    config: &Config,
+    socket: &Path,
+    metrics_port: Option<u16>,
+    config_file: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let service = LlmService::with_network(config.llm.clone(), &config.network)?;
+    if let Some(port) = metrics_port {
+        let listener = metrics::bind(port).await?;
+        eprintln!("Serving metrics on http://127.0.0.1:{port}/metrics");
+        tokio::spawn(metrics::serve(listener, config.history.clone()));
+    }
+    eprintln!("Listening on {}", socket.display());
+    let gc_config = config.clone();
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now() + DAEMON_GC_INTERVAL;
+        let mut interval = tokio::time::interval_at(start, DAEMON_GC_INTERVAL);
+        loop {
+            interval.tick().await;
+            match HistoryManager::load(&gc_config.history).and_then(|mut h| h.garbage_collect(&gc_config, false)) {
+                Ok(report) => log::info!("Automatic gc: {report:?}"),
+                Err(e) => log::warn!("Automatic gc failed: {e}"),
+            }
+        }
+    });
+    let checker = SecurityChecker::new(&config.security)?;
+    let mut daemon = termichan_daemon::Daemon::new(config.clone(), service, checker);
+    if let Some(path) = config_file {
+        daemon = daemon.with_config_file(path.to_path_buf());
+    }
+    daemon.serve(socket).await?;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+use termichan_config::{config_file_path, Config, Persona};
+
+use crate::cli::PersonaCommand;
+
+/// 执行 `termichan persona` 子命令。
+pub fn run(command: PersonaCommand, config: &Config) -> Result<(), Box<dyn Error>> {
+    match command {
+        PersonaCommand::List => list(config),
+        PersonaCommand::Use { name } => {
+            let mut locked = Config::lock(&config_file_path()?)?;
+            if locked.prompt.persona(&name).is_none() {
+                return Err(format!("Unknown persona `{name}`, see `termichan persona list`").into());
+            }
+            locked.llm.active_persona = Some(name.clone());
+            locked.store()?;
+            println!("Using persona `{name}` (saved to {})", locked.path().display());
+        }
+        PersonaCommand::Add {
+            name,
+            prefix,
+            temperature,
+        } => {
+            if name.trim().is_empty() {
+                return Err("Persona name must not be empty".into());
+            }
+            if let Some(temperature) = temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+                return Err(format!("Temperature must be between 0.0 and 2.0, got {temperature}").into());
+            }
+            let persona = Persona {
+                name: name.clone(),
+                system_prompt_prefix: prefix,
+                temperature_override: temperature,
+            };
+            let mut locked = Config::lock(&config_file_path()?)?;
+            let personas = &mut locked.prompt.role_play_personas;
+            let replaced = match personas.iter_mut().find(|p| p.name == name) {
+                Some(existing) => {
+                    *existing = persona;
+                    true
+                }
+                None => {
+                    personas.push(persona);
+                    false
+                }
+            };
+            locked.store()?;
+            let action = if replaced { "Updated" } else { "Added" };
+            println!("{action} persona `{name}` in {}", locked.path().display());
+            println!("Run `termichan persona use {name}` to switch to it.");
+        }
+    }
+    Ok(())
+}
+
+/// 列出可用的风格和各自的前缀，当前使用的风格以 `*` 标记。
+fn list(config: &Config) {
+    for persona in config.prompt.personas() {
+        let marker = if config.llm.active_persona.as_ref() == Some(&persona.name) { "*" } else { " " };
+        let source = if config.prompt.role_play_personas.contains(&persona) { "custom" } else { "built-in" };
+        let mut details = vec![source.to_string()];
+        if let Some(temperature) = persona.temperature_override {
+            details.push(format!("temperature {temperature}"));
+        }
+        println!("{marker} {} ({})", persona.name, details.join(", "));
+        println!("    {}", persona.system_prompt_prefix.trim());
+    }
+}
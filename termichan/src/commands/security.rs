@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::path::Path;
+use termichan_config::Config;
+use termichan_core::SecurityChecker;
+
+use crate::cli::SecurityCommand;
+
+/// 执行 `termichan security` 子命令。
+///
+/// `config_file` 是 `config` 读取的配置文件，`--persist` 写入此文件；只从环境变量构建配置时为 `None`。
+pub async fn run(command: SecurityCommand, config: &Config, config_file: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    match command {
+        SecurityCommand::AddPattern { pattern, persist, socket } => {
+            // 先在本地检查，表达式无效时不修改任何地方
+            SecurityChecker::default()
+                .add_pattern(&pattern)
+                .map_err(|e| format!("Invalid pattern `{pattern}`: {e}"))?;
+            if !persist && socket.is_none() {
+                return Err("Nothing to update: pass --socket to add the pattern to a running daemon, \
+                            or --persist to save it to the config file"
+                    .into());
+            }
+            if let Some(socket) = socket {
+                daemon::add_pattern(&socket, &pattern).await?;
+                println!("Added pattern to the daemon at {}", socket.display());
+            }
+            if persist {
+                let mut locked = Config::lock(persist_path(config_file)?)?;
+                if locked.security.dangerous_patterns.contains(&pattern) {
+                    println!("Pattern is already in {}", locked.path().display());
+                } else {
+                    locked.security.dangerous_patterns.push(pattern);
+                    locked.store()?;
+                    println!("Added pattern to {}", locked.path().display());
+                }
+            }
+        }
+        SecurityCommand::RemovePattern { pattern, persist, socket } => {
+            if !persist && socket.is_none() {
+                return Err("Nothing to update: pass --socket to remove the pattern from a running daemon, \
+                            or --persist to remove it from the config file"
+                    .into());
+            }
+            if let Some(socket) = socket {
+                daemon::remove_pattern(&socket, &pattern).await?;
+                println!("Removed pattern from the daemon at {}", socket.display());
+            }
+            if persist {
+                let mut locked = Config::lock(persist_path(config_file)?)?;
+                let patterns = &mut locked.security.dangerous_patterns;
+                let before = patterns.len();
+                patterns.retain(|p| *p != pattern);
+                if patterns.len() == before {
+                    println!("Pattern is not in {}", locked.path().display());
+                } else {
+                    locked.store()?;
+                    println!("Removed pattern from {}", locked.path().display());
+                }
+            }
+        }
+        SecurityCommand::ListPatterns { socket } => {
+            let patterns = match socket {
+                Some(socket) => daemon::patterns(&socket).await?,
+                None => SecurityChecker::new(&config.security)?.patterns(),
+            };
+            if patterns.is_empty() {
+                println!("No dangerous patterns configured.");
+            }
+            for pattern in patterns {
+                println!("{pattern}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `--persist` 写入的配置文件，即本次运行读取的文件。
+fn persist_path(config_file: Option<&Path>) -> Result<&Path, Box<dyn Error>> {
+    config_file.ok_or_else(|| {
+        "The config file is disabled (--no-config-file or TERMICHAN_NO_CONFIG_FILE), nothing to persist to".into()
+    })
+}
+
+/// 通过守护进程的套接字修改或列出运行中的规则。
+#[cfg(unix)]
+mod daemon {
+    use std::path::Path;
+    use termichan_daemon::{DaemonClient, DaemonError};
+
+    pub async fn add_pattern(socket: &Path, pattern: &str) -> Result<Vec<String>, DaemonError> {
+        DaemonClient::connect(socket).await?.add_pattern(pattern).await
+    }
+
+    pub async fn remove_pattern(socket: &Path, pattern: &str) -> Result<Vec<String>, DaemonError> {
+        DaemonClient::connect(socket).await?.remove_pattern(pattern).await
+    }
+
+    pub async fn patterns(socket: &Path) -> Result<Vec<String>, DaemonError> {
+        DaemonClient::connect(socket).await?.patterns().await
+    }
+}
+
+/// 守护进程只支持 Unix 域套接字。
+#[cfg(not(unix))]
+mod daemon {
+    use std::path::Path;
+
+    const UNSUPPORTED: &str = "--socket requires the daemon, which is only available on Unix";
+
+    pub async fn add_pattern(_socket: &Path, _pattern: &str) -> Result<Vec<String>, &'static str> {
+        Err(UNSUPPORTED)
+    }
+
+    pub async fn remove_pattern(_socket: &Path, _pattern: &str) -> Result<Vec<String>, &'static str> {
+        Err(UNSUPPORTED)
+    }
+
+    pub async fn patterns(_socket: &Path) -> Result<Vec<String>, &'static str> {
+        Err(UNSUPPORTED)
+    }
+}
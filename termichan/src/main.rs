@@ -1,13 +1,472 @@
-use termichan_config::{load_or_create_config, Config};
+mod cli;
+mod commands;
+mod output;
+
+use clap::Parser;
+use cli::{Cli, Command};
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::process::{ExitCode, ExitStatus};
 use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use termichan_config::{format_relative, load_or_create_config, Config, ConfirmationMode, OutputFormat};
+use termichan_core::{
+    current_session_id, AuditEventType, CommandClassifier, CommandResponse, ExportOnExit, HistoryEntry,
+    HistoryManager, ResponseParser, SecurityChecker,
+};
+use futures::StreamExt;
+use termichan_llm::{
+    estimate_text_tokens, ChatCompletionRequestMessage, LazyLlmService, LlmError, LlmService, PromptContext,
+    StreamEvent, ToolCall,
+};
+use termichan_ui::{AsyncSpinner, CsvRenderer, Pager, Renderer, StreamBuffer, StreamProgress};
 
 pub static CONFIG: OnceLock<Config> = OnceLock::new();
 
-fn main() {
+#[tokio::main]
+async fn main() -> ExitCode {
     env_logger::init();
 
-    let config = load_or_create_config(None).expect("Failed to load config.");
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    // 向导要在加载配置之前运行，否则会先创建默认配置文件
+    if let Some(Command::Init { overwrite, non_interactive }) = cli.command {
+        return commands::init::run(overwrite, non_interactive).await;
+    }
+    // 诊断也不加载配置，配置文件无效时照样给出报告
+    if let Some(Command::Doctor) = cli.command {
+        return commands::doctor::run().await;
+    }
+    let mut config = if cli.no_config_file {
+        Config::from_env_only()?
+    } else {
+        load_or_create_config(None)?
+    };
+    if let Some(format) = cli.format {
+        config.ui.output_format = format;
+    }
+    // JSON 和 CSV 输出无法承载确认提示，不询问也不执行命令，只把结果交给调用方
+    let structured = matches!(config.ui.output_format, OutputFormat::Json | OutputFormat::Csv);
+    if structured {
+        config.security.confirmation_mode = ConfirmationMode::Never;
+    }
+    config.validate()?;
+    config.apply_active_persona()?;
     CONFIG.set(config).expect("CONFIG has already initialized.");
+    let config = CONFIG.get().expect("CONFIG is initialized above.");
+    // 在 `run` 返回时按 `history.export_on_exit` 导出历史记录
+    let _export = ExportOnExit::new(&config.history);
+
+    if let Some(command) = cli.command {
+        let config_file = if cli.no_config_file || termichan_config::config_file_disabled() {
+            None
+        } else {
+            termichan_config::config_file_path().ok()
+        };
+        return commands::dispatch(command, config, config_file.as_deref()).await;
+    }
+
+    if cli.query.is_empty() {
+        println!("{config}");
+        return Ok(());
+    }
+
+    let query = cli.query.join(" ");
+    if let Some(models) = cli.compare {
+        return commands::compare::compare(config, query, models).await;
+    }
+
+    // 最近成功执行过相似的查询时，先询问是否直接使用当时的命令
+    let reused = match offer_history_suggestion(config, &query, cli.quiet || structured)? {
+        HistoryChoice::Reuse(entry) => Some(*entry),
+        HistoryChoice::Generate => None,
+        HistoryChoice::Cancel => return Ok(()),
+    };
+
+    let service = commands::lazy_service(config);
+    if config.llm.validate_key_on_startup && reused.is_none() {
+        service.get()?.validate_api_key().await?;
+    }
+    if cli.health {
+        let status = service.get()?.health_check().await?;
+        eprintln!("{status}");
+    } else if config.llm.prewarm_on_startup && reused.is_none() {
+        // 在构建提示词等准备工作期间创建服务并建立连接；失败只影响延迟，结果不需要等待
+        let service = service.clone();
+        tokio::spawn(async move { service.prewarm().await });
+    }
+
+    // 启用 A/B 测试时在这里选定变体，记录到历史中
+    let (prompt, prompt_variant) = config.prompt.render();
+    let environment = PromptContext::detect()
+        .with_injection_guard(&config.llm)
+        .with_recent_errors(&prompt)
+        .with_context_providers(&prompt);
+    // 历史记录保存原始查询，预处理后的查询只用于提示词
+    let processed = match &reused {
+        Some(_) => query.clone(),
+        None => service.get()?.pre_process(&query, &prompt)?,
+    };
+    let mut messages = environment.build_messages(&prompt, &processed);
+    if config.llm.auto_inject_session_context && reused.is_none() {
+        inject_session_context(config, &mut messages);
+    }
+    let started = Instant::now();
+    let n = config.llm.n_completions.unwrap_or(1);
+    let context = || format!("while generating command for query: {query}");
+    let raw = if let Some(entry) = &reused {
+        vec![entry.generated_command.clone()]
+    } else if n > 1 {
+        service.get()?.chat_completion_n(messages, n).await.map_err(|e| e.context(context()))?
+    } else if cli.stream {
+        vec![stream_completion(service.get()?, messages, config).await?]
+    } else if config.llm.enable_function_calling {
+        let tools = LlmService::function_definitions_from_context(&environment);
+        let _spinner = AsyncSpinner::new(config.ui.spinner_style, "Generating command...").start();
+        let response = service.get()?.chat_completion_with_tools(messages, &tools, run_tool).await;
+        vec![response.map_err(|e| e.context(context()))?]
+    } else {
+        let _spinner = AsyncSpinner::new(config.ui.spinner_style, "Generating command...").start();
+        vec![service.chat_completion(messages, None).await.map_err(|e| e.context(context()))?]
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+    // 复用历史命令时没有发出请求，也不需要创建服务
+    let (request_id, cache_usage) = match &reused {
+        Some(_) => (None, None),
+        None => {
+            let service = service.get()?;
+            commands::cache::save_cache(service);
+            (service.last_request_id(), service.last_cache_usage())
+        }
+    };
+    if config.llm.slow_query_warn_ms.is_some_and(|limit| latency_ms > limit) {
+        log::warn!("Slow query: {} took {latency_ms} ms", config.llm.model);
+    }
+    let mut responses: Vec<CommandResponse> = raw
+        .iter()
+        .map(|raw| {
+            let mut parsed = ResponseParser::parse(raw);
+            parsed.cache_read_tokens = cache_usage.map(|usage| usage.read_tokens);
+            parsed.cache_creation_tokens = cache_usage.map(|usage| usage.creation_tokens);
+            CommandResponse {
+                parsed,
+                provider: config.llm.provider.clone(),
+                model: config.llm.model.clone(),
+                latency_ms,
+            }
+        })
+        .collect();
+
+    let mut response = if structured {
+        // 只输出一个结果，多个候选时取第一个
+        let response = responses.swap_remove(0);
+        if config.ui.output_format == OutputFormat::Csv {
+            print!("{}", CsvRenderer::render(&query, &response, None));
+        } else {
+            println!("{}", serde_json::to_string(&response)?);
+        }
+        response
+    } else if responses.len() > 1 {
+        Pager::display(&Renderer::render_choices(&responses, &config.ui), &config.ui)?;
+        let index = choose(responses.len(), config)?;
+        responses.swap_remove(index)
+    } else {
+        let response = responses.swap_remove(0);
+        if !cli.quiet {
+            Pager::display(&Renderer::render(&response, &config.ui), &config.ui)?;
+        }
+        response
+    };
+
+    commands::audit(config, AuditEventType::CommandGenerated, &query, &response.parsed.command);
+
+    if let Some(path) = &cli.output {
+        output::write_response(path, cli.output_format, &query, &response)?;
+    }
+
+    // `--quiet` 时用户没有看到命令，不执行；JSON 输出时命令交给调用方处理
+    let generated = response.parsed.command.clone();
+    let checker = SecurityChecker::new(&config.security)?;
+    let status = if cli.quiet
+        || structured
+        || !ensure_explained(&service, &mut response, config, &checker).await
+    {
+        None
+    } else {
+        commands::confirm_and_execute(config, &checker, &service, &query, &mut response.parsed.command).await?
+    };
+
+    if config.history.enabled {
+        let mut entry = HistoryEntry::new(&query, &response.parsed.command, &config.llm);
+        entry.latency_ms = Some(response.latency_ms);
+        entry.request_id = request_id;
+        entry.prompt_variant = prompt_variant;
+        entry.replayed_from = reused.map(|entry| entry.id);
+        if response.parsed.command != generated {
+            entry.original_command = Some(generated);
+        }
+        // 只有关闭确认时才会执行含有占位符的命令，见 `security.placeholder_detection`
+        let placeholders = commands::unfilled_placeholders(&response.parsed.command, &config.security);
+        if status.is_some() && !placeholders.is_empty() {
+            entry.annotation = Some(format!("Executed with unfilled placeholders: {}", placeholders.join(", ")));
+        }
+        if config.llm.enable_embeddings {
+            entry.query_embedding = embed_query(&service, &query).await;
+        }
+        record_history(config, entry, status);
+    }
+    Ok(())
+}
+
+/// 按 `security.require_explanation_for_dangerous` 为缺少解释的危险命令补充解释。
+///
+/// 返回是否可以进入确认流程；无法获取解释时不执行命令。只有需要请求解释时才创建服务，
+/// 复用历史命令等不需要解释的情况在没有 API 密钥时也能执行。
+async fn ensure_explained(
+    service: &LazyLlmService,
+    response: &mut CommandResponse,
+    config: &Config,
+    checker: &SecurityChecker,
+) -> bool {
+    let parsed = &response.parsed;
+    if !config.security.require_explanation_for_dangerous
+        || parsed.explanation.is_some()
+        || parsed.safety_note.is_some()
+        || !CommandClassifier::is_dangerous(&parsed.command, &config.security, checker)
+    {
+        return true;
+    }
+
+    let explanation = match service.get() {
+        Ok(service) => service.generate_explanation(&parsed.command).await,
+        Err(e) => Err(e),
+    };
+    match explanation {
+        Ok(explanation) => {
+            eprintln!("# Explanation: {explanation}");
+            response.parsed.explanation = Some(explanation);
+            true
+        }
+        Err(e) => {
+            eprintln!("Not executing: could not get an explanation for this dangerous command ({e}).");
+            false
+        }
+    }
+}
+
+/// 以流式请求获取完整响应，期间显示 token 估算和实时计数。
+async fn stream_completion(
+    service: &LlmService,
+    messages: Vec<ChatCompletionRequestMessage>,
+    config: &Config,
+) -> Result<String, Box<dyn Error>> {
+    let mut progress = StreamProgress::start(
+        service.count_messages_tokens(&messages),
+        config.llm.max_tokens,
+        config.ui.spinner_style,
+    );
+    let (handle, stream) = service.streaming_with_metadata(messages).await?;
+    let mut stream = Box::pin(stream);
+    // Ctrl+C 中止流式响应，流以包含已收到内容的 `LlmError::Aborted` 结束，之后由 `restore_ctrl_c` 恢复为退出进程
+    let ctrl_c = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            handle.abort();
+        }
+    });
+
+    // 标准输出是终端时实时显示响应内容，按 `ui.stream_buffer_ms` 批量写出
+    // JSON 和 CSV 输出时标准输出只写最终的结果
+    let echo_enabled = io::stdout().is_terminal()
+        && !matches!(config.ui.output_format, OutputFormat::Json | OutputFormat::Csv);
+    let mut echo = echo_enabled.then(|| {
+        progress.hide();
+        StreamBuffer::new(Duration::from_millis(config.ui.stream_buffer_ms), io::stdout())
+    });
+
+    let mut raw = String::new();
+    let mut completion_tokens = None;
+    loop {
+        let event = match &mut echo {
+            Some(echo) => echo.next_from(&mut stream).await?,
+            None => stream.next().await,
+        };
+        let Some(event) = event else { break };
+        match event {
+            Ok(StreamEvent::Token(text)) => {
+                progress.on_chunk(estimate_text_tokens(&text).max(1));
+                if let Some(echo) = &mut echo {
+                    echo.push(&text)?;
+                }
+                raw.push_str(&text);
+            }
+            Ok(StreamEvent::ThrottleWarning { wait_ms }) => progress.on_throttle(wait_ms),
+            Ok(StreamEvent::FinishReason(reason)) => progress.on_finish_reason(reason),
+            Ok(StreamEvent::Usage { completion_tokens: tokens, .. }) => completion_tokens = Some(u64::from(tokens)),
+            Err(e) => {
+                restore_ctrl_c(ctrl_c);
+                match echo {
+                    Some(echo) => finish_echo(echo)?,
+                    None => {
+                        if let LlmError::Aborted(partial) = &e {
+                            eprintln!("{partial}");
+                        }
+                    }
+                }
+                progress.finish(None);
+                return Err(e.into());
+            }
+        }
+    }
+    restore_ctrl_c(ctrl_c);
+    if let Some(echo) = echo {
+        finish_echo(echo)?;
+    }
+    // API 未报告用量时显示计数值
+    progress.finish(completion_tokens);
+    Ok(raw)
+}
+
+/// 停止中止流式响应的 Ctrl+C 监听，之后 Ctrl+C 以 130 退出进程。
+///
+/// `tokio::signal::ctrl_c` 注册的信号处理在进程结束前不会注销，SIGINT 不再终止进程，
+/// 不接管的话之后在确认提示处按 Ctrl+C 没有任何反应。
+fn restore_ctrl_c(listener: tokio::task::JoinHandle<()>) {
+    listener.abort();
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!();
+            std::process::exit(130);
+        }
+    });
+}
+
+/// 执行模型请求的工具调用，失败时把错误作为结果告诉模型。
+fn run_tool(call: &ToolCall) -> String {
+    log::info!("Model called tool {}", call.name);
+    termichan_executor::run_tool(&call.name, &call.arguments).unwrap_or_else(|e| format!("Error: {e}"))
+}
+
+/// 写出实时显示的剩余内容，并换行以免与之后的输出连在一起。
+fn finish_echo(echo: StreamBuffer<io::Stdout>) -> io::Result<()> {
+    let mut stdout = echo.finish()?;
+    writeln!(stdout)
+}
+
+/// 让用户从 `count` 个候选中选择一个，返回从 0 开始的下标。
+///
+/// 标准输入不是终端或输入为空时选择第一个候选。
+fn choose(count: usize, config: &Config) -> Result<usize, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        return Ok(0);
+    }
+    loop {
+        eprint!("{}", Renderer::choice_prompt(count, &config.ui));
+        io::stderr().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(0);
+        }
+        match line.trim().parse::<usize>() {
+            Ok(choice) if (1..=count).contains(&choice) => return Ok(choice - 1),
+            _ => eprintln!("Please enter a number between 1 and {count}."),
+        }
+    }
+}
+
+/// 用户对历史命令建议的选择。
+enum HistoryChoice {
+    /// 直接使用历史记录中的命令。
+    Reuse(Box<HistoryEntry>),
+    /// 请求 LLM 生成新命令。
+    Generate,
+    /// 不生成也不执行任何命令。
+    Cancel,
+}
+
+/// 查找与 `query` 相似且执行成功的历史命令，询问用户是否直接使用。
+///
+/// 未启用历史记录、`--quiet` 或标准输入不是终端时不询问；读取历史失败只记录警告。
+fn offer_history_suggestion(config: &Config, query: &str, quiet: bool) -> Result<HistoryChoice, Box<dyn Error>> {
+    if !config.history.enabled || quiet || !io::stdin().is_terminal() {
+        return Ok(HistoryChoice::Generate);
+    }
+    let suggestion = match HistoryManager::load(&config.history) {
+        Ok(history) => history.suggest_from_history(query),
+        Err(e) => {
+            log::warn!("Failed to read history for suggestions: {e}");
+            None
+        }
+    };
+    let Some(entry) = suggestion else {
+        return Ok(HistoryChoice::Generate);
+    };
+
+    let ago = format_relative(Utc::now() - entry.timestamp);
+    loop {
+        eprint!(
+            "I found a similar command from {ago}: {}. Use it? [y/n/new] ",
+            entry.generated_command
+        );
+        io::stderr().flush()?;
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(HistoryChoice::Reuse(Box::new(entry))),
+            "n" | "no" => return Ok(HistoryChoice::Cancel),
+            "new" | "" => return Ok(HistoryChoice::Generate),
+            _ => eprintln!("Please answer y, n or new."),
+        }
+    }
+}
+
+/// 把当前会话最近的问答插入到系统提示词之后、本次查询之前。
+///
+/// 未启用历史记录时没有可用的上下文；读取历史失败只记录警告，照常发送请求。
+fn inject_session_context(config: &Config, messages: &mut Vec<ChatCompletionRequestMessage>) {
+    if !config.history.enabled {
+        return;
+    }
+    let context = match HistoryManager::load(&config.history) {
+        Ok(history) => history.build_context_from_history(current_session_id(), config.llm.max_context_messages),
+        Err(e) => {
+            log::warn!("Failed to read history for session context: {e}");
+            return;
+        }
+    };
+    let at = match messages.first() {
+        Some(ChatCompletionRequestMessage::System(_)) => 1,
+        _ => 0,
+    };
+    messages.splice(at..at, context);
+}
+
+/// 为历史记录生成查询的嵌入向量，失败时只记录警告，记录照常保存。
+async fn embed_query(service: &LazyLlmService, query: &str) -> Option<Vec<f32>> {
+    let result = match service.get() {
+        Ok(service) => service.embed(query).await,
+        Err(e) => Err(e),
+    };
+    result.inspect_err(|e| log::warn!("Failed to embed query for history: {e}")).ok()
+}
 
-    println!("{:#?}", CONFIG);
+/// 将生成结果追加到历史记录。历史记录失败不影响命令生成，只记录警告。
+fn record_history(config: &Config, mut entry: HistoryEntry, status: Option<ExitStatus>) {
+    let result = HistoryManager::load(&config.history).and_then(|mut manager| {
+        entry.executed = status.is_some();
+        entry.exit_code = status.and_then(|s| s.code());
+        manager.add(entry);
+        manager.save()
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to record history: {e}");
+    }
 }
@@ -0,0 +1,49 @@
+use clap::ValueEnum;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use termichan_core::CommandResponse;
+
+/// `--output` 文件的格式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFileFormat {
+    /// Markdown：命令放在代码块中，解释作为正文。
+    #[default]
+    Md,
+    /// `CommandResponse` 的 JSON 序列化形式。
+    Json,
+}
+
+/// 将响应按 `format` 写入 `path`，自动创建不存在的上级目录。
+pub fn write_response(
+    path: &Path,
+    format: OutputFileFormat,
+    query: &str,
+    response: &CommandResponse,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let content = match format {
+        OutputFileFormat::Md => to_markdown(query, response),
+        OutputFileFormat::Json => serde_json::to_string_pretty(response)? + "\n",
+    };
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn to_markdown(query: &str, response: &CommandResponse) -> String {
+    let parsed = &response.parsed;
+    let mut out = format!("# {query}\n\n```sh\n{}\n```\n", parsed.command);
+    if let Some(note) = &parsed.safety_note {
+        out.push_str(&format!("\n> **Be careful:** {note}\n"));
+    }
+    if let Some(explanation) = &parsed.explanation {
+        out.push_str(&format!("\n{explanation}\n"));
+    }
+    out.push_str(&format!(
+        "\n_Generated by {} / {} in {} ms._\n",
+        response.provider, response.model, response.latency_ms
+    ));
+    out
+}